@@ -0,0 +1,59 @@
+#define BLUR_RADIUS 2
+// Relative depth difference at which a neighbour stops counting.
+#define DEPTH_SHARPNESS 20.0
+
+struct PSInput {
+    float4 position: SV_Position;
+};
+
+struct AoBlurResources {
+    uint aoIndex;
+    uint depthPyramidIndex;
+};
+
+ConstantBuffer<AoBlurResources> blurResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    return result;
+}
+
+// Averages the 5x5 neighbourhood, weighting each texel by how close its depth is to the center so
+// the noise of the horizon search is smoothed out without bleeding across silhouettes.
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> aoTexture = ResourceDescriptorHeap[blurResource.aoIndex];
+    Texture2D<float> depthPyramid = ResourceDescriptorHeap[blurResource.depthPyramidIndex];
+
+    int2 pixel = int2(input.position.xy);
+    float4 center = aoTexture.Load(int3(pixel, 0));
+    float depth = depthPyramid.Load(int3(pixel, 0));
+    if (depth <= 0.0) {
+        return center;
+    }
+
+    uint width;
+    uint height;
+    aoTexture.GetDimensions(width, height);
+    int2 maxPixel = int2(width, height) - 1;
+
+    float4 result = 0.0;
+    float totalWeight = 0.0;
+    for (int y = -BLUR_RADIUS; y <= BLUR_RADIUS; y++) {
+        for (int x = -BLUR_RADIUS; x <= BLUR_RADIUS; x++) {
+            int2 samplePixel = clamp(pixel + int2(x, y), int2(0, 0), maxPixel);
+            float sampleDepth = depthPyramid.Load(int3(samplePixel, 0));
+            float4 ao = aoTexture.Load(int3(samplePixel, 0));
+            float difference = abs(sampleDepth - depth) / depth;
+            float weight = saturate(1.0 - difference * DEPTH_SHARPNESS);
+            result += ao * weight;
+            totalWeight += weight;
+        }
+    }
+
+    // The center always has full weight, so the total never reaches zero.
+    result /= totalWeight;
+    return float4(normalize(result.xyz), result.w);
+}
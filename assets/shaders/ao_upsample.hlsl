@@ -0,0 +1,64 @@
+struct PSInput {
+    float4 position: SV_Position;
+};
+
+struct AoUpsampleResources {
+    uint aoIndex;
+    uint depthPyramidIndex;
+};
+
+ConstantBuffer<AoUpsampleResources> upsampleResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    return result;
+}
+
+// Blends the four nearest half resolution texels, weighting each by how close its depth is to the
+// full resolution depth so occlusion doesn't bleed across silhouettes.
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> halfAo = ResourceDescriptorHeap[upsampleResource.aoIndex];
+    Texture2D<float> depthPyramid = ResourceDescriptorHeap[upsampleResource.depthPyramidIndex];
+
+    int2 pixel = int2(input.position.xy);
+    float depth = depthPyramid.Load(int3(pixel, 0));
+    if (depth <= 0.0) {
+        return float4(0.0, 0.0, -1.0, 1.0);
+    }
+
+    uint width;
+    uint height;
+    halfAo.GetDimensions(width, height);
+    int2 maxPixel = int2(width, height) - 1;
+    float2 halfPosition = input.position.xy * 0.5 - 0.5;
+    int2 base = int2(floor(halfPosition));
+    float2 f = frac(halfPosition);
+
+    float4 result = 0.0;
+    float totalWeight = 0.0;
+    float4 nearest = float4(0.0, 0.0, -1.0, 1.0);
+    float nearestDifference = 1e30;
+    for (int y = 0; y < 2; y++) {
+        for (int x = 0; x < 2; x++) {
+            int2 samplePixel = clamp(base + int2(x, y), int2(0, 0), maxPixel);
+            float sampleDepth = depthPyramid.Load(int3(samplePixel, 1));
+            float4 ao = halfAo.Load(int3(samplePixel, 0));
+
+            float bilinear = (x == 0 ? 1.0 - f.x : f.x) * (y == 0 ? 1.0 - f.y : f.y);
+            float difference = abs(sampleDepth - depth) / depth;
+            float weight = bilinear / (difference + 1e-3);
+            result += ao * weight;
+            totalWeight += weight;
+            if (difference < nearestDifference) {
+                nearestDifference = difference;
+                nearest = ao;
+            }
+        }
+    }
+
+    result = totalWeight > 1e-4 ? result / totalWeight : nearest;
+    return float4(normalize(result.xyz), result.w);
+}
@@ -0,0 +1,89 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+};
+
+struct BloomResources {
+    uint sourceIndex;
+    uint samplerIndex;
+    float threshold;
+    uint prefilter;
+    float radius;
+};
+
+ConstantBuffer<BloomResources> bloomResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+float Luminance(float3 color) {
+    return dot(color, float3(0.2126, 0.7152, 0.0722));
+}
+
+// Keeps the part of the color above the threshold, with a soft knee so bloom fades in.
+float3 Threshold(float3 color) {
+    float threshold = bloomResource.threshold;
+    float knee = threshold * 0.5;
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-4);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 1e-4);
+    return color * contribution;
+}
+
+// Weights a group of samples by inverse luminance so single very bright pixels don't flicker.
+float3 KarisAverage(float3 a, float3 b, float3 c, float3 d) {
+    float wa = 1.0 / (1.0 + Luminance(a));
+    float wb = 1.0 / (1.0 + Luminance(b));
+    float wc = 1.0 / (1.0 + Luminance(c));
+    float wd = 1.0 / (1.0 + Luminance(d));
+    return (a * wa + b * wb + c * wc + d * wd) / (wa + wb + wc + wd);
+}
+
+// 13 bilinear taps over a 4x4 source texel footprint, as five overlapping 2x2 boxes.
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> source = ResourceDescriptorHeap[bloomResource.sourceIndex];
+    SamplerState linearSampler = SamplerDescriptorHeap[bloomResource.samplerIndex];
+
+    uint width;
+    uint height;
+    source.GetDimensions(width, height);
+    float2 texel = 1.0 / float2(width, height);
+    float2 uv = input.uv;
+
+    float3 a = source.SampleLevel(linearSampler, uv + texel * float2(-2.0, -2.0), 0.0).rgb;
+    float3 b = source.SampleLevel(linearSampler, uv + texel * float2(0.0, -2.0), 0.0).rgb;
+    float3 c = source.SampleLevel(linearSampler, uv + texel * float2(2.0, -2.0), 0.0).rgb;
+    float3 d = source.SampleLevel(linearSampler, uv + texel * float2(-2.0, 0.0), 0.0).rgb;
+    float3 e = source.SampleLevel(linearSampler, uv, 0.0).rgb;
+    float3 f = source.SampleLevel(linearSampler, uv + texel * float2(2.0, 0.0), 0.0).rgb;
+    float3 g = source.SampleLevel(linearSampler, uv + texel * float2(-2.0, 2.0), 0.0).rgb;
+    float3 h = source.SampleLevel(linearSampler, uv + texel * float2(0.0, 2.0), 0.0).rgb;
+    float3 i = source.SampleLevel(linearSampler, uv + texel * float2(2.0, 2.0), 0.0).rgb;
+    float3 j = source.SampleLevel(linearSampler, uv + texel * float2(-1.0, -1.0), 0.0).rgb;
+    float3 k = source.SampleLevel(linearSampler, uv + texel * float2(1.0, -1.0), 0.0).rgb;
+    float3 l = source.SampleLevel(linearSampler, uv + texel * float2(-1.0, 1.0), 0.0).rgb;
+    float3 m = source.SampleLevel(linearSampler, uv + texel * float2(1.0, 1.0), 0.0).rgb;
+
+    float3 color;
+    if (bloomResource.prefilter != 0) {
+        color = KarisAverage(j, k, l, m) * 0.5
+            + KarisAverage(a, b, d, e) * 0.125
+            + KarisAverage(b, c, e, f) * 0.125
+            + KarisAverage(d, e, g, h) * 0.125
+            + KarisAverage(e, f, h, i) * 0.125;
+        color = Threshold(color);
+    } else {
+        color = (j + k + l + m) * 0.125
+            + (a + c + g + i) * 0.03125
+            + (b + d + f + h) * 0.0625
+            + e * 0.125;
+    }
+    return float4(max(color, 0.0), 1.0);
+}
@@ -0,0 +1,46 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+};
+
+struct BloomResources {
+    uint sourceIndex;
+    uint samplerIndex;
+    float threshold;
+    uint prefilter;
+    float radius;
+};
+
+ConstantBuffer<BloomResources> bloomResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+// 3x3 tent over the lower mip, added onto the target by the pipeline's blend state.
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> source = ResourceDescriptorHeap[bloomResource.sourceIndex];
+    SamplerState linearSampler = SamplerDescriptorHeap[bloomResource.samplerIndex];
+
+    uint width;
+    uint height;
+    source.GetDimensions(width, height);
+    float2 offset = bloomResource.radius / float2(width, height);
+    float2 uv = input.uv;
+
+    float3 color = source.SampleLevel(linearSampler, uv, 0.0).rgb * 4.0;
+    color += source.SampleLevel(linearSampler, uv + float2(-offset.x, 0.0), 0.0).rgb * 2.0;
+    color += source.SampleLevel(linearSampler, uv + float2(offset.x, 0.0), 0.0).rgb * 2.0;
+    color += source.SampleLevel(linearSampler, uv + float2(0.0, -offset.y), 0.0).rgb * 2.0;
+    color += source.SampleLevel(linearSampler, uv + float2(0.0, offset.y), 0.0).rgb * 2.0;
+    color += source.SampleLevel(linearSampler, uv + float2(-offset.x, -offset.y), 0.0).rgb;
+    color += source.SampleLevel(linearSampler, uv + float2(offset.x, -offset.y), 0.0).rgb;
+    color += source.SampleLevel(linearSampler, uv + float2(-offset.x, offset.y), 0.0).rgb;
+    color += source.SampleLevel(linearSampler, uv + float2(offset.x, offset.y), 0.0).rgb;
+    return float4(color / 16.0, 1.0);
+}
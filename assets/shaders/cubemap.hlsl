@@ -0,0 +1,157 @@
+struct CubemapResources {
+    uint sourceIndex;
+    uint targetIndex;
+    uint samplerIndex;
+    uint faceSize;
+    float roughness;
+    uint sampleCount;
+};
+
+ConstantBuffer<CubemapResources> cubemapResource: register(b0);
+
+static const float PI = 3.14159265359;
+
+// Direction through the center of a texel of a face, in the D3D face order +X, -X, +Y, -Y, +Z, -Z.
+float3 FaceDirection(uint3 id) {
+    float2 st = (float2(id.xy) + 0.5) / float(cubemapResource.faceSize) * 2.0 - 1.0;
+    float3 direction;
+    switch (id.z) {
+        case 0: direction = float3(1.0, -st.y, -st.x); break;
+        case 1: direction = float3(-1.0, -st.y, st.x); break;
+        case 2: direction = float3(st.x, 1.0, st.y); break;
+        case 3: direction = float3(st.x, -1.0, -st.y); break;
+        case 4: direction = float3(st.x, -st.y, 1.0); break;
+        default: direction = float3(-st.x, -st.y, -1.0); break;
+    }
+    return normalize(direction);
+}
+
+// Any two axes perpendicular to the normal, for turning tangent space samples into world space.
+float3x3 TangentBasis(float3 normal) {
+    float3 up = abs(normal.y) < 0.999 ? float3(0.0, 1.0, 0.0) : float3(1.0, 0.0, 0.0);
+    float3 tangent = normalize(cross(up, normal));
+    float3 bitangent = cross(normal, tangent);
+    return float3x3(tangent, bitangent, normal);
+}
+
+float2 Hammersley(uint i, uint count) {
+    return float2(float(i) / float(count), float(reversebits(i)) * 2.3283064365386963e-10);
+}
+
+float3 ImportanceSampleGgx(float2 xi, float3x3 basis, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+    float3 h = float3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+    return normalize(mul(h, basis));
+}
+
+[numthreads(8, 8, 1)]
+void EquirectToCubeMain(uint3 id: SV_DispatchThreadID) {
+    if (id.x >= cubemapResource.faceSize || id.y >= cubemapResource.faceSize) {
+        return;
+    }
+    Texture2D<float4> source = ResourceDescriptorHeap[cubemapResource.sourceIndex];
+    SamplerState linearSampler = SamplerDescriptorHeap[cubemapResource.samplerIndex];
+    RWTexture2DArray<float4> target = ResourceDescriptorHeap[cubemapResource.targetIndex];
+
+    float3 direction = FaceDirection(id);
+    float2 uv = float2(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(direction.y) / PI);
+    target[id] = float4(source.SampleLevel(linearSampler, uv, 0.0).rgb, 1.0);
+}
+
+// Cosine weighted average of the incoming light over the hemisphere around each normal, walked
+// on a fixed grid of sampleCount steps in each angle.
+[numthreads(8, 8, 1)]
+void IrradianceMain(uint3 id: SV_DispatchThreadID) {
+    if (id.x >= cubemapResource.faceSize || id.y >= cubemapResource.faceSize) {
+        return;
+    }
+    TextureCube<float4> source = ResourceDescriptorHeap[cubemapResource.sourceIndex];
+    SamplerState linearSampler = SamplerDescriptorHeap[cubemapResource.samplerIndex];
+    RWTexture2DArray<float4> target = ResourceDescriptorHeap[cubemapResource.targetIndex];
+
+    float3 normal = FaceDirection(id);
+    float3x3 basis = TangentBasis(normal);
+    uint steps = max(cubemapResource.sampleCount, 1);
+    float3 irradiance = 0.0;
+    for (uint i = 0; i < steps * 4; i++) {
+        float phi = (float(i) + 0.5) / float(steps * 4) * 2.0 * PI;
+        for (uint j = 0; j < steps; j++) {
+            float theta = (float(j) + 0.5) / float(steps) * 0.5 * PI;
+            float3 tangentSample = float3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            float3 direction = mul(tangentSample, basis);
+            irradiance += source.SampleLevel(linearSampler, direction, 0.0).rgb * cos(theta) * sin(theta);
+        }
+    }
+    irradiance *= PI / float(steps * steps * 4);
+    target[id] = float4(irradiance, 1.0);
+}
+
+// Split sum prefilter: GGX importance samples around the reflection vector, assuming the view
+// direction equals the normal, weighted by N.L.
+[numthreads(8, 8, 1)]
+void PrefilterMain(uint3 id: SV_DispatchThreadID) {
+    if (id.x >= cubemapResource.faceSize || id.y >= cubemapResource.faceSize) {
+        return;
+    }
+    TextureCube<float4> source = ResourceDescriptorHeap[cubemapResource.sourceIndex];
+    SamplerState linearSampler = SamplerDescriptorHeap[cubemapResource.samplerIndex];
+    RWTexture2DArray<float4> target = ResourceDescriptorHeap[cubemapResource.targetIndex];
+
+    float3 normal = FaceDirection(id);
+    if (cubemapResource.roughness == 0.0) {
+        target[id] = float4(source.SampleLevel(linearSampler, normal, 0.0).rgb, 1.0);
+        return;
+    }
+
+    float3x3 basis = TangentBasis(normal);
+    float3 color = 0.0;
+    float totalWeight = 0.0;
+    for (uint i = 0; i < cubemapResource.sampleCount; i++) {
+        float3 h = ImportanceSampleGgx(Hammersley(i, cubemapResource.sampleCount), basis, cubemapResource.roughness);
+        float3 l = normalize(2.0 * dot(normal, h) * h - normal);
+        float nDotL = dot(normal, l);
+        if (nDotL > 0.0) {
+            color += source.SampleLevel(linearSampler, l, 0.0).rgb * nDotL;
+            totalWeight += nDotL;
+        }
+    }
+    target[id] = float4(color / max(totalWeight, 0.0001), 1.0);
+}
+
+float GeometrySchlickGgx(float nDotX, float roughness) {
+    float k = roughness * roughness * 0.5;
+    return nDotX / (nDotX * (1.0 - k) + k);
+}
+
+// Split sum scale and bias to F0 of the specular term, with N.V along x and roughness along y.
+// faceSize is the size of the square lookup table.
+[numthreads(8, 8, 1)]
+void BrdfLutMain(uint3 id: SV_DispatchThreadID) {
+    if (id.x >= cubemapResource.faceSize || id.y >= cubemapResource.faceSize) {
+        return;
+    }
+    RWTexture2D<float2> target = ResourceDescriptorHeap[cubemapResource.targetIndex];
+
+    float nDotV = (float(id.x) + 0.5) / float(cubemapResource.faceSize);
+    float roughness = (float(id.y) + 0.5) / float(cubemapResource.faceSize);
+    float3 v = float3(sqrt(1.0 - nDotV * nDotV), 0.0, nDotV);
+    float3x3 basis = TangentBasis(float3(0.0, 0.0, 1.0));
+    float2 result = 0.0;
+    for (uint i = 0; i < cubemapResource.sampleCount; i++) {
+        float3 h = ImportanceSampleGgx(Hammersley(i, cubemapResource.sampleCount), basis, roughness);
+        float3 l = normalize(2.0 * dot(v, h) * h - v);
+        float nDotL = saturate(l.z);
+        if (nDotL > 0.0) {
+            float nDotH = saturate(h.z);
+            float vDotH = saturate(dot(v, h));
+            float g = GeometrySchlickGgx(nDotV, roughness) * GeometrySchlickGgx(nDotL, roughness);
+            float visibility = g * vDotH / max(nDotH * nDotV, 0.0001);
+            float fresnel = pow(1.0 - vDotH, 5.0);
+            result += float2((1.0 - fresnel) * visibility, fresnel * visibility);
+        }
+    }
+    target[id.xy] = result / float(cubemapResource.sampleCount);
+}
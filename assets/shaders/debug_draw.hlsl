@@ -0,0 +1,41 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float4 color: COLOR;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+    float4 sun_direction;
+    float4 sun_color;
+    float4 ambient_color;
+};
+
+struct LineVertex {
+    float3 position;
+    float4 color;
+};
+
+struct DebugDrawResources {
+    uint vertexBufferIndex;
+    uint viewBufferIndex;
+};
+
+ConstantBuffer<DebugDrawResources> drawResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    StructuredBuffer<LineVertex> vertexBuffer = ResourceDescriptorHeap[drawResource.vertexBufferIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[drawResource.viewBufferIndex];
+    LineVertex vertex = vertexBuffer[vertexID];
+
+    float4 pos = mul(viewBuffer.view, float4(vertex.position, 1.0));
+    PSInput result;
+    result.position = mul(viewBuffer.projection, pos);
+    result.color = vertex.color;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    return input.color;
+}
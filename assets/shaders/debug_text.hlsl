@@ -0,0 +1,64 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+    float4 color: COLOR;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+    float4 sun_direction;
+    float4 sun_color;
+    float4 ambient_color;
+};
+
+struct TextGlyph {
+    float3 position;
+    uint color;
+    float2 offset;
+    float2 uvMin;
+    float2 uvMax;
+};
+
+struct DebugTextResources {
+    uint glyphBufferIndex;
+    uint fontTextureIndex;
+    uint samplerIndex;
+    uint viewBufferIndex;
+    float2 screenSize;
+    float2 glyphSize;
+    float scale;
+};
+
+ConstantBuffer<DebugTextResources> textResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID, uint instanceID: SV_InstanceID) {
+    static const float2 corners[6] = {
+        float2(0.0, 0.0), float2(1.0, 0.0), float2(0.0, 1.0),
+        float2(0.0, 1.0), float2(1.0, 0.0), float2(1.0, 1.0),
+    };
+    float2 corner = corners[vertexID];
+    StructuredBuffer<TextGlyph> glyphBuffer = ResourceDescriptorHeap[textResource.glyphBufferIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[textResource.viewBufferIndex];
+    TextGlyph glyph = glyphBuffer[instanceID];
+
+    float4 pos = mul(viewBuffer.view, float4(glyph.position, 1.0));
+    pos = mul(viewBuffer.projection, pos);
+    // Offsets are in pixels with y pointing down, scaled by w so they survive the perspective divide.
+    float2 pixels = (glyph.offset + corner * textResource.glyphSize) * textResource.scale;
+    pos.xy += pixels / textResource.screenSize * float2(2.0, -2.0) * pos.w;
+
+    PSInput result;
+    // Anchors behind the camera are pushed outside the clip volume.
+    result.position = pos.w > 0.0 ? pos : float4(0.0, 0.0, 0.0, -1.0);
+    result.uv = lerp(glyph.uvMin, glyph.uvMax, corner);
+    result.color = float4((glyph.color >> uint4(0, 8, 16, 24)) & 0xFF) / 255.0;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> font = ResourceDescriptorHeap[textResource.fontTextureIndex];
+    SamplerState fontSampler = SamplerDescriptorHeap[textResource.samplerIndex];
+    return input.color * font.Sample(fontSampler, input.uv);
+}
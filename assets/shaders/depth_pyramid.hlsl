@@ -0,0 +1,48 @@
+struct PSInput {
+    float4 position: SV_Position;
+};
+
+struct DepthPyramidResources {
+    uint sourceIndex;
+    uint mode;
+};
+
+ConstantBuffer<DepthPyramidResources> pyramidResource: register(b0);
+
+static const uint MODE_COPY = 0;
+static const uint MODE_COPY_MULTISAMPLED = 1;
+static const uint MODE_DOWNSAMPLE = 2;
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    return result;
+}
+
+// The first level copies the depth buffer, every following level keeps the closest depth of each
+// 2x2 block. With reverse-Z the closest depth is the largest value.
+float PSMain(PSInput input): SV_Target {
+    int2 pixel = int2(input.position.xy);
+    if (pyramidResource.mode == MODE_COPY_MULTISAMPLED) {
+        Texture2DMS<float> depth = ResourceDescriptorHeap[pyramidResource.sourceIndex];
+        return depth.Load(pixel, 0);
+    }
+
+    Texture2D<float> source = ResourceDescriptorHeap[pyramidResource.sourceIndex];
+    if (pyramidResource.mode == MODE_COPY) {
+        return source.Load(int3(pixel, 0));
+    }
+
+    uint width;
+    uint height;
+    source.GetDimensions(width, height);
+    int2 maxPixel = int2(width, height) - 1;
+    int2 base = pixel * 2;
+    float d0 = source.Load(int3(min(base, maxPixel), 0));
+    float d1 = source.Load(int3(min(base + int2(1, 0), maxPixel), 0));
+    float d2 = source.Load(int3(min(base + int2(0, 1), maxPixel), 0));
+    float d3 = source.Load(int3(min(base + int2(1, 1), maxPixel), 0));
+    return max(max(d0, d1), max(d2, d3));
+}
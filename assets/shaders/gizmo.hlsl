@@ -0,0 +1,44 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float4 color: COLOR;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+};
+
+struct GizmoResources {
+    uint viewBufferIndex;
+};
+
+ConstantBuffer<GizmoResources> gizmoResource: register(b0);
+
+static const float AXIS_SCALE = 0.8;
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[gizmoResource.viewBufferIndex];
+
+    uint axis = vertexID / 4;
+    bool negative = (vertexID / 2) % 2 == 1;
+    bool tip = vertexID % 2 == 1;
+
+    float3 direction = float3(axis == 0, axis == 1, axis == 2);
+    if (negative) {
+        direction = -direction;
+    }
+    float3 pos = tip ? direction : float3(0.0, 0.0, 0.0);
+    pos = mul((float3x3)viewBuffer.view, pos) * AXIS_SCALE;
+
+    float3 color = float3(axis == 0, axis == 1, axis == 2);
+
+    PSInput result;
+    result.position = float4(pos.xy, 0.5 - pos.z * 0.5, 1.0);
+    result.color = float4(negative ? color * 0.35 : color, 1.0);
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    return input.color;
+}
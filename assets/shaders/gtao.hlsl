@@ -0,0 +1,193 @@
+#define PI 3.1415926535897932384626433832795
+#define HALF_PI 1.5707963267948966192313216916398
+#define FALLOFF 0.6
+
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+};
+
+struct GtaoResources {
+    uint depthPyramidIndex;
+    uint viewBufferIndex;
+    uint samplerIndex;
+    uint sliceCount;
+    uint stepCount;
+    uint mipCount;
+    float radius;
+    uint baseMip;
+    float intensity;
+};
+
+ConstantBuffer<GtaoResources> gtaoResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+// Clip space w of a view space depth: z for perspective projections, 1 for orthographic ones.
+float ClipW(float4x4 projection, float z) {
+    return projection[3][2] * z + projection[3][3];
+}
+
+float3 ViewPosition(float4x4 projection, float2 uv, float depth) {
+    float z = (projection[2][3] - depth * projection[3][3]) / (depth * projection[3][2] - projection[2][2]);
+    float w = ClipW(projection, z);
+    float2 ndc = float2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    return float3(ndc.x * w / projection[0][0], ndc.y * w / projection[1][1], z);
+}
+
+float InterleavedGradientNoise(float2 pixel) {
+    return frac(52.9829189 * frac(dot(pixel, float2(0.06711056, 0.00583715))));
+}
+
+float3x3 RotFromToMatrix(float3 from, float3 to) {
+    float e = dot(from, to);
+    float3 v = cross(from, to);
+    float h = 1.0 / (1.0 + e);
+    float hvx = h * v.x;
+    float hvz = h * v.z;
+    float hvxy = hvx * v.y;
+    float hvxz = hvx * v.z;
+    float hvyz = hvz * v.y;
+
+    float3x3 m;
+    m[0][0] = e + hvx * v.x;
+    m[0][1] = hvxy - v.z;
+    m[0][2] = hvxz + v.y;
+    m[1][0] = hvxy + v.z;
+    m[1][1] = e + h * v.y * v.y;
+    m[1][2] = hvyz - v.x;
+    m[2][0] = hvxz - v.y;
+    m[2][1] = hvyz + v.x;
+    m[2][2] = e + hvz * v.z;
+    return m;
+}
+
+float3 LoadViewPosition(Texture2D<float> depth, float4x4 projection, int2 pixel, int2 size, uint mip) {
+    pixel = clamp(pixel, int2(0, 0), size - 1);
+    return ViewPosition(projection, (pixel + 0.5) / float2(size), depth.Load(int3(pixel, mip)));
+}
+
+// Picks the neighbour pair with the smaller depth step on each axis so normals stay sharp at
+// silhouettes.
+float3 ReconstructNormal(Texture2D<float> depth, float4x4 projection, int2 pixel, int2 size, uint mip, float3 center) {
+    float3 left = LoadViewPosition(depth, projection, pixel + int2(-1, 0), size, mip);
+    float3 right = LoadViewPosition(depth, projection, pixel + int2(1, 0), size, mip);
+    float3 top = LoadViewPosition(depth, projection, pixel + int2(0, -1), size, mip);
+    float3 bottom = LoadViewPosition(depth, projection, pixel + int2(0, 1), size, mip);
+
+    float3 dx = abs(right.z - center.z) < abs(center.z - left.z) ? right - center : center - left;
+    float3 dy = abs(bottom.z - center.z) < abs(center.z - top.z) ? bottom - center : center - top;
+    float3 normal = normalize(cross(dy, dx));
+    return dot(normal, -center) < 0.0 ? -normal : normal;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float> depthPyramid = ResourceDescriptorHeap[gtaoResource.depthPyramidIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[gtaoResource.viewBufferIndex];
+    SamplerState pointSampler = SamplerDescriptorHeap[gtaoResource.samplerIndex];
+    float4x4 projection = viewBuffer.projection;
+
+    uint width;
+    uint height;
+    uint levels;
+    // At half resolution the AO target matches the second pyramid level, so every lookup starts there.
+    uint baseMip = gtaoResource.baseMip;
+    depthPyramid.GetDimensions(baseMip, width, height, levels);
+    float2 size = float2(width, height);
+    int2 pixel = int2(input.position.xy);
+
+    float depth = depthPyramid.Load(int3(pixel, baseMip));
+    if (depth <= 0.0) {
+        return float4(0.0, 0.0, -1.0, 1.0);
+    }
+    float3 position = ViewPosition(projection, input.uv, depth);
+    float3 viewVec = normalize(float3(0.0, 0.0, -projection[3][3]) - position * projection[3][2]);
+    float3 normal = ReconstructNormal(depthPyramid, projection, pixel, int2(width, height), baseMip, position);
+
+    float2 radiusUv = gtaoResource.radius * float2(projection[0][0], projection[1][1]) * 0.5 / ClipW(projection, position.z);
+    if (max(radiusUv.x * size.x, radiusUv.y * size.y) < 1.0) {
+        return float4(normal, 1.0);
+    }
+
+    float falloffRange = FALLOFF * gtaoResource.radius;
+    float falloffMul = -1.0 / falloffRange;
+    float falloffAdd = (gtaoResource.radius - falloffRange) / falloffRange + 1.0;
+
+    float noiseSlice = InterleavedGradientNoise(input.position.xy);
+    float noiseStep = InterleavedGradientNoise(input.position.xy + float2(5.588238, 5.588238));
+
+    float visibility = 0.0;
+    float3 bentNormal = 0.0;
+    float3x3 rotation = RotFromToMatrix(float3(0.0, 0.0, -1.0), viewVec);
+
+    for (uint slice = 0; slice < gtaoResource.sliceCount; slice++) {
+        float phi = (slice + noiseSlice) * PI / gtaoResource.sliceCount;
+        float3 direction = float3(cos(phi), sin(phi), 0.0);
+        float2 omega = float2(direction.x, -direction.y) * radiusUv;
+
+        float3 orthoDirection = direction - dot(direction, viewVec) * viewVec;
+        float3 axis = normalize(cross(direction, viewVec));
+        float3 projectedNormal = normal - axis * dot(normal, axis);
+        float projectedNormalLength = length(projectedNormal);
+        float signNormal = sign(dot(orthoDirection, projectedNormal));
+        float cosNormal = saturate(dot(projectedNormal, viewVec) / projectedNormalLength);
+        float n = signNormal * acos(cosNormal);
+
+        float lowHorizonCos0 = cos(n + HALF_PI);
+        float lowHorizonCos1 = cos(n - HALF_PI);
+        float horizonCos0 = lowHorizonCos0;
+        float horizonCos1 = lowHorizonCos1;
+
+        for (uint step = 0; step < gtaoResource.stepCount; step++) {
+            float s = (step + noiseStep) / gtaoResource.stepCount;
+            s *= s;
+            float2 offset = omega * s;
+            float mip = clamp(baseMip + log2(length(offset * size)) - 3.3, baseMip, gtaoResource.mipCount - 1.0);
+
+            float2 uv0 = input.uv + offset;
+            float2 uv1 = input.uv - offset;
+            float3 delta0 = ViewPosition(projection, uv0, depthPyramid.SampleLevel(pointSampler, uv0, mip)) - position;
+            float3 delta1 = ViewPosition(projection, uv1, depthPyramid.SampleLevel(pointSampler, uv1, mip)) - position;
+            float distance0 = length(delta0);
+            float distance1 = length(delta1);
+            float shc0 = dot(delta0 / distance0, viewVec);
+            float shc1 = dot(delta1 / distance1, viewVec);
+            shc0 = lerp(lowHorizonCos0, shc0, saturate(distance0 * falloffMul + falloffAdd));
+            shc1 = lerp(lowHorizonCos1, shc1, saturate(distance1 * falloffMul + falloffAdd));
+            horizonCos0 = max(horizonCos0, shc0);
+            horizonCos1 = max(horizonCos1, shc1);
+        }
+
+        float h0 = -acos(clamp(horizonCos1, -1.0, 1.0));
+        float h1 = acos(clamp(horizonCos0, -1.0, 1.0));
+        h0 = n + clamp(h0 - n, -HALF_PI, HALF_PI);
+        h1 = n + clamp(h1 - n, -HALF_PI, HALF_PI);
+
+        float iarc0 = (cosNormal + 2.0 * h0 * sin(n) - cos(2.0 * h0 - n)) / 4.0;
+        float iarc1 = (cosNormal + 2.0 * h1 * sin(n) - cos(2.0 * h1 - n)) / 4.0;
+        visibility += projectedNormalLength * (iarc0 + iarc1);
+
+        float t0 = (6.0 * sin(h0 - n) - sin(3.0 * h0 - n) + 6.0 * sin(h1 - n) - sin(3.0 * h1 - n)
+            + 16.0 * sin(n) - 3.0 * (sin(h0 + n) + sin(h1 + n))) / 12.0;
+        float t1 = (-cos(3.0 * h0 - n) - cos(3.0 * h1 - n) + 8.0 * cos(n)
+            - 3.0 * (cos(h0 + n) + cos(h1 + n))) / 12.0;
+        float3 localBentNormal = float3(direction.x * t0, direction.y * t0, -t1);
+        bentNormal += mul(rotation, localBentNormal) * projectedNormalLength;
+    }
+
+    visibility = pow(saturate(visibility / gtaoResource.sliceCount), gtaoResource.intensity);
+    return float4(normalize(bentNormal), visibility);
+}
@@ -0,0 +1,29 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float4 color: COLOR;
+};
+
+struct MarkerResources {
+    float2 rectMin;
+    float2 rectMax;
+    uint color;
+};
+
+ConstantBuffer<MarkerResources> markerResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    static const float2 corners[6] = {
+        float2(0.0, 0.0), float2(1.0, 0.0), float2(0.0, 1.0),
+        float2(0.0, 1.0), float2(1.0, 0.0), float2(1.0, 1.0),
+    };
+    float2 corner = corners[vertexID];
+
+    PSInput result;
+    result.position = float4(lerp(markerResource.rectMin, markerResource.rectMax, corner), 0.0, 1.0);
+    result.color = float4((markerResource.color >> uint4(0, 8, 16, 24)) & 0xFF) / 255.0;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    return input.color;
+}
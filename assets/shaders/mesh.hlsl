@@ -1,4 +1,5 @@
 #define PI 3.1415926535897932384626433832795
+#define INVALID_INDEX 0xFFFFFFFF
 
 struct PSInput {
     float4 position: SV_Position;
@@ -15,45 +16,158 @@ struct Vertex {
     float2 pad;
 };
 
+struct MorphDelta {
+    float4 position;
+    float4 normal;
+};
+
 struct Transform {
     float4x4 model;
 };
 
+struct EnvironmentUniform {
+    uint irradianceIndex;
+    uint prefilteredIndex;
+    uint brdfLutIndex;
+    uint samplerIndex;
+    float maxMip;
+    float intensity;
+    float2 pad;
+};
+
 struct ViewUniform {
     float4x4 projection;
     float4x4 view;
     float4 view_position;
+    float4 sun_direction;
+    float4 sun_color;
+    float4 ambient_color;
+    EnvironmentUniform environment;
+};
+
+// The per-cascade floats are packed into float4s, one component per cascade.
+struct ShadowUniform {
+    float4x4 viewProjections[4];
+    float4 splitDepths;
+    float4 texelSizes;
+    uint cascadeCount;
+    uint shadowMapIndex;
+    uint samplerIndex;
+    uint pad;
+};
+
+// Matches PackedMaterialUniform: unorm8 factors with the first in the lowest byte, and half floats
+// two to a uint with the first in the low bits.
+struct PackedMaterial {
+    uint base_color;
+    uint2 emissive_normal_scale;
+    uint surface;
+    uint base_color_texture;
+    uint base_color_sampler;
+    uint metallic_roughness_texture;
+    uint metallic_roughness_sampler;
+    uint normal_texture;
+    uint normal_sampler;
+    uint occlusion_texture;
+    uint occlusion_sampler;
+    uint emissive_texture;
+    uint emissive_sampler;
+    uint2 pad;
 };
 
 struct Material {
     float4 base_color_factors;
+    float3 emissive_factors;
+    float normal_scale;
     float perceptual_roughness;
     float metallic;
     float reflectance;
-    float pad;
+    float occlusion_strength;
+    uint base_color_texture;
+    uint base_color_sampler;
+    uint metallic_roughness_texture;
+    uint metallic_roughness_sampler;
+    uint normal_texture;
+    uint normal_sampler;
+    uint occlusion_texture;
+    uint occlusion_sampler;
+    uint emissive_texture;
+    uint emissive_sampler;
 };
 
 struct RenderResources {
     uint vertexBufferIndex;
     uint transformBufferIndex;
-    uint transformOffset;
+    uint instanceBufferIndex;
+    uint instanceOffset;
     uint viewBufferIndex;
     uint materialBufferIndex;
     uint materialOffset;
+    uint aoTextureIndex;
+    uint morphTargetBufferIndex;
+    uint morphWeightBufferIndex;
+    uint morphWeightOffset;
+    uint shadowBufferIndex;
 };
 
 ConstantBuffer<RenderResources> renderResource: register(b0);
 
-PSInput VSMain(uint vertexID: SV_VertexID) {
+float4 UnpackUnorm8(uint packed) {
+    return float4((packed >> uint4(0, 8, 16, 24)) & 0xFF) / 255.0;
+}
+
+Material UnpackMaterial(PackedMaterial packed) {
+    float4 surface = UnpackUnorm8(packed.surface);
+
+    Material material;
+    material.base_color_factors = UnpackUnorm8(packed.base_color);
+    material.emissive_factors = float3(f16tof32(packed.emissive_normal_scale.x), f16tof32(packed.emissive_normal_scale.x >> 16), f16tof32(packed.emissive_normal_scale.y));
+    material.normal_scale = f16tof32(packed.emissive_normal_scale.y >> 16);
+    material.perceptual_roughness = surface.x;
+    material.metallic = surface.y;
+    material.reflectance = surface.z;
+    material.occlusion_strength = surface.w;
+    material.base_color_texture = packed.base_color_texture;
+    material.base_color_sampler = packed.base_color_sampler;
+    material.metallic_roughness_texture = packed.metallic_roughness_texture;
+    material.metallic_roughness_sampler = packed.metallic_roughness_sampler;
+    material.normal_texture = packed.normal_texture;
+    material.normal_sampler = packed.normal_sampler;
+    material.occlusion_texture = packed.occlusion_texture;
+    material.occlusion_sampler = packed.occlusion_sampler;
+    material.emissive_texture = packed.emissive_texture;
+    material.emissive_sampler = packed.emissive_sampler;
+    return material;
+}
+
+PSInput VSMain(uint vertexID: SV_VertexID, uint instanceID: SV_InstanceID) {
     StructuredBuffer<Vertex> vertexBuffer = ResourceDescriptorHeap[renderResource.vertexBufferIndex];
     StructuredBuffer<Transform> transformBuffer = ResourceDescriptorHeap[renderResource.transformBufferIndex];
+    StructuredBuffer<uint> instanceBuffer = ResourceDescriptorHeap[renderResource.instanceBufferIndex];
     ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[renderResource.viewBufferIndex];
 
-    float4x4 model = transformBuffer[renderResource.transformOffset].model;
+    uint transformIndex = instanceBuffer[renderResource.instanceOffset + instanceID];
+    float4x4 model = transformBuffer[transformIndex].model;
     float4x4 view = viewBuffer.view;
     float4x4 projection = viewBuffer.projection;
 
-    float4 pos = float4(vertexBuffer[vertexID].position.xyz, 1.0f);
+    float3 position = vertexBuffer[vertexID].position.xyz;
+    float3 normal = vertexBuffer[vertexID].normal.xyz;
+    if (renderResource.morphTargetBufferIndex != INVALID_INDEX) {
+        StructuredBuffer<MorphDelta> morphTargets = ResourceDescriptorHeap[renderResource.morphTargetBufferIndex];
+        StructuredBuffer<float> morphWeights = ResourceDescriptorHeap[renderResource.morphWeightBufferIndex];
+        uint vertexCount, deltaCount, stride;
+        vertexBuffer.GetDimensions(vertexCount, stride);
+        morphTargets.GetDimensions(deltaCount, stride);
+        for (uint target = 0; target < deltaCount / vertexCount; target++) {
+            float weight = morphWeights[renderResource.morphWeightOffset + target];
+            MorphDelta delta = morphTargets[target * vertexCount + vertexID];
+            position += weight * delta.position.xyz;
+            normal += weight * delta.normal.xyz;
+        }
+    }
+
+    float4 pos = float4(position, 1.0f);
     pos = mul(model, pos);
     float4 frag_pos = pos;
     pos = mul(view, pos);
@@ -61,12 +175,72 @@ PSInput VSMain(uint vertexID: SV_VertexID) {
 
     PSInput result;
     result.position = pos;
-    result.normal = vertexBuffer[vertexID].normal.xyz;
+    result.normal = mul((float3x3)model, normal);
     result.uv = vertexBuffer[vertexID].uv;
     result.frag_pos = frag_pos;
     return result;
 }
 
+float4 SampleTexture(uint textureIndex, uint samplerIndex, float2 uv, float4 fallback) {
+    if (textureIndex == INVALID_INDEX) {
+        return fallback;
+    }
+    Texture2D<float4> texture = ResourceDescriptorHeap[textureIndex];
+    SamplerState textureSampler = SamplerDescriptorHeap[samplerIndex];
+    return texture.Sample(textureSampler, uv);
+}
+
+// Builds the tangent frame from screen-space derivatives, so meshes don't need tangents.
+float3 PerturbNormal(float3 n, float3 position, float2 uv, float3 mapped) {
+    float3 dp1 = ddx(position);
+    float3 dp2 = ddy(position);
+    float2 duv1 = ddx(uv);
+    float2 duv2 = ddy(uv);
+
+    float3 dp2perp = cross(dp2, n);
+    float3 dp1perp = cross(n, dp1);
+    float3 t = dp2perp * duv1.x + dp1perp * duv2.x;
+    float3 b = dp2perp * duv1.y + dp1perp * duv2.y;
+    float invmax = rsqrt(max(max(dot(t, t), dot(b, b)), 1e-12));
+    float3x3 tbn = float3x3(t * invmax, b * invmax, n);
+    return normalize(mul(mapped, tbn));
+}
+
+// How much of the sun reaches the surface, from four comparison taps in the first cascade that
+// covers it. Surfaces past the last cascade are fully lit.
+float SunVisibility(float3 position, float3 n, float NoL, float viewDepth) {
+    if (renderResource.shadowBufferIndex == INVALID_INDEX) {
+        return 1.0;
+    }
+    ConstantBuffer<ShadowUniform> shadows = ResourceDescriptorHeap[renderResource.shadowBufferIndex];
+    uint cascade = 0;
+    while (cascade < shadows.cascadeCount && viewDepth > shadows.splitDepths[cascade]) {
+        cascade++;
+    }
+    if (cascade == shadows.cascadeCount) {
+        return 1.0;
+    }
+
+    // Offsetting along the normal, more so at grazing angles, keeps surfaces from shadowing
+    // themselves.
+    float3 offset = n * shadows.texelSizes[cascade] * (2.0 - NoL);
+    float4 shadowPosition = mul(shadows.viewProjections[cascade], float4(position + offset, 1.0));
+    float2 uv = shadowPosition.xy * float2(0.5, -0.5) + 0.5;
+
+    Texture2DArray<float> shadowMap = ResourceDescriptorHeap[shadows.shadowMapIndex];
+    SamplerComparisonState shadowSampler = SamplerDescriptorHeap[shadows.samplerIndex];
+    uint width, height, layers;
+    shadowMap.GetDimensions(width, height, layers);
+    float2 texel = 1.0 / float2(width, height);
+    float visibility = 0.0;
+    [unroll]
+    for (uint i = 0; i < 4; i++) {
+        float2 tap = (float2(i & 1, i >> 1) - 0.5) * texel;
+        visibility += shadowMap.SampleCmpLevelZero(shadowSampler, float3(uv + tap, cascade), shadowPosition.z);
+    }
+    return visibility * 0.25;
+}
+
 float D_GGX(float NoH, float roughness) {
     float a = roughness * roughness;
     float a2 = a * a;
@@ -96,7 +270,35 @@ float Fd_Lambert() {
     return 1.0 / PI;
 }
 
-float3 BRDF(float3 n, float3 v, float3 l, Material material) {
+// Approximates the solid angle shared by the visibility cone around the bent normal and the
+// specular lobe cone around the reflection vector, as in Filament.
+float SphericalCapsIntersection(float cosCap1, float cosCap2, float cosDistance) {
+    float r1 = acos(cosCap1);
+    float r2 = acos(cosCap2);
+    float d = acos(cosDistance);
+
+    if (min(r1, r2) <= max(r1, r2) - d) {
+        return 1.0 - max(cosCap1, cosCap2);
+    } else if (r1 + r2 <= d) {
+        return 0.0;
+    }
+
+    float delta = abs(r1 - r2);
+    float x = 1.0 - saturate((d - delta) / max(r1 + r2 - delta, 1e-4));
+    float area = x * x * (-2.0 * x + 3.0);
+    return area * (1.0 - max(cosCap1, cosCap2));
+}
+
+float SpecularOcclusion(float3 bentNormal, float visibility, float3 r, float roughness) {
+    float a = roughness * roughness;
+    float cosAv = sqrt(1.0 - visibility);
+    float cosAs = exp2(-3.321928 * a * a);
+    float cosB = dot(bentNormal, r);
+    float occlusion = SphericalCapsIntersection(cosAv, cosAs, cosB) / (1.0 - cosAs);
+    return lerp(1.0, occlusion, smoothstep(0.01, 0.09, a));
+}
+
+float3 BRDF(float3 n, float3 v, float3 l, float3 baseColor, float metallic, float roughness, float reflectance) {
     float3 h = normalize(v + l);
 
     float NoV = abs(dot(n, v)) + 1e-5;
@@ -104,9 +306,8 @@ float3 BRDF(float3 n, float3 v, float3 l, Material material) {
     float NoH = clamp(dot(n, h), 0.0, 1.0);
     float HoV = clamp(dot(h, v), 0.0, 1.0);
 
-    float roughness = material.perceptual_roughness;
-    float3 diffuseColor = (1.0 - material.metallic)  * material.base_color_factors.rgb;
-    float3 f0 = 0.16 * material.reflectance * material.reflectance * (1.0 - material.metallic) + material.base_color_factors.rgb * material.metallic;
+    float3 diffuseColor = (1.0 - metallic) * baseColor;
+    float3 f0 = 0.16 * reflectance * reflectance * (1.0 - metallic) + baseColor * metallic;
 
     float NDF = D_GGX(NoH, roughness);
     float3 G = G_Smith(NoV, NoL, roughness);
@@ -118,16 +319,67 @@ float3 BRDF(float3 n, float3 v, float3 l, Material material) {
 
     float3 diffuse = diffuseColor * Fd_Lambert();
 
-    return material.base_color_factors.rgb * (diffuse + specular);
+    return baseColor * (diffuse + specular);
 }
 
 float4 PSMain(PSInput input): SV_Target {
-    StructuredBuffer<Material> materialBuffer = ResourceDescriptorHeap[renderResource.materialBufferIndex];
+    StructuredBuffer<PackedMaterial> materialBuffer = ResourceDescriptorHeap[renderResource.materialBufferIndex];
     ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[renderResource.viewBufferIndex];
 
-    Material material = materialBuffer[renderResource.materialOffset];
+    Material material = UnpackMaterial(materialBuffer[renderResource.materialOffset]);
+
+    float4 baseColor = material.base_color_factors * SampleTexture(material.base_color_texture, material.base_color_sampler, input.uv, float4(1.0, 1.0, 1.0, 1.0));
+    float4 metallicRoughness = SampleTexture(material.metallic_roughness_texture, material.metallic_roughness_sampler, input.uv, float4(1.0, 1.0, 1.0, 1.0));
+    float roughness = material.perceptual_roughness * metallicRoughness.g;
+    float metallic = material.metallic * metallicRoughness.b;
+    float occlusion = SampleTexture(material.occlusion_texture, material.occlusion_sampler, input.uv, float4(1.0, 1.0, 1.0, 1.0)).r;
+    occlusion = lerp(1.0, occlusion, material.occlusion_strength);
+    float3 emissive = material.emissive_factors * SampleTexture(material.emissive_texture, material.emissive_sampler, input.uv, float4(1.0, 1.0, 1.0, 1.0)).rgb;
+
+    float3 n = normalize(input.normal);
+    if (material.normal_texture != INVALID_INDEX) {
+        float3 mapped = SampleTexture(material.normal_texture, material.normal_sampler, input.uv, float4(0.5, 0.5, 1.0, 1.0)).xyz * 2.0 - 1.0;
+        mapped.xy *= material.normal_scale;
+        n = PerturbNormal(n, input.frag_pos.xyz, input.uv, normalize(mapped));
+    }
+    // w is 0 for orthographic views, where view_position holds the direction towards the camera.
+    float3 v = normalize(viewBuffer.view_position.xyz - input.frag_pos.xyz * viewBuffer.view_position.w);
+    float3 l = viewBuffer.sun_direction.xyz;
+    float NoL = clamp(dot(n, l), 0.0, 1.0);
+
+    float viewDepth = mul(viewBuffer.view, float4(input.frag_pos.xyz, 1.0)).z;
+    float3 sun = viewBuffer.sun_color.rgb * viewBuffer.sun_direction.w * SunVisibility(input.frag_pos.xyz, normalize(input.normal), NoL, viewDepth);
+    float visibility = 1.0;
+    float3 bentNormal = n;
+    if (renderResource.aoTextureIndex != INVALID_INDEX) {
+        Texture2D<float4> aoTexture = ResourceDescriptorHeap[renderResource.aoTextureIndex];
+        float4 ao = aoTexture.Load(int3(input.position.xy, 0));
+        visibility = ao.w;
+        bentNormal = normalize(mul(ao.xyz, (float3x3)viewBuffer.view));
+    }
 
-    float3 result = BRDF(input.normal, normalize(viewBuffer.view_position.xyz - input.frag_pos.xyz), float3(-2.0, 2.0, -2.0), material);
+    float NoV = abs(dot(n, v)) + 1e-5;
+    float3 diffuseColor = (1.0 - metallic) * baseColor.rgb;
+    float3 f0 = 0.16 * material.reflectance * material.reflectance * (1.0 - metallic) + baseColor.rgb * metallic;
+    float specularOcclusion = SpecularOcclusion(bentNormal, visibility, reflect(-v, n), roughness);
 
-    return float4(result, 1.0);
-}
\ No newline at end of file
+    float3 ambient = viewBuffer.ambient_color.rgb * viewBuffer.ambient_color.w;
+    float3 ambientDiffuse = ambient;
+    float3 ambientSpecular = ambient * F_Schlick(NoV, f0);
+    EnvironmentUniform environment = viewBuffer.environment;
+    if (environment.irradianceIndex != INVALID_INDEX) {
+        TextureCube<float4> irradianceMap = ResourceDescriptorHeap[environment.irradianceIndex];
+        TextureCube<float4> prefilteredMap = ResourceDescriptorHeap[environment.prefilteredIndex];
+        Texture2D<float2> brdfLut = ResourceDescriptorHeap[environment.brdfLutIndex];
+        SamplerState environmentSampler = SamplerDescriptorHeap[environment.samplerIndex];
+        // Split sum: prefiltered radiance along the reflection times the tabulated BRDF integral.
+        float2 brdf = brdfLut.SampleLevel(environmentSampler, float2(NoV, roughness), 0.0);
+        float3 radiance = prefilteredMap.SampleLevel(environmentSampler, reflect(-v, n), roughness * environment.maxMip).rgb;
+        ambientDiffuse = irradianceMap.SampleLevel(environmentSampler, n, 0.0).rgb * environment.intensity;
+        ambientSpecular = radiance * (f0 * brdf.x + brdf.y) * environment.intensity;
+    }
+    float3 ambientLight = (diffuseColor * ambientDiffuse * visibility + ambientSpecular * specularOcclusion) * occlusion;
+    float3 result = BRDF(n, v, l, baseColor.rgb, metallic, roughness, material.reflectance) * sun * NoL + ambientLight + emissive;
+
+    return float4(result, baseColor.a);
+}
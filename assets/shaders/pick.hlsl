@@ -0,0 +1,79 @@
+#define INVALID_INDEX 0xFFFFFFFF
+
+struct PSInput {
+    float4 position: SV_Position;
+    nointerpolation uint id: ID;
+};
+
+struct Vertex {
+    float4 position;
+    float4 normal;
+    float4 color;
+    float2 uv;
+    float2 pad;
+};
+
+struct MorphDelta {
+    float4 position;
+    float4 normal;
+};
+
+struct Transform {
+    float4x4 model;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+};
+
+// Same layout as RenderResources in mesh.hlsl; only the vertex inputs are read.
+struct RenderResources {
+    uint vertexBufferIndex;
+    uint transformBufferIndex;
+    uint instanceBufferIndex;
+    uint instanceOffset;
+    uint viewBufferIndex;
+    uint materialBufferIndex;
+    uint materialOffset;
+    uint aoTextureIndex;
+    uint morphTargetBufferIndex;
+    uint morphWeightBufferIndex;
+    uint morphWeightOffset;
+};
+
+ConstantBuffer<RenderResources> renderResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID, uint instanceID: SV_InstanceID) {
+    StructuredBuffer<Vertex> vertexBuffer = ResourceDescriptorHeap[renderResource.vertexBufferIndex];
+    StructuredBuffer<Transform> transformBuffer = ResourceDescriptorHeap[renderResource.transformBufferIndex];
+    StructuredBuffer<uint> instanceBuffer = ResourceDescriptorHeap[renderResource.instanceBufferIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[renderResource.viewBufferIndex];
+
+    uint transformIndex = instanceBuffer[renderResource.instanceOffset + instanceID];
+    float3 position = vertexBuffer[vertexID].position.xyz;
+    if (renderResource.morphTargetBufferIndex != INVALID_INDEX) {
+        StructuredBuffer<MorphDelta> morphTargets = ResourceDescriptorHeap[renderResource.morphTargetBufferIndex];
+        StructuredBuffer<float> morphWeights = ResourceDescriptorHeap[renderResource.morphWeightBufferIndex];
+        uint vertexCount, deltaCount, stride;
+        vertexBuffer.GetDimensions(vertexCount, stride);
+        morphTargets.GetDimensions(deltaCount, stride);
+        for (uint target = 0; target < deltaCount / vertexCount; target++) {
+            float weight = morphWeights[renderResource.morphWeightOffset + target];
+            position += weight * morphTargets[target * vertexCount + vertexID].position.xyz;
+        }
+    }
+
+    float4 pos = mul(transformBuffer[transformIndex].model, float4(position, 1.0));
+    pos = mul(viewBuffer.view, pos);
+
+    PSInput result;
+    result.position = mul(viewBuffer.projection, pos);
+    result.id = transformIndex;
+    return result;
+}
+
+// Zero is left for the cleared background, so slots are written one based.
+uint PSMain(PSInput input): SV_Target {
+    return input.id + 1;
+}
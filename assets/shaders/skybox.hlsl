@@ -0,0 +1,44 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 ndc: TEXCOORD0;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+    float4 sun_direction;
+    float4 sun_color;
+    float4 ambient_color;
+};
+
+struct SkyboxResources {
+    uint cubemapIndex;
+    uint samplerIndex;
+    uint viewBufferIndex;
+};
+
+ConstantBuffer<SkyboxResources> skyboxResource: register(b0);
+
+// Depth is reversed, so the far plane is at 0.
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+    PSInput result;
+    result.ndc = uv * float2(2.0, -2.0) + float2(-1.0, 1.0);
+    result.position = float4(result.ndc, 0.0, 1.0);
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    TextureCube<float4> environment = ResourceDescriptorHeap[skyboxResource.cubemapIndex];
+    SamplerState environmentSampler = SamplerDescriptorHeap[skyboxResource.samplerIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[skyboxResource.viewBufferIndex];
+
+    // Views look down +z. Orthographic views see the same direction everywhere, marked by w = 0.
+    float3 viewDirection = float3(0.0, 0.0, 1.0);
+    if (viewBuffer.view_position.w != 0.0) {
+        viewDirection = float3(input.ndc.x / viewBuffer.projection[0][0], input.ndc.y / viewBuffer.projection[1][1], 1.0);
+    }
+    float3 direction = normalize(mul(viewDirection, (float3x3)viewBuffer.view));
+    return float4(environment.SampleLevel(environmentSampler, direction, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,122 @@
+#define TWO_PI 6.283185307179586476925286766559
+#define GOLDEN_ANGLE 2.3999632297286533222315555066336
+// Cosine below which a sample doesn't occlude, so flat surfaces don't darken themselves.
+#define BIAS 0.1
+
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+};
+
+struct ViewUniform {
+    float4x4 projection;
+    float4x4 view;
+    float4 view_position;
+};
+
+struct SsaoResources {
+    uint depthPyramidIndex;
+    uint viewBufferIndex;
+    uint samplerIndex;
+    uint sampleCount;
+    float radius;
+    uint baseMip;
+    float intensity;
+};
+
+ConstantBuffer<SsaoResources> ssaoResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+// Clip space w of a view space depth: z for perspective projections, 1 for orthographic ones.
+float ClipW(float4x4 projection, float z) {
+    return projection[3][2] * z + projection[3][3];
+}
+
+float3 ViewPosition(float4x4 projection, float2 uv, float depth) {
+    float z = (projection[2][3] - depth * projection[3][3]) / (depth * projection[3][2] - projection[2][2]);
+    float w = ClipW(projection, z);
+    float2 ndc = float2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    return float3(ndc.x * w / projection[0][0], ndc.y * w / projection[1][1], z);
+}
+
+float InterleavedGradientNoise(float2 pixel) {
+    return frac(52.9829189 * frac(dot(pixel, float2(0.06711056, 0.00583715))));
+}
+
+float3 LoadViewPosition(Texture2D<float> depth, float4x4 projection, int2 pixel, int2 size, uint mip) {
+    pixel = clamp(pixel, int2(0, 0), size - 1);
+    return ViewPosition(projection, (pixel + 0.5) / float2(size), depth.Load(int3(pixel, mip)));
+}
+
+// Picks the neighbour pair with the smaller depth step on each axis so normals stay sharp at
+// silhouettes.
+float3 ReconstructNormal(Texture2D<float> depth, float4x4 projection, int2 pixel, int2 size, uint mip, float3 center) {
+    float3 left = LoadViewPosition(depth, projection, pixel + int2(-1, 0), size, mip);
+    float3 right = LoadViewPosition(depth, projection, pixel + int2(1, 0), size, mip);
+    float3 top = LoadViewPosition(depth, projection, pixel + int2(0, -1), size, mip);
+    float3 bottom = LoadViewPosition(depth, projection, pixel + int2(0, 1), size, mip);
+
+    float3 dx = abs(right.z - center.z) < abs(center.z - left.z) ? right - center : center - left;
+    float3 dy = abs(bottom.z - center.z) < abs(center.z - top.z) ? bottom - center : center - top;
+    float3 normal = normalize(cross(dy, dx));
+    return dot(normal, -center) < 0.0 ? -normal : normal;
+}
+
+// Basic SSAO: a spiral of depth samples in a disk around the pixel, each occluding by how far it
+// rises above the surface. There is no horizon search to bend the normal, so the reconstructed
+// normal is passed through in its place.
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float> depthPyramid = ResourceDescriptorHeap[ssaoResource.depthPyramidIndex];
+    ConstantBuffer<ViewUniform> viewBuffer = ResourceDescriptorHeap[ssaoResource.viewBufferIndex];
+    SamplerState pointSampler = SamplerDescriptorHeap[ssaoResource.samplerIndex];
+    float4x4 projection = viewBuffer.projection;
+
+    uint width;
+    uint height;
+    uint levels;
+    uint baseMip = ssaoResource.baseMip;
+    depthPyramid.GetDimensions(baseMip, width, height, levels);
+    float2 size = float2(width, height);
+    int2 pixel = int2(input.position.xy);
+
+    float depth = depthPyramid.Load(int3(pixel, baseMip));
+    if (depth <= 0.0) {
+        return float4(0.0, 0.0, -1.0, 1.0);
+    }
+    float3 position = ViewPosition(projection, input.uv, depth);
+    float3 normal = ReconstructNormal(depthPyramid, projection, pixel, int2(width, height), baseMip, position);
+
+    float radius = ssaoResource.radius;
+    float2 radiusUv = radius * float2(projection[0][0], projection[1][1]) * 0.5 / ClipW(projection, position.z);
+    if (max(radiusUv.x * size.x, radiusUv.y * size.y) < 1.0) {
+        return float4(normal, 1.0);
+    }
+
+    float noise = InterleavedGradientNoise(input.position.xy);
+    float occlusion = 0.0;
+    for (uint i = 0; i < ssaoResource.sampleCount; i++) {
+        float angle = i * GOLDEN_ANGLE + noise * TWO_PI;
+        float2 offset = float2(cos(angle), -sin(angle)) * radiusUv * sqrt((i + noise) / ssaoResource.sampleCount);
+        float2 uv = input.uv + offset;
+        float sampleDepth = depthPyramid.SampleLevel(pointSampler, uv, baseMip);
+        if (sampleDepth <= 0.0) {
+            continue;
+        }
+        float3 delta = ViewPosition(projection, uv, sampleDepth) - position;
+        float distanceSq = dot(delta, delta);
+        float falloff = saturate(1.0 - distanceSq / (radius * radius));
+        occlusion += max(dot(delta, normal) * rsqrt(distanceSq + 1e-4) - BIAS, 0.0) * falloff;
+    }
+
+    float visibility = saturate(1.0 - occlusion / ssaoResource.sampleCount);
+    visibility = pow(visibility, ssaoResource.intensity);
+    return float4(normal, visibility);
+}
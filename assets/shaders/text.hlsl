@@ -0,0 +1,47 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+    float4 color: COLOR;
+};
+
+struct TextQuad {
+    float2 position;
+    float2 uvMin;
+    float2 uvMax;
+    uint color;
+};
+
+struct TextResources {
+    uint glyphBufferIndex;
+    uint fontTextureIndex;
+    uint samplerIndex;
+    float2 screenSize;
+    float2 glyphSize;
+    float2 offset;
+    float scale;
+};
+
+ConstantBuffer<TextResources> textResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID, uint instanceID: SV_InstanceID) {
+    static const float2 corners[6] = {
+        float2(0.0, 0.0), float2(1.0, 0.0), float2(0.0, 1.0),
+        float2(0.0, 1.0), float2(1.0, 0.0), float2(1.0, 1.0),
+    };
+    float2 corner = corners[vertexID];
+    StructuredBuffer<TextQuad> glyphBuffer = ResourceDescriptorHeap[textResource.glyphBufferIndex];
+    TextQuad glyph = glyphBuffer[instanceID];
+
+    PSInput result;
+    float2 pixel = textResource.offset + (glyph.position + corner * textResource.glyphSize) * textResource.scale;
+    result.position = float4(pixel / textResource.screenSize * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = lerp(glyph.uvMin, glyph.uvMax, corner);
+    result.color = float4((glyph.color >> uint4(0, 8, 16, 24)) & 0xFF) / 255.0;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> font = ResourceDescriptorHeap[textResource.fontTextureIndex];
+    SamplerState fontSampler = SamplerDescriptorHeap[textResource.samplerIndex];
+    return input.color * font.Sample(fontSampler, input.uv);
+}
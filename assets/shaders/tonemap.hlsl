@@ -0,0 +1,68 @@
+#define INVALID_INDEX 0xFFFFFFFF
+
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+};
+
+struct TonemapResources {
+    uint sceneColorIndex;
+    uint samplerIndex;
+    uint bloomIndex;
+    float bloomIntensity;
+    float exposure;
+    uint tonemapping;
+    uint encodeSrgb;
+};
+
+ConstantBuffer<TonemapResources> tonemapResource: register(b0);
+
+static const uint TONEMAPPING_NONE = 0;
+static const uint TONEMAPPING_REINHARD = 1;
+static const uint TONEMAPPING_ACES = 2;
+
+float3 Aces(float3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return saturate((x * (a * x + b)) / (x * (c * x + d) + e));
+}
+
+float3 LinearToSrgb(float3 color) {
+    float3 low = color * 12.92;
+    float3 high = 1.055 * pow(color, 1.0 / 2.4) - 0.055;
+    return select(color <= 0.0031308, low, high);
+}
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    float2 uv = float2((vertexID << 1) & 2, vertexID & 2);
+
+    PSInput result;
+    result.position = float4(uv * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> sceneColor = ResourceDescriptorHeap[tonemapResource.sceneColorIndex];
+    SamplerState sceneSampler = SamplerDescriptorHeap[tonemapResource.samplerIndex];
+
+    float3 color = sceneColor.Sample(sceneSampler, input.uv).rgb;
+    if (tonemapResource.bloomIndex != INVALID_INDEX) {
+        Texture2D<float4> bloom = ResourceDescriptorHeap[tonemapResource.bloomIndex];
+        color += bloom.Sample(sceneSampler, input.uv).rgb * tonemapResource.bloomIntensity;
+    }
+    color *= tonemapResource.exposure;
+    if (tonemapResource.tonemapping == TONEMAPPING_REINHARD) {
+        color = color / (1.0 + color);
+    } else if (tonemapResource.tonemapping == TONEMAPPING_ACES) {
+        color = Aces(color);
+    }
+    color = saturate(color);
+    if (tonemapResource.encodeSrgb != 0) {
+        color = LinearToSrgb(color);
+    }
+    return float4(color, 1.0);
+}
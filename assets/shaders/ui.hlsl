@@ -0,0 +1,40 @@
+struct PSInput {
+    float4 position: SV_Position;
+    float2 uv: TEXCOORD0;
+    float4 color: COLOR;
+};
+
+struct UiVertex {
+    float2 position;
+    float2 uv;
+    uint color;
+};
+
+struct UiResources {
+    uint vertexBufferIndex;
+    uint textureIndex;
+    uint samplerIndex;
+    float2 screenSize;
+    float2 offset;
+    float scale;
+};
+
+ConstantBuffer<UiResources> uiResource: register(b0);
+
+PSInput VSMain(uint vertexID: SV_VertexID) {
+    StructuredBuffer<UiVertex> vertexBuffer = ResourceDescriptorHeap[uiResource.vertexBufferIndex];
+    UiVertex vertex = vertexBuffer[vertexID];
+
+    PSInput result;
+    float2 pixel = uiResource.offset + vertex.position * uiResource.scale;
+    result.position = float4(pixel / uiResource.screenSize * float2(2.0, -2.0) + float2(-1.0, 1.0), 0.0, 1.0);
+    result.uv = vertex.uv;
+    result.color = float4((vertex.color >> uint4(0, 8, 16, 24)) & 0xFF) / 255.0;
+    return result;
+}
+
+float4 PSMain(PSInput input): SV_Target {
+    Texture2D<float4> texture = ResourceDescriptorHeap[uiResource.textureIndex];
+    SamplerState textureSampler = SamplerDescriptorHeap[uiResource.samplerIndex];
+    return input.color * texture.Sample(textureSampler, input.uv);
+}
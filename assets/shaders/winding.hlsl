@@ -0,0 +1,13 @@
+// Drawn with the vertex shader of mesh.hlsl so depth matches the prepass exactly.
+struct PSInput {
+    float4 position: SV_Position;
+};
+
+// Back faces are what a mesh with flipped winding or inverted normals shows the camera, so they
+// stand out; front faces only get a faint tint to show the overlay is on.
+static const float4 FRONT_FACE_TINT = float4(0.2, 0.4, 1.0, 0.15);
+static const float4 BACK_FACE_TINT = float4(1.0, 0.1, 0.1, 0.6);
+
+float4 PSMain(PSInput input, bool isFrontFace: SV_IsFrontFace): SV_Target {
+    return isFrontFace ? FRONT_FACE_TINT : BACK_FACE_TINT;
+}
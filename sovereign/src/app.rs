@@ -0,0 +1,455 @@
+use crate::{
+    bookmarks::CameraBookmarks,
+    config::AppConfig,
+    debug_ui::draw_debug_ui,
+    fly_camera,
+    material_editor::MaterialEditor,
+    scene::{frame_camera, load_environment_from_args, process_load_events, SceneLoader},
+    schedule::Schedule,
+    stress::{spawn_stress_scene, stress_count_from_args},
+    time::Time,
+    window_control::{CursorGrabMode, CursorIcon, WindowControl},
+    window_events, MOUSE_SENSITIVITY, ORTHOGRAPHIC_HEIGHT,
+};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use sovereign_ecs::{EventReader, Snapshot, SnapshotRegistry, TracedQuery, World};
+use sovereign_gltf::LoadEvent;
+use sovereign_input::Input;
+use sovereign_render::{
+    camera::{Camera, Projection},
+    diagnostics::{register_snapshot_components, FrameBudget},
+    gizmo::axis_aligned_transform,
+    light::{DirectionalLight, Sky},
+    settings::RenderSettings,
+    time_of_day::{update_time_of_day, TimeOfDay},
+    transform::{propagate_transforms, GlobalTransform},
+    ui::{UiContext, UiInput},
+    RenderError, Renderer,
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use winit::{
+    event::{DeviceEvent, ElementState, Event, MouseButton, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+// Singletons and events the app expects, before the renderer adds its own.
+pub fn new_world() -> World {
+    let mut world = World::new();
+    world.add_event::<window_events::WindowEvent>();
+    world.add_event::<LoadEvent>();
+    world.set_singleton(WindowControl::default());
+    world.set_singleton(Time::new(Duration::from_secs_f64(1.0 / 60.0)));
+    world.set_singleton(Input::new(crate::camera_actions()));
+    world
+}
+
+// Everything a frame needs apart from the window, so recorded input can drive the same code
+// headlessly. Window-only events such as closing and fullscreen stay with the event loop.
+pub struct App {
+    pub world: World,
+    pub renderer: Renderer,
+    pub bookmarks: CameraBookmarks,
+    width: u32,
+    height: u32,
+    schedule: Schedule,
+    scene_loader: SceneLoader,
+    camera_query: TracedQuery<(&'static Camera, &'static mut GlobalTransform)>,
+    material_editor: MaterialEditor,
+    window_event_reader: EventReader<window_events::WindowEvent>,
+    load_event_reader: EventReader<LoadEvent>,
+    // Only live runs warn about slow frames; recordings and replays are not timed.
+    frame_budget: Option<FrameBudget>,
+    cursor: Vec2,
+    mouse_down: bool,
+    mouse_look: bool,
+    profiler_overlay: bool,
+    last_overlay_update: Instant,
+    ui: UiContext,
+    debug_ui: bool,
+    ui_hovered: bool,
+    snapshot_registry: SnapshotRegistry,
+    world_snapshot: Option<Snapshot>,
+}
+
+impl App {
+    // Runs that are not live finish loading the scene here so they do not depend on load timing.
+    pub fn new(mut world: World, mut renderer: Renderer, config: &AppConfig, live: bool) -> Self {
+        let (width, height) = renderer.size();
+        {
+            // Restored settings are clamped to the device like any other change on the next frame.
+            let mut settings_query = world.get_singleton::<RenderSettings>();
+            let (settings,) = settings_query.get().unwrap();
+            *settings = config.render.clone();
+        }
+        load_environment_from_args(&mut renderer, &world);
+
+        world.spawn((
+            Camera::new(renderer.desc().projection(), width, height),
+            GlobalTransform {
+                transform: Mat4::look_to_lh(Vec3::ZERO, Vec3::new(2.0, -1.0, 1.0), Vec3::Y)
+                    .inverse(),
+            },
+        ));
+
+        let mut scene_loader = SceneLoader::new();
+        if let Some(count) = stress_count_from_args() {
+            if let Some(bounds) = spawn_stress_scene(&mut world, count) {
+                frame_camera(&world, &bounds);
+            }
+        } else {
+            scene_loader.load_async(
+                &world,
+                PathBuf::from("assets/meshes/MetalRoughSpheresNoTextures.glb"),
+            );
+        }
+        if !live {
+            scene_loader.wait(&mut renderer, &mut world);
+        }
+
+        world.spawn((
+            TimeOfDay::new(9.0, 240.0),
+            DirectionalLight::default(),
+            Sky::default(),
+        ));
+
+        let mut bookmarks = CameraBookmarks::new();
+        config.restore_bookmarks(&mut bookmarks);
+        let mut snapshot_registry = SnapshotRegistry::new();
+        register_snapshot_components(&mut snapshot_registry);
+        snapshot_registry
+            .register::<Time>()
+            .register::<WindowControl>();
+        let mut schedule = Schedule::new();
+        schedule
+            .add_fixed("update_time_of_day", |world, delta| {
+                update_time_of_day(world, delta)
+            })
+            .add_update("propagate_transforms", |world, _delta| {
+                propagate_transforms(world)
+            });
+
+        Self {
+            world,
+            renderer,
+            bookmarks,
+            width,
+            height,
+            schedule,
+            scene_loader,
+            camera_query: TracedQuery::new("camera_query"),
+            material_editor: MaterialEditor::new(),
+            window_event_reader: EventReader::new(),
+            load_event_reader: EventReader::new(),
+            frame_budget: live
+                .then(|| FrameBudget::new(Duration::from_millis(20), Duration::from_millis(16))),
+            cursor: Vec2::ZERO,
+            mouse_down: false,
+            mouse_look: false,
+            profiler_overlay: false,
+            last_overlay_update: Instant::now(),
+            ui: UiContext::new(),
+            debug_ui: false,
+            ui_hovered: false,
+            snapshot_registry,
+            world_snapshot: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event<()>) {
+        {
+            let mut input_query = self.world.get_singleton::<Input>();
+            let (input,) = input_query.get().unwrap();
+            input.handle_event(event);
+        }
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                if let Err(err) = self.renderer.resize(size.width, size.height) {
+                    println!("{:?}", err);
+                }
+                (self.width, self.height) = self.renderer.size();
+                for (_entity, camera) in self.world.query::<&mut Camera>().iter() {
+                    camera.set_viewport_size(self.width, self.height);
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                self.cursor = Vec2::new(position.x as f32, position.y as f32);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Right,
+                        ..
+                    },
+                ..
+            } => {
+                self.mouse_look = *state == ElementState::Pressed && !self.ui.wants_input();
+                let mut control_query = self.world.get_singleton::<WindowControl>();
+                let (control,) = control_query.get().unwrap();
+                control.set_cursor_grab(if self.mouse_look {
+                    CursorGrabMode::Locked
+                } else {
+                    CursorGrabMode::None
+                });
+                control.set_cursor_visible(!self.mouse_look);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } => {
+                self.mouse_down = *state == ElementState::Pressed;
+                if self.mouse_down && !self.ui.wants_input() {
+                    self.pick();
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                if !self.mouse_look {
+                    return;
+                }
+                self.camera_query.query(self.world.get()).iter().for_each(
+                    |(_entity, (_camera, transform))| {
+                        let (scale, rotation, translation) =
+                            transform.transform.to_scale_rotation_translation();
+                        let yaw = Quat::from_rotation_y(delta.0 as f32 * MOUSE_SENSITIVITY);
+                        let pitch = Quat::from_rotation_x(delta.1 as f32 * MOUSE_SENSITIVITY);
+                        transform.transform = Mat4::from_scale_rotation_translation(
+                            scale,
+                            yaw * rotation * pitch,
+                            translation,
+                        );
+                    },
+                );
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::Key(raw_key_event),
+                ..
+            } => {
+                if let PhysicalKey::Code(key) = raw_key_event.physical_key {
+                    if raw_key_event.state == ElementState::Pressed {
+                        self.handle_key(key);
+                    }
+                    self.bookmarks
+                        .handle_key(&self.world, key, raw_key_event.state);
+                }
+            }
+            Event::WindowEvent { event, .. } => {
+                if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+                    self.renderer.set_scale_factor(*scale_factor);
+                }
+                if let Some(event) = window_events::WindowEvent::from_winit(event) {
+                    self.world.send_event(event);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Steps the simulation by frame_time and renders the result. Errors from earlier stages are
+    // logged so the frame still finishes; a render error is returned for the caller to act on.
+    pub fn frame(&mut self, frame_time: Duration) -> Result<(), RenderError> {
+        let now = Instant::now();
+        window_events::process_window_events(
+            &self.world,
+            &mut self.window_event_reader,
+            &mut self.scene_loader,
+        );
+        self.scene_loader.poll(&mut self.renderer, &mut self.world);
+        process_load_events(&self.world, &mut self.load_event_reader);
+        {
+            let mut time_query = self.world.get_singleton::<Time>();
+            let (time,) = time_query.get().unwrap();
+            time.update(frame_time);
+        }
+        self.bookmarks.update(&self.world, frame_time.as_secs_f32());
+        fly_camera(&self.world, frame_time.as_secs_f32());
+        self.schedule.run(&mut self.world);
+        if let Some(frame_budget) = &mut self.frame_budget {
+            frame_budget.check(frame_time, self.renderer.frame_stats());
+        }
+
+        if let Err(err) = self.renderer.prepare(&mut self.world) {
+            println!("{:?}", err);
+        }
+        if let Err(err) = self.renderer.prepare_render(&self.world) {
+            println!("{:?}", err);
+        }
+        let ui_viewport = self.renderer.ui_viewport();
+        let ui_input = UiInput {
+            cursor: self.cursor,
+            mouse_down: self.mouse_down,
+        };
+        let default_sampler = self.renderer.default_sampler();
+        self.ui.run(ui_input, &ui_viewport, |ctx| {
+            if self.debug_ui {
+                draw_debug_ui(ctx, &mut self.renderer, &self.world, &self.scene_loader);
+            }
+            self.material_editor.show(ctx, &self.world, default_sampler);
+        });
+        if self.ui.wants_input() != self.ui_hovered {
+            self.ui_hovered = self.ui.wants_input();
+            let mut control_query = self.world.get_singleton::<WindowControl>();
+            let (control,) = control_query.get().unwrap();
+            control.set_cursor_icon(if self.ui_hovered {
+                CursorIcon::Pointer
+            } else {
+                CursorIcon::Default
+            });
+        }
+        if let Err(err) = self.renderer.render_ui(&mut self.ui) {
+            println!("{:?}", err);
+        }
+        if self.profiler_overlay {
+            let stats = self.renderer.frame_stats().to_string();
+            self.renderer.draw_text(8.0, 8.0, &stats);
+        }
+        let rendered = self.renderer.render(&self.world);
+        if self.profiler_overlay && now - self.last_overlay_update >= Duration::from_millis(250) {
+            let mut control_query = self.world.get_singleton::<WindowControl>();
+            let (control,) = control_query.get().unwrap();
+            control.set_title(format!("Sovereign | {}", self.renderer.frame_stats()));
+            self.last_overlay_update = now;
+        }
+        {
+            let mut input_query = self.world.get_singleton::<Input>();
+            let (input,) = input_query.get().unwrap();
+            input.end_frame();
+        }
+        self.world.update_events();
+        rendered
+    }
+
+    // Snaps the camera to a gizmo axis under the cursor, or picks the entity there.
+    fn pick(&mut self) {
+        let (cursor, width, height) = (self.cursor, self.width, self.height);
+        let mut snapped = false;
+        self.camera_query.query(self.world.get()).iter().for_each(
+            |(_entity, (_camera, transform))| {
+                let view = transform.transform.inverse();
+                if let Some(axis) = self.renderer.gizmo.pick(cursor, &view, width, height) {
+                    transform.transform =
+                        axis_aligned_transform(transform.transform.w_axis.truncate(), axis);
+                    tracing::info!(axis = ?axis, "Snapped camera to axis");
+                    snapped = true;
+                }
+            },
+        );
+        if !snapped {
+            match self.renderer.pick(&self.world, cursor) {
+                Ok(Some(entity)) => tracing::info!(entity = entity.id(), "Picked entity"),
+                Ok(None) => {}
+                Err(err) => tracing::error!(%err, "Failed to pick entity"),
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        let world = &self.world;
+        self.material_editor.handle_key(key);
+        match key {
+            KeyCode::F2 => {
+                self.renderer.labels.enabled = !self.renderer.labels.enabled;
+                tracing::info!(enabled = self.renderer.labels.enabled, "Label overlay");
+            }
+            KeyCode::F3 => {
+                self.profiler_overlay = !self.profiler_overlay;
+                if !self.profiler_overlay {
+                    let mut control_query = world.get_singleton::<WindowControl>();
+                    let (control,) = control_query.get().unwrap();
+                    control.set_title("Sovereign");
+                }
+                tracing::info!(enabled = self.profiler_overlay, "Profiler overlay");
+            }
+            KeyCode::F4 => {
+                self.debug_ui = !self.debug_ui;
+                tracing::info!(enabled = self.debug_ui, "Debug UI");
+            }
+            KeyCode::KeyP | KeyCode::Period | KeyCode::Minus | KeyCode::Equal => {
+                let mut time_query = world.get_singleton::<Time>();
+                let (time,) = time_query.get().unwrap();
+                match key {
+                    KeyCode::KeyP => time.set_paused(!time.is_paused()),
+                    KeyCode::Period => time.step(),
+                    KeyCode::Minus => time.time_scale = (time.time_scale * 0.5).max(1.0 / 16.0),
+                    _ => time.time_scale = (time.time_scale * 2.0).min(16.0),
+                }
+                tracing::info!(
+                    paused = time.is_paused(),
+                    time_scale = time.time_scale,
+                    "Simulation time"
+                );
+            }
+            KeyCode::KeyO => {
+                for (_entity, camera) in world.query::<&mut Camera>().iter() {
+                    camera.projection = match camera.projection {
+                        Projection::Perspective { near, far, .. } => Projection::Orthographic {
+                            height: ORTHOGRAPHIC_HEIGHT,
+                            near,
+                            far,
+                        },
+                        Projection::Orthographic { .. } => self.renderer.desc().projection(),
+                    };
+                    tracing::info!(projection = ?camera.projection, "Camera projection");
+                }
+            }
+            KeyCode::KeyN => {
+                // Each snapshot is compared with the one before it.
+                let snapshot = world.snapshot(&self.snapshot_registry);
+                match &self.world_snapshot {
+                    Some(previous) => tracing::info!(
+                        "World changes since last snapshot: {}",
+                        previous.diff(&snapshot)
+                    ),
+                    None => tracing::info!(entities = snapshot.entities.len(), "World snapshot"),
+                }
+                self.world_snapshot = Some(snapshot);
+            }
+            KeyCode::F9 => tracing::info!("{}", self.renderer.resource_report()),
+            KeyCode::F10 => self.renderer.capture_barriers(),
+            KeyCode::KeyG => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                self.renderer
+                    .capture_frame_graph(format!("frame-graph-{}.dot", timestamp));
+            }
+            KeyCode::F12 => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                self.renderer
+                    .capture_screenshot(format!("screenshot-{}.png", timestamp));
+            }
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                let step = if key == KeyCode::BracketRight {
+                    1.0
+                } else {
+                    -1.0
+                };
+                for (_entity, time_of_day) in world.query::<&mut TimeOfDay>().iter() {
+                    time_of_day.set_hour(time_of_day.hour + step);
+                    tracing::info!(hour = time_of_day.hour, "Time of day");
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use glam::{Mat4, Quat, Vec3};
+use sovereign_ecs::World;
+use sovereign_render::{camera::Camera, transform::GlobalTransform};
+use std::collections::BTreeMap;
+use winit::{event::ElementState, keyboard::KeyCode};
+
+const FLIGHT_DURATION: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl CameraPose {
+    pub fn from_transform(transform: &Mat4) -> Self {
+        let (_scale, rotation, position) = transform.to_scale_rotation_translation();
+        Self { position, rotation }
+    }
+
+    pub fn to_transform(self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position)
+    }
+
+    pub fn lerp(self, other: CameraPose, t: f32) -> Self {
+        Self {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+        }
+    }
+}
+
+struct Flight {
+    from: CameraPose,
+    to: CameraPose,
+    elapsed: f32,
+    duration: f32,
+}
+
+pub struct CameraBookmarks {
+    bookmarks: BTreeMap<String, CameraPose>,
+    flight: Option<Flight>,
+    shift_held: bool,
+}
+
+impl CameraBookmarks {
+    pub fn new() -> Self {
+        Self {
+            bookmarks: BTreeMap::new(),
+            flight: None,
+            shift_held: false,
+        }
+    }
+
+    pub fn save(&mut self, name: impl Into<String>, pose: CameraPose) {
+        self.bookmarks.insert(name.into(), pose);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, CameraPose)> {
+        self.bookmarks
+            .iter()
+            .map(|(name, pose)| (name.as_str(), *pose))
+    }
+
+    pub fn fly_to(&mut self, name: &str, from: CameraPose, duration: f32) -> bool {
+        let Some(to) = self.bookmarks.get(name) else {
+            return false;
+        };
+        self.flight = Some(Flight {
+            from,
+            to: *to,
+            elapsed: 0.0,
+            duration,
+        });
+        true
+    }
+
+    pub fn handle_key(&mut self, world: &World, key: KeyCode, state: ElementState) {
+        if matches!(key, KeyCode::ShiftLeft | KeyCode::ShiftRight) {
+            self.shift_held = state == ElementState::Pressed;
+            return;
+        }
+        if state != ElementState::Pressed {
+            return;
+        }
+
+        let name = match key {
+            KeyCode::F5 => "1",
+            KeyCode::F6 => "2",
+            KeyCode::F7 => "3",
+            KeyCode::F8 => "4",
+            _ => return,
+        };
+        let Some(current) = camera_pose(world) else {
+            return;
+        };
+        if self.shift_held {
+            self.save(name, current);
+            tracing::info!(bookmark = name, position = ?current.position, "Saved camera bookmark");
+        } else if self.fly_to(name, current, FLIGHT_DURATION) {
+            tracing::info!(bookmark = name, "Flying to camera bookmark");
+        }
+    }
+
+    pub fn update(&mut self, world: &World, dt: f32) {
+        let Some(flight) = &mut self.flight else {
+            return;
+        };
+        flight.elapsed += dt;
+        let t = (flight.elapsed / flight.duration).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let pose = flight.from.lerp(flight.to, t);
+
+        for (_entity, (_camera, transform)) in
+            world.query::<(&Camera, &mut GlobalTransform)>().iter()
+        {
+            transform.transform = pose.to_transform();
+        }
+
+        if flight.elapsed >= flight.duration {
+            self.flight = None;
+        }
+    }
+}
+
+fn camera_pose(world: &World) -> Option<CameraPose> {
+    world
+        .query::<(&Camera, &GlobalTransform)>()
+        .iter()
+        .next()
+        .map(|(_entity, (_camera, transform))| CameraPose::from_transform(&transform.transform))
+}
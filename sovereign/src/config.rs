@@ -0,0 +1,304 @@
+use crate::bookmarks::{CameraBookmarks, CameraPose};
+use glam::{Quat, Vec3};
+use sovereign_render::settings::{
+    AmbientOcclusion, AntiAliasing, EffectResolution, RenderSettings, ShadowQuality,
+    TextureQuality, Tonemapping, UiScaling,
+};
+use std::{fmt::Debug, fmt::Write, fs, io, path::Path, str::FromStr};
+
+// Settings that survive between sessions, stored as `key = value` lines. Unknown keys and
+// unparsable values are skipped so an older or hand-edited file still loads.
+#[derive(Clone, Debug, Default)]
+pub struct AppConfig {
+    pub window_size: Option<(u32, u32)>,
+    pub window_position: Option<(i32, i32)>,
+    pub render: RenderSettings,
+    pub bookmarks: Vec<(String, CameraPose)>,
+}
+
+impl AppConfig {
+    pub fn load(path: &Path) -> Self {
+        let mut config = Self::default();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return config,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), %err, "Failed to read config");
+                return config;
+            }
+        };
+
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line
+                .split_once('=')
+                .and_then(|(key, value)| config.set(key.trim(), value.trim()));
+            if parsed.is_none() {
+                tracing::warn!(path = %path.display(), line = line_idx + 1, "Ignoring config line");
+            }
+        }
+        config
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let render = &self.render;
+        let mut contents = String::new();
+        // A minimized window reports 0x0, which would not be a usable size to restore.
+        if let Some((width, height)) = self.window_size.filter(|&(w, h)| w > 0 && h > 0) {
+            let _ = writeln!(contents, "window.size = {} {}", width, height);
+        }
+        if let Some((x, y)) = self.window_position {
+            let _ = writeln!(contents, "window.position = {} {}", x, y);
+        }
+        let _ = writeln!(contents, "render.vsync = {}", render.vsync);
+        let _ = writeln!(
+            contents,
+            "render.shadow_quality = {:?}",
+            render.shadow_quality
+        );
+        let _ = writeln!(
+            contents,
+            "render.anti_aliasing = {:?}",
+            render.anti_aliasing
+        );
+        let _ = writeln!(
+            contents,
+            "render.texture_quality = {:?}",
+            render.texture_quality
+        );
+        let _ = writeln!(
+            contents,
+            "render.ambient_occlusion = {:?}",
+            render.ambient_occlusion
+        );
+        let _ = writeln!(
+            contents,
+            "render.ambient_occlusion_resolution = {:?}",
+            render.ambient_occlusion_resolution
+        );
+        let _ = writeln!(
+            contents,
+            "render.ambient_occlusion_radius = {}",
+            render.ambient_occlusion_radius
+        );
+        let _ = writeln!(
+            contents,
+            "render.ambient_occlusion_intensity = {}",
+            render.ambient_occlusion_intensity
+        );
+        let _ = writeln!(
+            contents,
+            "render.resolution_scale = {}",
+            render.resolution_scale
+        );
+        let _ = writeln!(
+            contents,
+            "render.tonemapping = {:?}",
+            render.post.tonemapping
+        );
+        let _ = writeln!(contents, "render.exposure = {}", render.post.exposure);
+        let _ = writeln!(contents, "render.bloom = {}", render.post.bloom);
+        let _ = writeln!(
+            contents,
+            "render.bloom_threshold = {}",
+            render.post.bloom_threshold
+        );
+        let _ = writeln!(
+            contents,
+            "render.bloom_intensity = {}",
+            render.post.bloom_intensity
+        );
+        let _ = match render.ui_scaling {
+            UiScaling::Reference { width, height } => {
+                writeln!(
+                    contents,
+                    "render.ui_scaling = Reference {} {}",
+                    width, height
+                )
+            }
+            scaling => writeln!(contents, "render.ui_scaling = {:?}", scaling),
+        };
+        for (name, pose) in &self.bookmarks {
+            let (position, rotation) = (pose.position, pose.rotation);
+            let _ = writeln!(
+                contents,
+                "bookmark.{} = {} {} {} {} {} {} {}",
+                escape_name(name),
+                position.x,
+                position.y,
+                position.z,
+                rotation.x,
+                rotation.y,
+                rotation.z,
+                rotation.w
+            );
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn restore_bookmarks(&self, bookmarks: &mut CameraBookmarks) {
+        for (name, pose) in &self.bookmarks {
+            bookmarks.save(name.clone(), *pose);
+        }
+    }
+
+    pub fn store_bookmarks(&mut self, bookmarks: &CameraBookmarks) {
+        self.bookmarks = bookmarks
+            .iter()
+            .map(|(name, pose)| (name.to_string(), pose))
+            .collect();
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        let render = &mut self.render;
+        match key {
+            "window.size" => {
+                let [width, height] = parse_numbers(value)?;
+                if width == 0 || height == 0 {
+                    return None;
+                }
+                self.window_size = Some((width, height));
+            }
+            "window.position" => {
+                let [x, y] = parse_numbers(value)?;
+                self.window_position = Some((x, y));
+            }
+            "render.vsync" => render.vsync = value.parse().ok()?,
+            "render.shadow_quality" => {
+                render.shadow_quality = parse_variant(
+                    value,
+                    &[
+                        ShadowQuality::Off,
+                        ShadowQuality::Low,
+                        ShadowQuality::Medium,
+                        ShadowQuality::High,
+                    ],
+                )?
+            }
+            "render.anti_aliasing" => {
+                render.anti_aliasing = parse_variant(
+                    value,
+                    &[
+                        AntiAliasing::None,
+                        AntiAliasing::Msaa2x,
+                        AntiAliasing::Msaa4x,
+                        AntiAliasing::Msaa8x,
+                    ],
+                )?
+            }
+            "render.texture_quality" => {
+                render.texture_quality = parse_variant(
+                    value,
+                    &[
+                        TextureQuality::Low,
+                        TextureQuality::Medium,
+                        TextureQuality::High,
+                    ],
+                )?
+            }
+            "render.ambient_occlusion" => {
+                render.ambient_occlusion = parse_variant(
+                    value,
+                    &[
+                        AmbientOcclusion::Off,
+                        AmbientOcclusion::Low,
+                        AmbientOcclusion::Medium,
+                        AmbientOcclusion::High,
+                    ],
+                )?
+            }
+            "render.ambient_occlusion_resolution" => {
+                render.ambient_occlusion_resolution =
+                    parse_variant(value, &[EffectResolution::Full, EffectResolution::Half])?
+            }
+            "render.ambient_occlusion_radius" => {
+                render.ambient_occlusion_radius = value.parse().ok()?
+            }
+            "render.ambient_occlusion_intensity" => {
+                render.ambient_occlusion_intensity = value.parse().ok()?
+            }
+            "render.resolution_scale" => render.resolution_scale = value.parse().ok()?,
+            "render.tonemapping" => {
+                render.post.tonemapping = parse_variant(
+                    value,
+                    &[Tonemapping::None, Tonemapping::Reinhard, Tonemapping::Aces],
+                )?
+            }
+            "render.exposure" => render.post.exposure = value.parse().ok()?,
+            "render.bloom" => render.post.bloom = value.parse().ok()?,
+            "render.bloom_threshold" => render.post.bloom_threshold = value.parse().ok()?,
+            "render.bloom_intensity" => render.post.bloom_intensity = value.parse().ok()?,
+            "render.ui_scaling" => {
+                render.ui_scaling = match value.strip_prefix("Reference") {
+                    Some(size) => {
+                        let [width, height] = parse_numbers(size)?;
+                        UiScaling::Reference { width, height }
+                    }
+                    None => parse_variant(value, &[UiScaling::Pixel, UiScaling::Dpi])?,
+                }
+            }
+            _ => {
+                let name = unescape_name(key.strip_prefix("bookmark.")?)?;
+                let [px, py, pz, rx, ry, rz, rw] = parse_numbers(value)?;
+                let pose = CameraPose {
+                    position: Vec3::new(px, py, pz),
+                    rotation: Quat::from_xyzw(rx, ry, rz, rw).normalize(),
+                };
+                self.bookmarks.push((name, pose));
+            }
+        }
+        Some(())
+    }
+}
+
+// Bookmark names are part of the key, so `=`, whitespace and `%` are written as %XX UTF-8 bytes
+// to keep them from being split or trimmed when the line is read back.
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '%' || c == '=' || c.is_whitespace() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                let _ = write!(escaped, "%{:02X}", byte);
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+fn unescape_name(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn parse_numbers<T: FromStr, const N: usize>(value: &str) -> Option<[T; N]> {
+    let numbers = value
+        .split_whitespace()
+        .map(|number| number.parse().ok())
+        .collect::<Option<Vec<T>>>()?;
+    numbers.try_into().ok()
+}
+
+// Variants are written with their Debug name, so match against that on the way back in.
+pub fn parse_variant<T: Copy + Debug>(value: &str, variants: &[T]) -> Option<T> {
+    variants
+        .iter()
+        .copied()
+        .find(|variant| format!("{:?}", variant) == value)
+}
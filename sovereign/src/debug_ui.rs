@@ -0,0 +1,177 @@
+use crate::{scene::SceneLoader, time::Time};
+use sovereign_ecs::World;
+use sovereign_render::{
+    settings::{AmbientOcclusion, RenderSettings},
+    time_of_day::TimeOfDay,
+    ui::egui,
+    Renderer,
+};
+
+const PANEL_WIDTH: f32 = 280.0;
+const PANEL_MARGIN: f32 = 8.0;
+
+pub fn draw_debug_ui(
+    ctx: &egui::Context,
+    renderer: &mut Renderer,
+    world: &World,
+    scene_loader: &SceneLoader,
+) {
+    let stats = renderer.frame_stats();
+    let mut lines = vec![
+        format!(
+            "CPU {:.2}ms  GPU {:.2}ms",
+            stats.cpu_time.as_secs_f64() * 1000.0,
+            stats.gpu_time.as_secs_f64() * 1000.0
+        ),
+        format!(
+            "{} draws, {} instances, {} culled",
+            stats.draw_calls, stats.instances, stats.culled
+        ),
+        format!(
+            "{} presents, {} missed vblanks{}",
+            stats.present.present_count,
+            stats.present.missed_refreshes,
+            stats
+                .present
+                .refresh_rate
+                .map(|refresh_rate| format!(" @ {:.0}Hz", refresh_rate))
+                .unwrap_or_default()
+        ),
+    ];
+    if let Some(last) = renderer.shadow_cascades().last() {
+        lines.push(format!(
+            "{} shadow cascades to {:.1}m",
+            renderer.shadow_cascades().len(),
+            last.split_depth
+        ));
+    }
+    if let Some(progress) = scene_loader.progress() {
+        lines.push(format!(
+            "Loading scene {:.0}% ({}/{} images, {}/{} meshes)",
+            progress.fraction() * 100.0,
+            progress.images_uploaded,
+            progress.image_count,
+            progress.meshes_uploaded,
+            progress.mesh_count
+        ));
+    }
+    lines.extend(stats.gpu_passes.iter().map(|pass| {
+        format!(
+            "  {:<14}{:.2}ms",
+            pass.name,
+            pass.gpu_time.as_secs_f64() * 1000.0
+        )
+    }));
+    let stats = lines.join("\n");
+
+    egui::Window::new("Debug")
+        .default_pos([PANEL_MARGIN, PANEL_MARGIN])
+        .default_width(PANEL_WIDTH)
+        .show(ctx, |ui| {
+            egui::CollapsingHeader::new("Stats")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.monospace(stats);
+                });
+
+            egui::CollapsingHeader::new("Renderer")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let mut settings_query = world.get_singleton::<RenderSettings>();
+                    let (settings,) = settings_query.get().unwrap();
+                    egui::ComboBox::from_label("Ambient occlusion")
+                        .selected_text(format!("{:?}", settings.ambient_occlusion))
+                        .show_ui(ui, |ui| {
+                            for tier in [
+                                AmbientOcclusion::Off,
+                                AmbientOcclusion::Low,
+                                AmbientOcclusion::Medium,
+                                AmbientOcclusion::High,
+                            ] {
+                                ui.selectable_value(
+                                    &mut settings.ambient_occlusion,
+                                    tier,
+                                    format!("{:?}", tier),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::Slider::new(&mut settings.ambient_occlusion_radius, 0.1..=2.0)
+                            .text("AO radius"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut settings.ambient_occlusion_intensity, 0.0..=4.0)
+                            .text("AO intensity"),
+                    );
+                    ui.checkbox(&mut settings.vsync, "VSync");
+                    ui.add(
+                        egui::Slider::new(&mut settings.post.exposure, 0.1..=4.0).text("Exposure"),
+                    );
+                    ui.checkbox(&mut settings.post.bloom, "Bloom");
+                    ui.add(
+                        egui::Slider::new(&mut settings.post.bloom_threshold, 0.0..=4.0)
+                            .text("Bloom threshold"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut settings.post.bloom_intensity, 0.0..=1.0)
+                            .text("Bloom intensity"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut settings.resolution_scale, 0.25..=2.0)
+                            .text("Resolution scale"),
+                    );
+                    ui.separator();
+                    ui.checkbox(&mut renderer.gizmo.enabled, "Orientation gizmo");
+                    ui.checkbox(&mut renderer.labels.enabled, "Labels");
+                    ui.checkbox(&mut renderer.labels.light_icons, "Light icons");
+                    ui.checkbox(&mut renderer.debug_draw.bounds, "Bounding boxes");
+                    ui.checkbox(&mut renderer.debug_draw.winding, "Face winding");
+                    ui.checkbox(&mut renderer.debug_text.entity_ids, "Entity IDs");
+                    ui.checkbox(&mut renderer.debug_text.distances, "Distances");
+                    ui.checkbox(
+                        &mut renderer.debug_text.light_intensities,
+                        "Light intensities",
+                    );
+                });
+
+            egui::CollapsingHeader::new("Simulation")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let mut time_query = world.get_singleton::<Time>();
+                    let (time,) = time_query.get().unwrap();
+                    let mut paused = time.is_paused();
+                    if ui.checkbox(&mut paused, "Paused").changed() {
+                        time.set_paused(paused);
+                    }
+                    if paused && ui.button("Step").clicked() {
+                        time.step();
+                    }
+                    ui.add(egui::Slider::new(&mut time.time_scale, 0.0..=4.0).text("Time scale"));
+                    ui.label(format!(
+                        "{:.1}s elapsed, frame {}",
+                        time.elapsed().as_secs_f32(),
+                        time.frame_count()
+                    ));
+                    ui.label(format!(
+                        "{} fixed steps, {:.0}% into the next",
+                        time.fixed_steps(),
+                        time.fixed_alpha() * 100.0
+                    ));
+                });
+
+            egui::CollapsingHeader::new("Time of day")
+                .default_open(true)
+                .show(ui, |ui| {
+                    for (_entity, time_of_day) in world.query::<&mut TimeOfDay>().iter() {
+                        let mut hour = time_of_day.hour;
+                        if ui
+                            .add(egui::Slider::new(&mut hour, 0.0..=24.0).text("Hour"))
+                            .changed()
+                        {
+                            time_of_day.set_hour(hour);
+                        }
+                        ui.checkbox(&mut time_of_day.paused, "Paused");
+                    }
+                });
+        });
+}
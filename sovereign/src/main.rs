@@ -1,149 +1,200 @@
-use glam::{Mat4, Vec3, Vec4};
-use sovereign_ecs::{Entity, EntityBuilder, ParentOf, PreparedQuery, World};
-use sovereign_gltf::{load_gltf, Gltf, GltfNode};
-use sovereign_render::{camera::Camera, transform::GlobalTransform, Renderer};
-use std::{error::Error, path::Path};
+mod app;
+mod bookmarks;
+mod config;
+mod debug_ui;
+mod material_editor;
+mod replay;
+mod scene;
+mod schedule;
+mod stress;
+mod time;
+mod window_control;
+mod window_events;
+
+use app::{new_world, App};
+use config::AppConfig;
+use glam::{Vec2, Vec3};
+use replay::{InputLog, Replay};
+use tracing_subscriber::fmt::format::FmtSpan;
+use sovereign_input::{ActionMap, Binding, GamepadAxis, Input};
+use sovereign_ecs::World;
+use sovereign_render::{
+    camera::Camera,
+    desc::RendererDesc,
+    settings::{FullscreenMode, RenderSettings},
+    transform::GlobalTransform,
+    Renderer,
+};
+use std::{
+    error::Error,
+    path::Path,
+    time::Instant,
+};
+use window_control::{apply_window_control, WindowControl};
 use winit::{
-    dpi::PhysicalSize, event::{DeviceEvent, Event, WindowEvent}, event_loop::{ControlFlow, EventLoop}, keyboard::{KeyCode, PhysicalKey}, window::WindowBuilder
+    dpi::{PhysicalPosition, PhysicalSize}, event::{DeviceEvent, ElementState, Event, WindowEvent}, event_loop::{ControlFlow, EventLoop}, keyboard::{KeyCode, PhysicalKey}, window::{Fullscreen, WindowBuilder}
 };
 
+const MOUSE_SENSITIVITY: f32 = 0.0025;
+// World units per second with a move action fully held.
+const CAMERA_SPEED: f32 = 2.0;
+// World units covered vertically when the camera is switched to orthographic.
+const ORTHOGRAPHIC_HEIGHT: f32 = 10.0;
+const CONFIG_PATH: &str = "sovereign.cfg";
+const DEFAULT_WINDOW_SIZE: (u32, u32) = (1280, 960);
+
 fn main() -> Result<(), Box<dyn Error>> {
-    tracing_subscriber::fmt::init();
-    let width = 1280;
-    let height = 960;
+    // `--trace-spans` logs how long each query and prepare step took as its span closes.
+    if std::env::args().any(|arg| arg == "--trace-spans") {
+        tracing_subscriber::fmt().with_max_level(tracing::Level::DEBUG).with_span_events(FmtSpan::CLOSE).init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+    if let Some(replay) = Replay::from_args()? {
+        return replay.run();
+    }
+    let mut input_log = InputLog::from_args()?;
+    // Recorded runs start from defaults so their replays do not depend on the local config.
+    let mut config = if input_log.is_live() { AppConfig::load(Path::new(CONFIG_PATH)) } else { AppConfig::default() };
+    let (width, height) = config.window_size.unwrap_or(DEFAULT_WINDOW_SIZE);
 
     let event_loop = EventLoop::new()?;
-    let window = WindowBuilder::new()
+    let mut window_builder = WindowBuilder::new()
         .with_title("Sovereign")
-        .with_inner_size(PhysicalSize::new(width, height))
-        .build(&event_loop)?;
-    let mut world = World::new();
-    let mut renderer = Renderer::new(1280, 960, &window, &mut world)?;
-    tracing::info!("Renderer loaded");
-
-    let gltf = load_gltf(
-        &mut renderer,
-        &mut world,
-        &Path::new("assets/meshes/MetalRoughSpheresNoTextures.glb"),
-    )?;
-    for top_node in &gltf.top_nodes {
-        let _ = spawn_node(&mut world, &gltf, &gltf.nodes[*top_node], None);
+        .with_inner_size(PhysicalSize::new(width, height));
+    if let Some((x, y)) = config.window_position {
+        window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
     }
-
-    world.spawn((
-        Camera {
-            projection: Mat4::perspective_lh(
-                60.0f32.to_radians(),
-                width as f32 / height as f32,
-                10000.0,
-                0.0001,
-            ),
-        },
-        GlobalTransform {
-            transform: Mat4::look_at_lh(Vec3::new(-0.01, 0.005, -0.005), Vec3::ZERO, Vec3::Y)
-                .inverse(),
-        },
-    ));
-
-    renderer.prepare(&mut world)?;
-
-    let mut camera_query: PreparedQuery<(&'static Camera, &'static mut GlobalTransform)> = PreparedQuery::new();
+    let window = window_builder.build(&event_loop)?;
+    let mut world = new_world();
+    {
+        let mut control_query = world.get_singleton::<WindowControl>();
+        let (control,) = control_query.get().unwrap();
+        control.set_icon(window_icon(32), 32, 32);
+    }
+    let mut renderer = Renderer::new(width, height, &window, &mut world, RendererDesc::default())?;
+    renderer.set_scale_factor(window.scale_factor());
+    tracing::info!("Renderer loaded");
+    let mut app = App::new(world, renderer, &config, input_log.is_live());
+    let mut last_frame = Instant::now();
 
     event_loop.set_control_flow(ControlFlow::Poll);
-    event_loop.run(move |event, elwt| match event {
-        Event::WindowEvent {
-            event: WindowEvent::CloseRequested,
-            ..
-        } => {
-            elwt.exit();
-        }
-        Event::AboutToWait => {
-            if let Err(err) = renderer.prepare_render(&world) {
-                println!("{:?}", err);
+    event_loop.run(move |event, elwt| {
+        input_log.record(&event);
+        match &event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                tracing::info!("{}", app.renderer.resource_report());
+                if window.fullscreen().is_none() && app.renderer.fullscreen() == FullscreenMode::Windowed {
+                    // Minimized windows report 0x0; keep the last real size instead.
+                    let size = window.inner_size();
+                    if size.width > 0 && size.height > 0 {
+                        config.window_size = Some((size.width, size.height));
+                    }
+                    config.window_position = window.outer_position().ok().map(|position| (position.x, position.y));
+                }
+                config.render = {
+                    let mut settings_query = app.world.get_singleton::<RenderSettings>();
+                    let (settings,) = settings_query.get().unwrap();
+                    settings.clone()
+                };
+                config.store_bookmarks(&app.bookmarks);
+                if input_log.is_live() {
+                    if let Err(err) = config.save(Path::new(CONFIG_PATH)) {
+                        tracing::warn!(%err, "Failed to save config");
+                    }
+                }
+                elwt.exit();
             }
-            if let Err(err) = renderer.render(&world) {
-                println!("{:?}", err);
+            Event::WindowEvent {
+                event: WindowEvent::Moved(_),
+                ..
+            } => {
+                app.renderer.update_refresh_rate();
             }
-        },
-        Event::DeviceEvent { event: DeviceEvent::Key(raw_key_event), .. } => {
-            if let PhysicalKey::Code(key) = raw_key_event.physical_key {
-                if key == KeyCode::KeyW {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let forward = transform.transform * Vec4::Z;
-                        let forward = forward.normalize() * 0.0001;
-                        transform.transform.w_axis += forward;
-                    });
-                } else if key == KeyCode::KeyS {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let forward = transform.transform * -Vec4::Z;
-                        let forward = forward.normalize() * 0.0001;
-                        transform.transform.w_axis += forward;
-                    });
-                }
-                if key == KeyCode::KeyD {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let right = transform.transform * Vec4::X;
-                        let right = right.normalize() * 0.0001;
-                        transform.transform.w_axis += right;
-                    });
-                } else if key == KeyCode::KeyA {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let right = transform.transform * -Vec4::X;
-                        let right = right.normalize() * 0.0001;
-                        transform.transform.w_axis += right;
-                    });
+            Event::AboutToWait => {
+                let now = Instant::now();
+                let frame_time = now - last_frame;
+                input_log.end_frame(frame_time);
+                last_frame = now;
+                if let Err(err) = app.frame(frame_time) {
+                    println!("{:?}", err);
+                    if err.is_device_lost() {
+                        elwt.exit();
+                    }
                 }
-                if key == KeyCode::KeyE {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let up = transform.transform * Vec4::Y;
-                        let up = up.normalize() * 0.0001;
-                        transform.transform.w_axis += up;
-                    });
-                } else if key == KeyCode::KeyQ {
-                    camera_query.query(world.get()).iter().for_each(|(_entity, (_camera, transform))| {
-                        let up = transform.transform * -Vec4::Y;
-                        let up = up.normalize() * 0.0001;
-                        transform.transform.w_axis += up;
-                    });
+                apply_window_control(&app.world, &window);
+            }
+            Event::DeviceEvent { event: DeviceEvent::Key(raw_key_event), .. }
+                if raw_key_event.physical_key == PhysicalKey::Code(KeyCode::F11) && raw_key_event.state == ElementState::Pressed =>
+            {
+                let mode = match app.renderer.fullscreen() {
+                    FullscreenMode::Windowed => FullscreenMode::Borderless,
+                    FullscreenMode::Borderless => FullscreenMode::Exclusive,
+                    FullscreenMode::Exclusive => FullscreenMode::Windowed,
+                };
+                window.set_fullscreen((mode == FullscreenMode::Borderless).then_some(Fullscreen::Borderless(None)));
+                if let Err(err) = app.renderer.set_fullscreen(mode) {
+                    println!("{:?}", err);
                 }
             }
+            _ => {}
         }
-        _ => {}
+        app.handle_event(&event);
     })?;
 
     Ok(())
 }
 
-fn spawn_node(
-    world: &mut World,
-    gltf: &Gltf,
-    node: &GltfNode,
-    parent: Option<&GltfNode>,
-) -> Entity {
-    let mut builder = EntityBuilder::new();
-    if let Some(mesh_idx) = node.mesh_idx {
-        let mesh = &gltf.meshes[mesh_idx];
-        let material = &gltf.materials[mesh.material_idx];
-        builder.add(mesh.mesh).add(*material);
-    }
+fn camera_actions() -> ActionMap {
+    let mut actions = ActionMap::new();
+    actions
+        .bind("MoveForward", Binding::Key(KeyCode::KeyW))
+        .bind("MoveForward", Binding::positive(GamepadAxis::LeftStickY))
+        .bind("MoveBack", Binding::Key(KeyCode::KeyS))
+        .bind("MoveBack", Binding::negative(GamepadAxis::LeftStickY))
+        .bind("MoveRight", Binding::Key(KeyCode::KeyD))
+        .bind("MoveRight", Binding::positive(GamepadAxis::LeftStickX))
+        .bind("MoveLeft", Binding::Key(KeyCode::KeyA))
+        .bind("MoveLeft", Binding::negative(GamepadAxis::LeftStickX))
+        .bind("MoveUp", Binding::Key(KeyCode::KeyE))
+        .bind("MoveUp", Binding::positive(GamepadAxis::RightTrigger))
+        .bind("MoveDown", Binding::Key(KeyCode::KeyQ))
+        .bind("MoveDown", Binding::positive(GamepadAxis::LeftTrigger));
+    actions
+}
 
-    if let Some(parent) = parent {
-        builder.add(GlobalTransform {
-            transform: node.local_transform * parent.local_transform,
-        });
-    } else {
-        builder.add(GlobalTransform {
-            transform: node.local_transform,
-        });
+// Moves the camera along its own axes for as long as the move actions are held.
+fn fly_camera(world: &World, delta_time: f32) {
+    let direction = {
+        let mut input_query = world.get_singleton::<Input>();
+        let (input,) = input_query.get().unwrap();
+        Vec3::new(
+            input.action_axis("MoveLeft", "MoveRight"),
+            input.action_axis("MoveDown", "MoveUp"),
+            input.action_axis("MoveBack", "MoveForward"),
+        )
+    };
+    if direction == Vec3::ZERO {
+        return;
     }
-
-    for children in &node.children {
-        builder.add(ParentOf(spawn_node(
-            world,
-            gltf,
-            &gltf.nodes[*children],
-            Some(node),
-        )));
+    for (_entity, (_camera, transform)) in world.query::<(&Camera, &mut GlobalTransform)>().iter() {
+        let speed = direction.length().min(1.0) * CAMERA_SPEED * delta_time;
+        let offset = transform.transform.transform_vector3(direction).normalize_or_zero() * speed;
+        transform.transform.w_axis += offset.extend(0.0);
     }
-    world.spawn(builder.build())
+}
+
+// A filled disc with a soft edge, until there is a proper icon asset.
+fn window_icon(size: u32) -> Vec<u8> {
+    let center = (size as f32 - 1.0) / 2.0;
+    (0..size * size)
+        .flat_map(|i| {
+            let offset = Vec2::new((i % size) as f32, (i / size) as f32) - center;
+            let alpha = (center - offset.length()).clamp(0.0, 1.0);
+            [0x2e, 0x6b, 0xd8, (alpha * 255.0) as u8]
+        })
+        .collect()
 }
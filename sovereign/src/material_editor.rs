@@ -0,0 +1,218 @@
+use sovereign_ecs::World;
+use sovereign_math::Color;
+use sovereign_render::{
+    asset::Assets,
+    id::SamplerId,
+    material::{Material, MaterialTexture, MaterialUniform},
+    texture::Texture,
+    ui::egui,
+};
+use winit::keyboard::KeyCode;
+
+const PANEL_WIDTH: f32 = 300.0;
+const THUMBNAIL_SIZE: f32 = 48.0;
+const MAX_EMISSIVE_STRENGTH: f32 = 100.0;
+const TEXTURE_SLOTS: [&str; 5] = [
+    "Base color",
+    "Metallic roughness",
+    "Normal",
+    "Occlusion",
+    "Emissive",
+];
+
+pub struct MaterialEditor {
+    enabled: bool,
+    selected: usize,
+}
+
+impl MaterialEditor {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            selected: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if key == KeyCode::F1 {
+            self.enabled = !self.enabled;
+            tracing::info!(enabled = self.enabled, "Material editor");
+        }
+    }
+
+    // Edits are written back through Assets::get_mut, which queues the material for upload before
+    // the next frame. Only changed materials are written, so the rest stay off the upload list.
+    pub fn show(&mut self, ctx: &egui::Context, world: &World, default_sampler: SamplerId) {
+        if !self.enabled {
+            return;
+        }
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (materials,) = materials_query.get().unwrap();
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+
+        let mut open = true;
+        egui::Window::new("Materials")
+            .open(&mut open)
+            .default_width(PANEL_WIDTH)
+            .show(ctx, |ui| {
+                let names = materials
+                    .iter()
+                    .map(|(handle, _material)| {
+                        let name = asset_name(materials.key(handle), "Material", handle.idx());
+                        (handle, name)
+                    })
+                    .collect::<Vec<_>>();
+                let Some(first) = names.first() else {
+                    ui.label("No materials loaded");
+                    return;
+                };
+                let (handle, selected_name) = names
+                    .iter()
+                    .find(|(handle, _name)| handle.idx() == self.selected)
+                    .unwrap_or(first);
+                let handle = *handle;
+                self.selected = handle.idx();
+
+                egui::ComboBox::from_label("Material")
+                    .selected_text(selected_name.as_str())
+                    .show_ui(ui, |ui| {
+                        for (handle, name) in &names {
+                            ui.selectable_value(&mut self.selected, handle.idx(), name);
+                        }
+                    });
+
+                let material = materials.get(handle).unwrap();
+                let mut uniform = material.uniform;
+                let mut slots = material.textures().map(Option::<&MaterialTexture>::cloned);
+                ui.separator();
+                let mut changed = edit_factors(ui, &mut uniform);
+                ui.separator();
+                for (slot, name) in slots.iter_mut().zip(TEXTURE_SLOTS) {
+                    changed |= edit_texture_slot(ui, name, slot, textures, default_sampler);
+                }
+
+                if changed {
+                    let material = materials.get_mut(handle).unwrap();
+                    material.uniform = uniform;
+                    for (target, slot) in material.textures_mut().into_iter().zip(slots) {
+                        *target = slot;
+                    }
+                }
+            });
+        if !open {
+            self.enabled = false;
+        }
+    }
+}
+
+fn edit_factors(ui: &mut egui::Ui, uniform: &mut MaterialUniform) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut base_color = uniform.base_color_factors.to_array();
+        if ui
+            .color_edit_button_rgba_unmultiplied(&mut base_color)
+            .changed()
+        {
+            let [r, g, b, a] = base_color;
+            uniform.base_color_factors = Color::rgba(r, g, b, a);
+            changed = true;
+        }
+        ui.label("Base color");
+    });
+    for (value, text) in [
+        (&mut uniform.metallic, "Metallic"),
+        (&mut uniform.perceptual_roughness, "Roughness"),
+        (&mut uniform.reflectance, "Reflectance"),
+    ] {
+        changed |= ui
+            .add(egui::Slider::new(value, 0.0..=1.0).text(text))
+            .changed();
+    }
+    ui.horizontal(|ui| {
+        let emissive = uniform.emissive_factors;
+        let mut rgb = [emissive.r, emissive.g, emissive.b];
+        if ui.color_edit_button_rgb(&mut rgb).changed() {
+            let [r, g, b] = rgb;
+            uniform.emissive_factors = Color::rgba(r, g, b, emissive.a);
+            changed = true;
+        }
+        ui.label("Emissive");
+    });
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut uniform.emissive_strength, 0.0..=MAX_EMISSIVE_STRENGTH)
+                .logarithmic(true)
+                .text("Emissive strength"),
+        )
+        .changed();
+    changed
+}
+
+// A thumbnail of the bound texture and a list to rebind the slot to any loaded texture. A slot
+// that was empty takes the default sampler.
+fn edit_texture_slot(
+    ui: &mut egui::Ui,
+    name: &str,
+    slot: &mut Option<MaterialTexture>,
+    textures: &Assets<Texture>,
+    default_sampler: SamplerId,
+) -> bool {
+    let current = slot.as_ref().map(|slot| slot.texture.idx());
+    let mut selected = current;
+    ui.horizontal(|ui| {
+        let thumbnail = egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        match slot.as_ref().and_then(|slot| textures.get(&slot.texture)) {
+            // User texture ids are bindless SRV indices.
+            Some(texture) => {
+                ui.add(egui::Image::new((
+                    egui::TextureId::User(texture.view.0 as u64),
+                    thumbnail,
+                )));
+            }
+            None => {
+                ui.add_sized(thumbnail, egui::Label::new("None"));
+            }
+        }
+        ui.vertical(|ui| {
+            ui.label(name);
+            let selected_text = match selected {
+                Some(idx) => asset_name(textures_key(textures, idx), "Texture", idx),
+                None => "None".to_owned(),
+            };
+            egui::ComboBox::from_id_source(name)
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "None");
+                    for (handle, _texture) in textures.iter() {
+                        let text = asset_name(textures.key(handle), "Texture", handle.idx());
+                        ui.selectable_value(&mut selected, Some(handle.idx()), text);
+                    }
+                });
+        });
+    });
+    if selected == current {
+        return false;
+    }
+
+    let sampler = slot.as_ref().map_or(default_sampler, |slot| slot.sampler);
+    *slot = selected
+        .and_then(|idx| {
+            let (handle, _texture) = textures.iter().find(|(handle, _)| handle.idx() == idx)?;
+            textures.upgrade(handle)
+        })
+        .map(|texture| MaterialTexture { texture, sampler });
+    true
+}
+
+fn textures_key(textures: &Assets<Texture>, idx: usize) -> Option<&str> {
+    let (handle, _texture) = textures.iter().find(|(handle, _)| handle.idx() == idx)?;
+    textures.key(handle)
+}
+
+fn asset_name(key: Option<&str>, kind: &str, idx: usize) -> String {
+    match key {
+        Some(key) => key.to_owned(),
+        None => format!("{} {}", kind, idx),
+    }
+}
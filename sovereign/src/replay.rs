@@ -0,0 +1,318 @@
+use crate::{
+    app::{new_world, App},
+    config::{parse_variant, AppConfig},
+    DEFAULT_WINDOW_SIZE,
+};
+use sovereign_render::{desc::RendererDesc, Renderer};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, DeviceId, ElementState, Event, MouseButton, RawKeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowId,
+};
+
+// Keys the app reacts to; anything else is left out of recordings.
+const KEYS: &[KeyCode] = &[
+    KeyCode::KeyW,
+    KeyCode::KeyA,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+    KeyCode::KeyQ,
+    KeyCode::KeyE,
+    KeyCode::KeyP,
+    KeyCode::KeyO,
+    KeyCode::KeyN,
+    KeyCode::KeyG,
+    KeyCode::Period,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::Tab,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+const BUTTONS: &[MouseButton] = &[MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+const STATES: &[ElementState] = &[ElementState::Pressed, ElementState::Released];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum InputEvent {
+    Key(KeyCode, ElementState),
+    CursorMoved(f64, f64),
+    MouseButton(MouseButton, ElementState),
+    MouseMotion(f64, f64),
+    // Window changes that affect the simulation, so replays see the same viewport and pauses.
+    Resized(u32, u32),
+    Focused(bool),
+}
+
+impl InputEvent {
+    fn from_winit(event: &Event<()>) -> Option<Self> {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => Some(InputEvent::CursorMoved(position.x, position.y)),
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => BUTTONS
+                .contains(button)
+                .then_some(InputEvent::MouseButton(*button, *state)),
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => Some(InputEvent::MouseMotion(delta.0, delta.1)),
+            Event::DeviceEvent {
+                event:
+                    DeviceEvent::Key(RawKeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                    }),
+                ..
+            } => KEYS.contains(key).then_some(InputEvent::Key(*key, *state)),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => Some(InputEvent::Resized(size.width, size.height)),
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                ..
+            } => Some(InputEvent::Focused(*focused)),
+            _ => None,
+        }
+    }
+
+    fn to_winit(self, window_id: WindowId) -> Event<()> {
+        // Replayed events did not come from a real device.
+        let device_id = unsafe { DeviceId::dummy() };
+        match self {
+            InputEvent::Key(key, state) => Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::Key(RawKeyEvent {
+                    physical_key: PhysicalKey::Code(key),
+                    state,
+                }),
+            },
+            InputEvent::CursorMoved(x, y) => Event::WindowEvent {
+                window_id,
+                event: WindowEvent::CursorMoved {
+                    device_id,
+                    position: PhysicalPosition::new(x, y),
+                },
+            },
+            InputEvent::MouseButton(button, state) => Event::WindowEvent {
+                window_id,
+                event: WindowEvent::MouseInput {
+                    device_id,
+                    state,
+                    button,
+                },
+            },
+            InputEvent::MouseMotion(x, y) => Event::DeviceEvent {
+                device_id,
+                event: DeviceEvent::MouseMotion { delta: (x, y) },
+            },
+            InputEvent::Resized(width, height) => Event::WindowEvent {
+                window_id,
+                event: WindowEvent::Resized(PhysicalSize::new(width, height)),
+            },
+            InputEvent::Focused(focused) => Event::WindowEvent {
+                window_id,
+                event: WindowEvent::Focused(focused),
+            },
+        }
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            InputEvent::Key(key, state) => writeln!(out, "key {:?} {:?}", key, state),
+            InputEvent::CursorMoved(x, y) => writeln!(out, "cursor {} {}", x, y),
+            InputEvent::MouseButton(button, state) => {
+                writeln!(out, "button {:?} {:?}", button, state)
+            }
+            InputEvent::MouseMotion(x, y) => writeln!(out, "motion {} {}", x, y),
+            InputEvent::Resized(width, height) => writeln!(out, "resize {} {}", width, height),
+            InputEvent::Focused(focused) => writeln!(out, "focus {}", focused),
+        }
+    }
+
+    fn parse(kind: &str, a: &str, b: &str) -> Option<Self> {
+        Some(match kind {
+            "key" => InputEvent::Key(parse_variant(a, KEYS)?, parse_variant(b, STATES)?),
+            "cursor" => InputEvent::CursorMoved(a.parse().ok()?, b.parse().ok()?),
+            "button" => {
+                InputEvent::MouseButton(parse_variant(a, BUTTONS)?, parse_variant(b, STATES)?)
+            }
+            "motion" => InputEvent::MouseMotion(a.parse().ok()?, b.parse().ok()?),
+            "resize" => InputEvent::Resized(a.parse().ok()?, b.parse().ok()?),
+            _ => return None,
+        })
+    }
+}
+
+struct ReplayFrame {
+    frame_time: Duration,
+    events: Vec<InputEvent>,
+}
+
+// Records input with the frame it arrived in and each frame's delta.
+pub enum InputLog {
+    Live,
+    Recording(BufWriter<File>),
+}
+
+impl InputLog {
+    // `--record <file>` on the command line.
+    pub fn from_args() -> Result<Self, Box<dyn Error>> {
+        if let Some(path) = path_arg("--record") {
+            let mut file = BufWriter::new(File::create(&path)?);
+            writeln!(file, "# sovereign input recording")?;
+            tracing::info!(path = %path.display(), "Recording input");
+            Ok(InputLog::Recording(file))
+        } else {
+            Ok(InputLog::Live)
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        matches!(self, InputLog::Live)
+    }
+
+    pub fn record(&mut self, event: &Event<()>) {
+        let InputLog::Recording(file) = self else {
+            return;
+        };
+        if let Some(input) = InputEvent::from_winit(event) {
+            if let Err(err) = input.write(file) {
+                tracing::warn!(%err, "Failed to record input");
+            }
+        }
+    }
+
+    // Closes the current frame of a recording; the input recorded since belongs to it.
+    pub fn end_frame(&mut self, frame_time: Duration) {
+        let InputLog::Recording(file) = self else {
+            return;
+        };
+        if let Err(err) = writeln!(file, "frame {}", frame_time.as_nanos()) {
+            tracing::warn!(%err, "Failed to record input");
+        }
+    }
+}
+
+// Plays a recording back without a window, rendering offscreen. Each frame gets its recorded
+// input and delta, so the simulation steps exactly as it did while recording. Replays start from
+// the default config like recordings do.
+pub struct Replay {
+    frames: Vec<ReplayFrame>,
+    capture_dir: Option<PathBuf>,
+}
+
+impl Replay {
+    // `--replay <file>` on the command line, plus `--capture <dir>` to save every frame as a PNG.
+    pub fn from_args() -> Result<Option<Self>, Box<dyn Error>> {
+        let Some(path) = path_arg("--replay") else {
+            return Ok(None);
+        };
+        let frames = Self::load(&path)?;
+        tracing::info!(path = %path.display(), frames = frames.len(), "Replaying input");
+        Ok(Some(Replay {
+            frames,
+            capture_dir: path_arg("--capture"),
+        }))
+    }
+
+    fn load(path: &Path) -> Result<Vec<ReplayFrame>, Box<dyn Error>> {
+        let mut frames = Vec::new();
+        let mut events = Vec::new();
+        for (line_idx, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let parsed = match (tokens.next(), tokens.next(), tokens.next()) {
+                (None, ..) => Some(()),
+                (Some(comment), ..) if comment.starts_with('#') => Some(()),
+                (Some("frame"), Some(nanos), None) => nanos.parse().ok().map(|nanos| {
+                    frames.push(ReplayFrame {
+                        frame_time: Duration::from_nanos(nanos),
+                        events: std::mem::take(&mut events),
+                    })
+                }),
+                (Some("focus"), Some(focused), None) => focused
+                    .parse()
+                    .ok()
+                    .map(|focused| events.push(InputEvent::Focused(focused))),
+                (Some(kind), Some(a), Some(b)) => {
+                    InputEvent::parse(kind, a, b).map(|event| events.push(event))
+                }
+                _ => None,
+            };
+            if parsed.is_none() {
+                return Err(
+                    format!("{}:{}: invalid replay line", path.display(), line_idx + 1).into(),
+                );
+            }
+        }
+        Ok(frames)
+    }
+
+    pub fn run(self) -> Result<(), Box<dyn Error>> {
+        let (width, height) = DEFAULT_WINDOW_SIZE;
+        let mut world = new_world();
+        let renderer = Renderer::new_offscreen(width, height, &mut world, RendererDesc::default())?;
+        let mut app = App::new(world, renderer, &AppConfig::default(), false);
+        if let Some(capture_dir) = &self.capture_dir {
+            fs::create_dir_all(capture_dir)?;
+        }
+
+        // Replayed events did not come from a real window.
+        let window_id = unsafe { WindowId::dummy() };
+        let frame_count = self.frames.len();
+        for (frame_idx, frame) in self.frames.into_iter().enumerate() {
+            for input in frame.events {
+                app.handle_event(&input.to_winit(window_id));
+            }
+            if let Some(capture_dir) = &self.capture_dir {
+                app.renderer
+                    .capture_screenshot(capture_dir.join(format!("frame-{:05}.png", frame_idx)));
+            }
+            app.frame(frame.frame_time)?;
+        }
+        tracing::info!(frames = frame_count, "Replay finished");
+        Ok(())
+    }
+}
+
+fn path_arg(flag: &str) -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
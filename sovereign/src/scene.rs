@@ -0,0 +1,184 @@
+use glam::Mat4;
+use sovereign_ecs::{EventReader, Events, World};
+use sovereign_gltf::{load_gltf_async, Gltf, LoadEvent, LoadHandle, LoadProgress, SceneInstance};
+use sovereign_math::Aabb;
+use sovereign_obj::{build_obj, import_obj, ObjImport};
+use sovereign_render::{camera::Camera, transform::GlobalTransform, Renderer};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::Duration,
+};
+
+// Upload time spent per frame on a loading scene.
+const LOAD_BUDGET: Duration = Duration::from_millis(4);
+// Texels along each cube face edge of an environment loaded from a panorama.
+const ENVIRONMENT_SIZE: u32 = 1024;
+
+enum PendingScene {
+    Gltf(LoadHandle),
+    // OBJ files carry little besides geometry, so they are built in one go once imported.
+    Obj(PathBuf, Receiver<Result<ObjImport, String>>),
+}
+
+pub struct SceneLoader {
+    scene: Option<Gltf>,
+    instance: Option<SceneInstance>,
+    pending: Option<PendingScene>,
+}
+
+impl SceneLoader {
+    pub fn new() -> Self {
+        Self {
+            scene: None,
+            instance: None,
+            pending: None,
+        }
+    }
+
+    // Cancels the scene still loading, if any, so it stops taking upload time.
+    pub fn load_async(&mut self, world: &World, path: PathBuf) {
+        match self.pending.take() {
+            Some(PendingScene::Gltf(mut handle)) => handle.cancel(world),
+            Some(PendingScene::Obj(pending_path, _receiver)) => {
+                tracing::info!(path = %pending_path.display(), "Canceled scene load")
+            }
+            None => {}
+        }
+        tracing::info!(path = %path.display(), "Loading scene");
+        self.pending = Some(if is_obj(&path) {
+            let (sender, receiver) = mpsc::channel();
+            let import_path = path.clone();
+            std::thread::spawn(move || {
+                let result = import_obj(&import_path).map_err(|err| err.to_string());
+                let _ = sender.send(result);
+            });
+            PendingScene::Obj(path, receiver)
+        } else {
+            PendingScene::Gltf(load_gltf_async(path))
+        });
+    }
+
+    // Progress of the scene currently loading, if any.
+    pub fn progress(&self) -> Option<LoadProgress> {
+        self.pending.as_ref().map(|pending| match pending {
+            PendingScene::Gltf(handle) => handle.progress(),
+            PendingScene::Obj(..) => LoadProgress::default(),
+        })
+    }
+
+    pub fn poll(&mut self, renderer: &mut Renderer, world: &mut World) {
+        self.poll_with_budget(renderer, world, LOAD_BUDGET);
+    }
+
+    // Finishes the pending load before returning, for runs that must not depend on load timing.
+    pub fn wait(&mut self, renderer: &mut Renderer, world: &mut World) {
+        while self.pending.is_some() {
+            self.poll_with_budget(renderer, world, Duration::MAX);
+            if self.pending.is_some() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    fn poll_with_budget(&mut self, renderer: &mut Renderer, world: &mut World, budget: Duration) {
+        let (path, result): (PathBuf, Result<Gltf, Box<dyn Error>>) = match &mut self.pending {
+            None => return,
+            Some(PendingScene::Gltf(handle)) => {
+                let Some(result) = handle.poll(renderer, world, budget) else {
+                    return;
+                };
+                (handle.path().to_path_buf(), result)
+            }
+            Some(PendingScene::Obj(path, receiver)) => {
+                let result = match receiver.try_recv() {
+                    Ok(result) => result,
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => Err("import thread panicked".to_string()),
+                };
+                let result = result
+                    .map(|import| build_obj(renderer, world, import))
+                    .map_err(Into::into);
+                (path.clone(), result)
+            }
+        };
+        self.pending = None;
+
+        match result {
+            Ok(gltf) => {
+                let bounds = self.replace(world, gltf);
+                if let Some(bounds) = bounds {
+                    frame_camera(world, &bounds);
+                }
+                tracing::info!(path = %path.display(), "Loaded scene");
+            }
+            Err(err) => tracing::error!(path = %path.display(), %err, "Failed to load scene"),
+        }
+    }
+
+    fn replace(&mut self, world: &mut World, gltf: Gltf) -> Option<Aabb> {
+        if let Some(instance) = self.instance.take() {
+            instance.despawn(world);
+        }
+
+        let instance = gltf.spawn(world, Mat4::IDENTITY);
+        let bounds = instance.bounds;
+        self.instance = Some(instance);
+        self.scene = Some(gltf);
+        bounds
+    }
+}
+
+// Nothing here waits on the events sent by glTF loads, so they are only logged.
+pub fn process_load_events(world: &World, reader: &mut EventReader<LoadEvent>) {
+    let mut events_query = world.get_singleton::<Events<LoadEvent>>();
+    let (events,) = events_query.get().unwrap();
+
+    for event in reader.read(events) {
+        match event {
+            LoadEvent::Progress(path, progress) => tracing::debug!(
+                path = %path.display(),
+                bytes_decoded = progress.bytes_decoded,
+                images = progress.images_uploaded,
+                meshes = progress.meshes_uploaded,
+                "Scene load progress"
+            ),
+            LoadEvent::Canceled(path) => {
+                tracing::info!(path = %path.display(), "Canceled scene load")
+            }
+            LoadEvent::Loaded(..) | LoadEvent::Failed(..) => {}
+        }
+    }
+}
+
+// `--environment <panorama>` on the command line, such as an .hdr file, drawn behind the scene.
+pub fn load_environment_from_args(renderer: &mut Renderer, world: &World) {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args
+        .by_ref()
+        .find(|arg| arg == "--environment")
+        .and_then(|_| args.next())
+        .map(PathBuf::from)
+    else {
+        return;
+    };
+    match renderer.load_cubemap_from_equirect(world, &path, ENVIRONMENT_SIZE) {
+        Ok(cubemap) => match renderer.set_environment(cubemap) {
+            Ok(()) => tracing::info!(path = %path.display(), "Loaded environment"),
+            Err(err) => tracing::warn!(%err, "Failed to filter environment"),
+        },
+        Err(err) => tracing::warn!(%err, path = %path.display(), "Failed to load environment"),
+    }
+}
+
+pub fn is_obj(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("obj"))
+}
+
+pub fn frame_camera(world: &World, bounds: &Aabb) {
+    for (_entity, (camera, transform)) in world.query::<(&Camera, &mut GlobalTransform)>().iter() {
+        transform.transform = camera.frame_bounds(&transform.transform, bounds);
+    }
+}
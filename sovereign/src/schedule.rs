@@ -0,0 +1,56 @@
+use sovereign_ecs::World;
+
+use crate::time::Time;
+
+// Takes the time step in seconds.
+pub type System = fn(&mut World, f32);
+
+// Game logic run from the frame loop. Fixed systems run once per fixed step of the Time
+// singleton, so they advance the same amount whatever the frame rate; update systems run once
+// per frame after them with the frame's scaled delta.
+pub struct Schedule {
+    fixed: Vec<(&'static str, System)>,
+    update: Vec<(&'static str, System)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            fixed: Vec::new(),
+            update: Vec::new(),
+        }
+    }
+
+    pub fn add_fixed(&mut self, name: &'static str, system: System) -> &mut Self {
+        self.fixed.push((name, system));
+        self
+    }
+
+    pub fn add_update(&mut self, name: &'static str, system: System) -> &mut Self {
+        self.update.push((name, system));
+        self
+    }
+
+    // Expects Time to have been updated for this frame.
+    pub fn run(&self, world: &mut World) {
+        let (fixed_steps, fixed_timestep, delta) = {
+            let mut time_query = world.get_singleton::<Time>();
+            let (time,) = time_query.get().unwrap();
+            (
+                time.fixed_steps(),
+                time.fixed_timestep().as_secs_f32(),
+                time.delta().as_secs_f32(),
+            )
+        };
+        for _ in 0..fixed_steps {
+            for (name, system) in &self.fixed {
+                let _span = tracing::trace_span!("fixed_system", name).entered();
+                system(world, fixed_timestep);
+            }
+        }
+        for (name, system) in &self.update {
+            let _span = tracing::trace_span!("system", name).entered();
+            system(world, delta);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use glam::{Mat4, Quat, Vec3};
+use sovereign_ecs::World;
+use sovereign_math::{Aabb, Color};
+use sovereign_render::{
+    asset::Assets,
+    material::{Material, MaterialUniform},
+    mesh::{shapes, Mesh},
+    transform::{GlobalTransform, Transform},
+};
+use std::f32::consts::TAU;
+
+// Enough distinct materials to stress the material buffer and batching without one per instance.
+const MATERIAL_COUNT: usize = 256;
+const SPACING: f32 = 3.0;
+
+// `--stress <n>` on the command line.
+pub fn stress_count_from_args() -> Option<usize> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--stress" {
+            return match args.next().map(|count| count.parse()) {
+                Some(Ok(count)) => Some(count),
+                _ => {
+                    tracing::warn!("--stress expects an instance count");
+                    None
+                }
+            };
+        }
+    }
+    None
+}
+
+// Spawns `count` cubes and spheres on a jittered grid, cycling through a fixed set of materials,
+// and returns the bounds of everything spawned.
+pub fn spawn_stress_scene(world: &mut World, count: usize) -> Option<Aabb> {
+    let meshes = {
+        let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
+        let (meshes,) = meshes_query.get().unwrap();
+        [shapes::cube(1.0), shapes::uv_sphere(0.5, 16, 24)].map(|mesh| {
+            let bounds = mesh.compute_bounds();
+            (meshes.push(mesh), bounds)
+        })
+    };
+    let materials = {
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (materials,) = materials_query.get().unwrap();
+        (0..MATERIAL_COUNT.min(count))
+            .map(|i| materials.push(stress_material(i as u32)))
+            .collect::<Vec<_>>()
+    };
+
+    let side = (count as f32).cbrt().ceil().max(1.0) as usize;
+    let offset = (side as f32 - 1.0) * SPACING * 0.5;
+    let mut bounds: Option<Aabb> = None;
+    for i in 0..count {
+        let cell = Vec3::new(
+            (i % side) as f32,
+            (i / side % side) as f32,
+            (i / (side * side)) as f32,
+        );
+        let jitter = Vec3::new(
+            random(i as u32, 0),
+            random(i as u32, 1),
+            random(i as u32, 2),
+        );
+        let position = cell * SPACING - Vec3::splat(offset) + (jitter - 0.5) * SPACING * 0.5;
+        let rotation = Quat::from_euler(
+            glam::EulerRot::YXZ,
+            random(i as u32, 3) * TAU,
+            random(i as u32, 4) * TAU,
+            0.0,
+        );
+        let scale = 0.5 + random(i as u32, 5);
+        let transform =
+            Mat4::from_scale_rotation_translation(Vec3::splat(scale), rotation, position);
+
+        let (mesh, mesh_bounds) = &meshes[i % meshes.len()];
+        let world_bounds = mesh_bounds.transformed(&transform);
+        bounds = Some(match &bounds {
+            Some(bounds) => bounds.union(&world_bounds),
+            None => world_bounds,
+        });
+        world.spawn((
+            Transform { transform },
+            GlobalTransform { transform },
+            mesh.clone(),
+            materials[i % materials.len()].clone(),
+            *mesh_bounds,
+        ));
+    }
+
+    tracing::info!(
+        instances = count,
+        materials = materials.len(),
+        "Spawned stress scene"
+    );
+    bounds
+}
+
+fn stress_material(i: u32) -> Material {
+    let hue = random(i, 6);
+    Material {
+        uniform: MaterialUniform {
+            base_color_factors: hue_color(hue),
+            emissive_factors: Color::BLACK,
+            emissive_strength: 1.0,
+            normal_scale: 1.0,
+            perceptual_roughness: 0.1 + 0.9 * random(i, 7),
+            metallic: if random(i, 8) > 0.5 { 1.0 } else { 0.0 },
+            reflectance: 0.5,
+            occlusion_strength: 1.0,
+        },
+        base_color_texture: None,
+        metallic_roughness_texture: None,
+        normal_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+    }
+}
+
+fn hue_color(hue: f32) -> Color {
+    let channel = |offset: f32| ((hue + offset) * TAU).cos() * 0.5 + 0.5;
+    Color::rgb(channel(0.0), channel(1.0 / 3.0), channel(2.0 / 3.0))
+}
+
+// Cheap integer hash so the layout is the same on every run.
+fn random(i: u32, stream: u32) -> f32 {
+    let mut x = i.wrapping_mul(0x9E37_79B9) ^ stream.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1 << 24) as f32
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+// Long hitches (breakpoints, window drags) would otherwise turn into a burst of fixed steps.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+const MAX_FIXED_STEPS: u32 = 8;
+
+pub struct Time {
+    pub time_scale: f32,
+    fixed_timestep: Duration,
+    delta: Duration,
+    elapsed: Duration,
+    accumulator: Duration,
+    fixed_steps: u32,
+    frame_count: u64,
+    paused: bool,
+    pending_steps: u32,
+}
+
+impl Time {
+    pub fn new(fixed_timestep: Duration) -> Self {
+        Self {
+            time_scale: 1.0,
+            fixed_timestep,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            accumulator: Duration::ZERO,
+            fixed_steps: 0,
+            frame_count: 0,
+            paused: false,
+            pending_steps: 0,
+        }
+    }
+
+    pub fn update(&mut self, real_delta: Duration) {
+        if self.paused {
+            // Each queued step advances exactly one fixed step and leaves the accumulator alone.
+            self.fixed_steps = self.pending_steps.min(MAX_FIXED_STEPS);
+            self.pending_steps -= self.fixed_steps;
+            self.delta = self.fixed_timestep * self.fixed_steps;
+        } else {
+            self.delta = real_delta
+                .min(MAX_FRAME_TIME)
+                .mul_f32(self.time_scale.max(0.0));
+            self.accumulator += self.delta;
+            self.fixed_steps = 0;
+            while self.accumulator >= self.fixed_timestep && self.fixed_steps < MAX_FIXED_STEPS {
+                self.accumulator -= self.fixed_timestep;
+                self.fixed_steps += 1;
+            }
+            if self.fixed_steps == MAX_FIXED_STEPS {
+                self.accumulator = Duration::ZERO;
+            }
+        }
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+
+    // Scaled time since the last update; zero while paused unless stepping.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    // Scaled game time; stands still while paused unless stepping.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    pub fn fixed_steps(&self) -> u32 {
+        self.fixed_steps
+    }
+
+    // How far the leftover time is into the next fixed step, from 0 to 1, for blending between
+    // the last two fixed states when rendering.
+    pub fn fixed_alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.fixed_timestep.as_secs_f32()
+    }
+
+    // Updates so far, counting paused ones.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    // Queues one fixed step for the next frame; only meaningful while paused.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use sovereign_ecs::World;
+use winit::window::{Icon, Window};
+
+pub use winit::window::{CursorGrabMode, CursorIcon};
+
+#[derive(Clone, Debug)]
+struct WindowIcon {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+// Systems queue window changes here; they are applied to the winit window once per frame.
+#[derive(Clone, Debug, Default)]
+pub struct WindowControl {
+    title: Option<String>,
+    icon: Option<WindowIcon>,
+    cursor_visible: Option<bool>,
+    cursor_grab: Option<CursorGrabMode>,
+    cursor_icon: Option<CursorIcon>,
+}
+
+impl WindowControl {
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = Some(title.into());
+    }
+
+    pub fn set_icon(&mut self, rgba: Vec<u8>, width: u32, height: u32) {
+        self.icon = Some(WindowIcon {
+            rgba,
+            width,
+            height,
+        });
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = Some(visible);
+    }
+
+    pub fn set_cursor_grab(&mut self, grab: CursorGrabMode) {
+        self.cursor_grab = Some(grab);
+    }
+
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.cursor_icon = Some(icon);
+    }
+}
+
+pub fn apply_window_control(world: &World, window: &Window) {
+    let mut control_query = world.get_singleton::<WindowControl>();
+    let (control,) = control_query.get().unwrap();
+
+    if let Some(title) = control.title.take() {
+        window.set_title(&title);
+    }
+    if let Some(icon) = control.icon.take() {
+        match Icon::from_rgba(icon.rgba, icon.width, icon.height) {
+            Ok(icon) => window.set_window_icon(Some(icon)),
+            Err(err) => tracing::warn!(%err, "Invalid window icon"),
+        }
+    }
+    if let Some(visible) = control.cursor_visible.take() {
+        window.set_cursor_visible(visible);
+    }
+    if let Some(grab) = control.cursor_grab.take() {
+        // Not every platform can lock the cursor in place, so fall back to confining it.
+        let result = window.set_cursor_grab(grab).or_else(|err| match grab {
+            CursorGrabMode::Locked => window.set_cursor_grab(CursorGrabMode::Confined),
+            _ => Err(err),
+        });
+        if let Err(err) = result {
+            tracing::warn!(?grab, %err, "Failed to grab cursor");
+        }
+    }
+    if let Some(icon) = control.cursor_icon.take() {
+        window.set_cursor_icon(icon);
+    }
+}
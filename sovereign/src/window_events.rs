@@ -0,0 +1,69 @@
+use crate::scene::{is_obj, SceneLoader};
+use sovereign_ecs::{EventReader, Events, World};
+use sovereign_render::time_of_day::TimeOfDay;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowEvent {
+    Resized { width: u32, height: u32 },
+    Focused(bool),
+    Moved { x: i32, y: i32 },
+    ScaleFactorChanged(f64),
+    FileHovered(PathBuf),
+    FileHoverCancelled,
+    FileDropped(PathBuf),
+}
+
+impl WindowEvent {
+    pub fn from_winit(event: &winit::event::WindowEvent) -> Option<Self> {
+        use winit::event::WindowEvent as Winit;
+        match event {
+            Winit::Resized(size) => Some(WindowEvent::Resized {
+                width: size.width,
+                height: size.height,
+            }),
+            Winit::Focused(focused) => Some(WindowEvent::Focused(*focused)),
+            Winit::Moved(position) => Some(WindowEvent::Moved {
+                x: position.x,
+                y: position.y,
+            }),
+            Winit::ScaleFactorChanged { scale_factor, .. } => {
+                Some(WindowEvent::ScaleFactorChanged(*scale_factor))
+            }
+            Winit::HoveredFile(path) => Some(WindowEvent::FileHovered(path.clone())),
+            Winit::HoveredFileCancelled => Some(WindowEvent::FileHoverCancelled),
+            Winit::DroppedFile(path) => Some(WindowEvent::FileDropped(path.clone())),
+            _ => None,
+        }
+    }
+}
+
+pub fn process_window_events(
+    world: &World,
+    reader: &mut EventReader<WindowEvent>,
+    scene_loader: &mut SceneLoader,
+) {
+    let mut events_query = world.get_singleton::<Events<WindowEvent>>();
+    let (events,) = events_query.get().unwrap();
+
+    for event in reader.read(events) {
+        match event {
+            WindowEvent::Focused(focused) => {
+                for (_entity, time_of_day) in world.query::<&mut TimeOfDay>().iter() {
+                    time_of_day.paused = !focused;
+                }
+            }
+            WindowEvent::FileDropped(path) => {
+                let is_gltf = path.extension().is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("glb") || extension.eq_ignore_ascii_case("gltf")
+                });
+                if is_gltf || is_obj(path) {
+                    scene_loader.load_async(world, path.clone());
+                } else {
+                    tracing::warn!(path = %path.display(), "Dropped file is not a glTF or OBJ scene");
+                }
+            }
+            event => tracing::debug!(?event, "Window event"),
+        }
+    }
+}
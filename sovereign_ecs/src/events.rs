@@ -0,0 +1,143 @@
+use std::{any::type_name, marker::PhantomData};
+
+struct EventInstance<T> {
+    id: usize,
+    event: T,
+}
+
+// Events are double buffered: `update` moves the ones sent since the last update into the
+// previous buffer and drops the ones already there, so every event stays readable for two
+// updates. Readers running once per frame see everything regardless of whether they run before
+// or after the systems sending to them.
+pub struct Events<T> {
+    previous: Vec<EventInstance<T>>,
+    current: Vec<EventInstance<T>>,
+    // Id of the next event sent, doubling as the writer's cursor.
+    event_count: usize,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.send(event);
+        }
+    }
+
+    // Called once per frame through `World::update_events`.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    // A reader that sees every event still buffered.
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            last: self.oldest_id(),
+            marker: PhantomData,
+        }
+    }
+
+    // A reader that only sees events sent after it was created.
+    pub fn reader_current(&self) -> EventReader<T> {
+        EventReader {
+            last: self.event_count,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous
+            .iter()
+            .chain(&self.current)
+            .map(|instance| &instance.event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn oldest_id(&self) -> usize {
+        self.previous
+            .first()
+            .or(self.current.first())
+            .map_or(self.event_count, |instance| instance.id)
+    }
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A cursor into an `Events<T>`, owned by whatever consumes the events. Any number of readers can
+// consume the same events independently.
+pub struct EventReader<T> {
+    last: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        Self {
+            last: 0,
+            marker: PhantomData,
+        }
+    }
+
+    // Events sent since the last read. A reader that goes two updates without reading misses
+    // the events dropped in between.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let missed = events.oldest_id().saturating_sub(self.last);
+        if missed > 0 {
+            tracing::warn!(missed, event = type_name::<T>(), "Event reader fell behind");
+        }
+        let last = self.last;
+        self.last = events.event_count;
+        events
+            .previous
+            .iter()
+            .chain(&events.current)
+            .filter(move |instance| instance.id >= last)
+            .map(|instance| &instance.event)
+    }
+
+    pub fn len(&self, events: &Events<T>) -> usize {
+        events.event_count - self.last.max(events.oldest_id())
+    }
+
+    pub fn is_empty(&self, events: &Events<T>) -> bool {
+        self.len(events) == 0
+    }
+
+    // Skips everything sent so far.
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.last = events.event_count;
+    }
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,23 +1,42 @@
+mod events;
+mod snapshot;
+mod traced_query;
+
 use hecs::{
     Component, DynamicBundle, NoSuchEntity, Query, QueryBorrow, QueryOne, World as HecsWorld,
 };
 use std::{any::TypeId, collections::HashMap};
 
-pub use hecs::{CommandBuffer, Entity, EntityBuilder, PreparedQuery};
+pub use events::{EventReader, Events};
+pub use hecs::{CommandBuffer, Entity, EntityBuilder, PreparedQuery, With, Without};
+pub use snapshot::{EntityDiff, Snapshot, SnapshotDiff, SnapshotRegistry};
+pub use traced_query::{TracedQuery, TracedQueryBorrow, TracedQueryIter};
+
+// Implemented by the component linking an entity to its parent, so hierarchies can be walked
+// without this crate knowing about transforms.
+pub trait ParentOf: Component {
+    fn parent(&self) -> Entity;
+}
+
+// Sent for every despawned entity, after its components are gone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Despawned(pub Entity);
 
 pub struct World {
     world: HecsWorld,
     singletons: HashMap<TypeId, Entity>,
+    event_updates: Vec<fn(&World)>,
 }
 
-pub struct ParentOf(pub Entity);
-
 impl World {
     pub fn new() -> Self {
-        Self {
+        let mut world = Self {
             world: HecsWorld::new(),
             singletons: HashMap::new(),
-        }
+            event_updates: Vec::new(),
+        };
+        world.add_event::<Despawned>();
+        world
     }
 
     pub fn get(&self) -> &HecsWorld {
@@ -45,6 +64,26 @@ impl World {
         query.0
     }
 
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) {
+        if !self.singletons.contains_key(&TypeId::of::<Events<T>>()) {
+            self.set_singleton(Events::<T>::new());
+            self.event_updates.push(update_events::<T>);
+        }
+    }
+
+    // Swaps the buffers of every event type added with `add_event`, once per frame.
+    pub fn update_events(&self) {
+        for update in &self.event_updates {
+            update(self);
+        }
+    }
+
+    pub fn send_event<T: Send + Sync + 'static>(&self, event: T) {
+        let mut query = self.get_singleton::<Events<T>>();
+        let (events,) = query.get().unwrap();
+        events.send(event);
+    }
+
     pub fn query<Q: Query>(&self) -> QueryBorrow<'_, Q> {
         self.world.query::<Q>()
     }
@@ -60,4 +99,44 @@ impl World {
     pub fn spawn(&mut self, components: impl DynamicBundle) -> Entity {
         self.world.spawn(components)
     }
+
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        self.world.despawn(entity)?;
+        self.send_event(Despawned(entity));
+        Ok(())
+    }
+
+    // Despawns the entity along with everything whose chain of P links leads back to it.
+    pub fn despawn_recursive<P: ParentOf>(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        if !self.world.contains(entity) {
+            return Err(NoSuchEntity);
+        }
+        let mut children = HashMap::<Entity, Vec<Entity>>::new();
+        for (child, parent) in self.world.query::<&P>().iter() {
+            children.entry(parent.parent()).or_default().push(child);
+        }
+        // Each list is taken once, so a cycle can't loop forever.
+        let mut pending = vec![entity];
+        while let Some(entity) = pending.pop() {
+            pending.extend(children.remove(&entity).unwrap_or_default());
+            let _ = self.despawn(entity);
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self, registry: &SnapshotRegistry) -> Snapshot {
+        Snapshot {
+            entities: self
+                .world
+                .iter()
+                .map(|entity| (entity.entity(), registry.capture(entity)))
+                .collect(),
+        }
+    }
+}
+
+fn update_events<T: Send + Sync + 'static>(world: &World) {
+    let mut query = world.get_singleton::<Events<T>>();
+    let (events,) = query.get().unwrap();
+    events.update();
 }
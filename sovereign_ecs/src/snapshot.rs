@@ -0,0 +1,240 @@
+use hecs::{Component, Entity, EntityRef};
+use std::{
+    any::{type_name, TypeId},
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+type Capture = Box<dyn Fn(&EntityRef<'_>) -> Option<String> + Send + Sync>;
+
+struct ComponentInfo {
+    name: String,
+    capture: Option<Capture>,
+}
+
+// hecs keeps no names for component types, so snapshots only know the types registered here.
+// Anything else shows up under its TypeId.
+#[derive(Default)]
+pub struct SnapshotRegistry {
+    components: HashMap<TypeId, ComponentInfo>,
+}
+
+impl SnapshotRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Records only whether an entity has the component.
+    pub fn register<T: Component>(&mut self) -> &mut Self {
+        self.insert::<T>(None)
+    }
+
+    pub fn register_debug<T: Component + fmt::Debug>(&mut self) -> &mut Self {
+        self.register_with::<T>(|component| format!("{:?}", component))
+    }
+
+    // Records the value returned by format, for components without Debug or where only a
+    // few fields are worth comparing.
+    pub fn register_with<T: Component>(&mut self, format: fn(&T) -> String) -> &mut Self {
+        self.insert::<T>(Some(Box::new(move |entity| {
+            entity.get::<&T>().map(|component| format(&component))
+        })))
+    }
+
+    fn insert<T: Component>(&mut self, capture: Option<Capture>) -> &mut Self {
+        self.components.insert(
+            TypeId::of::<T>(),
+            ComponentInfo {
+                name: short_type_name(type_name::<T>()),
+                capture,
+            },
+        );
+        self
+    }
+
+    pub(crate) fn capture(&self, entity: EntityRef<'_>) -> BTreeMap<String, Option<String>> {
+        entity
+            .component_types()
+            .map(|ty| match self.components.get(&ty) {
+                Some(info) => (
+                    info.name.clone(),
+                    info.capture.as_ref().and_then(|capture| capture(&entity)),
+                ),
+                None => (format!("{:?}", ty), None),
+            })
+            .collect()
+    }
+}
+
+// Drops module paths, including those of generic arguments.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (i, c) in name.char_indices() {
+        match c {
+            ':' => segment_start = i + 1,
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&' => {
+                short.push_str(&name[segment_start..i]);
+                short.push(c);
+                segment_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    short.push_str(&name[segment_start..]);
+    short
+}
+
+// The components of every entity at one point in time, with the values of those registered
+// with a formatter.
+pub struct Snapshot {
+    pub entities: BTreeMap<Entity, BTreeMap<String, Option<String>>>,
+}
+
+impl Snapshot {
+    pub fn component_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for components in self.entities.values() {
+            for name in components.keys() {
+                *counts.entry(name.as_str()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    // What changed between this snapshot and a later one.
+    pub fn diff(&self, later: &Snapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        for (entity, components) in &self.entities {
+            match later.entities.get(entity) {
+                Some(later_components) => {
+                    let changes = EntityDiff::new(*entity, components, later_components);
+                    if !changes.is_empty() {
+                        diff.changed.push(changes);
+                    }
+                }
+                None => diff
+                    .despawned
+                    .push((*entity, components.keys().cloned().collect())),
+            }
+        }
+        for (entity, components) in &later.entities {
+            if !self.entities.contains_key(entity) {
+                diff.spawned
+                    .push((*entity, components.keys().cloned().collect()));
+            }
+        }
+
+        let counts = self.component_counts();
+        let later_counts = later.component_counts();
+        let mut names = counts.keys().chain(later_counts.keys()).collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        diff.counts = names
+            .into_iter()
+            .map(|name| {
+                let before = counts.get(name).copied().unwrap_or_default();
+                let after = later_counts.get(name).copied().unwrap_or_default();
+                (name.to_string(), before, after)
+            })
+            .filter(|(_name, before, after)| before != after)
+            .collect();
+        diff
+    }
+}
+
+pub struct EntityDiff {
+    pub entity: Entity,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // Component name, old value and new value.
+    pub modified: Vec<(String, String, String)>,
+}
+
+impl EntityDiff {
+    fn new(
+        entity: Entity,
+        before: &BTreeMap<String, Option<String>>,
+        after: &BTreeMap<String, Option<String>>,
+    ) -> Self {
+        let mut diff = Self {
+            entity,
+            added: Vec::new(),
+            removed: Vec::new(),
+            modified: Vec::new(),
+        };
+        for (name, value) in before {
+            match after.get(name) {
+                Some(later_value) => {
+                    if let (Some(value), Some(later_value)) = (value, later_value) {
+                        if value != later_value {
+                            diff.modified
+                                .push((name.clone(), value.clone(), later_value.clone()));
+                        }
+                    }
+                }
+                None => diff.removed.push(name.clone()),
+            }
+        }
+        diff.added = after
+            .keys()
+            .filter(|name| !before.contains_key(*name))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct SnapshotDiff {
+    pub spawned: Vec<(Entity, Vec<String>)>,
+    pub despawned: Vec<(Entity, Vec<String>)>,
+    pub changed: Vec<EntityDiff>,
+    // Component name with how many entities had it before and after, for the types whose
+    // count changed. A count that only grows points at a leak.
+    pub counts: Vec<(String, usize, usize)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty() && self.despawned.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} spawned, {} despawned, {} changed",
+            self.spawned.len(),
+            self.despawned.len(),
+            self.changed.len()
+        )?;
+        for (name, before, after) in &self.counts {
+            write!(f, "\n  {}: {} -> {}", name, before, after)?;
+        }
+        for (entity, components) in &self.spawned {
+            write!(f, "\n+ {:?} [{}]", entity, components.join(", "))?;
+        }
+        for (entity, components) in &self.despawned {
+            write!(f, "\n- {:?} [{}]", entity, components.join(", "))?;
+        }
+        for changes in &self.changed {
+            write!(f, "\n~ {:?}", changes.entity)?;
+            for name in &changes.added {
+                write!(f, "\n    + {}", name)?;
+            }
+            for name in &changes.removed {
+                write!(f, "\n    - {}", name)?;
+            }
+            for (name, before, after) in &changes.modified {
+                write!(f, "\n    {}: {} -> {}", name, before, after)?;
+            }
+        }
+        Ok(())
+    }
+}
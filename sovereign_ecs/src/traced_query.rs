@@ -0,0 +1,63 @@
+use hecs::{
+    Entity, PreparedQuery, PreparedQueryBorrow, PreparedQueryIter, Query, World as HecsWorld,
+};
+use tracing::{field, span::Entered, Span};
+
+// A PreparedQuery that opens a span named after it while its results are iterated, so the time
+// spent walking them, loop bodies included, shows up under the query with how many entities
+// matched.
+pub struct TracedQuery<Q: Query> {
+    name: &'static str,
+    query: PreparedQuery<Q>,
+}
+
+impl<Q: Query> TracedQuery<Q> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            query: PreparedQuery::new(),
+        }
+    }
+
+    pub fn query<'q>(&'q mut self, world: &'q HecsWorld) -> TracedQueryBorrow<'q, Q> {
+        TracedQueryBorrow {
+            span: tracing::debug_span!("query", query = self.name, entities = field::Empty),
+            borrow: self.query.query(world),
+        }
+    }
+}
+
+pub struct TracedQueryBorrow<'q, Q: Query> {
+    span: Span,
+    borrow: PreparedQueryBorrow<'q, Q>,
+}
+
+impl<'q, Q: Query> TracedQueryBorrow<'q, Q> {
+    pub fn iter(&mut self) -> TracedQueryIter<'_, Q> {
+        let iter = self.borrow.iter();
+        self.span.record("entities", iter.len());
+        TracedQueryIter {
+            iter,
+            _entered: self.span.enter(),
+        }
+    }
+}
+
+pub struct TracedQueryIter<'q, Q: Query> {
+    iter: PreparedQueryIter<'q, Q>,
+    _entered: Entered<'q>,
+}
+
+impl<'q, Q: Query> Iterator for TracedQueryIter<'q, Q> {
+    type Item = (Entity, Q::Item<'q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<Q: Query> ExactSizeIterator for TracedQueryIter<'_, Q> {}
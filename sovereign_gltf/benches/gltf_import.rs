@@ -0,0 +1,95 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use sovereign_gltf::import_gltf;
+use std::{fs, path::PathBuf};
+
+// Writes a subdivided plane as a .gltf/.bin pair, so the benchmark needs no checked-in assets.
+fn write_grid(resolution: u32) -> PathBuf {
+    let vertex_count = (resolution + 1) * (resolution + 1);
+    let index_count = resolution * resolution * 6;
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    for y in 0..=resolution {
+        for x in 0..=resolution {
+            let u = x as f32 / resolution as f32;
+            let v = y as f32 / resolution as f32;
+            positions.extend([u - 0.5, 0.0, v - 0.5]);
+            normals.extend([0.0f32, 1.0, 0.0]);
+            uvs.extend([u, v]);
+        }
+    }
+    let mut indices = Vec::new();
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let a = y * (resolution + 1) + x;
+            let b = a + resolution + 1;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    let mut buffer = Vec::new();
+    let mut views = Vec::new();
+    for bytes in [
+        bytes_of(&positions),
+        bytes_of(&normals),
+        bytes_of(&uvs),
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect(),
+    ] {
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{}}}"#,
+            buffer.len(),
+            bytes.len()
+        ));
+        buffer.extend(bytes);
+    }
+
+    let dir = std::env::temp_dir().join("sovereign_gltf_bench");
+    fs::create_dir_all(&dir).unwrap();
+    let name = format!("grid{}", resolution);
+    fs::write(dir.join(format!("{}.bin", name)), &buffer).unwrap();
+    let json = format!(
+        r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [{{"mesh": 0}}],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}}, "indices": 3}}]}}],
+  "buffers": [{{"uri": "{name}.bin", "byteLength": {buffer_len}}}],
+  "bufferViews": [{views}],
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": {vertex_count}, "type": "VEC3", "min": [-0.5, 0, -0.5], "max": [0.5, 0, 0.5]}},
+    {{"bufferView": 1, "componentType": 5126, "count": {vertex_count}, "type": "VEC3"}},
+    {{"bufferView": 2, "componentType": 5126, "count": {vertex_count}, "type": "VEC2"}},
+    {{"bufferView": 3, "componentType": 5125, "count": {index_count}, "type": "SCALAR"}}
+  ]
+}}"#,
+        buffer_len = buffer.len(),
+        views = views.join(","),
+    );
+    let path = dir.join(format!("{}.gltf", name));
+    fs::write(&path, json).unwrap();
+    path
+}
+
+fn bytes_of(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn gltf_import(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gltf_import");
+    group.sample_size(20);
+    for resolution in [64, 256] {
+        let path = write_grid(resolution);
+        group.bench_with_input(BenchmarkId::from_parameter(resolution), &path, |b, path| {
+            b.iter(|| import_gltf(path).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, gltf_import);
+criterion_main!(benches);
@@ -1,40 +1,154 @@
 use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 use gltf::{
+    image::Format,
     scene::Transform,
-    texture::{MinFilter, WrappingMode},
+    texture::{self as gltf_texture, MinFilter, WrappingMode},
 };
 use sovereign_ecs::World;
+use sovereign_math::{Aabb, Color};
 use sovereign_render::{
-    asset::{Assets, Handle},
-    id::{ImageId, SamplerId},
-    material::{Material, MaterialUniform},
-    mesh::{Mesh, Vertex},
+    asset::{texture::TextureData, Assets, Handle},
+    hash::ContentHash,
+    id::SamplerId,
+    material::{Material, MaterialTexture, MaterialUniform},
+    mesh::{Indices, Mesh, MorphDelta, Vertex},
+    texture::Texture,
     *,
 };
-use std::{error::Error, path::Path};
+use std::{
+    collections::HashSet,
+    error::Error,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+mod loader;
+mod report;
+mod scene;
+
+pub use loader::{load_gltf_async, LoadEvent, LoadHandle, LoadProgress};
+pub use report::{ImportIssue, ImportReport};
+pub use scene::SceneInstance;
 
 #[derive(Debug)]
 pub struct Gltf {
     pub samplers: Vec<SamplerId>,
-    pub images: Vec<ImageId>,
+    pub images: Vec<Handle<Texture>>,
     pub materials: Vec<Handle<Material>>,
     pub meshes: Vec<GltfMesh>,
     pub nodes: Vec<GltfNode>,
-    pub top_nodes: Vec<usize>,
+    pub scenes: Vec<GltfScene>,
+    pub default_scene: usize,
+    pub report: ImportReport,
+}
+
+impl Gltf {
+    pub fn scene_by_name(&self, name: &str) -> Option<usize> {
+        self.scenes
+            .iter()
+            .position(|scene| scene.name.as_deref() == Some(name))
+    }
+}
+
+#[derive(Debug)]
+pub struct GltfScene {
+    pub name: Option<String>,
+    pub nodes: Vec<usize>,
 }
 
 #[derive(Debug)]
 pub struct GltfMesh {
+    pub primitives: Vec<GltfPrimitive>,
+    // Empty unless the mesh has morph targets.
+    pub morph_weights: Vec<f32>,
+}
+
+#[derive(Debug)]
+pub struct GltfPrimitive {
     pub mesh: Handle<Mesh>,
     pub material_idx: usize,
+    pub bounds: Aabb,
 }
 
 #[derive(Debug)]
 pub struct GltfNode {
+    pub name: Option<String>,
     pub mesh_idx: Option<usize>,
+    pub morph_weights: Option<Vec<f32>>,
     pub local_transform: Mat4,
     pub parent: Option<usize>,
     pub children: Vec<usize>,
+    pub skin: Option<GltfSkin>,
+}
+
+// Bounds of the vertices each joint moves, in that joint's space, so a skinned mesh's bounds can
+// follow its joints without skinning on the CPU. Joints that move no vertices are left out.
+#[derive(Debug)]
+pub struct GltfSkin {
+    pub joints: Vec<usize>,
+    pub joint_bounds: Vec<Aabb>,
+}
+
+pub struct GltfImport {
+    source: String,
+    // Kept until the meshes are built, which read their attributes straight from the buffers.
+    document: gltf::Document,
+    buffers: Vec<gltf::buffer::Data>,
+    samplers: Vec<(String, D3D12_SAMPLER_DESC)>,
+    images: Vec<ImportedImage>,
+    materials: Vec<ImportedMaterial>,
+    meshes: Vec<ImportedMesh>,
+    nodes: Vec<GltfNode>,
+    scenes: Vec<GltfScene>,
+    default_scene: usize,
+    report: ImportReport,
+}
+
+impl GltfImport {
+    pub fn report(&self) -> &ImportReport {
+        &self.report
+    }
+}
+
+// Decoded image waiting for upload; data is None if decoding failed and a placeholder is used.
+pub struct ImportedImage {
+    pub key: String,
+    pub data: Option<TextureData>,
+}
+
+struct ImportedMaterial {
+    key: String,
+    uniform: MaterialUniform,
+    base_color_texture: Option<ImportedTexture>,
+    metallic_roughness_texture: Option<ImportedTexture>,
+    normal_texture: Option<ImportedTexture>,
+    occlusion_texture: Option<ImportedTexture>,
+    emissive_texture: Option<ImportedTexture>,
+}
+
+#[derive(Clone, Copy)]
+struct ImportedTexture {
+    image_idx: usize,
+    sampler_idx: Option<usize>,
+}
+
+struct ImportedMesh {
+    primitives: Vec<ImportedPrimitive>,
+    morph_weights: Vec<f32>,
+}
+
+struct ImportedPrimitive {
+    key: String,
+    mesh_idx: usize,
+    primitive_idx: usize,
+    // Decoded up front for the import report; the other attributes wait for the build.
+    positions: Vec<Vec3>,
+    indices: Indices,
+    morph_targets: Vec<Vec<MorphDelta>>,
+    // Joint slots and weights per vertex, empty unless the primitive is skinned.
+    skin_weights: Vec<([u16; 4], [f32; 4])>,
+    material_idx: usize,
+    bounds: Aabb,
 }
 
 pub fn load_gltf(
@@ -42,20 +156,54 @@ pub fn load_gltf(
     world: &mut World,
     path: &Path,
 ) -> Result<Gltf, Box<dyn Error>> {
-    let (document, buffers, _images) = gltf::import(path)?;
+    let import = import_gltf(path)?;
+    Ok(build_gltf(renderer, world, import))
+}
 
-    let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
-    let (asset_meshes,) = meshes_query.get().unwrap();
+pub fn import_gltf(path: &Path) -> Result<GltfImport, gltf::Error> {
+    // Nothing else holds the control, so the import always runs to the end.
+    let control = ImportControl::default();
+    import_gltf_with(path, &control).map(Option::unwrap)
+}
 
-    let mut materials_query = world.get_singleton::<Assets<Material>>();
-    let (asset_materials,) = materials_query.get().unwrap();
+// Shared with the thread running an import, which stops between steps once canceled.
+#[derive(Default)]
+pub(crate) struct ImportControl {
+    pub canceled: AtomicBool,
+    pub bytes_decoded: AtomicU64,
+}
+
+impl ImportControl {
+    fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Relaxed)
+    }
+
+    fn add_decoded(&self, bytes: usize) {
+        self.bytes_decoded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+// Returns None if the import was canceled before it finished.
+pub(crate) fn import_gltf_with(
+    path: &Path,
+    control: &ImportControl,
+) -> Result<Option<GltfImport>, gltf::Error> {
+    let (document, buffers, gltf_images) = gltf::import(path)?;
+    control.add_decoded(buffers.iter().map(|buffer| buffer.len()).sum());
+    if control.is_canceled() {
+        return Ok(None);
+    }
+    let source = path
+        .canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string();
 
     let mut samplers = Vec::new();
-    let mut images = Vec::new();
     let mut materials = Vec::new();
     let mut meshes = Vec::new();
     let mut nodes = Vec::new();
-    let mut top_nodes = Vec::new();
 
     for sampler in document.samplers() {
         let desc = D3D12_SAMPLER_DESC {
@@ -67,87 +215,164 @@ pub fn load_gltf(
             AddressW: D3D12_TEXTURE_ADDRESS_MODE_BORDER,
             ..Default::default()
         };
-        samplers.push(renderer.sampler_heap.create_sampler(&desc));
-    }
-
-    for _ in document.images() {
-        images.push(renderer.checkerboard_image);
+        let name = sampler
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("glTF Sampler {}", sampler.index().unwrap_or_default()));
+        samplers.push((name, desc));
     }
 
+    let mut srgb_images = HashSet::new();
     for material in document.materials() {
+        let pbr = material.pbr_metallic_roughness();
         let uniform = MaterialUniform {
-            base_color_factors: Vec4::from_array(
-                material.pbr_metallic_roughness().base_color_factor(),
-            ),
-            perceptual_roughness: material.pbr_metallic_roughness().roughness_factor(),
-            metallic: material.pbr_metallic_roughness().metallic_factor(),
+            base_color_factors: Color::from(pbr.base_color_factor()),
+            emissive_factors: Color::from(material.emissive_factor()),
+            emissive_strength: material.emissive_strength().unwrap_or(1.0),
+            normal_scale: material.normal_texture().map_or(1.0, |t| t.scale()),
+            perceptual_roughness: pbr.roughness_factor(),
+            metallic: pbr.metallic_factor(),
             reflectance: 0.5,
-            pad: 0.0,
+            occlusion_strength: material.occlusion_texture().map_or(1.0, |t| t.strength()),
         };
 
-        let (color_image, color_sampler) = if let Some(base_color_texture) =
-            material.pbr_metallic_roughness().base_color_texture()
-        {
-            let image_idx = base_color_texture.texture().source().index();
-            let sampler_idx = base_color_texture.texture().sampler().index().unwrap();
-            (Some(images[image_idx]), Some(samplers[sampler_idx]))
-        } else {
-            (None, None)
-        };
+        let base_color_texture = pbr
+            .base_color_texture()
+            .map(|t| import_texture(t.texture()));
+        let emissive_texture = material
+            .emissive_texture()
+            .map(|t| import_texture(t.texture()));
+        for texture in base_color_texture.iter().chain(&emissive_texture) {
+            srgb_images.insert(texture.image_idx);
+        }
 
-        materials.push(asset_materials.push(Material {
+        materials.push(ImportedMaterial {
+            key: format!(
+                "{}#material{}",
+                source,
+                material.index().unwrap_or_default()
+            ),
             uniform,
-            color_image,
-            color_sampler,
-        }));
+            base_color_texture,
+            metallic_roughness_texture: pbr
+                .metallic_roughness_texture()
+                .map(|t| import_texture(t.texture())),
+            normal_texture: material
+                .normal_texture()
+                .map(|t| import_texture(t.texture())),
+            occlusion_texture: material
+                .occlusion_texture()
+                .map(|t| import_texture(t.texture())),
+            emissive_texture,
+        });
     }
 
+    let images = gltf_images
+        .into_iter()
+        .enumerate()
+        .map(|(idx, image)| {
+            let format = if srgb_images.contains(&idx) {
+                DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+            } else {
+                DXGI_FORMAT_R8G8B8A8_UNORM
+            };
+            control.add_decoded(image.pixels.len());
+            let data = to_rgba8(&image).map(|pixels| TextureData {
+                width: image.width,
+                height: image.height,
+                format,
+                mips: vec![pixels],
+            });
+            if data.is_none() {
+                tracing::warn!(
+                    image = idx,
+                    format = ?image.format,
+                    "Unsupported glTF image format, using a placeholder"
+                );
+            }
+            // Keyed by content so images repeated across references or files upload once.
+            let key = match &data {
+                Some(data) => format!("image:{:016x}", data.content_hash()),
+                None => format!("{}#image{}", source, idx),
+            };
+            ImportedImage { key, data }
+        })
+        .collect::<Vec<_>>();
+
     for gltf_mesh in document.meshes() {
+        if control.is_canceled() {
+            return Ok(None);
+        }
+        let mut primitives = Vec::new();
         for primitive in gltf_mesh.primitives() {
             let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-
             let positions = reader
                 .read_positions()
                 .unwrap()
-                .map(|p| Vec4::new(p[0], p[1], p[2], 1.0))
-                .collect::<Vec<_>>();
-            let normals = reader
-                .read_normals()
-                .unwrap()
-                .map(|n| Vec4::new(n[0], n[1], n[2], 1.0))
+                .map(Vec3::from_array)
                 .collect::<Vec<_>>();
-            let colors = reader.read_colors(0).map(|c| {
-                c.into_rgba_f32()
-                    .map(|c| Vec4::from_array(c))
-                    .collect::<Vec<_>>()
-            });
-            let uvs = reader.read_tex_coords(0).map(|u| {
-                u.into_f32()
-                    .map(|u| Vec2::from_array(u))
-                    .collect::<Vec<_>>()
-            });
 
-            let indices = reader
-                .read_indices()
-                .unwrap()
-                .into_u32()
+            let indices = Indices::from_u32(
+                reader
+                    .read_indices()
+                    .unwrap()
+                    .into_u32()
+                    .collect::<Vec<_>>(),
+            );
+
+            let vertex_count = positions.len();
+            let morph_targets = reader
+                .read_morph_targets()
+                .map(|(positions, normals, _tangents)| {
+                    let mut deltas = vec![MorphDelta::default(); vertex_count];
+                    for (delta, position) in deltas.iter_mut().zip(positions.into_iter().flatten())
+                    {
+                        delta.position = Vec3::from_array(position).extend(0.0);
+                    }
+                    for (delta, normal) in deltas.iter_mut().zip(normals.into_iter().flatten()) {
+                        delta.normal = Vec3::from_array(normal).extend(0.0);
+                    }
+                    deltas
+                })
                 .collect::<Vec<_>>();
-            let mut vertices = Vec::new();
-            for i in 0..positions.len() {
-                vertices.push(Vertex {
-                    position: positions[i],
-                    normal: normals[i],
-                    color: colors.as_ref().map(|c| c[i]).unwrap_or_else(|| Vec4::ONE),
-                    uv: uvs.as_ref().map(|u| u[i]).unwrap_or_else(|| Vec2::ZERO),
-                    pad: Vec2::ZERO,
-                });
-            }
 
-            meshes.push(GltfMesh {
-                mesh: asset_meshes.push(Mesh { vertices, indices }),
+            let skin_weights = reader
+                .read_joints(0)
+                .zip(reader.read_weights(0))
+                .map(|(joints, weights)| joints.into_u16().zip(weights.into_f32()).collect())
+                .unwrap_or_default();
+
+            let bounds = primitive.bounding_box();
+            primitives.push(ImportedPrimitive {
+                key: format!(
+                    "{}#mesh{}/primitive{}",
+                    source,
+                    gltf_mesh.index(),
+                    primitive.index()
+                ),
+                mesh_idx: gltf_mesh.index(),
+                primitive_idx: primitive.index(),
+                positions,
+                indices,
+                morph_targets,
+                skin_weights,
                 material_idx: primitive.material().index().unwrap_or(0),
+                bounds: Aabb::new(Vec3::from_array(bounds.min), Vec3::from_array(bounds.max)),
             });
         }
+
+        // Every primitive of a mesh has the same targets, driven by the mesh's weights.
+        let target_count = primitives
+            .iter()
+            .map(|primitive| primitive.morph_targets.len())
+            .max()
+            .unwrap_or(0);
+        let mut morph_weights = gltf_mesh.weights().unwrap_or_default().to_vec();
+        morph_weights.resize(target_count, 0.0);
+        meshes.push(ImportedMesh {
+            primitives,
+            morph_weights,
+        });
     }
 
     for gltf_node in document.nodes() {
@@ -166,10 +391,16 @@ pub fn load_gltf(
             }
         };
         nodes.push(GltfNode {
+            name: gltf_node.name().map(str::to_string),
             mesh_idx,
+            morph_weights: gltf_node.weights().map(<[f32]>::to_vec),
             local_transform: transform,
             parent: None,
             children: Vec::new(),
+            skin: gltf_node
+                .skin()
+                .zip(mesh_idx)
+                .map(|(skin, mesh_idx)| import_skin(&skin, &buffers, &meshes[mesh_idx])),
         });
     }
 
@@ -180,20 +411,163 @@ pub fn load_gltf(
         }
     }
 
-    for (idx, node) in nodes.iter().enumerate() {
-        if node.parent.is_none() {
-            top_nodes.push(idx);
-        }
+    let mut scenes = document
+        .scenes()
+        .map(|scene| GltfScene {
+            name: scene.name().map(str::to_string),
+            nodes: scene.nodes().map(|node| node.index()).collect(),
+        })
+        .collect::<Vec<_>>();
+    // Files without scenes still get one, holding every root node.
+    if scenes.is_empty() {
+        scenes.push(GltfScene {
+            name: None,
+            nodes: (0..nodes.len())
+                .filter(|idx| nodes[*idx].parent.is_none())
+                .collect(),
+        });
     }
+    let default_scene = document.default_scene().map_or(0, |scene| scene.index());
+
+    let report = ImportReport::new(&images, &materials, &meshes);
+    report.log(&source);
 
-    Ok(Gltf {
+    Ok(Some(GltfImport {
+        source,
+        document,
+        buffers,
         samplers,
         images,
         materials,
         meshes,
         nodes,
-        top_nodes,
-    })
+        scenes,
+        default_scene,
+        report,
+    }))
+}
+
+pub fn build_gltf(renderer: &mut Renderer, world: &mut World, import: GltfImport) -> Gltf {
+    GltfBuild::new(import).finish(renderer, world)
+}
+
+// Turns an import into GPU resources a piece at a time; images and meshes are the expensive
+// part, so they are handed to the renderer one per call and everything else is created in
+// `finish`.
+pub(crate) struct GltfBuild {
+    import: GltfImport,
+    pending_images: std::vec::IntoIter<ImportedImage>,
+    images: Vec<Handle<Texture>>,
+    pending_meshes: std::vec::IntoIter<ImportedMesh>,
+    meshes: Vec<GltfMesh>,
+}
+
+impl GltfBuild {
+    pub fn new(mut import: GltfImport) -> Self {
+        let pending_images = std::mem::take(&mut import.images).into_iter();
+        let pending_meshes = std::mem::take(&mut import.meshes).into_iter();
+        Self {
+            images: Vec::with_capacity(pending_images.len()),
+            meshes: Vec::with_capacity(pending_meshes.len()),
+            import,
+            pending_images,
+            pending_meshes,
+        }
+    }
+
+    pub fn image_count(&self) -> usize {
+        self.images.len() + self.pending_images.len()
+    }
+
+    pub fn built_images(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len() + self.pending_meshes.len()
+    }
+
+    pub fn built_meshes(&self) -> usize {
+        self.meshes.len()
+    }
+
+    // Builds the next image, or the next mesh once every image is done. Returns false once
+    // both are.
+    pub fn build_next(&mut self, renderer: &mut Renderer, world: &World) -> bool {
+        if let Some(image) = self.pending_images.next() {
+            self.images.push(build_image(renderer, world, image));
+            return true;
+        }
+        let Some(mesh) = self.pending_meshes.next() else {
+            return false;
+        };
+        self.meshes
+            .push(build_mesh(renderer, world, &self.import, mesh));
+        true
+    }
+
+    pub fn finish(mut self, renderer: &mut Renderer, world: &World) -> Gltf {
+        while self.build_next(renderer, world) {}
+        let import = self.import;
+        let images = self.images;
+
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (asset_materials,) = materials_query.get().unwrap();
+
+        let default_sampler = renderer.default_sampler();
+        let samplers = import
+            .samplers
+            .iter()
+            .map(|(name, desc)| {
+                renderer.create_sampler(name, desc).unwrap_or_else(|err| {
+                    tracing::warn!(sampler = %name, "Failed to create glTF sampler: {}", err);
+                    default_sampler
+                })
+            })
+            .collect::<Vec<_>>();
+        let resolve = |texture: Option<ImportedTexture>| {
+            texture.map(|texture| MaterialTexture {
+                texture: images[texture.image_idx].clone(),
+                sampler: texture
+                    .sampler_idx
+                    .map_or(default_sampler, |idx| samplers[idx]),
+            })
+        };
+
+        let materials = import
+            .materials
+            .into_iter()
+            .map(|material| {
+                if let Some(handle) = asset_materials.get_by_key(&material.key) {
+                    return handle;
+                }
+                asset_materials.insert_with_key(
+                    material.key,
+                    Material {
+                        uniform: material.uniform,
+                        base_color_texture: resolve(material.base_color_texture),
+                        metallic_roughness_texture: resolve(material.metallic_roughness_texture),
+                        normal_texture: resolve(material.normal_texture),
+                        occlusion_texture: resolve(material.occlusion_texture),
+                        emissive_texture: resolve(material.emissive_texture),
+                    },
+                )
+            })
+            .collect();
+
+        tracing::debug!(source = %import.source, "Built glTF scene");
+
+        Gltf {
+            samplers,
+            images,
+            materials,
+            meshes: self.meshes,
+            nodes: import.nodes,
+            scenes: import.scenes,
+            default_scene: import.default_scene,
+            report: import.report,
+        }
+    }
 }
 
 fn extract_filter(filter: MinFilter) -> D3D12_FILTER {
@@ -214,3 +588,175 @@ fn extract_addressing_mode(mode: WrappingMode) -> D3D12_TEXTURE_ADDRESS_MODE {
         WrappingMode::Repeat => D3D12_TEXTURE_ADDRESS_MODE_WRAP,
     }
 }
+
+// Reuses a texture already uploaded under the same key, shared by the other scene importers.
+pub fn build_image(
+    renderer: &mut Renderer,
+    world: &World,
+    image: ImportedImage,
+) -> Handle<Texture> {
+    {
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+        if let Some(handle) = textures.get_by_key(&image.key) {
+            return handle;
+        }
+    }
+    let Some(data) = image.data else {
+        return renderer.checkerboard();
+    };
+    match renderer.create_texture(world, &image.key, &data) {
+        Ok(handle) => {
+            let mut textures_query = world.get_singleton::<Assets<Texture>>();
+            let (textures,) = textures_query.get().unwrap();
+            textures.set_key(&handle, image.key);
+            handle
+        }
+        Err(err) => {
+            tracing::warn!(image = %image.key, "Failed to upload image: {}", err);
+            renderer.checkerboard()
+        }
+    }
+}
+
+fn build_mesh(
+    renderer: &mut Renderer,
+    world: &World,
+    import: &GltfImport,
+    mesh: ImportedMesh,
+) -> GltfMesh {
+    let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
+    let (asset_meshes,) = meshes_query.get().unwrap();
+    GltfMesh {
+        primitives: mesh
+            .primitives
+            .into_iter()
+            .map(|primitive| GltfPrimitive {
+                mesh: asset_meshes.get_by_key(&primitive.key).unwrap_or_else(|| {
+                    let mesh = build_primitive_mesh(renderer, import, &primitive);
+                    asset_meshes.insert_with_key(primitive.key.clone(), mesh)
+                }),
+                material_idx: primitive.material_idx,
+                bounds: primitive.bounds,
+            })
+            .collect(),
+        morph_weights: mesh.morph_weights,
+    }
+}
+
+// Interleaves the attributes straight from the accessors into upload memory, so a large mesh is
+// never held as vertices on the CPU.
+fn build_primitive_mesh(
+    renderer: &mut Renderer,
+    import: &GltfImport,
+    primitive: &ImportedPrimitive,
+) -> Mesh {
+    let gltf_primitive = import
+        .document
+        .meshes()
+        .nth(primitive.mesh_idx)
+        .and_then(|mesh| mesh.primitives().nth(primitive.primitive_idx))
+        .unwrap();
+    let reader = gltf_primitive.reader(|buffer| Some(&import.buffers[buffer.index()]));
+    let read_vertices = || {
+        let mut normals = reader.read_normals().into_iter().flatten();
+        let mut colors = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgba_f32())
+            .into_iter()
+            .flatten();
+        let mut uvs = reader
+            .read_tex_coords(0)
+            .map(|uvs| uvs.into_f32())
+            .into_iter()
+            .flatten();
+        primitive.positions.iter().map(move |position| Vertex {
+            position: position.extend(1.0),
+            normal: normals
+                .next()
+                .map_or(Vec4::ZERO, |n| Vec3::from_array(n).extend(1.0)),
+            color: colors.next().map_or(Vec4::ONE, Vec4::from_array),
+            uv: uvs.next().map_or(Vec2::ZERO, Vec2::from_array),
+            pad: Vec2::ZERO,
+        })
+    };
+
+    let (vertices, staged_vertices) =
+        match renderer.stage_vertices(primitive.positions.len(), read_vertices()) {
+            Ok(staged) => (Vec::new(), Some(staged)),
+            Err(err) => {
+                tracing::warn!(mesh = %primitive.key, "Failed to stage vertices: {}", err);
+                (read_vertices().collect(), None)
+            }
+        };
+    Mesh {
+        vertices,
+        staged_vertices,
+        indices: primitive.indices.clone(),
+        morph_targets: primitive.morph_targets.clone(),
+    }
+}
+
+fn import_skin(skin: &gltf::Skin, buffers: &[gltf::buffer::Data], mesh: &ImportedMesh) -> GltfSkin {
+    let inverse_binds = skin
+        .reader(|buffer| Some(&buffers[buffer.index()]))
+        .read_inverse_bind_matrices()
+        .map(|matrices| {
+            matrices
+                .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let joints = skin.joints().map(|joint| joint.index()).collect::<Vec<_>>();
+
+    let mut joint_points = vec![Vec::new(); joints.len()];
+    for primitive in &mesh.primitives {
+        for (position, (slots, weights)) in primitive.positions.iter().zip(&primitive.skin_weights)
+        {
+            for (slot, weight) in slots.iter().zip(weights) {
+                let slot = *slot as usize;
+                if *weight <= 0.0 || slot >= joints.len() {
+                    continue;
+                }
+                let inverse_bind = inverse_binds.get(slot).unwrap_or(&Mat4::IDENTITY);
+                joint_points[slot].push(inverse_bind.transform_point3(*position));
+            }
+        }
+    }
+
+    let (joints, joint_bounds) = joints
+        .into_iter()
+        .zip(joint_points)
+        .filter(|(_joint, points)| !points.is_empty())
+        .map(|(joint, points)| (joint, Aabb::from_points(points)))
+        .unzip();
+    GltfSkin {
+        joints,
+        joint_bounds,
+    }
+}
+
+fn import_texture(texture: gltf_texture::Texture) -> ImportedTexture {
+    ImportedTexture {
+        image_idx: texture.source().index(),
+        sampler_idx: texture.sampler().index(),
+    }
+}
+
+fn to_rgba8(image: &gltf::image::Data) -> Option<Vec<u8>> {
+    let pixels = &image.pixels;
+    let rgba = match image.format {
+        Format::R8G8B8A8 => pixels.clone(),
+        Format::R8G8B8 => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R8G8 => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[1], 0, 255])
+            .collect(),
+        Format::R8 => pixels.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        _ => return None,
+    };
+    Some(rgba)
+}
@@ -0,0 +1,174 @@
+use sovereign_ecs::World;
+use sovereign_render::Renderer;
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{import_gltf_with, Gltf, GltfBuild, ImportControl};
+
+type ImportResult = Result<crate::GltfImport, String>;
+
+enum LoadState {
+    Importing(Receiver<ImportResult>),
+    Building(Box<GltfBuild>),
+    Done,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadProgress {
+    pub imported: bool,
+    // Buffer and image bytes decoded by the import thread so far.
+    pub bytes_decoded: u64,
+    pub images_uploaded: usize,
+    pub image_count: usize,
+    pub meshes_uploaded: usize,
+    pub mesh_count: usize,
+}
+
+impl LoadProgress {
+    // Import counts as the first half, uploads as the second.
+    pub fn fraction(&self) -> f32 {
+        if !self.imported {
+            return 0.0;
+        }
+        let total = (self.image_count + self.mesh_count).max(1) as f32;
+        let uploaded = (self.images_uploaded + self.meshes_uploaded) as f32;
+        0.5 + 0.5 * uploaded / total
+    }
+}
+
+// Sent through the world's `Events<LoadEvent>`, which has to be registered with
+// `World::add_event` before a load is polled.
+#[derive(Clone, Debug)]
+pub enum LoadEvent {
+    Progress(PathBuf, LoadProgress),
+    Loaded(PathBuf),
+    Failed(PathBuf, String),
+    Canceled(PathBuf),
+}
+
+// An in-flight `load_gltf_async`. Parsing and mesh building run on a worker thread; GPU uploads
+// happen in `poll`, a few images and meshes at a time so a large file doesn't stall a single
+// frame. Dropping the handle stops the worker at its next step.
+pub struct LoadHandle {
+    path: PathBuf,
+    state: LoadState,
+    control: Arc<ImportControl>,
+}
+
+pub fn load_gltf_async(path: PathBuf) -> LoadHandle {
+    let (sender, receiver) = mpsc::channel();
+    let import_path = path.clone();
+    let control = Arc::new(ImportControl::default());
+    let import_control = control.clone();
+    std::thread::spawn(move || {
+        let result = match import_gltf_with(&import_path, &import_control) {
+            Ok(Some(import)) => Ok(import),
+            Ok(None) => return,
+            Err(err) => Err(err.to_string()),
+        };
+        let _ = sender.send(result);
+    });
+    LoadHandle {
+        path,
+        state: LoadState::Importing(receiver),
+        control,
+    }
+}
+
+impl LoadHandle {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        let mut progress = LoadProgress {
+            bytes_decoded: self.control.bytes_decoded.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        match &self.state {
+            LoadState::Importing(_) => {}
+            LoadState::Building(build) => {
+                progress.imported = true;
+                progress.images_uploaded = build.built_images();
+                progress.image_count = build.image_count();
+                progress.meshes_uploaded = build.built_meshes();
+                progress.mesh_count = build.mesh_count();
+            }
+            LoadState::Done => {
+                progress.imported = true;
+            }
+        }
+        progress
+    }
+
+    // Stops the import thread and drops whatever was already uploaded, so a load for a scene that
+    // is no longer wanted stops taking upload time. Later polls return None.
+    pub fn cancel(&mut self, world: &World) {
+        if matches!(self.state, LoadState::Done) {
+            return;
+        }
+        self.control.canceled.store(true, Ordering::Relaxed);
+        self.state = LoadState::Done;
+        world.send_event(LoadEvent::Canceled(self.path.clone()));
+    }
+
+    // Uploads until `budget` is spent and returns the result once the load finishes. At least one
+    // image or mesh is uploaded per call so progress is always made.
+    pub fn poll(
+        &mut self,
+        renderer: &mut Renderer,
+        world: &World,
+        budget: Duration,
+    ) -> Option<Result<Gltf, Box<dyn Error>>> {
+        let start = Instant::now();
+        if let LoadState::Importing(receiver) = &self.state {
+            let result = match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => Err("import thread panicked".to_string()),
+            };
+            match result {
+                Ok(import) => self.state = LoadState::Building(Box::new(GltfBuild::new(import))),
+                Err(err) => {
+                    self.state = LoadState::Done;
+                    world.send_event(LoadEvent::Failed(self.path.clone(), err.clone()));
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+
+        let LoadState::Building(build) = &mut self.state else {
+            return None;
+        };
+        while build.build_next(renderer, world) {
+            if start.elapsed() >= budget {
+                world.send_event(LoadEvent::Progress(self.path.clone(), self.progress()));
+                return None;
+            }
+        }
+
+        // Taken before the build is consumed, with every image and mesh counted.
+        let progress = self.progress();
+        let LoadState::Building(build) = std::mem::replace(&mut self.state, LoadState::Done) else {
+            unreachable!();
+        };
+        let gltf = build.finish(renderer, world);
+        world.send_event(LoadEvent::Progress(self.path.clone(), progress));
+        world.send_event(LoadEvent::Loaded(self.path.clone()));
+        Some(Ok(gltf))
+    }
+}
+
+impl Drop for LoadHandle {
+    fn drop(&mut self) {
+        self.control.canceled.store(true, Ordering::Relaxed);
+    }
+}
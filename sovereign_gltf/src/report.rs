@@ -0,0 +1,225 @@
+use glam::Vec3;
+use sovereign_render::mesh::Indices;
+use std::fmt;
+
+use crate::{ImportedImage, ImportedMaterial, ImportedMesh, ImportedTexture};
+
+// Problems in a file that would otherwise only show up as rendering artifacts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportIssue {
+    // The image is out of range or in a format that couldn't be decoded, so a placeholder is
+    // drawn instead.
+    MissingTexture {
+        material: usize,
+        slot: &'static str,
+        image: usize,
+    },
+    // Triangles with no area, or an index count that isn't a multiple of three.
+    DegeneratePrimitive {
+        mesh: usize,
+        primitive: usize,
+        degenerate_triangles: usize,
+        leftover_indices: usize,
+    },
+    IndexOutOfRange {
+        mesh: usize,
+        primitive: usize,
+        // The largest offending index.
+        index: u32,
+        vertex_count: usize,
+    },
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportIssue::MissingTexture {
+                material,
+                slot,
+                image,
+            } => write!(
+                f,
+                "material {} {} texture uses missing image {}",
+                material, slot, image
+            ),
+            ImportIssue::DegeneratePrimitive {
+                mesh,
+                primitive,
+                degenerate_triangles,
+                leftover_indices,
+            } => {
+                write!(
+                    f,
+                    "mesh {} primitive {} has {} degenerate triangles",
+                    mesh, primitive, degenerate_triangles
+                )?;
+                if *leftover_indices > 0 {
+                    write!(f, " and {} leftover indices", leftover_indices)?;
+                }
+                Ok(())
+            }
+            ImportIssue::IndexOutOfRange {
+                mesh,
+                primitive,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "mesh {} primitive {} indexes vertex {} of {}",
+                mesh, primitive, index, vertex_count
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    pub mesh_count: usize,
+    pub primitive_count: usize,
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub material_count: usize,
+    pub image_count: usize,
+    // Decoded image data as uploaded, without any mips generated on the GPU.
+    pub texture_bytes: u64,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub(crate) fn new(
+        images: &[ImportedImage],
+        materials: &[ImportedMaterial],
+        meshes: &[ImportedMesh],
+    ) -> Self {
+        let mut report = Self {
+            mesh_count: meshes.len(),
+            material_count: materials.len(),
+            image_count: images.len(),
+            texture_bytes: images
+                .iter()
+                .flat_map(|image| &image.data)
+                .flat_map(|data| &data.mips)
+                .map(|mip| mip.len() as u64)
+                .sum(),
+            ..Default::default()
+        };
+
+        for (material_idx, material) in materials.iter().enumerate() {
+            let slots = [
+                ("base color", material.base_color_texture),
+                ("metallic roughness", material.metallic_roughness_texture),
+                ("normal", material.normal_texture),
+                ("occlusion", material.occlusion_texture),
+                ("emissive", material.emissive_texture),
+            ];
+            for (slot, texture) in slots {
+                let Some(ImportedTexture { image_idx, .. }) = texture else {
+                    continue;
+                };
+                let data = images.get(image_idx).and_then(|image| image.data.as_ref());
+                if data.is_none() {
+                    report.issues.push(ImportIssue::MissingTexture {
+                        material: material_idx,
+                        slot,
+                        image: image_idx,
+                    });
+                }
+            }
+        }
+
+        for (mesh_idx, mesh) in meshes.iter().enumerate() {
+            for (primitive_idx, primitive) in mesh.primitives.iter().enumerate() {
+                let vertices = &primitive.positions;
+                let check = match &primitive.indices {
+                    Indices::U16(indices) => check_primitive(indices, vertices),
+                    Indices::U32(indices) => check_primitive(indices, vertices),
+                };
+                report.primitive_count += 1;
+                report.vertex_count += vertices.len();
+                report.triangle_count += check.triangles;
+                if let Some(index) = check.max_out_of_range {
+                    report.issues.push(ImportIssue::IndexOutOfRange {
+                        mesh: mesh_idx,
+                        primitive: primitive_idx,
+                        index,
+                        vertex_count: vertices.len(),
+                    });
+                }
+                if check.degenerate_triangles > 0 || check.leftover_indices > 0 {
+                    report.issues.push(ImportIssue::DegeneratePrimitive {
+                        mesh: mesh_idx,
+                        primitive: primitive_idx,
+                        degenerate_triangles: check.degenerate_triangles,
+                        leftover_indices: check.leftover_indices,
+                    });
+                }
+            }
+        }
+        report
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub(crate) fn log(&self, source: &str) {
+        tracing::info!(source, "Imported glTF scene: {}", self);
+        for issue in &self.issues {
+            tracing::warn!(source, "glTF import issue: {}", issue);
+        }
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} meshes, {} primitives, {} vertices, {} triangles, {} materials, {} images ({:.1} MiB)",
+            self.mesh_count,
+            self.primitive_count,
+            self.vertex_count,
+            self.triangle_count,
+            self.material_count,
+            self.image_count,
+            self.texture_bytes as f64 / (1024.0 * 1024.0),
+        )?;
+        if !self.issues.is_empty() {
+            write!(f, ", {} issues", self.issues.len())?;
+        }
+        Ok(())
+    }
+}
+
+struct PrimitiveCheck {
+    triangles: usize,
+    degenerate_triangles: usize,
+    leftover_indices: usize,
+    max_out_of_range: Option<u32>,
+}
+
+fn check_primitive<I: Copy + Into<u32>>(indices: &[I], vertices: &[Vec3]) -> PrimitiveCheck {
+    let triangles = indices.chunks_exact(3);
+    let mut check = PrimitiveCheck {
+        triangles: triangles.len(),
+        degenerate_triangles: 0,
+        leftover_indices: triangles.remainder().len(),
+        max_out_of_range: None,
+    };
+    for &index in indices {
+        let index: u32 = index.into();
+        if index as usize >= vertices.len() {
+            check.max_out_of_range = check.max_out_of_range.max(Some(index));
+        }
+    }
+    for triangle in triangles {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices.get(Into::<u32>::into(triangle[i]) as usize));
+        // Triangles reaching outside the vertices are already reported above.
+        let (Some(a), Some(b), Some(c)) = (a, b, c) else {
+            continue;
+        };
+        if (*b - *a).cross(*c - *a).length_squared() == 0.0 {
+            check.degenerate_triangles += 1;
+        }
+    }
+    check
+}
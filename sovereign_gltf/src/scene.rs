@@ -0,0 +1,193 @@
+use glam::Mat4;
+use sovereign_ecs::{Entity, EntityBuilder, World};
+use sovereign_math::Aabb;
+use sovereign_render::{
+    bounds::SkinnedBounds,
+    label::Label,
+    mesh::MorphWeights,
+    transform::{GlobalTransform, Parent, Transform},
+};
+use std::collections::HashMap;
+
+use crate::{Gltf, GltfPrimitive};
+
+#[derive(Debug)]
+pub struct SceneInstance {
+    pub root: Entity,
+    pub entities: Vec<Entity>,
+    pub bounds: Option<Aabb>,
+}
+
+impl SceneInstance {
+    // Also takes anything parented under the scene since it was spawned.
+    pub fn despawn(self, world: &mut World) {
+        let _ = world.despawn_recursive::<Parent>(self.root);
+    }
+}
+
+impl Gltf {
+    pub fn spawn(&self, world: &mut World, root_transform: Mat4) -> SceneInstance {
+        // Import always leaves at least one scene and points default_scene at one of them.
+        self.spawn_scene(world, self.default_scene, root_transform)
+            .expect("default scene is missing")
+    }
+
+    // Top-level nodes are parented to a root entity so the whole scene moves with root_transform.
+    // Returns None without spawning anything if there is no scene at scene_idx.
+    pub fn spawn_scene(
+        &self,
+        world: &mut World,
+        scene_idx: usize,
+        root_transform: Mat4,
+    ) -> Option<SceneInstance> {
+        let scene = self.scenes.get(scene_idx)?;
+        let root = world.spawn((
+            Transform {
+                transform: root_transform,
+            },
+            GlobalTransform {
+                transform: root_transform,
+            },
+        ));
+        let mut instance = SceneInstance {
+            root,
+            entities: vec![root],
+            bounds: None,
+        };
+        let mut node_entities = HashMap::new();
+        for node in &scene.nodes {
+            self.spawn_node(
+                world,
+                *node,
+                root,
+                root_transform,
+                &mut instance,
+                &mut node_entities,
+            );
+        }
+        self.add_skinned_bounds(world, &node_entities);
+        Some(instance)
+    }
+
+    // node_entities maps each spawned node to the entities drawing its primitives, the node's own
+    // entity first.
+    fn spawn_node(
+        &self,
+        world: &mut World,
+        node_idx: usize,
+        parent: Entity,
+        parent_transform: Mat4,
+        instance: &mut SceneInstance,
+        node_entities: &mut HashMap<usize, Vec<Entity>>,
+    ) {
+        let node = &self.nodes[node_idx];
+        let transform = parent_transform * node.local_transform;
+
+        let mut builder = EntityBuilder::new();
+        builder
+            .add(Transform {
+                transform: node.local_transform,
+            })
+            .add(GlobalTransform { transform })
+            .add(Parent(parent));
+        if let Some(name) = &node.name {
+            builder.add(Label::new(name.clone()));
+        }
+        let mut extra_primitives: &[GltfPrimitive] = &[];
+        let mut weights = None;
+        if let Some(mesh_idx) = node.mesh_idx {
+            let mesh = &self.meshes[mesh_idx];
+            if !mesh.morph_weights.is_empty() {
+                let node_weights = node.morph_weights.as_ref().unwrap_or(&mesh.morph_weights);
+                weights = Some(MorphWeights(node_weights.clone()));
+            }
+            if let Some((first, rest)) = mesh.primitives.split_first() {
+                self.add_primitive(&mut builder, first, weights.as_ref(), &transform, instance);
+                extra_primitives = rest;
+            }
+        }
+        let entity = world.spawn(builder.build());
+        instance.entities.push(entity);
+        let entities = node_entities.entry(node_idx).or_default();
+        entities.push(entity);
+
+        // The node renders its mesh's first primitive itself; any others become child entities.
+        for primitive in extra_primitives {
+            let mut builder = EntityBuilder::new();
+            builder
+                .add(Transform {
+                    transform: Mat4::IDENTITY,
+                })
+                .add(GlobalTransform { transform })
+                .add(Parent(entity));
+            self.add_primitive(
+                &mut builder,
+                primitive,
+                weights.as_ref(),
+                &transform,
+                instance,
+            );
+            let primitive_entity = world.spawn(builder.build());
+            instance.entities.push(primitive_entity);
+            entities.push(primitive_entity);
+        }
+
+        for child in &node.children {
+            self.spawn_node(world, *child, entity, transform, instance, node_entities);
+        }
+    }
+
+    // Added once the whole scene is spawned, since joints can come after the mesh they move.
+    // Joints outside the spawned scene are left out.
+    fn add_skinned_bounds(&self, world: &mut World, node_entities: &HashMap<usize, Vec<Entity>>) {
+        for (node_idx, entities) in node_entities {
+            let Some(skin) = &self.nodes[*node_idx].skin else {
+                continue;
+            };
+            let (joints, joint_bounds): (Vec<_>, Vec<_>) = skin
+                .joints
+                .iter()
+                .zip(&skin.joint_bounds)
+                .filter_map(|(joint, bounds)| Some((*node_entities.get(joint)?.first()?, *bounds)))
+                .unzip();
+            if joints.is_empty() {
+                continue;
+            }
+            // Only entities with a mesh are skinned; a skin on an empty node moves nothing.
+            for entity in entities {
+                if world.get().satisfies::<&Aabb>(*entity).unwrap_or(false) {
+                    let _ = world.insert_one(
+                        *entity,
+                        SkinnedBounds {
+                            joints: joints.clone(),
+                            joint_bounds: joint_bounds.clone(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    fn add_primitive(
+        &self,
+        builder: &mut EntityBuilder,
+        primitive: &GltfPrimitive,
+        weights: Option<&MorphWeights>,
+        transform: &Mat4,
+        instance: &mut SceneInstance,
+    ) {
+        builder
+            .add(primitive.mesh.clone())
+            .add(self.materials[primitive.material_idx].clone())
+            .add(primitive.bounds);
+        if let Some(weights) = weights {
+            builder.add(weights.clone());
+        }
+
+        let world_bounds = primitive.bounds.transformed(transform);
+        instance.bounds = Some(match &instance.bounds {
+            Some(bounds) => bounds.union(&world_bounds),
+            None => world_bounds,
+        });
+    }
+}
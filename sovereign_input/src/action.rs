@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    MouseButton(MouseButton),
+    // One direction of an axis, so a stick can drive a pair of opposing actions.
+    GamepadAxis { axis: GamepadAxis, positive: bool },
+}
+
+impl Binding {
+    pub fn positive(axis: GamepadAxis) -> Self {
+        Self::GamepadAxis {
+            axis,
+            positive: true,
+        }
+    }
+
+    pub fn negative(axis: GamepadAxis) -> Self {
+        Self::GamepadAxis {
+            axis,
+            positive: false,
+        }
+    }
+}
+
+// Named actions and the inputs that trigger them. An action is active while any of its
+// bindings is.
+#[derive(Clone, Debug, Default)]
+pub struct ActionMap {
+    bindings: HashMap<String, Vec<Binding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) -> &mut Self {
+        let bindings = self.bindings.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    // Removes every binding of the action.
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,239 @@
+mod action;
+
+use glam::Vec2;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+use winit::{
+    event::{DeviceEvent, ElementState, Event, MouseButton, RawKeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+pub use action::{ActionMap, Binding, GamepadAxis};
+
+// Stick values below this are treated as the stick resting at the center.
+const GAMEPAD_DEADZONE: f32 = 0.15;
+// How far an axis has to be pushed for its binding to count as pressed.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+// Per-frame input state built from winit events. Pressed and released hold only what changed
+// since the last `end_frame`; held lasts until the release. Key repeats are ignored.
+#[derive(Default)]
+pub struct Input {
+    keys_pressed: HashSet<KeyCode>,
+    keys_held: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_held: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_delta: Vec2,
+    cursor: Vec2,
+    axes: HashMap<GamepadAxis, f32>,
+    previous_axes: HashMap<GamepadAxis, f32>,
+    actions: ActionMap,
+}
+
+impl Input {
+    pub fn new(actions: ActionMap) -> Self {
+        Self {
+            actions,
+            ..Default::default()
+        }
+    }
+
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::DeviceEvent {
+                event:
+                    DeviceEvent::Key(RawKeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                    }),
+                ..
+            } => match state {
+                ElementState::Pressed => press(&mut self.keys_pressed, &mut self.keys_held, *key),
+                ElementState::Released => {
+                    release(&mut self.keys_held, &mut self.keys_released, *key)
+                }
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                self.mouse_delta += Vec2::new(delta.0 as f32, delta.1 as f32);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                self.cursor = Vec2::new(position.x as f32, position.y as f32);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    press(&mut self.buttons_pressed, &mut self.buttons_held, *button)
+                }
+                ElementState::Released => {
+                    release(&mut self.buttons_held, &mut self.buttons_released, *button)
+                }
+            },
+            // Releases that happen while another window has focus never arrive.
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => self.release_all(),
+            _ => {}
+        }
+    }
+
+    // Called once the frame has read the input.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.previous_axes.clone_from(&self.axes);
+    }
+
+    pub fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_held.drain());
+        self.buttons_released.extend(self.buttons_held.drain());
+        self.axes.clear();
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn key_held(&self, key: KeyCode) -> bool {
+        self.keys_held.contains(&key)
+    }
+
+    pub fn key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn button_held(&self, button: MouseButton) -> bool {
+        self.buttons_held.contains(&button)
+    }
+
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    // Raw mouse motion accumulated this frame, unaffected by cursor acceleration or grabs.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    // Cursor position in physical pixels from the top left of the window.
+    pub fn cursor(&self) -> Vec2 {
+        self.cursor
+    }
+
+    // winit has no gamepad support, so whatever polls the gamepad feeds its axes in here.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        let value = if value.abs() < GAMEPAD_DEADZONE {
+            0.0
+        } else {
+            value.clamp(-1.0, 1.0)
+        };
+        self.axes.insert(axis, value);
+    }
+
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or_default()
+    }
+
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    // Strength of the action from 0 to 1: 1 while a key or button binding is held, otherwise
+    // the furthest any axis binding is pushed in its direction.
+    pub fn action_value(&self, action: &str) -> f32 {
+        self.actions
+            .bindings(action)
+            .iter()
+            .map(|binding| self.binding_value(binding, &self.axes))
+            .fold(0.0, f32::max)
+    }
+
+    pub fn action_held(&self, action: &str) -> bool {
+        self.action_value(action) >= AXIS_PRESS_THRESHOLD
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.actions
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.key_pressed(*key),
+                Binding::MouseButton(button) => self.button_pressed(*button),
+                Binding::GamepadAxis { .. } => {
+                    self.binding_value(binding, &self.axes) >= AXIS_PRESS_THRESHOLD
+                        && self.binding_value(binding, &self.previous_axes) < AXIS_PRESS_THRESHOLD
+                }
+            })
+    }
+
+    pub fn action_released(&self, action: &str) -> bool {
+        self.actions
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.key_released(*key),
+                Binding::MouseButton(button) => self.button_released(*button),
+                Binding::GamepadAxis { .. } => {
+                    self.binding_value(binding, &self.axes) < AXIS_PRESS_THRESHOLD
+                        && self.binding_value(binding, &self.previous_axes) >= AXIS_PRESS_THRESHOLD
+                }
+            })
+    }
+
+    // The positive action's value minus the negative one's, from -1 to 1.
+    pub fn action_axis(&self, negative: &str, positive: &str) -> f32 {
+        self.action_value(positive) - self.action_value(negative)
+    }
+
+    fn binding_value(&self, binding: &Binding, axes: &HashMap<GamepadAxis, f32>) -> f32 {
+        match binding {
+            Binding::Key(key) => self.key_held(*key) as u32 as f32,
+            Binding::MouseButton(button) => self.button_held(*button) as u32 as f32,
+            Binding::GamepadAxis { axis, positive } => {
+                let value = axes.get(axis).copied().unwrap_or_default();
+                if *positive {
+                    value.max(0.0)
+                } else {
+                    (-value).max(0.0)
+                }
+            }
+        }
+    }
+}
+
+// A press and release within one frame leave the input both pressed and released, so short taps
+// are not lost.
+fn press<T: Copy + Eq + Hash>(pressed: &mut HashSet<T>, held: &mut HashSet<T>, input: T) {
+    if held.insert(input) {
+        pressed.insert(input);
+    }
+}
+
+fn release<T: Copy + Eq + Hash>(held: &mut HashSet<T>, released: &mut HashSet<T>, input: T) {
+    if held.remove(&input) {
+        released.insert(input);
+    }
+}
@@ -0,0 +1,43 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{Mat4, Vec3};
+use sovereign_math::{Aabb, Frustum};
+
+// A cube of boxes around the camera, roughly half of which fall outside the view.
+fn grid(count: usize) -> Vec<Aabb> {
+    let side = (count as f32).cbrt().ceil() as usize;
+    (0..count)
+        .map(|i| {
+            let cell = Vec3::new(
+                (i % side) as f32,
+                (i / side % side) as f32,
+                (i / (side * side)) as f32,
+            );
+            let center = (cell - Vec3::splat(side as f32 * 0.5)) * 4.0;
+            Aabb::from_center_half_extents(center, Vec3::splat(1.0))
+        })
+        .collect()
+}
+
+fn frustum_culling(c: &mut Criterion) {
+    let view = Mat4::look_to_lh(Vec3::ZERO, Vec3::new(1.0, -0.2, 0.6), Vec3::Y);
+    // Reversed depth, matching the projection the app builds.
+    let projection = Mat4::perspective_lh(60f32.to_radians(), 16.0 / 9.0, 10000.0, 0.0001);
+    let frustum = Frustum::from_view_projection(&(projection * view));
+
+    let mut group = c.benchmark_group("frustum_culling");
+    for count in [1_000, 10_000, 100_000] {
+        let boxes = grid(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &boxes, |b, boxes| {
+            b.iter(|| {
+                boxes
+                    .iter()
+                    .filter(|aabb| frustum.intersects_aabb(black_box(aabb)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frustum_culling);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec3, Vec4};
+
+// Linear RGBA. Channels are not clamped, so values above 1.0 carry HDR intensity for lights and
+// emissive materials.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::rgba(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    pub fn srgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::srgba_u8(r, g, b, 255)
+    }
+
+    pub fn srgba_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let [r, g, b, a] = [r, g, b, a].map(|c| c as f32 / 255.0);
+        Self::srgba(r, g, b, a)
+    }
+
+    pub fn to_srgba(self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        ]
+    }
+
+    pub fn to_srgba_u8(self) -> [u8; 4] {
+        self.to_srgba()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        Self::rgba(
+            self.r * intensity,
+            self.g * intensity,
+            self.b * intensity,
+            self.a,
+        )
+    }
+
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        self.to_vec4().lerp(other.to_vec4(), t).into()
+    }
+
+    pub fn luminance(self) -> f32 {
+        self.to_vec3().dot(Vec3::new(0.2126, 0.7152, 0.0722))
+    }
+
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.r, self.g, self.b)
+    }
+
+    pub fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.r, self.g, self.b, self.a)
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<Vec3> for Color {
+    fn from(rgb: Vec3) -> Self {
+        Self::rgb(rgb.x, rgb.y, rgb.z)
+    }
+}
+
+impl From<Vec4> for Color {
+    fn from(rgba: Vec4) -> Self {
+        Self::rgba(rgba.x, rgba.y, rgba.z, rgba.w)
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self::rgb(r, g, b)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for Vec4 {
+    fn from(color: Color) -> Self {
+        color.to_vec4()
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,15 @@
+mod aabb;
+mod color;
+mod frustum;
+mod obb;
+mod plane;
+mod ray;
+mod rect;
+
+pub use aabb::Aabb;
+pub use color::{linear_to_srgb, srgb_to_linear, Color};
+pub use frustum::Frustum;
+pub use obb::Obb;
+pub use plane::Plane;
+pub use ray::Ray;
+pub use rect::Rect;
@@ -0,0 +1,49 @@
+use glam::{Mat3, Mat4, Vec3};
+
+use crate::Aabb;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub axes: Mat3,
+}
+
+impl Obb {
+    pub fn from_aabb(aabb: &Aabb, transform: &Mat4) -> Self {
+        let (scale, rotation, _translation) = transform.to_scale_rotation_translation();
+        Self {
+            center: transform.transform_point3(aabb.center()),
+            half_extents: aabb.half_extents() * scale.abs(),
+            axes: Mat3::from_quat(rotation),
+        }
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let x = self.axes.x_axis * self.half_extents.x;
+        let y = self.axes.y_axis * self.half_extents.y;
+        let z = self.axes.z_axis * self.half_extents.z;
+        [
+            self.center - x - y - z,
+            self.center + x - y - z,
+            self.center - x + y - z,
+            self.center + x + y - z,
+            self.center - x - y + z,
+            self.center + x - y + z,
+            self.center - x + y + z,
+            self.center + x + y + z,
+        ]
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        let local = self.axes.transpose() * (point - self.center);
+        local.abs().cmple(self.half_extents).all()
+    }
+
+    pub fn to_aabb(&self) -> Aabb {
+        let extents = self.axes.x_axis.abs() * self.half_extents.x
+            + self.axes.y_axis.abs() * self.half_extents.y
+            + self.axes.z_axis.abs() * self.half_extents.z;
+        Aabb::from_center_half_extents(self.center, extents)
+    }
+}
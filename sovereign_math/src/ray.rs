@@ -0,0 +1,53 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::{Aabb, Plane};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    // Unprojects through the near plane and a point halfway into the depth range, which stays finite
+    // for reverse-Z and infinite far projections.
+    pub fn from_screen(cursor: Vec2, viewport: Vec2, view_projection: &Mat4) -> Self {
+        let ndc = Vec2::new(
+            cursor.x / viewport.x * 2.0 - 1.0,
+            1.0 - cursor.y / viewport.y * 2.0,
+        );
+        let inverse = view_projection.inverse();
+        let near = inverse.project_point3(ndc.extend(1.0));
+        let far = inverse.project_point3(ndc.extend(0.5));
+        Self::new(near, far - near)
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denom = plane.normal.dot(self.direction);
+        if denom.abs() <= f32::EPSILON {
+            return None;
+        }
+        let t = -plane.signed_distance(self.origin) / denom;
+        (t >= 0.0).then_some(t)
+    }
+
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let inv_direction = self.direction.recip();
+        let t0 = (aabb.min - self.origin) * inv_direction;
+        let t1 = (aabb.max - self.origin) * inv_direction;
+        let near = t0.min(t1).max_element();
+        let far = t0.max(t1).min_element();
+        (far >= near.max(0.0)).then_some(near.max(0.0))
+    }
+}
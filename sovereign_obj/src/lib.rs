@@ -0,0 +1,537 @@
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use sovereign_ecs::World;
+use sovereign_gltf::{
+    build_image, Gltf, GltfMesh, GltfNode, GltfPrimitive, GltfScene, ImportReport, ImportedImage,
+};
+use sovereign_math::Aabb;
+use sovereign_render::{
+    asset::{texture::TextureData, Assets},
+    hash::ContentHash,
+    material::{Material, MaterialTexture, MaterialUniform},
+    mesh::{Indices, Mesh, Vertex},
+    *,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+mod mtl;
+
+use mtl::{load_mtl, MtlMaterial};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ObjError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{path}:{line}: {message}")]
+    Parse {
+        path: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ObjError {
+    fn parse(path: &Path, line_idx: usize, message: &str) -> Self {
+        ObjError::Parse {
+            path: path.display().to_string(),
+            line: line_idx + 1,
+            message: message.to_string(),
+        }
+    }
+}
+
+pub struct ObjImport {
+    source: String,
+    images: Vec<ImportedImage>,
+    materials: Vec<ImportedMaterial>,
+    meshes: Vec<ImportedMesh>,
+    nodes: Vec<GltfNode>,
+}
+
+struct ImportedMaterial {
+    key: String,
+    uniform: MaterialUniform,
+    base_color_texture: Option<usize>,
+    normal_texture: Option<usize>,
+    emissive_texture: Option<usize>,
+}
+
+struct ImportedMesh {
+    key: String,
+    mesh: Mesh,
+    material_idx: usize,
+    bounds: Aabb,
+}
+
+// Position, texture coordinate and normal indices of a face corner, already resolved.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// Faces are split by object/group and material, so each group ends up as one mesh.
+struct FaceGroup {
+    name: Option<String>,
+    material: Option<String>,
+    corners: Vec<Corner>,
+}
+
+// OBJ files are returned in the same shape as glTF ones, one node per group and material, so
+// they spawn through `Gltf::spawn` like any other scene.
+pub fn load_obj(
+    renderer: &mut Renderer,
+    world: &mut World,
+    path: &Path,
+) -> Result<Gltf, Box<dyn Error>> {
+    let import = import_obj(path)?;
+    Ok(build_obj(renderer, world, import))
+}
+
+pub fn import_obj(path: &Path) -> Result<ObjImport, ObjError> {
+    let contents = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let source = path
+        .canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string();
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut library = HashMap::new();
+    let mut groups: Vec<FaceGroup> = Vec::new();
+    let mut group_lookup = HashMap::new();
+    let mut group_name: Option<String> = None;
+    let mut material: Option<String> = None;
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (keyword, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(keyword, rest)| (keyword, rest.trim()));
+        let error = |message: &str| ObjError::parse(path, line_idx, message);
+
+        match keyword {
+            "v" => {
+                // Vertex colours after the position are not supported and are ignored.
+                let [x, y, z] = parse_floats(first_tokens(rest, 3))
+                    .ok_or_else(|| error("invalid vertex position"))?;
+                positions.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_floats(first_tokens(rest, 2))
+                    .or_else(|| parse_floats(rest).map(|[u]| [u, 0.0]))
+                    .ok_or_else(|| error("invalid texture coordinate"))?;
+                // OBJ puts the texture origin at the bottom left.
+                uvs.push(Vec2::new(u, 1.0 - v));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats(rest).ok_or_else(|| error("invalid vertex normal"))?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "f" => {
+                let corners = rest
+                    .split_whitespace()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|corners| corners.len() >= 3)
+                    .ok_or_else(|| error("invalid face"))?;
+
+                let key = (group_name.clone(), material.clone());
+                let group_idx = *group_lookup.entry(key).or_insert_with(|| {
+                    groups.push(FaceGroup {
+                        name: group_name.clone(),
+                        material: material.clone(),
+                        corners: Vec::new(),
+                    });
+                    groups.len() - 1
+                });
+                groups[group_idx].corners.extend(fan_triangles(&corners));
+            }
+            "o" | "g" => group_name = (!rest.is_empty()).then(|| rest.to_string()),
+            "usemtl" => material = (!rest.is_empty()).then(|| rest.to_string()),
+            "mtllib" => {
+                for file in rest.split_whitespace() {
+                    let mtl_path = dir.join(file);
+                    if let Err(err) = load_mtl(&mtl_path, &mut library) {
+                        tracing::warn!(path = %mtl_path.display(), %err, "Failed to load material library");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut image_lookup = HashMap::new();
+    // A file used for both colour and data is imported once per format.
+    let mut image_idx = |path: &PathBuf, srgb: bool| {
+        *image_lookup.entry((path.clone(), srgb)).or_insert_with(|| {
+            images.push(import_image(path, srgb));
+            images.len() - 1
+        })
+    };
+
+    let mut materials = Vec::new();
+    let mut material_lookup = HashMap::new();
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+    let default_material = MtlMaterial::default();
+    for group in groups {
+        let material_idx = *material_lookup
+            .entry(group.material.clone())
+            .or_insert_with(|| {
+                let name = group.material.as_deref().unwrap_or("default");
+                let mtl = group
+                    .material
+                    .as_ref()
+                    .and_then(|name| library.get(name))
+                    .unwrap_or_else(|| {
+                        if group.material.is_some() {
+                            tracing::warn!(material = name, "Missing OBJ material");
+                        }
+                        &default_material
+                    });
+                materials.push(ImportedMaterial {
+                    key: format!("{}#material/{}", source, name),
+                    uniform: mtl.uniform(),
+                    base_color_texture: mtl.diffuse_map.as_ref().map(|p| image_idx(p, true)),
+                    normal_texture: mtl.normal_map.as_ref().map(|p| image_idx(p, false)),
+                    emissive_texture: mtl.emissive_map.as_ref().map(|p| image_idx(p, true)),
+                });
+                materials.len() - 1
+            });
+
+        let mesh = build_mesh(&group.corners, &positions, &uvs, &normals);
+        nodes.push(GltfNode {
+            name: group.name.clone().or_else(|| group.material.clone()),
+            mesh_idx: Some(meshes.len()),
+            morph_weights: None,
+            local_transform: Mat4::IDENTITY,
+            parent: None,
+            children: Vec::new(),
+            skin: None,
+        });
+        meshes.push(ImportedMesh {
+            key: format!("{}#mesh{}", source, meshes.len()),
+            bounds: mesh.compute_bounds(),
+            mesh,
+            material_idx,
+        });
+    }
+
+    tracing::debug!(source = %source, meshes = meshes.len(), "Imported OBJ file");
+    Ok(ObjImport {
+        source,
+        images,
+        materials,
+        meshes,
+        nodes,
+    })
+}
+
+pub fn build_obj(renderer: &mut Renderer, world: &mut World, import: ObjImport) -> Gltf {
+    // Faces are triangulated here and indexed through the parsed vertices, so only the counts
+    // are worth reporting.
+    let report = ImportReport {
+        mesh_count: import.meshes.len(),
+        primitive_count: import.meshes.len(),
+        vertex_count: import
+            .meshes
+            .iter()
+            .map(|mesh| mesh.mesh.vertices.len())
+            .sum(),
+        triangle_count: import
+            .meshes
+            .iter()
+            .map(|mesh| mesh.mesh.indices.len() / 3)
+            .sum(),
+        material_count: import.materials.len(),
+        image_count: import.images.len(),
+        texture_bytes: import
+            .images
+            .iter()
+            .flat_map(|image| &image.data)
+            .flat_map(|data| &data.mips)
+            .map(|mip| mip.len() as u64)
+            .sum(),
+        issues: Vec::new(),
+    };
+
+    let images = import
+        .images
+        .into_iter()
+        .map(|image| build_image(renderer, world, image))
+        .collect::<Vec<_>>();
+
+    let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
+    let (asset_meshes,) = meshes_query.get().unwrap();
+
+    let mut materials_query = world.get_singleton::<Assets<Material>>();
+    let (asset_materials,) = materials_query.get().unwrap();
+
+    let sampler = renderer.default_sampler();
+    let resolve = |image_idx: Option<usize>| {
+        image_idx.map(|image_idx| MaterialTexture {
+            texture: images[image_idx].clone(),
+            sampler,
+        })
+    };
+
+    let materials = import
+        .materials
+        .into_iter()
+        .map(|material| {
+            if let Some(handle) = asset_materials.get_by_key(&material.key) {
+                return handle;
+            }
+            asset_materials.insert_with_key(
+                material.key,
+                Material {
+                    uniform: material.uniform,
+                    base_color_texture: resolve(material.base_color_texture),
+                    metallic_roughness_texture: None,
+                    normal_texture: resolve(material.normal_texture),
+                    occlusion_texture: None,
+                    emissive_texture: resolve(material.emissive_texture),
+                },
+            )
+        })
+        .collect();
+
+    let meshes = import
+        .meshes
+        .into_iter()
+        .map(|mesh| GltfMesh {
+            primitives: vec![GltfPrimitive {
+                mesh: asset_meshes
+                    .get_by_key(&mesh.key)
+                    .unwrap_or_else(|| asset_meshes.insert_with_key(mesh.key, mesh.mesh)),
+                material_idx: mesh.material_idx,
+                bounds: mesh.bounds,
+            }],
+            morph_weights: Vec::new(),
+        })
+        .collect();
+
+    tracing::debug!(source = %import.source, "Built OBJ scene");
+
+    Gltf {
+        samplers: vec![sampler],
+        images,
+        materials,
+        meshes,
+        scenes: vec![GltfScene {
+            name: None,
+            nodes: (0..import.nodes.len()).collect(),
+        }],
+        nodes: import.nodes,
+        default_scene: 0,
+        report,
+    }
+}
+
+fn build_mesh(corners: &[Corner], positions: &[Vec3], uvs: &[Vec2], normals: &[Vec3]) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut vertex_corners = Vec::new();
+    let mut indices = Vec::with_capacity(corners.len());
+    let mut lookup = HashMap::new();
+    for corner in corners {
+        let index = *lookup.entry(*corner).or_insert_with(|| {
+            let (position, uv, normal) = *corner;
+            vertex_corners.push(*corner);
+            vertices.push(Vertex {
+                position: positions[position].extend(1.0),
+                normal: normal.map_or(Vec3::ZERO, |n| normals[n]).extend(1.0),
+                color: Vec4::ONE,
+                uv: uv.map_or(Vec2::ZERO, |uv| uvs[uv]),
+                pad: Vec2::ZERO,
+            });
+            vertices.len() as u32 - 1
+        });
+        indices.push(index);
+    }
+
+    // Corners without normals get area-weighted smooth normals from every face sharing their
+    // position. Corners with explicit normals keep them, so authored hard edges survive.
+    if corners.iter().any(|(_, _, normal)| normal.is_none()) {
+        let mut accumulated = vec![Vec3::ZERO; positions.len()];
+        for triangle in corners.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i].0]);
+            let face_normal = (b - a).cross(c - a);
+            for (position, _, _) in triangle {
+                accumulated[*position] += face_normal;
+            }
+        }
+        for (vertex, (position, _, normal)) in vertices.iter_mut().zip(vertex_corners) {
+            if normal.is_none() {
+                vertex.normal = accumulated[position].normalize_or_zero().extend(1.0);
+            }
+        }
+    }
+
+    Mesh {
+        vertices,
+        staged_vertices: None,
+        indices: Indices::from_u32(indices),
+        morph_targets: Vec::new(),
+    }
+}
+
+fn import_image(path: &Path, srgb: bool) -> ImportedImage {
+    let data = match image::open(path) {
+        Ok(image) => {
+            let image = image.into_rgba8();
+            let (width, height) = image.dimensions();
+            Some(TextureData {
+                width,
+                height,
+                format: if srgb {
+                    DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+                } else {
+                    DXGI_FORMAT_R8G8B8A8_UNORM
+                },
+                mips: vec![image.into_raw()],
+            })
+        }
+        Err(err) => {
+            tracing::warn!(path = %path.display(), %err, "Failed to load OBJ texture, using a placeholder");
+            None
+        }
+    };
+    // Keyed by content like glTF images, so the format is part of the key.
+    let key = match &data {
+        Some(data) => format!("image:{:016x}", data.content_hash()),
+        None => path.display().to_string(),
+    };
+    ImportedImage { key, data }
+}
+
+// Polygons are fanned into triangles around their first corner.
+fn fan_triangles(corners: &[Corner]) -> impl Iterator<Item = Corner> + '_ {
+    corners
+        .windows(2)
+        .skip(1)
+        .flat_map(|pair| [corners[0], pair[0], pair[1]])
+}
+
+// `v`, `v/vt`, `v//vn` or `v/vt/vn`, with negative indices counting back from the latest element.
+fn parse_corner(
+    corner: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Option<Corner> {
+    let mut parts = corner.split('/');
+    let position = resolve_index(parts.next()?, position_count)?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, uv_count)?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, normal_count)?),
+    };
+    Some((position, uv, normal))
+}
+
+fn resolve_index(index: &str, count: usize) -> Option<usize> {
+    let index = index.parse::<i64>().ok()?;
+    let resolved = match index {
+        0 => return None,
+        index if index > 0 => index - 1,
+        index => count as i64 + index,
+    };
+    (0..count as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+fn first_tokens(rest: &str, count: usize) -> String {
+    rest.split_whitespace()
+        .take(count)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn parse_floats<T: AsRef<str>, const N: usize>(value: T) -> Option<[f32; N]> {
+    let numbers = value
+        .as_ref()
+        .split_whitespace()
+        .map(|number| f32::from_str(number).ok())
+        .collect::<Option<Vec<_>>>()?;
+    numbers.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positive_indices_count_from_one() {
+        assert_eq!(resolve_index("1", 3), Some(0));
+        assert_eq!(resolve_index("3", 3), Some(2));
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest() {
+        assert_eq!(resolve_index("-1", 3), Some(2));
+        assert_eq!(resolve_index("-3", 3), Some(0));
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        assert_eq!(resolve_index("0", 3), None);
+        assert_eq!(resolve_index("4", 3), None);
+        assert_eq!(resolve_index("-4", 3), None);
+        assert_eq!(resolve_index("x", 3), None);
+        assert_eq!(parse_corner("1/4", 3, 3, 3), None);
+        assert_eq!(parse_corner("1//4", 3, 3, 3), None);
+    }
+
+    #[test]
+    fn corners_parse_every_layout() {
+        assert_eq!(parse_corner("2", 3, 3, 3), Some((1, None, None)));
+        assert_eq!(parse_corner("2/3", 3, 3, 3), Some((1, Some(2), None)));
+        assert_eq!(parse_corner("2//1", 3, 3, 3), Some((1, None, Some(0))));
+        assert_eq!(parse_corner("2/3/1", 3, 3, 3), Some((1, Some(2), Some(0))));
+        assert_eq!(
+            parse_corner("-1/-1/-1", 3, 3, 3),
+            Some((2, Some(2), Some(2)))
+        );
+    }
+
+    #[test]
+    fn polygons_fan_around_the_first_corner() {
+        let corners = (0..5).map(|i| (i, None, None)).collect::<Vec<_>>();
+        let fanned = fan_triangles(&corners)
+            .map(|(position, _, _)| position)
+            .collect::<Vec<_>>();
+        assert_eq!(fanned, [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+        assert_eq!(fan_triangles(&corners[..3]).count(), 3);
+    }
+
+    #[test]
+    fn generated_normals_leave_explicit_ones_alone() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::new(1.0, 1.0, 0.0)];
+        let normals = [Vec3::X];
+        let corners = [
+            (0, None, Some(0)),
+            (1, None, Some(0)),
+            (2, None, Some(0)),
+            (1, None, None),
+            (3, None, None),
+            (2, None, None),
+        ];
+        let mesh = build_mesh(&corners, &positions, &[], &normals);
+        assert!(mesh.vertices[..3]
+            .iter()
+            .all(|vertex| vertex.normal == Vec4::new(1.0, 0.0, 0.0, 1.0)));
+        assert!(mesh.vertices[3..]
+            .iter()
+            .all(|vertex| vertex.normal == Vec4::new(0.0, 0.0, 1.0, 1.0)));
+    }
+}
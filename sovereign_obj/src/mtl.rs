@@ -0,0 +1,171 @@
+use glam::Vec3;
+use sovereign_math::Color;
+use sovereign_render::material::MaterialUniform;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{parse_floats, ObjError};
+
+#[derive(Debug)]
+pub(crate) struct MtlMaterial {
+    pub diffuse: Vec3,
+    pub alpha: f32,
+    pub emissive: Vec3,
+    pub shininess: Option<f32>,
+    pub roughness: Option<f32>,
+    pub metallic: f32,
+    pub diffuse_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+    pub emissive_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3::ONE,
+            alpha: 1.0,
+            emissive: Vec3::ZERO,
+            shininess: None,
+            roughness: None,
+            metallic: 0.0,
+            diffuse_map: None,
+            normal_map: None,
+            emissive_map: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    pub fn uniform(&self) -> MaterialUniform {
+        // Without the PBR extension's Pr, map the Blinn-Phong exponent onto GGX roughness.
+        let perceptual_roughness = self.roughness.unwrap_or_else(|| {
+            self.shininess.map_or(1.0, |shininess| {
+                (2.0 / (shininess.max(0.0) + 2.0)).powf(0.25)
+            })
+        });
+        MaterialUniform {
+            base_color_factors: Color::rgba(
+                self.diffuse.x,
+                self.diffuse.y,
+                self.diffuse.z,
+                self.alpha,
+            ),
+            emissive_factors: Color::rgb(self.emissive.x, self.emissive.y, self.emissive.z),
+            emissive_strength: 1.0,
+            normal_scale: 1.0,
+            perceptual_roughness: perceptual_roughness.clamp(0.0, 1.0),
+            metallic: self.metallic.clamp(0.0, 1.0),
+            reflectance: 0.5,
+            occlusion_strength: 1.0,
+        }
+    }
+}
+
+pub(crate) fn load_mtl(
+    path: &Path,
+    materials: &mut HashMap<String, MtlMaterial>,
+) -> Result<(), ObjError> {
+    let contents = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut current: Option<&mut MtlMaterial> = None;
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((keyword, rest)) = line
+            .split_once(char::is_whitespace)
+            .map(|(keyword, rest)| (keyword, rest.trim()))
+            .or_else(|| (!line.is_empty()).then_some((line, "")))
+        else {
+            continue;
+        };
+        let error = |message: &str| ObjError::parse(path, line_idx, message);
+
+        if keyword == "newmtl" {
+            current = Some(materials.entry(rest.to_string()).or_default());
+            continue;
+        }
+        let Some(material) = current.as_deref_mut() else {
+            continue;
+        };
+        match keyword {
+            "Kd" => {
+                let [r, g, b] = parse_floats(rest).ok_or_else(|| error("invalid Kd"))?;
+                material.diffuse = Vec3::new(r, g, b);
+            }
+            "Ke" => {
+                let [r, g, b] = parse_floats(rest).ok_or_else(|| error("invalid Ke"))?;
+                material.emissive = Vec3::new(r, g, b);
+            }
+            "d" => {
+                let [d] = parse_floats(rest).ok_or_else(|| error("invalid d"))?;
+                material.alpha = d;
+            }
+            "Tr" => {
+                let [tr] = parse_floats(rest).ok_or_else(|| error("invalid Tr"))?;
+                material.alpha = 1.0 - tr;
+            }
+            "Ns" => {
+                let [ns] = parse_floats(rest).ok_or_else(|| error("invalid Ns"))?;
+                material.shininess = Some(ns);
+            }
+            "Pr" => {
+                let [pr] = parse_floats(rest).ok_or_else(|| error("invalid Pr"))?;
+                material.roughness = Some(pr);
+            }
+            "Pm" => {
+                let [pm] = parse_floats(rest).ok_or_else(|| error("invalid Pm"))?;
+                material.metallic = pm;
+            }
+            "map_Kd" => material.diffuse_map = map_path(dir, rest),
+            "map_Ke" => material.emissive_map = map_path(dir, rest),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = map_path(dir, rest),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Texture statements can carry options (`-bm 0.5 normal.png`); the file name comes last.
+fn map_path(dir: &Path, rest: &str) -> Option<PathBuf> {
+    rest.split_whitespace()
+        .next_back()
+        .map(|file| dir.join(file.replace('\\', "/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_options_are_stripped() {
+        let dir = Path::new("models");
+        assert_eq!(
+            map_path(dir, "-bm 0.5 normal.png"),
+            Some(PathBuf::from("models/normal.png"))
+        );
+        assert_eq!(
+            map_path(dir, "-s 1 1 1 -clamp on diffuse.png"),
+            Some(PathBuf::from("models/diffuse.png"))
+        );
+        assert_eq!(
+            map_path(dir, "diffuse.png"),
+            Some(PathBuf::from("models/diffuse.png"))
+        );
+    }
+
+    #[test]
+    fn map_paths_use_forward_slashes() {
+        assert_eq!(
+            map_path(Path::new("models"), r"textures\diffuse.png"),
+            Some(PathBuf::from("models/textures/diffuse.png"))
+        );
+    }
+
+    #[test]
+    fn empty_maps_have_no_path() {
+        assert_eq!(map_path(Path::new("models"), ""), None);
+    }
+}
@@ -0,0 +1,75 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use sovereign_render::{
+    draw_list::{batches, sort_draws, DrawItem},
+    id::{BufferId, ViewId},
+    material::GPUMaterial,
+    mesh::GPUMesh,
+    BufferView,
+};
+use windows::Win32::Graphics::{
+    Direct3D12::D3D12_INDEX_BUFFER_VIEW, Dxgi::Common::DXGI_FORMAT_R16_UINT,
+};
+
+const MESH_COUNT: usize = 64;
+const MATERIAL_COUNT: usize = 256;
+
+fn buffer_view(idx: usize) -> BufferView {
+    BufferView {
+        buffer: BufferId(idx),
+        view: ViewId(idx),
+    }
+}
+
+// Draws in query order, which interleaves meshes and materials the way a real scene does.
+fn draws(count: usize) -> Vec<DrawItem> {
+    (0..count)
+        .map(|i| {
+            let hash = (i as u32).wrapping_mul(0x9E37_79B9) as usize;
+            let mesh = hash % MESH_COUNT;
+            DrawItem {
+                mesh: GPUMesh {
+                    vertex_buffer: buffer_view(mesh),
+                    index_buffer: BufferId(mesh),
+                    index_buffer_view: D3D12_INDEX_BUFFER_VIEW {
+                        BufferLocation: 0,
+                        SizeInBytes: 36 * 2,
+                        Format: DXGI_FORMAT_R16_UINT,
+                    },
+                    index_count: 36,
+                    morph_targets: None,
+                    morph_target_count: 0,
+                },
+                material: GPUMaterial {
+                    buffer: buffer_view(MESH_COUNT),
+                    offset: (hash >> 8) % MATERIAL_COUNT,
+                },
+                transform_offset: i as u32,
+                morph_weight_offset: u32::MAX,
+                visible: true,
+                casts_shadows: true,
+                receives_shadows: true,
+            }
+        })
+        .collect()
+}
+
+fn draw_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_list");
+    for count in [1_000, 10_000, 100_000] {
+        let draws = draws(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &draws, |b, draws| {
+            b.iter_batched_ref(
+                || draws.clone(),
+                |draws| {
+                    sort_draws(draws);
+                    batches(draws).count()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw_list);
+criterion_main!(benches);
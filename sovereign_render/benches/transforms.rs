@@ -0,0 +1,63 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{Mat4, Vec3};
+use sovereign_ecs::World;
+use sovereign_math::Aabb;
+use sovereign_render::transform::{propagate_transforms, GlobalTransform, Parent, Transform};
+
+const CHILDREN_PER_ROOT: usize = 8;
+const DEPTH: usize = 3;
+
+// Roots with a short chain of children under each, every node carrying bounds like a mesh would.
+fn hierarchy(roots: usize) -> World {
+    let mut world = World::new();
+    for i in 0..roots {
+        let transform = Mat4::from_translation(Vec3::new(i as f32, 0.0, 0.0));
+        let root = world.spawn((
+            Transform { transform },
+            GlobalTransform { transform },
+            Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE),
+        ));
+        for _ in 0..CHILDREN_PER_ROOT {
+            let mut parent = root;
+            for _ in 0..DEPTH {
+                let transform = Mat4::from_translation(Vec3::Y);
+                parent = world.spawn((
+                    Transform { transform },
+                    GlobalTransform { transform },
+                    Parent(parent),
+                    Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE),
+                ));
+            }
+        }
+    }
+    propagate_transforms(&mut world);
+    world
+}
+
+fn transform_propagation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("propagate_transforms");
+    for roots in [100, 1_000] {
+        let mut world = hierarchy(roots);
+        group.bench_function(BenchmarkId::new("static", roots), |b| {
+            b.iter(|| propagate_transforms(&mut world))
+        });
+
+        let mut world = hierarchy(roots);
+        let mut frame = 0.0;
+        group.bench_function(BenchmarkId::new("moving_roots", roots), |b| {
+            b.iter(|| {
+                frame += 1.0;
+                for (_entity, transform) in
+                    world.query::<&mut Transform>().without::<&Parent>().iter()
+                {
+                    transform.transform = Mat4::from_rotation_y(frame * 0.01);
+                }
+                propagate_transforms(&mut world);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, transform_propagation);
+criterion_main!(benches);
@@ -0,0 +1,594 @@
+use std::time::Instant;
+
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    command_encoder::CommandEncoder,
+    create_fullscreen_pipeline,
+    descriptor::{DescriptorHeap, TargetView},
+    device::Device,
+    error::RenderError,
+    id::{ImageId, SamplerId, ViewId},
+    settings::RenderSettings,
+    Renderer, INVALID_INDEX,
+};
+
+pub(crate) const AO_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16B16A16_FLOAT;
+pub(crate) const DEPTH_PYRAMID_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R32_FLOAT;
+const MAX_PYRAMID_MIPS: u32 = 5;
+
+#[repr(C)]
+pub(crate) struct DepthPyramidResources {
+    pub source_index: u32,
+    pub mode: u32,
+}
+
+impl DepthPyramidResources {
+    pub fn copy(source_index: u32, multisampled: bool) -> Self {
+        Self {
+            source_index,
+            mode: multisampled as u32,
+        }
+    }
+
+    pub fn downsample(source_index: u32) -> Self {
+        Self {
+            source_index,
+            mode: 2,
+        }
+    }
+}
+
+#[repr(C)]
+pub(crate) struct GtaoResources {
+    pub depth_pyramid_index: u32,
+    pub view_buffer_index: u32,
+    pub sampler_index: u32,
+    pub slice_count: u32,
+    pub step_count: u32,
+    pub mip_count: u32,
+    pub radius: f32,
+    pub base_mip: u32,
+    pub intensity: f32,
+}
+
+impl GtaoResources {
+    pub fn new(
+        targets: &AoTargets,
+        view_buffer_index: u32,
+        sampler_index: u32,
+        settings: &RenderSettings,
+    ) -> Self {
+        Self {
+            depth_pyramid_index: targets.pyramid_srv.0 as u32,
+            view_buffer_index,
+            sampler_index,
+            slice_count: settings.ambient_occlusion.slice_count(),
+            step_count: settings.ambient_occlusion.step_count(),
+            mip_count: targets.pyramid_mips.len() as u32,
+            radius: settings.ambient_occlusion_radius.max(0.01),
+            base_mip: targets.base_mip,
+            intensity: settings.ambient_occlusion_intensity.max(0.0),
+        }
+    }
+}
+
+#[repr(C)]
+pub(crate) struct SsaoResources {
+    pub depth_pyramid_index: u32,
+    pub view_buffer_index: u32,
+    pub sampler_index: u32,
+    pub sample_count: u32,
+    pub radius: f32,
+    pub base_mip: u32,
+    pub intensity: f32,
+}
+
+impl SsaoResources {
+    pub fn new(
+        targets: &AoTargets,
+        view_buffer_index: u32,
+        sampler_index: u32,
+        settings: &RenderSettings,
+    ) -> Self {
+        Self {
+            depth_pyramid_index: targets.pyramid_srv.0 as u32,
+            view_buffer_index,
+            sampler_index,
+            sample_count: settings.ambient_occlusion.sample_count(),
+            radius: settings.ambient_occlusion_radius.max(0.01),
+            base_mip: targets.base_mip,
+            intensity: settings.ambient_occlusion_intensity.max(0.0),
+        }
+    }
+}
+
+#[repr(C)]
+pub(crate) struct AoUpsampleResources {
+    pub ao_index: u32,
+    pub depth_pyramid_index: u32,
+}
+
+#[repr(C)]
+pub(crate) struct AoBlurResources {
+    pub ao_index: u32,
+    pub depth_pyramid_index: u32,
+}
+
+pub(crate) struct PyramidMip {
+    pub width: u32,
+    pub height: u32,
+    pub rtv: ViewId,
+    pub srv: ViewId,
+}
+
+pub(crate) struct AoImage {
+    pub image: ImageId,
+    pub rtv: ViewId,
+    pub srv: ViewId,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AoImage {
+    fn new(
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        name: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RenderError> {
+        let image = device.create_render_target(
+            name,
+            width,
+            height,
+            AO_FORMAT,
+            1,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        )?;
+        Ok(Self {
+            image,
+            rtv: rtv_heap.create_image_rtv(
+                device,
+                "Ambient Occlusion RTV",
+                image,
+                TargetView::default(),
+            )?,
+            srv: cbv_heap.create_srv(
+                "Ambient Occlusion SRV",
+                device.get_image(image).allocation.resource(),
+                &srv_desc(AO_FORMAT, 0, 1),
+            )?,
+            width,
+            height,
+        })
+    }
+
+    fn destroy(
+        &self,
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        encoder: &CommandEncoder,
+    ) {
+        encoder.untrack_resource(device.get_image(self.image).allocation.resource());
+        device.destroy_image(self.image);
+        rtv_heap.free(self.rtv);
+        cbv_heap.free(self.srv);
+    }
+}
+
+pub(crate) struct AoPipelines {
+    pub depth_pyramid: ID3D12PipelineState,
+    pub gtao: ID3D12PipelineState,
+    pub ssao: ID3D12PipelineState,
+    pub upsample: ID3D12PipelineState,
+    pub blur: ID3D12PipelineState,
+    pub point_sampler: SamplerId,
+}
+
+impl AoPipelines {
+    pub fn new(
+        device: &Device,
+        root_signature: &ID3D12RootSignature,
+        point_sampler: SamplerId,
+    ) -> Result<Self, RenderError> {
+        Ok(Self {
+            depth_pyramid: create_fullscreen_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/depth_pyramid.hlsl",
+                DEPTH_PYRAMID_FORMAT,
+            )?,
+            gtao: create_fullscreen_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/gtao.hlsl",
+                AO_FORMAT,
+            )?,
+            ssao: create_fullscreen_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/ssao.hlsl",
+                AO_FORMAT,
+            )?,
+            upsample: create_fullscreen_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/ao_upsample.hlsl",
+                AO_FORMAT,
+            )?,
+            blur: create_fullscreen_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/ao_blur.hlsl",
+                AO_FORMAT,
+            )?,
+            point_sampler,
+        })
+    }
+}
+
+pub(crate) struct AoTargets {
+    pub depth_srv: ViewId,
+    pub multisampled: bool,
+    pub pyramid: ImageId,
+    pub pyramid_srv: ViewId,
+    pub pyramid_mips: Vec<PyramidMip>,
+    pub base_mip: u32,
+    pub ao: AoImage,
+    pub half_ao: Option<AoImage>,
+    // The final occlusion the lighting reads, after the depth-aware blur.
+    pub blurred: AoImage,
+}
+
+impl AoTargets {
+    // One render target view per pyramid mip, and one per occlusion image.
+    pub fn rtv_count(width: u32, height: u32, settings: &RenderSettings) -> usize {
+        let mip_count = pyramid_mip_count(width, height);
+        let half_ao = base_mip(mip_count, settings) > 0;
+        mip_count as usize + 2 + half_ao as usize
+    }
+
+    pub fn new(
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        depth: ImageId,
+        depth_format: DXGI_FORMAT,
+        settings: &RenderSettings,
+    ) -> Result<Self, RenderError> {
+        let (width, height) = {
+            let depth = device.get_image(depth);
+            (depth.width, depth.height)
+        };
+        let multisampled = settings.anti_aliasing.sample_count() > 1;
+        let depth_srv = cbv_heap.create_srv(
+            "Depth SRV",
+            device.get_image(depth).allocation.resource(),
+            &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: depth_format,
+                ViewDimension: if multisampled {
+                    D3D12_SRV_DIMENSION_TEXTURE2DMS
+                } else {
+                    D3D12_SRV_DIMENSION_TEXTURE2D
+                },
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                        PlaneSlice: 0,
+                        ResourceMinLODClamp: 0.0,
+                    },
+                },
+            },
+        )?;
+
+        let mip_count = pyramid_mip_count(width, height);
+        let pyramid = device.create_render_target_mips(
+            "Depth Pyramid",
+            width,
+            height,
+            mip_count,
+            DEPTH_PYRAMID_FORMAT,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        )?;
+        let pyramid_resource = device.get_image(pyramid).allocation.resource();
+        let pyramid_srv = cbv_heap.create_srv(
+            "Depth Pyramid SRV",
+            pyramid_resource,
+            &srv_desc(DEPTH_PYRAMID_FORMAT, 0, mip_count),
+        )?;
+        let pyramid_mips = (0..mip_count)
+            .map(|mip| {
+                Ok(PyramidMip {
+                    width: (width >> mip).max(1),
+                    height: (height >> mip).max(1),
+                    rtv: rtv_heap.create_image_rtv(
+                        device,
+                        "Depth Pyramid Mip RTV",
+                        pyramid,
+                        TargetView::mip(mip),
+                    )?,
+                    srv: cbv_heap.create_srv(
+                        "Depth Pyramid Mip SRV",
+                        pyramid_resource,
+                        &srv_desc(DEPTH_PYRAMID_FORMAT, mip, 1),
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?;
+
+        let ao = AoImage::new(
+            device,
+            rtv_heap,
+            cbv_heap,
+            "Ambient Occlusion Target",
+            width,
+            height,
+        )?;
+        let blurred = AoImage::new(
+            device,
+            rtv_heap,
+            cbv_heap,
+            "Blurred Ambient Occlusion Target",
+            width,
+            height,
+        )?;
+        let base_mip = base_mip(mip_count, settings);
+        let half_ao = if base_mip > 0 {
+            let mip = &pyramid_mips[base_mip as usize];
+            Some(AoImage::new(
+                device,
+                rtv_heap,
+                cbv_heap,
+                "Half Resolution Ambient Occlusion Target",
+                mip.width,
+                mip.height,
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            depth_srv,
+            multisampled,
+            pyramid,
+            pyramid_srv,
+            pyramid_mips,
+            base_mip,
+            ao,
+            half_ao,
+            blurred,
+        })
+    }
+
+    pub fn track(&self, device: &Device, encoder: &CommandEncoder) {
+        for target in [&self.ao, &self.blurred].into_iter().chain(&self.half_ao) {
+            encoder.track_resource(
+                device.get_image(target.image).allocation.resource(),
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+        }
+    }
+
+    pub fn destroy(
+        &self,
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        encoder: &CommandEncoder,
+    ) {
+        cbv_heap.free(self.depth_srv);
+        device.destroy_image(self.pyramid);
+        cbv_heap.free(self.pyramid_srv);
+        for mip in &self.pyramid_mips {
+            rtv_heap.free(mip.rtv);
+            cbv_heap.free(mip.srv);
+        }
+        self.ao.destroy(device, rtv_heap, cbv_heap, encoder);
+        self.blurred.destroy(device, rtv_heap, cbv_heap, encoder);
+        if let Some(half_ao) = &self.half_ao {
+            half_ao.destroy(device, rtv_heap, cbv_heap, encoder);
+        }
+    }
+}
+
+fn srv_desc(format: DXGI_FORMAT, mip: u32, mip_levels: u32) -> D3D12_SHADER_RESOURCE_VIEW_DESC {
+    D3D12_SHADER_RESOURCE_VIEW_DESC {
+        Format: format,
+        ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+        Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+        Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+            Texture2D: D3D12_TEX2D_SRV {
+                MostDetailedMip: mip,
+                MipLevels: mip_levels,
+                PlaneSlice: 0,
+                ResourceMinLODClamp: 0.0,
+            },
+        },
+    }
+}
+
+impl Renderer {
+    pub(crate) fn render_ambient_occlusion(&mut self) -> u32 {
+        let Some(ao) = &self.targets.ao else {
+            return INVALID_INDEX;
+        };
+        let depth = self
+            .device
+            .get_image(self.targets.depth)
+            .allocation
+            .resource();
+        let pyramid_start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "depth_pyramid");
+        let pyramid = self.device.get_image(ao.pyramid).allocation.resource();
+        self.render_command_encoder.require_state(
+            depth,
+            D3D12_RESOURCE_STATE_DEPTH_READ | D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+        );
+        self.render_command_encoder
+            .set_pipeline(&self.ao_pipelines.depth_pyramid);
+        for (level, mip) in ao.pyramid_mips.iter().enumerate() {
+            let resources = match level {
+                0 => DepthPyramidResources::copy(ao.depth_srv.0 as u32, ao.multisampled),
+                _ => DepthPyramidResources::downsample(ao.pyramid_mips[level - 1].srv.0 as u32),
+            };
+            self.render_command_encoder
+                .set_render_target(self.rtv_heap.get_handle(mip.rtv.0), None);
+            self.render_command_encoder
+                .set_viewport(mip.width, mip.height);
+            self.render_command_encoder
+                .set_scissor(mip.width, mip.height);
+            self.render_command_encoder.set_root_constants(&resources);
+            self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+            self.render_command_encoder.transition_subresource(
+                pyramid,
+                level as u32,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+            self.stats.draw_calls += 1;
+        }
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats
+            .record_pass("depth_pyramid", pyramid_start.elapsed());
+
+        let occlusion_start = Instant::now();
+        let occlusion_pass = if self.settings.ambient_occlusion.uses_gtao() {
+            "gtao"
+        } else {
+            "ssao"
+        };
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, occlusion_pass);
+        let occlusion_target = ao.half_ao.as_ref().unwrap_or(&ao.ao);
+        let occlusion_resource = self
+            .device
+            .get_image(occlusion_target.image)
+            .allocation
+            .resource();
+        self.render_command_encoder
+            .require_state(occlusion_resource, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        self.render_command_encoder
+            .set_render_target(self.rtv_heap.get_handle(occlusion_target.rtv.0), None);
+        self.render_command_encoder
+            .set_viewport(occlusion_target.width, occlusion_target.height);
+        self.render_command_encoder
+            .set_scissor(occlusion_target.width, occlusion_target.height);
+        let view_buffer_index = self.view_buffer.0 as u32;
+        let sampler_index = self.ao_pipelines.point_sampler.0 as u32;
+        if self.settings.ambient_occlusion.uses_gtao() {
+            self.render_command_encoder
+                .set_pipeline(&self.ao_pipelines.gtao);
+            self.render_command_encoder
+                .set_root_constants(&GtaoResources::new(
+                    ao,
+                    view_buffer_index,
+                    sampler_index,
+                    &self.settings,
+                ));
+        } else {
+            self.render_command_encoder
+                .set_pipeline(&self.ao_pipelines.ssao);
+            self.render_command_encoder
+                .set_root_constants(&SsaoResources::new(
+                    ao,
+                    view_buffer_index,
+                    sampler_index,
+                    &self.settings,
+                ));
+        }
+        self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+        self.stats.draw_calls += 1;
+        self.render_command_encoder.require_state(
+            occlusion_resource,
+            D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+        );
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats
+            .record_pass(occlusion_pass, occlusion_start.elapsed());
+
+        let ao_target = self.device.get_image(ao.ao.image).allocation.resource();
+        if let Some(half_ao) = &ao.half_ao {
+            let upsample_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "ao_upsample");
+            self.render_command_encoder
+                .require_state(ao_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+            self.render_command_encoder
+                .set_render_target(self.rtv_heap.get_handle(ao.ao.rtv.0), None);
+            self.render_command_encoder
+                .set_viewport(ao.ao.width, ao.ao.height);
+            self.render_command_encoder
+                .set_scissor(ao.ao.width, ao.ao.height);
+            self.render_command_encoder
+                .set_pipeline(&self.ao_pipelines.upsample);
+            self.render_command_encoder
+                .set_root_constants(&AoUpsampleResources {
+                    ao_index: half_ao.srv.0 as u32,
+                    depth_pyramid_index: ao.pyramid_srv.0 as u32,
+                });
+            self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+            self.stats.draw_calls += 1;
+            self.render_command_encoder
+                .require_state(ao_target, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats
+                .record_pass("ao_upsample", upsample_start.elapsed());
+        }
+
+        let blur_start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "ao_blur");
+        let blurred_target = self
+            .device
+            .get_image(ao.blurred.image)
+            .allocation
+            .resource();
+        self.render_command_encoder
+            .require_state(blurred_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        self.render_command_encoder
+            .set_render_target(self.rtv_heap.get_handle(ao.blurred.rtv.0), None);
+        self.render_command_encoder
+            .set_viewport(ao.blurred.width, ao.blurred.height);
+        self.render_command_encoder
+            .set_scissor(ao.blurred.width, ao.blurred.height);
+        self.render_command_encoder
+            .set_pipeline(&self.ao_pipelines.blur);
+        self.render_command_encoder
+            .set_root_constants(&AoBlurResources {
+                ao_index: ao.ao.srv.0 as u32,
+                depth_pyramid_index: ao.pyramid_srv.0 as u32,
+            });
+        self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+        self.stats.draw_calls += 1;
+        self.render_command_encoder
+            .require_state(blurred_target, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats.record_pass("ao_blur", blur_start.elapsed());
+
+        self.render_command_encoder.transition_image(
+            pyramid,
+            D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        );
+        self.render_command_encoder
+            .require_state(depth, D3D12_RESOURCE_STATE_DEPTH_WRITE);
+        ao.blurred.srv.0 as u32
+    }
+}
+
+fn pyramid_mip_count(width: u32, height: u32) -> u32 {
+    (32 - width.max(height).leading_zeros()).min(MAX_PYRAMID_MIPS)
+}
+
+fn base_mip(mip_count: u32, settings: &RenderSettings) -> u32 {
+    settings
+        .ambient_occlusion_resolution
+        .mip_level()
+        .min(mip_count - 1)
+}
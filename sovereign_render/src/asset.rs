@@ -1,46 +1,292 @@
-use std::marker::PhantomData;
+pub mod texture;
+
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex, Weak},
+};
+
+struct HandleRef {
+    idx: usize,
+    dropped: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Drop for HandleRef {
+    fn drop(&mut self) {
+        if let Ok(mut dropped) = self.dropped.lock() {
+            dropped.push(self.idx);
+        }
+    }
+}
 
-#[derive(Debug, Eq, PartialEq)]
 pub struct Handle<T> {
-    pub idx: usize,
-    _p: PhantomData<T>,
+    inner: Arc<HandleRef>,
+    _p: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn idx(&self) -> usize {
+        self.inner.idx
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            idx: self.inner.idx,
+            _p: PhantomData,
+        }
+    }
+}
+
+pub struct WeakHandle<T> {
+    idx: usize,
+    _p: PhantomData<fn() -> T>,
+}
+
+impl<T> WeakHandle<T> {
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+}
+
+struct Slot<T> {
+    item: T,
+    handle: Weak<HandleRef>,
+    key: Option<String>,
 }
 
 pub struct Assets<T> {
-    items: Vec<T>,
-    count: usize,
+    slots: Vec<Option<Slot<T>>>,
+    len: usize,
+    modified: Vec<usize>,
+    removed: Vec<usize>,
+    dropped: Arc<Mutex<Vec<usize>>>,
+    keys: HashMap<String, usize>,
 }
 
 impl<T> Assets<T> {
     pub fn new() -> Self {
         Self {
-            items: Vec::new(),
-            count: 0,
+            slots: Vec::new(),
+            len: 0,
+            modified: Vec::new(),
+            removed: Vec::new(),
+            dropped: Arc::new(Mutex::new(Vec::new())),
+            keys: HashMap::new(),
         }
     }
 
     pub fn push(&mut self, item: T) -> Handle<T> {
-        self.items.push(item);
-        let idx = self.count;
-        self.count += 1;
-        Handle {
+        self.insert(item, None)
+    }
+
+    pub fn insert_with_key(&mut self, key: impl Into<String>, item: T) -> Handle<T> {
+        let key = key.into();
+        if let Some(idx) = self.keys.get(&key).copied() {
+            if let Some(slot) = self.slots[idx].as_mut() {
+                slot.key = None;
+            }
+        }
+        let handle = self.insert(item, Some(key.clone()));
+        self.keys.insert(key, handle.idx());
+        handle
+    }
+
+    pub fn set_key(&mut self, handle: impl Into<WeakHandle<T>>, key: impl Into<String>) {
+        let idx = handle.into().idx;
+        let key = key.into();
+        if let Some(previous) = self.keys.get(&key).copied() {
+            if let Some(slot) = self.slots[previous].as_mut() {
+                slot.key = None;
+            }
+        }
+        let Some(slot) = self.slots.get_mut(idx).and_then(Option::as_mut) else {
+            return;
+        };
+        if let Some(old_key) = slot.key.replace(key.clone()) {
+            self.keys.remove(&old_key);
+        }
+        self.keys.insert(key, idx);
+    }
+
+    pub fn get_by_key(&self, key: &str) -> Option<Handle<T>> {
+        let idx = *self.keys.get(key)?;
+        self.upgrade(WeakHandle {
             idx,
             _p: PhantomData,
+        })
+    }
+
+    pub fn key(&self, handle: impl Into<WeakHandle<T>>) -> Option<&str> {
+        let slot = self.slots.get(handle.into().idx)?.as_ref()?;
+        slot.key.as_deref()
+    }
+
+    fn insert(&mut self, item: T, key: Option<String>) -> Handle<T> {
+        let idx = self.slots.len();
+        let inner = Arc::new(HandleRef {
+            idx,
+            dropped: self.dropped.clone(),
+        });
+        self.slots.push(Some(Slot {
+            item,
+            handle: Arc::downgrade(&inner),
+            key,
+        }));
+        self.len += 1;
+        Handle {
+            inner,
+            _p: PhantomData,
         }
     }
 
-    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
-        self.items.get(handle.idx)
+    pub fn get(&self, handle: impl Into<WeakHandle<T>>) -> Option<&T> {
+        let slot = self.slots.get(handle.into().idx)?.as_ref()?;
+        Some(&slot.item)
+    }
+
+    pub fn get_mut(&mut self, handle: impl Into<WeakHandle<T>>) -> Option<&mut T> {
+        let idx = handle.into().idx;
+        let slot = self.slots.get_mut(idx)?.as_mut()?;
+        if !self.modified.contains(&idx) {
+            self.modified.push(idx);
+        }
+        Some(&mut slot.item)
+    }
+
+    pub fn upgrade(&self, handle: WeakHandle<T>) -> Option<Handle<T>> {
+        let slot = self.slots.get(handle.idx)?.as_ref()?;
+        Some(Handle {
+            inner: slot.handle.upgrade()?,
+            _p: PhantomData,
+        })
+    }
+
+    pub fn remove(&mut self, handle: impl Into<WeakHandle<T>>) -> Option<T> {
+        let idx = handle.into().idx;
+        let slot = self.slots.get_mut(idx)?.take()?;
+        if let Some(key) = &slot.key {
+            self.keys.remove(key);
+        }
+        self.len -= 1;
+        self.modified.retain(|modified| *modified != idx);
+        self.removed.push(idx);
+        Some(slot.item)
+    }
+
+    pub fn collect_garbage(&mut self) -> Vec<T> {
+        let dropped = std::mem::take(&mut *self.dropped.lock().unwrap());
+        let mut garbage = Vec::new();
+        for idx in dropped {
+            let unreferenced = self.slots[idx]
+                .as_ref()
+                .is_some_and(|slot| slot.handle.strong_count() == 0);
+            if unreferenced {
+                garbage.extend(self.remove(WeakHandle {
+                    idx,
+                    _p: PhantomData,
+                }));
+            }
+        }
+        garbage
+    }
+
+    pub fn drain_modified(&mut self) -> impl Iterator<Item = WeakHandle<T>> + '_ {
+        self.modified.drain(..).map(|idx| WeakHandle {
+            idx,
+            _p: PhantomData,
+        })
+    }
+
+    pub fn drain_removed(&mut self) -> impl Iterator<Item = WeakHandle<T>> + '_ {
+        self.removed.drain(..).map(|idx| WeakHandle {
+            idx,
+            _p: PhantomData,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WeakHandle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(idx, slot)| {
+            let slot = slot.as_ref()?;
+            Some((
+                WeakHandle {
+                    idx,
+                    _p: PhantomData,
+                },
+                &slot.item,
+            ))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 }
 
-impl<T> Copy for Handle<T> {}
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<T> Clone for Handle<T> {
     fn clone(&self) -> Self {
         Self {
-            idx: self.idx,
+            inner: self.inner.clone(),
             _p: PhantomData,
         }
     }
 }
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle").field("idx", &self.idx()).finish()
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.idx() == other.idx()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Copy for WeakHandle<T> {}
+
+impl<T> std::fmt::Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakHandle")
+            .field("idx", &self.idx)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for WeakHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.idx == other.idx
+    }
+}
+
+impl<T> Eq for WeakHandle<T> {}
+
+impl<T> std::hash::Hash for WeakHandle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.idx.hash(state);
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> From<&Handle<T>> for WeakHandle<T> {
+    fn from(handle: &Handle<T>) -> Self {
+        handle.downgrade()
+    }
+}
@@ -0,0 +1,313 @@
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+use crate::error::RenderError;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDPF_FOURCC: u32 = 0x4;
+
+const KTX2_IDENTIFIER: &[u8; 12] = b"\xabKTX 20\xbb\r\n\x1a\n";
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    pub format: DXGI_FORMAT,
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    pub fn rgba8(width: u32, height: u32, data: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            mips: vec![data],
+        }
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mips.len() as u32
+    }
+
+    // Block-compressed formats need every resident level to stay a whole number of blocks.
+    pub fn droppable_mips(&self, bias: u32) -> usize {
+        let block_size = format_block_info(self.format).map_or(1, |(block_size, _)| block_size);
+        (1..=bias as usize)
+            .take_while(|&skip| {
+                let width = self.width >> skip;
+                let height = self.height >> skip;
+                skip < self.mips.len()
+                    && width >= block_size
+                    && height >= block_size
+                    && width % block_size == 0
+                    && height % block_size == 0
+            })
+            .last()
+            .unwrap_or(0)
+    }
+}
+
+pub fn load_dds(bytes: &[u8]) -> Result<TextureData, RenderError> {
+    if bytes.len() < DDS_HEADER_SIZE || &bytes[0..4] != DDS_MAGIC {
+        return Err(invalid("missing DDS header"));
+    }
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let mip_levels = read_u32(bytes, 28)?.max(1);
+    let pixel_flags = read_u32(bytes, 80)?;
+    let four_cc = &bytes[84..88];
+
+    if pixel_flags & DDPF_FOURCC == 0 {
+        return Err(invalid("uncompressed DDS files are not supported"));
+    }
+    let (format, offset) = match four_cc {
+        b"DXT1" => (DXGI_FORMAT_BC1_UNORM, DDS_HEADER_SIZE),
+        b"DXT5" => (DXGI_FORMAT_BC3_UNORM, DDS_HEADER_SIZE),
+        b"ATI2" | b"BC5U" => (DXGI_FORMAT_BC5_UNORM, DDS_HEADER_SIZE),
+        b"BC5S" => (DXGI_FORMAT_BC5_SNORM, DDS_HEADER_SIZE),
+        b"DX10" => {
+            let format = DXGI_FORMAT(read_u32(bytes, DDS_HEADER_SIZE)? as i32);
+            let array_size = read_u32(bytes, DDS_HEADER_SIZE + 12)?;
+            if array_size > 1 {
+                return Err(invalid("DDS texture arrays are not supported"));
+            }
+            (format, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+        }
+        _ => {
+            return Err(invalid(format!(
+                "unsupported DDS format {}",
+                String::from_utf8_lossy(four_cc)
+            )))
+        }
+    };
+    let (block_size, block_bytes) = format_block_info(format)
+        .ok_or_else(|| invalid(format!("unsupported DDS format {:?}", format)))?;
+    check_mip_chain(width, height, mip_levels)?;
+
+    let mut mips = Vec::new();
+    let mut offset = offset;
+    for level in 0..mip_levels {
+        let size = mip_size(width, height, level, block_size, block_bytes);
+        let data = offset
+            .checked_add(size)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or_else(|| invalid("DDS mip data is truncated"))?;
+        mips.push(data.to_vec());
+        offset += size;
+    }
+
+    Ok(TextureData {
+        width,
+        height,
+        format,
+        mips,
+    })
+}
+
+pub fn load_ktx2(bytes: &[u8]) -> Result<TextureData, RenderError> {
+    if bytes.len() < KTX2_HEADER_SIZE || &bytes[0..12] != KTX2_IDENTIFIER {
+        return Err(invalid("missing KTX2 identifier"));
+    }
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)?;
+    let mip_levels = read_u32(bytes, 40)?.max(1);
+    let supercompression = read_u32(bytes, 44)?;
+
+    if depth > 1 || layers > 1 || faces > 1 {
+        return Err(invalid("only 2D KTX2 textures are supported"));
+    }
+    if supercompression != 0 {
+        return Err(invalid("supercompressed KTX2 files are not supported"));
+    }
+    let format = vk_format_to_dxgi(vk_format)
+        .ok_or_else(|| invalid(format!("unsupported KTX2 format {}", vk_format)))?;
+    let (block_size, block_bytes) = format_block_info(format).unwrap();
+    check_mip_chain(width, height, mip_levels)?;
+
+    let mut mips = Vec::new();
+    for level in 0..mip_levels {
+        let index = KTX2_HEADER_SIZE + level as usize * KTX2_LEVEL_INDEX_SIZE;
+        let offset = read_u64(bytes, index)? as usize;
+        let length = read_u64(bytes, index + 8)? as usize;
+        if length != mip_size(width, height, level, block_size, block_bytes) {
+            return Err(invalid("KTX2 mip size does not match its format"));
+        }
+        let data = offset
+            .checked_add(length)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or_else(|| invalid("KTX2 mip data is truncated"))?;
+        mips.push(data.to_vec());
+    }
+
+    Ok(TextureData {
+        width,
+        height,
+        format,
+        mips,
+    })
+}
+
+pub fn format_block_info(format: DXGI_FORMAT) -> Option<(u32, u32)> {
+    match format {
+        DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => Some((1, 4)),
+        DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB => Some((4, 8)),
+        DXGI_FORMAT_BC3_UNORM
+        | DXGI_FORMAT_BC3_UNORM_SRGB
+        | DXGI_FORMAT_BC5_UNORM
+        | DXGI_FORMAT_BC5_SNORM
+        | DXGI_FORMAT_BC7_UNORM
+        | DXGI_FORMAT_BC7_UNORM_SRGB => Some((4, 16)),
+        _ => None,
+    }
+}
+
+fn vk_format_to_dxgi(vk_format: u32) -> Option<DXGI_FORMAT> {
+    match vk_format {
+        37 => Some(DXGI_FORMAT_R8G8B8A8_UNORM),
+        43 => Some(DXGI_FORMAT_R8G8B8A8_UNORM_SRGB),
+        131 | 133 => Some(DXGI_FORMAT_BC1_UNORM),
+        132 | 134 => Some(DXGI_FORMAT_BC1_UNORM_SRGB),
+        137 => Some(DXGI_FORMAT_BC3_UNORM),
+        138 => Some(DXGI_FORMAT_BC3_UNORM_SRGB),
+        141 => Some(DXGI_FORMAT_BC5_UNORM),
+        142 => Some(DXGI_FORMAT_BC5_SNORM),
+        145 => Some(DXGI_FORMAT_BC7_UNORM),
+        146 => Some(DXGI_FORMAT_BC7_UNORM_SRGB),
+        _ => None,
+    }
+}
+
+// Mip counts come from the file header, so anything past the 1x1 level is rejected before a level
+// is shifted out of range.
+fn check_mip_chain(width: u32, height: u32, mip_levels: u32) -> Result<(), RenderError> {
+    if width == 0 || height == 0 {
+        return Err(invalid("texture has no pixels"));
+    }
+    let max_levels = u32::BITS - width.max(height).leading_zeros();
+    if mip_levels > max_levels {
+        return Err(invalid(format!(
+            "{} mips is more than a {}x{} texture has",
+            mip_levels, width, height
+        )));
+    }
+    Ok(())
+}
+
+fn mip_size(width: u32, height: u32, level: u32, block_size: u32, block_bytes: u32) -> usize {
+    let width = (width >> level).max(1).div_ceil(block_size);
+    let height = (height >> level).max(1).div_ceil(block_size);
+    width as usize * height as usize * block_bytes as usize
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, RenderError> {
+    let bytes = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| invalid("unexpected end of file"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, RenderError> {
+    let bytes = bytes
+        .get(offset..offset + 8)
+        .ok_or_else(|| invalid("unexpected end of file"))?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn invalid(message: impl Into<String>) -> RenderError {
+    RenderError::InvalidTexture(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dds_bc1(width: u32, height: u32, mip_levels: u32, data_len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; DDS_HEADER_SIZE + data_len];
+        bytes[0..4].copy_from_slice(DDS_MAGIC);
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&mip_levels.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DXT1");
+        bytes
+    }
+
+    fn ktx2_rgba8(width: u32, height: u32, levels: &[(u64, u64)]) -> Vec<u8> {
+        let mut bytes = vec![0; KTX2_HEADER_SIZE + levels.len() * KTX2_LEVEL_INDEX_SIZE];
+        bytes[0..12].copy_from_slice(KTX2_IDENTIFIER);
+        bytes[12..16].copy_from_slice(&37u32.to_le_bytes());
+        bytes[20..24].copy_from_slice(&width.to_le_bytes());
+        bytes[24..28].copy_from_slice(&height.to_le_bytes());
+        bytes[40..44].copy_from_slice(&(levels.len() as u32).to_le_bytes());
+        for (level, (offset, length)) in levels.iter().enumerate() {
+            let index = KTX2_HEADER_SIZE + level * KTX2_LEVEL_INDEX_SIZE;
+            bytes[index..index + 8].copy_from_slice(&offset.to_le_bytes());
+            bytes[index + 8..index + 16].copy_from_slice(&length.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn dds_bc1_chain_loads_every_level() {
+        // 8x8, 4x4, 2x2 and 1x1 each round up to whole 8 byte blocks.
+        let texture = load_dds(&dds_bc1(8, 8, 4, 32 + 8 + 8 + 8)).unwrap();
+        assert_eq!(texture.format, DXGI_FORMAT_BC1_UNORM);
+        assert_eq!(texture.mip_levels(), 4);
+        let sizes = texture.mips.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, [32, 8, 8, 8]);
+    }
+
+    #[test]
+    fn dds_truncated_files_are_rejected() {
+        assert!(load_dds(&dds_bc1(8, 8, 4, 32 + 8 + 8)).is_err());
+        assert!(load_dds(&dds_bc1(8, 8, 1, 0)[..DDS_HEADER_SIZE - 1]).is_err());
+        assert!(load_dds(b"DDS ").is_err());
+    }
+
+    #[test]
+    fn dds_oversized_mip_counts_are_rejected() {
+        assert!(load_dds(&dds_bc1(8, 8, 5, 64)).is_err());
+        assert!(load_dds(&dds_bc1(1, 1, 40, 64)).is_err());
+        assert!(load_dds(&dds_bc1(4, 4, u32::MAX, 64)).is_err());
+    }
+
+    #[test]
+    fn dds_empty_textures_are_rejected() {
+        assert!(load_dds(&dds_bc1(0, 8, 1, 64)).is_err());
+        assert!(load_dds(&dds_bc1(8, 0, 1, 64)).is_err());
+    }
+
+    #[test]
+    fn ktx2_rgba8_chain_loads_every_level() {
+        let data_start = (KTX2_HEADER_SIZE + 2 * KTX2_LEVEL_INDEX_SIZE) as u64;
+        let mut bytes = ktx2_rgba8(2, 2, &[(data_start, 16), (data_start + 16, 4)]);
+        bytes.resize(data_start as usize + 20, 0);
+        let texture = load_ktx2(&bytes).unwrap();
+        assert_eq!(texture.mip_levels(), 2);
+        assert_eq!(texture.mips[1].len(), 4);
+    }
+
+    #[test]
+    fn ktx2_truncated_files_are_rejected() {
+        let data_start = (KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE) as u64;
+        let mut bytes = ktx2_rgba8(2, 2, &[(data_start, 16)]);
+        bytes.resize(data_start as usize + 15, 0);
+        assert!(load_ktx2(&bytes).is_err());
+        assert!(load_ktx2(&bytes[..KTX2_HEADER_SIZE]).is_err());
+    }
+
+    #[test]
+    fn ktx2_oversized_mip_counts_are_rejected() {
+        let levels = vec![(0, 4); 33];
+        assert!(load_ktx2(&ktx2_rgba8(1 << 31, 1, &levels)).is_err());
+        assert!(load_ktx2(&ktx2_rgba8(2, 2, &levels[..3])).is_err());
+        assert!(load_ktx2(&ktx2_rgba8(0, 2, &levels[..1])).is_err());
+    }
+}
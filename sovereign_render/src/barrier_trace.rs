@@ -0,0 +1,147 @@
+use std::fmt;
+use windows::Win32::Graphics::Direct3D12::*;
+
+const STATE_NAMES: &[(D3D12_RESOURCE_STATES, &str)] = &[
+    (
+        D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER,
+        "VERTEX_AND_CONSTANT_BUFFER",
+    ),
+    (D3D12_RESOURCE_STATE_INDEX_BUFFER, "INDEX_BUFFER"),
+    (D3D12_RESOURCE_STATE_RENDER_TARGET, "RENDER_TARGET"),
+    (D3D12_RESOURCE_STATE_UNORDERED_ACCESS, "UNORDERED_ACCESS"),
+    (D3D12_RESOURCE_STATE_DEPTH_WRITE, "DEPTH_WRITE"),
+    (D3D12_RESOURCE_STATE_DEPTH_READ, "DEPTH_READ"),
+    (
+        D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE,
+        "NON_PIXEL_SHADER_RESOURCE",
+    ),
+    (
+        D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+        "PIXEL_SHADER_RESOURCE",
+    ),
+    (D3D12_RESOURCE_STATE_STREAM_OUT, "STREAM_OUT"),
+    (D3D12_RESOURCE_STATE_INDIRECT_ARGUMENT, "INDIRECT_ARGUMENT"),
+    (D3D12_RESOURCE_STATE_COPY_DEST, "COPY_DEST"),
+    (D3D12_RESOURCE_STATE_COPY_SOURCE, "COPY_SOURCE"),
+    (D3D12_RESOURCE_STATE_RESOLVE_DEST, "RESOLVE_DEST"),
+    (D3D12_RESOURCE_STATE_RESOLVE_SOURCE, "RESOLVE_SOURCE"),
+];
+
+const WRITE_STATES: D3D12_RESOURCE_STATES = D3D12_RESOURCE_STATES(
+    D3D12_RESOURCE_STATE_RENDER_TARGET.0
+        | D3D12_RESOURCE_STATE_UNORDERED_ACCESS.0
+        | D3D12_RESOURCE_STATE_DEPTH_WRITE.0
+        | D3D12_RESOURCE_STATE_STREAM_OUT.0
+        | D3D12_RESOURCE_STATE_COPY_DEST.0
+        | D3D12_RESOURCE_STATE_RESOLVE_DEST.0,
+);
+
+// Transitions recorded by a command encoder while a trace is active, before resources are named.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RecordedBarrier {
+    pub resource: usize,
+    pub subresource: Option<u32>,
+    pub before: D3D12_RESOURCE_STATES,
+    pub after: D3D12_RESOURCE_STATES,
+    pub redundant: bool,
+}
+
+#[derive(Debug)]
+pub struct BarrierRecord {
+    pub resource: String,
+    // None when every subresource transitions.
+    pub subresource: Option<u32>,
+    pub before: D3D12_RESOURCE_STATES,
+    pub after: D3D12_RESOURCE_STATES,
+    // Set for no-op transitions and ones that undo a transition still waiting to be flushed.
+    pub redundant: bool,
+}
+
+#[derive(Debug)]
+pub struct ResourceUse {
+    pub resource: String,
+    pub state: D3D12_RESOURCE_STATES,
+}
+
+impl ResourceUse {
+    pub fn is_write(&self) -> bool {
+        self.state.0 & WRITE_STATES.0 != 0
+    }
+}
+
+#[derive(Debug)]
+pub struct PassTrace {
+    pub name: String,
+    pub barriers: Vec<BarrierRecord>,
+    // The resources the pass declared, which unlike barriers includes those already in the
+    // state the pass needs.
+    pub uses: Vec<ResourceUse>,
+}
+
+// The passes of one frame in submission order, with the transitions each one issued.
+#[derive(Debug, Default)]
+pub struct BarrierTrace {
+    pub passes: Vec<PassTrace>,
+}
+
+impl BarrierTrace {
+    pub fn barrier_count(&self) -> usize {
+        self.passes.iter().map(|pass| pass.barriers.len()).sum()
+    }
+
+    pub fn redundant_count(&self) -> usize {
+        self.passes
+            .iter()
+            .flat_map(|pass| &pass.barriers)
+            .filter(|barrier| barrier.redundant)
+            .count()
+    }
+}
+
+impl fmt::Display for BarrierTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Frame barriers: {} passes, {} barriers, {} redundant",
+            self.passes.len(),
+            self.barrier_count(),
+            self.redundant_count()
+        )?;
+        for pass in &self.passes {
+            writeln!(f, "  {}", pass.name)?;
+            for barrier in &pass.barriers {
+                write!(f, "    \"{}\"", barrier.resource)?;
+                if let Some(subresource) = barrier.subresource {
+                    write!(f, " [{}]", subresource)?;
+                }
+                write!(
+                    f,
+                    " {} -> {}",
+                    state_name(barrier.before),
+                    state_name(barrier.after)
+                )?;
+                if barrier.redundant {
+                    write!(f, " (redundant)")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn state_name(state: D3D12_RESOURCE_STATES) -> String {
+    if state == D3D12_RESOURCE_STATE_COMMON {
+        return "COMMON".to_string();
+    }
+    let names = STATE_NAMES
+        .iter()
+        .filter(|(flag, _)| state.0 & flag.0 == flag.0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        format!("{:#x}", state.0)
+    } else {
+        names.join("|")
+    }
+}
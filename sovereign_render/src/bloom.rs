@@ -0,0 +1,311 @@
+use std::time::Instant;
+
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    compile_shader_pair,
+    descriptor::{DescriptorHeap, TargetView},
+    device::Device,
+    error::RenderError,
+    graphics_pipeline_desc,
+    id::{ImageId, SamplerId, ViewId},
+    Renderer, INVALID_INDEX,
+};
+
+pub(crate) const BLOOM_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R11G11B10_FLOAT;
+const MAX_BLOOM_MIPS: u32 = 6;
+// Radius of the upsample tent filter, in texels of the mip being sampled.
+const UPSAMPLE_RADIUS: f32 = 1.0;
+
+#[repr(C)]
+pub(crate) struct BloomResources {
+    pub source_index: u32,
+    pub sampler_index: u32,
+    pub threshold: f32,
+    pub prefilter: u32,
+    pub radius: f32,
+}
+
+impl BloomResources {
+    // The first downsample reads the scene and keeps only what is above the threshold.
+    pub fn downsample(source_index: u32, sampler_index: u32, threshold: Option<f32>) -> Self {
+        Self {
+            source_index,
+            sampler_index,
+            threshold: threshold.unwrap_or_default(),
+            prefilter: threshold.is_some() as u32,
+            radius: 0.0,
+        }
+    }
+
+    pub fn upsample(source_index: u32, sampler_index: u32) -> Self {
+        Self {
+            source_index,
+            sampler_index,
+            threshold: 0.0,
+            prefilter: 0,
+            radius: UPSAMPLE_RADIUS,
+        }
+    }
+}
+
+pub(crate) struct BloomPipelines {
+    pub downsample: ID3D12PipelineState,
+    // Adds the blurred lower mip onto the one above it.
+    pub upsample: ID3D12PipelineState,
+    pub sampler: SamplerId,
+}
+
+impl BloomPipelines {
+    pub fn new(
+        device: &Device,
+        root_signature: &ID3D12RootSignature,
+        sampler: SamplerId,
+    ) -> Result<Self, RenderError> {
+        Ok(Self {
+            downsample: create_bloom_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/bloom_downsample.hlsl",
+                false,
+            )?,
+            upsample: create_bloom_pipeline(
+                device,
+                root_signature,
+                "assets/shaders/bloom_upsample.hlsl",
+                true,
+            )?,
+            sampler,
+        })
+    }
+}
+
+fn create_bloom_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    path: &str,
+    additive: bool,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair(path)?;
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        BLOOM_FORMAT,
+        1,
+    );
+    pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+        DepthEnable: false.into(),
+        DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+        ..Default::default()
+    };
+    pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+    if additive {
+        let blend = &mut pipeline_desc.BlendState.RenderTarget[0];
+        blend.BlendEnable = true.into();
+        blend.SrcBlend = D3D12_BLEND_ONE;
+        blend.DestBlend = D3D12_BLEND_ONE;
+    }
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+pub(crate) struct BloomMip {
+    pub width: u32,
+    pub height: u32,
+    pub rtv: ViewId,
+    pub srv: ViewId,
+}
+
+// A mip chain starting at half the scene resolution. Between frames every mip is a render target.
+pub(crate) struct BloomTargets {
+    pub image: ImageId,
+    pub mips: Vec<BloomMip>,
+}
+
+impl BloomTargets {
+    // One render target view per mip.
+    pub fn rtv_count(scene_width: u32, scene_height: u32) -> usize {
+        let (width, height) = base_size(scene_width, scene_height);
+        mip_count(width, height) as usize
+    }
+
+    pub fn new(
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        scene_width: u32,
+        scene_height: u32,
+    ) -> Result<Self, RenderError> {
+        let (width, height) = base_size(scene_width, scene_height);
+        let mip_count = mip_count(width, height);
+        let image = device.create_render_target_mips(
+            "Bloom Target",
+            width,
+            height,
+            mip_count,
+            BLOOM_FORMAT,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        )?;
+        let resource = device.get_image(image).allocation.resource();
+        let mips = (0..mip_count)
+            .map(|mip| {
+                Ok(BloomMip {
+                    width: (width >> mip).max(1),
+                    height: (height >> mip).max(1),
+                    rtv: rtv_heap.create_image_rtv(
+                        device,
+                        "Bloom Mip RTV",
+                        image,
+                        TargetView::mip(mip),
+                    )?,
+                    srv: cbv_heap.create_srv(
+                        "Bloom Mip SRV",
+                        resource,
+                        &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                            Format: BLOOM_FORMAT,
+                            ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+                            Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                            Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                                Texture2D: D3D12_TEX2D_SRV {
+                                    MostDetailedMip: mip,
+                                    MipLevels: 1,
+                                    PlaneSlice: 0,
+                                    ResourceMinLODClamp: 0.0,
+                                },
+                            },
+                        },
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?;
+        Ok(Self { image, mips })
+    }
+
+    pub fn destroy(
+        &self,
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+    ) {
+        device.destroy_image(self.image);
+        for mip in &self.mips {
+            rtv_heap.free(mip.rtv);
+            cbv_heap.free(mip.srv);
+        }
+    }
+}
+
+impl Renderer {
+    // Runs on the resolved scene color. Returns the top of the blurred chain for the
+    // tonemap pass to add back, leaving the chain readable until end_bloom.
+    pub(crate) fn render_bloom(&mut self) -> u32 {
+        let Some(bloom) = &self.targets.bloom else {
+            return INVALID_INDEX;
+        };
+        let start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "bloom");
+        let scene_color = self
+            .device
+            .get_image(self.targets.scene_color)
+            .allocation
+            .resource();
+        self.render_command_encoder
+            .require_state(scene_color, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+        let image = self.device.get_image(bloom.image).allocation.resource();
+        // The chain's subresources are transitioned by hand, so require_state can't declare it.
+        self.render_command_encoder
+            .mark_use(image, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        let sampler_index = self.bloom_pipelines.sampler.0 as u32;
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .set_pipeline(&self.bloom_pipelines.downsample);
+        for (level, mip) in bloom.mips.iter().enumerate() {
+            let resources = match level {
+                0 => BloomResources::downsample(
+                    self.targets.scene_color_srv.0 as u32,
+                    sampler_index,
+                    Some(self.settings.post.bloom_threshold),
+                ),
+                _ => BloomResources::downsample(
+                    bloom.mips[level - 1].srv.0 as u32,
+                    sampler_index,
+                    None,
+                ),
+            };
+            self.render_command_encoder
+                .set_render_target(self.rtv_heap.get_handle(mip.rtv.0), None);
+            self.render_command_encoder
+                .set_viewport(mip.width, mip.height);
+            self.render_command_encoder
+                .set_scissor(mip.width, mip.height);
+            self.render_command_encoder.set_root_constants(&resources);
+            self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+            self.render_command_encoder.transition_subresource(
+                image,
+                level as u32,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+            self.stats.draw_calls += 1;
+        }
+
+        // Walk back up, blending each mip into the one above so every level contributes.
+        self.render_command_encoder
+            .set_pipeline(&self.bloom_pipelines.upsample);
+        for level in (0..bloom.mips.len().saturating_sub(1)).rev() {
+            let mip = &bloom.mips[level];
+            self.render_command_encoder.transition_subresource(
+                image,
+                level as u32,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+            self.render_command_encoder
+                .set_render_target(self.rtv_heap.get_handle(mip.rtv.0), None);
+            self.render_command_encoder
+                .set_viewport(mip.width, mip.height);
+            self.render_command_encoder
+                .set_scissor(mip.width, mip.height);
+            self.render_command_encoder
+                .set_root_constants(&BloomResources::upsample(
+                    bloom.mips[level + 1].srv.0 as u32,
+                    sampler_index,
+                ));
+            self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+            self.render_command_encoder.transition_subresource(
+                image,
+                level as u32,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+            self.stats.draw_calls += 1;
+        }
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats.record_pass("bloom", start.elapsed());
+        bloom.mips[0].srv.0 as u32
+    }
+
+    // Returns the chain to render targets once the tonemap pass has read it.
+    pub(crate) fn end_bloom(&self) {
+        if let Some(bloom) = &self.targets.bloom {
+            let image = self.device.get_image(bloom.image).allocation.resource();
+            self.render_command_encoder
+                .mark_use(image, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+            self.render_command_encoder.transition_image(
+                image,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+        }
+    }
+}
+
+fn base_size(scene_width: u32, scene_height: u32) -> (u32, u32) {
+    ((scene_width / 2).max(1), (scene_height / 2).max(1))
+}
+
+fn mip_count(width: u32, height: u32) -> u32 {
+    (32 - width.max(height).leading_zeros()).min(MAX_BLOOM_MIPS)
+}
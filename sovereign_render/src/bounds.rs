@@ -0,0 +1,79 @@
+use sovereign_ecs::{CommandBuffer, Entity, World};
+use sovereign_math::Aabb;
+use std::collections::HashSet;
+
+use crate::transform::GlobalTransform;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldBounds(pub Aabb);
+
+// Local space bounds used in place of the mesh's computed Aabb, including for skinned meshes whose
+// joints would otherwise decide them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundsOverride(pub Aabb);
+
+// Marks a mesh that is drawn even when its bounds fall outside the view, like a skybox or a
+// first-person view model.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NeverCull;
+
+pub struct SkinnedBounds {
+    pub joints: Vec<Entity>,
+    pub joint_bounds: Vec<Aabb>,
+}
+
+pub(crate) fn update_world_bounds(world: &mut World, dirty: &HashSet<Entity>) {
+    let mut commands = CommandBuffer::new();
+    for (entity, (aabb, global, world_bounds)) in world
+        .query::<(&Aabb, &GlobalTransform, Option<&mut WorldBounds>)>()
+        .without::<&SkinnedBounds>()
+        .without::<&BoundsOverride>()
+        .iter()
+    {
+        let bounds = WorldBounds(aabb.transformed(&global.transform));
+        match world_bounds {
+            Some(world_bounds) if dirty.contains(&entity) => *world_bounds = bounds,
+            Some(_) => {}
+            None => commands.insert_one(entity, bounds),
+        }
+    }
+
+    // Overrides can change without the transform moving, so they are always recomputed.
+    for (entity, (bounds_override, global, world_bounds)) in world
+        .query::<(&BoundsOverride, &GlobalTransform, Option<&mut WorldBounds>)>()
+        .iter()
+    {
+        let bounds = WorldBounds(bounds_override.0.transformed(&global.transform));
+        match world_bounds {
+            Some(world_bounds) => *world_bounds = bounds,
+            None => commands.insert_one(entity, bounds),
+        }
+    }
+
+    for (entity, (skin, world_bounds)) in world
+        .query::<(&SkinnedBounds, Option<&mut WorldBounds>)>()
+        .without::<&BoundsOverride>()
+        .iter()
+    {
+        let moved = skin.joints.iter().any(|joint| dirty.contains(joint));
+        if world_bounds.is_some() && !moved {
+            continue;
+        }
+        let bounds = skin
+            .joints
+            .iter()
+            .zip(&skin.joint_bounds)
+            .filter_map(|(joint, bounds)| {
+                let global = world.get().get::<&GlobalTransform>(*joint).ok()?;
+                Some(bounds.transformed(&global.transform))
+            })
+            .reduce(|a, b| a.union(&b));
+        match (bounds, world_bounds) {
+            (Some(bounds), Some(world_bounds)) => world_bounds.0 = bounds,
+            (Some(bounds), None) => commands.insert_one(entity, WorldBounds(bounds)),
+            (None, _) => {}
+        }
+    }
+
+    commands.run_on(world.get_mut());
+}
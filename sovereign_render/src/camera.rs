@@ -1,12 +1,157 @@
-use glam::{Mat4, Vec4};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use sovereign_math::{Aabb, Ray};
+use windows::Win32::Graphics::Direct3D12::*;
+
+use crate::ibl::EnvironmentUniform;
+
+// Depth is reversed: the near plane maps to 1 and the far plane to 0, which spreads float
+// precision far more evenly over the view distance. Projections, depth clears and depth tests all
+// take their conventions from here so they cannot disagree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    // Height of the view volume in world units.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub const DEPTH_CLEAR: f32 = 0.0;
+    pub(crate) const DEPTH_COMPARE: D3D12_COMPARISON_FUNC = D3D12_COMPARISON_FUNC_GREATER;
+    // For passes testing against depth that an earlier pass wrote for the same surfaces.
+    pub(crate) const DEPTH_COMPARE_EQUAL: D3D12_COMPARISON_FUNC =
+        D3D12_COMPARISON_FUNC_GREATER_EQUAL;
+
+    pub fn matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } => {
+                Mat4::perspective_lh(fov_y, aspect_ratio, far, near)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+                orthographic(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
+    }
+
+    // Half the width and height of the view volume at a view space depth.
+    pub fn half_extents(&self, aspect_ratio: f32, depth: f32) -> Vec2 {
+        let half_height = match *self {
+            Projection::Perspective { fov_y, .. } => (fov_y * 0.5).tan() * depth,
+            Projection::Orthographic { height, .. } => height * 0.5,
+        };
+        Vec2::new(half_height * aspect_ratio, half_height)
+    }
+}
+
+pub(crate) fn orthographic(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Mat4 {
+    Mat4::orthographic_lh(left, right, bottom, top, far, near)
+}
 
 pub struct Camera {
-    pub projection: Mat4,
+    pub projection: Projection,
+    // Width over height of the viewport the camera renders to.
+    pub aspect_ratio: f32,
+}
+
+impl Camera {
+    pub fn new(projection: Projection, width: u32, height: u32) -> Self {
+        let mut camera = Self {
+            projection,
+            aspect_ratio: 1.0,
+        };
+        camera.set_viewport_size(width, height);
+        camera
+    }
+
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        self.aspect_ratio = width as f32 / height.max(1) as f32;
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.matrix(self.aspect_ratio)
+    }
+
+    pub fn half_extents(&self, depth: f32) -> Vec2 {
+        self.projection.half_extents(self.aspect_ratio, depth)
+    }
+
+    // Where lighting takes the view direction from. Orthographic views all look the same way, so
+    // they get the direction back towards the camera with w = 0 instead of a position.
+    pub fn view_position(&self, transform: &Mat4) -> Vec4 {
+        match self.projection {
+            Projection::Perspective { .. } => transform.w_axis,
+            Projection::Orthographic { .. } => {
+                (-transform.z_axis.truncate().normalize()).extend(0.0)
+            }
+        }
+    }
+
+    pub fn view_projection(&self, transform: &Mat4) -> Mat4 {
+        self.projection_matrix() * transform.inverse()
+    }
+
+    pub fn world_to_viewport(&self, transform: &Mat4, viewport: Vec2, point: Vec3) -> Option<Vec2> {
+        let clip = self.view_projection(transform) * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(Vec2::new(
+            (ndc.x * 0.5 + 0.5) * viewport.x,
+            (0.5 - ndc.y * 0.5) * viewport.y,
+        ))
+    }
+
+    pub fn viewport_to_ray(&self, transform: &Mat4, viewport: Vec2, pixel: Vec2) -> Ray {
+        Ray::from_screen(pixel, viewport, &self.view_projection(transform))
+    }
+
+    pub fn frame_bounds(&self, transform: &Mat4, aabb: &Aabb) -> Mat4 {
+        let radius = aabb.half_extents().length().max(f32::EPSILON);
+        let distance = match self.projection {
+            Projection::Perspective { .. } => {
+                let half_fov = self.half_extents(1.0).min_element().atan();
+                radius / half_fov.sin()
+            }
+            // Size is fixed, so only keep the bounds in front of the near plane.
+            Projection::Orthographic { near, .. } => radius + near,
+        };
+
+        let forward = transform.z_axis.truncate().normalize();
+        let up = if forward.y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let position = aabb.center() - forward * distance;
+        Mat4::look_to_lh(position, forward, up).inverse()
+    }
 }
 
+#[derive(Clone, Copy)]
 #[repr(C)]
 pub struct ViewUniform {
     pub projection: Mat4,
     pub view: Mat4,
     pub position: Vec4,
+    pub sun_direction: Vec4,
+    pub sun_color: Vec4,
+    pub ambient_color: Vec4,
+    pub environment: EnvironmentUniform,
 }
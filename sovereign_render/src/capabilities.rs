@@ -0,0 +1,187 @@
+use std::{ffi::c_void, fmt};
+
+use windows::Win32::Graphics::{
+    Direct3D12::*,
+    Dxgi::{Common::*, *},
+};
+
+use crate::SCENE_COLOR_FORMAT;
+
+const SHADER_MODELS: [D3D_SHADER_MODEL; 9] = [
+    D3D_SHADER_MODEL_6_8,
+    D3D_SHADER_MODEL_6_7,
+    D3D_SHADER_MODEL_6_6,
+    D3D_SHADER_MODEL_6_5,
+    D3D_SHADER_MODEL_6_4,
+    D3D_SHADER_MODEL_6_3,
+    D3D_SHADER_MODEL_6_2,
+    D3D_SHADER_MODEL_6_1,
+    D3D_SHADER_MODEL_6_0,
+];
+const SAMPLE_COUNTS: [u32; 3] = [2, 4, 8];
+
+#[derive(Clone, Debug)]
+pub struct DeviceCapabilities {
+    pub adapter: String,
+    pub shader_model: (u32, u32),
+    pub resource_binding_tier: D3D12_RESOURCE_BINDING_TIER,
+    pub max_resource_descriptors: u32,
+    pub max_sampler_descriptors: u32,
+    pub mesh_shader_tier: D3D12_MESH_SHADER_TIER,
+    pub raytracing_tier: D3D12_RAYTRACING_TIER,
+    pub variable_shading_rate_tier: D3D12_VARIABLE_SHADING_RATE_TIER,
+    pub enhanced_barriers: bool,
+    pub sample_counts: Vec<u32>,
+}
+
+impl DeviceCapabilities {
+    pub(crate) fn query(device: &ID3D12Device, adapter: &IDXGIAdapter1) -> Self {
+        let mut desc = DXGI_ADAPTER_DESC1::default();
+        let adapter = match unsafe { adapter.GetDesc1(&mut desc) } {
+            Ok(()) => {
+                let len = desc.Description.iter().position(|c| *c == 0);
+                String::from_utf16_lossy(&desc.Description[..len.unwrap_or(desc.Description.len())])
+            }
+            Err(_) => String::new(),
+        };
+
+        // The query fails for models newer than the runtime knows, so walk down from the newest.
+        let shader_model = SHADER_MODELS
+            .into_iter()
+            .find_map(|model| {
+                check_feature(
+                    device,
+                    D3D12_FEATURE_SHADER_MODEL,
+                    D3D12_FEATURE_DATA_SHADER_MODEL {
+                        HighestShaderModel: model,
+                    },
+                )
+            })
+            .map_or((5, 1), |data| {
+                let model = data.HighestShaderModel.0 as u32;
+                (model >> 4, model & 0xF)
+            });
+
+        let options = check_feature(
+            device,
+            D3D12_FEATURE_D3D12_OPTIONS,
+            D3D12_FEATURE_DATA_D3D12_OPTIONS::default(),
+        )
+        .unwrap_or_default();
+        let options5 = check_feature(
+            device,
+            D3D12_FEATURE_D3D12_OPTIONS5,
+            D3D12_FEATURE_DATA_D3D12_OPTIONS5::default(),
+        )
+        .unwrap_or_default();
+        let options6 = check_feature(
+            device,
+            D3D12_FEATURE_D3D12_OPTIONS6,
+            D3D12_FEATURE_DATA_D3D12_OPTIONS6::default(),
+        )
+        .unwrap_or_default();
+        let options7 = check_feature(
+            device,
+            D3D12_FEATURE_D3D12_OPTIONS7,
+            D3D12_FEATURE_DATA_D3D12_OPTIONS7::default(),
+        )
+        .unwrap_or_default();
+        let options12 = check_feature(
+            device,
+            D3D12_FEATURE_D3D12_OPTIONS12,
+            D3D12_FEATURE_DATA_D3D12_OPTIONS12::default(),
+        )
+        .unwrap_or_default();
+
+        let sample_counts = SAMPLE_COUNTS
+            .into_iter()
+            .filter(|&count| {
+                [SCENE_COLOR_FORMAT, DXGI_FORMAT_D32_FLOAT]
+                    .into_iter()
+                    .all(|format| {
+                        check_feature(
+                            device,
+                            D3D12_FEATURE_MULTISAMPLE_QUALITY_LEVELS,
+                            D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS {
+                                Format: format,
+                                SampleCount: count,
+                                ..Default::default()
+                            },
+                        )
+                        .is_some_and(|data| data.NumQualityLevels > 0)
+                    })
+            })
+            .collect();
+
+        Self {
+            adapter,
+            shader_model,
+            resource_binding_tier: options.ResourceBindingTier,
+            max_resource_descriptors: D3D12_MAX_SHADER_VISIBLE_DESCRIPTOR_HEAP_SIZE_TIER_2,
+            max_sampler_descriptors: D3D12_MAX_SHADER_VISIBLE_SAMPLER_HEAP_SIZE,
+            mesh_shader_tier: options7.MeshShaderTier,
+            raytracing_tier: options5.RaytracingTier,
+            variable_shading_rate_tier: options6.VariableShadingRateTier,
+            enhanced_barriers: options12.EnhancedBarriersSupported.as_bool(),
+            sample_counts,
+        }
+    }
+
+    // Every pass indexes ResourceDescriptorHeap directly, which needs SM 6.6 on a tier 3 binding model.
+    pub fn supports_bindless(&self) -> bool {
+        self.shader_model >= (6, 6)
+            && self.resource_binding_tier.0 >= D3D12_RESOURCE_BINDING_TIER_3.0
+    }
+
+    pub fn supports_sample_count(&self, sample_count: u32) -> bool {
+        sample_count == 1 || self.sample_counts.contains(&sample_count)
+    }
+
+    pub fn supports_mesh_shaders(&self) -> bool {
+        self.mesh_shader_tier.0 >= D3D12_MESH_SHADER_TIER_1.0
+    }
+
+    pub fn supports_raytracing(&self) -> bool {
+        self.raytracing_tier.0 >= D3D12_RAYTRACING_TIER_1_0.0
+    }
+
+    pub fn supports_variable_rate_shading(&self) -> bool {
+        self.variable_shading_rate_tier.0 >= D3D12_VARIABLE_SHADING_RATE_TIER_1.0
+    }
+}
+
+impl fmt::Display for DeviceCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Device capabilities for {}", self.adapter)?;
+        writeln!(
+            f,
+            "  shader model {}.{}, resource binding tier {}, {} resource / {} sampler descriptors",
+            self.shader_model.0,
+            self.shader_model.1,
+            self.resource_binding_tier.0,
+            self.max_resource_descriptors,
+            self.max_sampler_descriptors,
+        )?;
+        writeln!(
+            f,
+            "  mesh shaders {}, raytracing {}, variable rate shading {}, enhanced barriers {}",
+            self.supports_mesh_shaders(),
+            self.supports_raytracing(),
+            self.supports_variable_rate_shading(),
+            self.enhanced_barriers,
+        )?;
+        write!(f, "  MSAA sample counts {:?}", self.sample_counts)
+    }
+}
+
+fn check_feature<T>(device: &ID3D12Device, feature: D3D12_FEATURE, mut data: T) -> Option<T> {
+    unsafe {
+        device.CheckFeatureSupport(
+            feature,
+            &mut data as *mut T as *mut c_void,
+            std::mem::size_of::<T>() as u32,
+        )
+    }
+    .ok()
+    .map(|()| data)
+}
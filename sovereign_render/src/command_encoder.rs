@@ -1,28 +1,139 @@
-use std::{error::Error, mem::ManuallyDrop};
+use std::{cell::RefCell, collections::HashMap, mem::ManuallyDrop};
 use windows::{
-    core::ComInterface,
+    core::{ComInterface, Interface},
     Win32::{
         Foundation::RECT,
-        Graphics::{
-            Direct3D::D3D_PRIMITIVE_TOPOLOGY, Direct3D12::*,
-            Dxgi::Common::DXGI_FORMAT_R8G8B8A8_UNORM,
-        },
+        Graphics::{Direct3D::D3D_PRIMITIVE_TOPOLOGY, Direct3D12::*, Dxgi::Common::DXGI_FORMAT},
     },
 };
 
-use crate::device::{AllocatedBuffer, AllocatedImage};
+use crate::{
+    barrier_trace::RecordedBarrier,
+    device::{AllocatedBuffer, AllocatedImage, SubresourceFootprint},
+    error::RenderError,
+    gpu_timer::GpuTimer,
+};
+
+// Written to a breadcrumb once the GPU gets to it.
+const BREADCRUMB_REACHED: u32 = 1;
+
+// PIX reads event payloads with this metadata value as null-terminated ANSI strings.
+const PIX_EVENT_ANSI_VERSION: u32 = 1;
+
+pub(crate) struct TracedPass {
+    pub name: String,
+    pub barriers: Vec<RecordedBarrier>,
+    // Each resource the pass required, with the state it was used in.
+    pub uses: Vec<(usize, D3D12_RESOURCE_STATES)>,
+}
+
+pub(crate) type TracedPasses = Vec<TracedPass>;
+
+// Barriers are grouped by the innermost open scope. Barriers issued after a scope closes go to a
+// segment named after the enclosing scopes, which is left out when it stays empty.
+#[derive(Default)]
+struct Trace {
+    scopes: Vec<&'static str>,
+    passes: TracedPasses,
+    continuation: bool,
+    // Resources with a transition batched but not yet flushed.
+    pending: Vec<usize>,
+}
+
+impl Trace {
+    fn begin_segment(&mut self, continuation: bool) {
+        self.drop_empty_continuation();
+        let name = if self.scopes.is_empty() {
+            "(outside passes)".to_string()
+        } else {
+            self.scopes.join("/")
+        };
+        self.passes.push(TracedPass {
+            name,
+            barriers: Vec::new(),
+            uses: Vec::new(),
+        });
+        self.continuation = continuation;
+    }
+
+    fn drop_empty_continuation(&mut self) {
+        if self.continuation
+            && self
+                .passes
+                .last()
+                .is_some_and(|pass| pass.barriers.is_empty() && pass.uses.is_empty())
+        {
+            self.passes.pop();
+        }
+    }
+}
 
 pub struct CommandEncoder {
     allocator: ID3D12CommandAllocator,
     list: ID3D12GraphicsCommandList,
+    // Needed for breadcrumbs, which are skipped on runtimes without it.
+    list2: Option<ID3D12GraphicsCommandList2>,
+    states: RefCell<HashMap<usize, D3D12_RESOURCE_STATES>>,
+    barriers: RefCell<Vec<D3D12_RESOURCE_BARRIER>>,
+    trace: RefCell<Option<Trace>>,
 }
 
 impl CommandEncoder {
     pub fn new(allocator: ID3D12CommandAllocator, list: ID3D12GraphicsCommandList) -> Self {
-        Self { allocator, list }
+        Self {
+            allocator,
+            list2: list.cast().ok(),
+            list,
+            states: RefCell::new(HashMap::new()),
+            barriers: RefCell::new(Vec::new()),
+            trace: RefCell::new(None),
+        }
     }
 
-    pub fn reset(&self) -> Result<(), Box<dyn Error>> {
+    // Records every transition issued until `take_trace`, grouped by timer scope.
+    pub fn begin_trace(&self) {
+        let mut trace = Trace::default();
+        trace.begin_segment(true);
+        *self.trace.borrow_mut() = Some(trace);
+    }
+
+    pub(crate) fn take_trace(&self) -> Option<TracedPasses> {
+        self.trace.borrow_mut().take().map(|mut trace| {
+            trace.drop_empty_continuation();
+            trace.passes
+        })
+    }
+
+    // `batched` transitions wait in `barriers` for the next flush; the others are issued directly.
+    fn record_barrier(
+        &self,
+        resource: &ID3D12Resource,
+        subresource: Option<u32>,
+        before: D3D12_RESOURCE_STATES,
+        after: D3D12_RESOURCE_STATES,
+        batched: bool,
+    ) {
+        let mut trace = self.trace.borrow_mut();
+        let Some(trace) = trace.as_mut() else {
+            return;
+        };
+        let resource = resource.as_raw() as usize;
+        let redundant = before == after || trace.pending.contains(&resource);
+        if batched {
+            trace.pending.push(resource);
+        }
+        if let Some(pass) = trace.passes.last_mut() {
+            pass.barriers.push(RecordedBarrier {
+                resource,
+                subresource,
+                before,
+                after,
+                redundant,
+            });
+        }
+    }
+
+    pub fn reset(&self) -> Result<(), RenderError> {
         unsafe {
             self.allocator.Reset()?;
             self.list.Reset(&self.allocator, None)?;
@@ -43,6 +154,12 @@ impl CommandEncoder {
         }
     }
 
+    pub fn set_compute_root_signature(&self, root_signature: &ID3D12RootSignature) {
+        unsafe {
+            self.list.SetComputeRootSignature(root_signature);
+        }
+    }
+
     pub fn set_pipeline(&self, pipeline: &ID3D12PipelineState) {
         unsafe {
             self.list.SetPipelineState(pipeline);
@@ -50,9 +167,28 @@ impl CommandEncoder {
     }
 
     pub fn set_viewport(&self, width: u32, height: u32) {
+        self.set_viewport_rect(0, 0, width, height);
+    }
+
+    pub fn set_viewport_rect(&self, x: u32, y: u32, width: u32, height: u32) {
         let view = D3D12_VIEWPORT {
-            TopLeftX: 0.0,
-            TopLeftY: 0.0,
+            TopLeftX: x as f32,
+            TopLeftY: y as f32,
+            Width: width as f32,
+            Height: height as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        };
+        unsafe {
+            self.list.RSSetViewports(&[view]);
+        }
+    }
+
+    // The origin may be negative, to move part of a larger view into a smaller target.
+    pub fn set_viewport_at(&self, x: f32, y: f32, width: u32, height: u32) {
+        let view = D3D12_VIEWPORT {
+            TopLeftX: x,
+            TopLeftY: y,
             Width: width as f32,
             Height: height as f32,
             MinDepth: 0.0,
@@ -64,63 +200,138 @@ impl CommandEncoder {
     }
 
     pub fn set_scissor(&self, width: u32, height: u32) {
+        self.set_scissor_rect(0, 0, width, height);
+    }
+
+    pub fn set_scissor_rect(&self, x: u32, y: u32, width: u32, height: u32) {
         let scissor = RECT {
-            left: 0,
-            top: 0,
-            right: width as i32,
-            bottom: height as i32,
+            left: x as i32,
+            top: y as i32,
+            right: (x + width) as i32,
+            bottom: (y + height) as i32,
         };
         unsafe {
             self.list.RSSetScissorRects(&[scissor]);
         }
     }
 
+    pub fn track_resource(&self, resource: &ID3D12Resource, state: D3D12_RESOURCE_STATES) {
+        self.states
+            .borrow_mut()
+            .insert(resource.as_raw() as usize, state);
+    }
+
+    pub fn untrack_resource(&self, resource: &ID3D12Resource) {
+        self.states
+            .borrow_mut()
+            .remove(&(resource.as_raw() as usize));
+    }
+
+    // Records that the current pass uses the resource for the frame graph, without a barrier. Only
+    // needed for resources transitioned by hand; `require_state` records its own.
+    pub fn mark_use(&self, resource: &ID3D12Resource, state: D3D12_RESOURCE_STATES) {
+        let mut trace = self.trace.borrow_mut();
+        // Transitions between passes restore resting states rather than use the resource.
+        let Some(trace) = trace.as_mut().filter(|trace| !trace.continuation) else {
+            return;
+        };
+        let resource = resource.as_raw() as usize;
+        if let Some(pass) = trace.passes.last_mut() {
+            if !pass.uses.contains(&(resource, state)) {
+                pass.uses.push((resource, state));
+            }
+        }
+    }
+
+    pub fn require_state(&self, resource: &ID3D12Resource, state: D3D12_RESOURCE_STATES) {
+        self.mark_use(resource, state);
+        let mut states = self.states.borrow_mut();
+        let current = states
+            .get_mut(&(resource.as_raw() as usize))
+            .expect("require_state called on an untracked resource");
+        if *current != state {
+            self.record_barrier(resource, None, *current, state, true);
+            self.barriers.borrow_mut().push(transition_barrier(
+                resource,
+                D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                *current,
+                state,
+            ));
+            *current = state;
+        }
+    }
+
+    pub fn flush_barriers(&self) {
+        if let Some(trace) = self.trace.borrow_mut().as_mut() {
+            trace.pending.clear();
+        }
+        let barriers = std::mem::take(&mut *self.barriers.borrow_mut());
+        if !barriers.is_empty() {
+            unsafe {
+                self.list.ResourceBarrier(&barriers);
+            }
+        }
+    }
+
     pub fn transition_image(
         &self,
         resource: &ID3D12Resource,
         state_before: D3D12_RESOURCE_STATES,
         state_after: D3D12_RESOURCE_STATES,
     ) {
-        let barrier = D3D12_RESOURCE_BARRIER {
-            Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-            Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
-            Anonymous: D3D12_RESOURCE_BARRIER_0 {
-                Transition: ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: unsafe { std::mem::transmute_copy(resource) },
-                    StateBefore: state_before,
-                    StateAfter: state_after,
-                    Subresource: D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                }),
-            },
-        };
+        if let Some(state) = self
+            .states
+            .borrow_mut()
+            .get_mut(&(resource.as_raw() as usize))
+        {
+            *state = state_after;
+        }
+        self.flush_barriers();
+        self.record_barrier(resource, None, state_before, state_after, false);
         unsafe {
-            self.list.ResourceBarrier(&[barrier]);
+            self.list.ResourceBarrier(&[transition_barrier(
+                resource,
+                D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+                state_before,
+                state_after,
+            )]);
         }
     }
 
-    pub fn transition_buffer(
+    pub fn transition_subresource(
         &self,
-        resource: &AllocatedBuffer,
+        resource: &ID3D12Resource,
+        subresource: u32,
         state_before: D3D12_RESOURCE_STATES,
         state_after: D3D12_RESOURCE_STATES,
     ) {
-        let barrier = D3D12_RESOURCE_BARRIER {
-            Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-            Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
-            Anonymous: D3D12_RESOURCE_BARRIER_0 {
-                Transition: ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
-                    pResource: unsafe { std::mem::transmute_copy(resource.allocation.resource()) },
-                    StateBefore: state_before,
-                    StateAfter: state_after,
-                    Subresource: D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-                }),
-            },
-        };
+        self.flush_barriers();
+        self.record_barrier(
+            resource,
+            Some(subresource),
+            state_before,
+            state_after,
+            false,
+        );
         unsafe {
-            self.list.ResourceBarrier(&[barrier]);
+            self.list.ResourceBarrier(&[transition_barrier(
+                resource,
+                subresource,
+                state_before,
+                state_after,
+            )]);
         }
     }
 
+    pub fn transition_buffer(
+        &self,
+        resource: &AllocatedBuffer,
+        state_before: D3D12_RESOURCE_STATES,
+        state_after: D3D12_RESOURCE_STATES,
+    ) {
+        self.transition_image(resource.allocation.resource(), state_before, state_after);
+    }
+
     pub fn set_render_target(
         &self,
         render_target: D3D12_CPU_DESCRIPTOR_HANDLE,
@@ -132,11 +343,19 @@ impl CommandEncoder {
         }
     }
 
+    pub fn set_depth_target(&self, depth_target: D3D12_CPU_DESCRIPTOR_HANDLE) {
+        unsafe {
+            self.list
+                .OMSetRenderTargets(0, None, false, Some(&depth_target));
+        }
+    }
+
     pub fn clear_render_target(
         &self,
         render_target: D3D12_CPU_DESCRIPTOR_HANDLE,
         clear_color: &[f32; 4],
     ) {
+        self.flush_barriers();
         unsafe {
             self.list
                 .ClearRenderTargetView(render_target, clear_color, None);
@@ -144,6 +363,7 @@ impl CommandEncoder {
     }
 
     pub fn clear_depth_target(&self, depth_target: D3D12_CPU_DESCRIPTOR_HANDLE, depth: f32) {
+        self.flush_barriers();
         unsafe {
             self.list
                 .ClearDepthStencilView(depth_target, D3D12_CLEAR_FLAG_DEPTH, depth, 0, &[]);
@@ -167,9 +387,20 @@ impl CommandEncoder {
         }
     }
 
-    pub fn bind_index_buffer(&self, index_buffer: *const D3D12_INDEX_BUFFER_VIEW) {
+    pub fn set_compute_root_constants<T>(&self, constants: &T) {
+        unsafe {
+            self.list.SetComputeRoot32BitConstants(
+                0,
+                std::mem::size_of::<T>() as u32 / std::mem::size_of::<u32>() as u32,
+                constants as *const _ as *const _,
+                0,
+            );
+        }
+    }
+
+    pub fn bind_index_buffer(&self, view: &D3D12_INDEX_BUFFER_VIEW) {
         unsafe {
-            self.list.IASetIndexBuffer(Some(index_buffer));
+            self.list.IASetIndexBuffer(Some(view));
         }
     }
 
@@ -180,6 +411,7 @@ impl CommandEncoder {
         start_index: u32,
         start_instance: u32,
     ) {
+        self.flush_barriers();
         unsafe {
             self.list.DrawIndexedInstanced(
                 index_count,
@@ -191,7 +423,29 @@ impl CommandEncoder {
         }
     }
 
-    pub fn finish(&self) -> Result<ID3D12CommandList, Box<dyn Error>> {
+    pub fn draw_instanced(
+        &self,
+        vertex_count: u32,
+        instance_count: u32,
+        start_vertex: u32,
+        start_instance: u32,
+    ) {
+        self.flush_barriers();
+        unsafe {
+            self.list
+                .DrawInstanced(vertex_count, instance_count, start_vertex, start_instance);
+        }
+    }
+
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.flush_barriers();
+        unsafe {
+            self.list.Dispatch(x, y, z);
+        }
+    }
+
+    pub fn finish(&self) -> Result<ID3D12CommandList, RenderError> {
+        self.flush_barriers();
         unsafe {
             self.list.Close()?;
         }
@@ -199,28 +453,53 @@ impl CommandEncoder {
         Ok(self.list.cast()?)
     }
 
-    pub fn copy_buffer_to_image(&self, buffer: &AllocatedBuffer, image: &AllocatedImage) {
+    pub fn copy_buffer_to_image(
+        &self,
+        buffer: &ID3D12Resource,
+        image: &AllocatedImage,
+        footprints: &[SubresourceFootprint],
+    ) {
+        self.flush_barriers();
+        for (subresource, footprint) in footprints.iter().enumerate() {
+            let src = D3D12_TEXTURE_COPY_LOCATION {
+                pResource: unsafe { std::mem::transmute_copy(buffer) },
+                Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    PlacedFootprint: footprint.layout,
+                },
+            };
+            let dst = D3D12_TEXTURE_COPY_LOCATION {
+                pResource: unsafe { std::mem::transmute_copy(image.allocation.resource()) },
+                Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    SubresourceIndex: subresource as u32,
+                },
+            };
+            unsafe {
+                self.list.CopyTextureRegion(&dst, 0, 0, 0, &src, None);
+            }
+        }
+    }
+
+    pub fn copy_image_to_buffer(
+        &self,
+        image: &ID3D12Resource,
+        buffer: &ID3D12Resource,
+        footprint: &SubresourceFootprint,
+    ) {
+        self.flush_barriers();
         let src = D3D12_TEXTURE_COPY_LOCATION {
-            pResource: unsafe { std::mem::transmute_copy(buffer.allocation.resource()) },
-            Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+            pResource: unsafe { std::mem::transmute_copy(image) },
+            Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
             Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
-                PlacedFootprint: D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
-                    Offset: 0,
-                    Footprint: D3D12_SUBRESOURCE_FOOTPRINT {
-                        Format: DXGI_FORMAT_R8G8B8A8_UNORM,
-                        Width: image.width,
-                        Height: image.height,
-                        Depth: 1,
-                        RowPitch: image.width * 4,
-                    },
-                },
+                SubresourceIndex: 0,
             },
         };
         let dst = D3D12_TEXTURE_COPY_LOCATION {
-            pResource: unsafe { std::mem::transmute_copy(image.allocation.resource()) },
-            Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+            pResource: unsafe { std::mem::transmute_copy(buffer) },
+            Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
             Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
-                SubresourceIndex: 0,
+                PlacedFootprint: footprint.layout,
             },
         };
         unsafe {
@@ -228,10 +507,118 @@ impl CommandEncoder {
         }
     }
 
+    pub fn resolve_image(&self, src: &ID3D12Resource, dst: &ID3D12Resource, format: DXGI_FORMAT) {
+        self.flush_barriers();
+        unsafe {
+            self.list.ResolveSubresource(dst, 0, src, 0, format);
+        }
+    }
+
+    pub fn begin_scope(&self, timer: &GpuTimer, name: &'static str) {
+        if let Some(trace) = self.trace.borrow_mut().as_mut() {
+            trace.scopes.push(name);
+            trace.begin_segment(false);
+        }
+        if timer.markers {
+            let mut label = name.as_bytes().to_vec();
+            label.push(0);
+            unsafe {
+                self.list.BeginEvent(
+                    PIX_EVENT_ANSI_VERSION,
+                    Some(label.as_ptr().cast()),
+                    label.len() as u32,
+                );
+            }
+        }
+        if let Some(query) = timer.begin_scope(name) {
+            unsafe {
+                self.list
+                    .EndQuery(timer.query_heap(), D3D12_QUERY_TYPE_TIMESTAMP, query);
+            }
+            self.write_breadcrumb(
+                timer.breadcrumb_address(query),
+                D3D12_WRITEBUFFERIMMEDIATE_MODE_MARKER_IN,
+            );
+        }
+    }
+
+    pub fn end_scope(&self, timer: &GpuTimer) {
+        if let Some(trace) = self.trace.borrow_mut().as_mut() {
+            trace.scopes.pop();
+            trace.begin_segment(true);
+        }
+        if let Some(query) = timer.end_scope() {
+            unsafe {
+                self.list
+                    .EndQuery(timer.query_heap(), D3D12_QUERY_TYPE_TIMESTAMP, query);
+            }
+            self.write_breadcrumb(
+                timer.breadcrumb_address(query),
+                D3D12_WRITEBUFFERIMMEDIATE_MODE_MARKER_OUT,
+            );
+        }
+        if timer.markers {
+            unsafe {
+                self.list.EndEvent();
+            }
+        }
+    }
+
+    // MARKER_IN lands once earlier work has started and MARKER_OUT once it has finished.
+    fn write_breadcrumb(&self, address: u64, mode: D3D12_WRITEBUFFERIMMEDIATE_MODE) {
+        let Some(list) = &self.list2 else {
+            return;
+        };
+        let parameter = D3D12_WRITEBUFFERIMMEDIATE_PARAMETER {
+            Dest: address,
+            Value: BREADCRUMB_REACHED,
+        };
+        unsafe { list.WriteBufferImmediate(1, &parameter, Some(&mode)) };
+    }
+
+    pub fn resolve_timer(&self, timer: &GpuTimer, readback: &AllocatedBuffer) {
+        let count = timer.query_count();
+        if count == 0 {
+            return;
+        }
+        self.flush_barriers();
+        unsafe {
+            self.list.ResolveQueryData(
+                timer.query_heap(),
+                D3D12_QUERY_TYPE_TIMESTAMP,
+                0,
+                count,
+                readback.allocation.resource(),
+                0,
+            );
+        }
+    }
+
     pub fn copy_buffer_to_buffer(&self, src: &AllocatedBuffer, dst: &AllocatedBuffer) {
+        self.flush_barriers();
         unsafe {
             self.list
                 .CopyResource(dst.allocation.resource(), src.allocation.resource());
         }
     }
 }
+
+fn transition_barrier(
+    resource: &ID3D12Resource,
+    subresource: u32,
+    state_before: D3D12_RESOURCE_STATES,
+    state_after: D3D12_RESOURCE_STATES,
+) -> D3D12_RESOURCE_BARRIER {
+    D3D12_RESOURCE_BARRIER {
+        Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
+        Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
+        Anonymous: D3D12_RESOURCE_BARRIER_0 {
+            Transition: ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
+                pResource: unsafe { std::mem::transmute_copy(resource) },
+                StateBefore: state_before,
+                StateAfter: state_after,
+                Subresource: subresource,
+            }),
+        },
+    }
+}
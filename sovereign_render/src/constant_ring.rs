@@ -0,0 +1,95 @@
+use gpu_allocator::MemoryLocation;
+use std::ptr::NonNull;
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::DXGI_FORMAT_UNKNOWN};
+
+use crate::{device::Device, error::RenderError};
+
+// Constant buffer views have to start on a 256 byte boundary and cover a multiple of it.
+pub const CONSTANT_ALIGNMENT: usize = D3D12_CONSTANT_BUFFER_DATA_PLACEMENT_ALIGNMENT as usize;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ConstantAllocation {
+    pub gpu_address: u64,
+    pub data: NonNull<u8>,
+    pub size: usize,
+}
+
+// Small per-frame uploads bumped linearly through one CPU-visible buffer that stays mapped. The
+// buffer holds a region per frame in flight, and starting a frame rewinds to that frame's region,
+// so nothing written for a frame the GPU may still be reading gets overwritten.
+pub struct ConstantRing {
+    gpu_address: u64,
+    data: NonNull<u8>,
+    frame_size: usize,
+    frame_start: usize,
+    head: usize,
+}
+
+impl ConstantRing {
+    pub fn new(
+        device: &mut Device,
+        frame_size: usize,
+        frames_in_flight: usize,
+    ) -> Result<Self, RenderError> {
+        let frame_size = frame_size.next_multiple_of(CONSTANT_ALIGNMENT);
+        let buffer = device.create_buffer(
+            "Constant Ring",
+            (frame_size * frames_in_flight) as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            MemoryLocation::CpuToGpu,
+        )?;
+        let gpu_address = unsafe {
+            device
+                .get_buffer(buffer)
+                .allocation
+                .resource()
+                .GetGPUVirtualAddress()
+        };
+        // Upload heaps may stay mapped while the GPU reads them.
+        let data = device.map_buffer::<u8>(buffer)?;
+        Ok(Self {
+            gpu_address,
+            data,
+            frame_size,
+            frame_start: 0,
+            head: 0,
+        })
+    }
+
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_start = frame_index * self.frame_size;
+        self.head = self.frame_start;
+    }
+
+    pub fn allocate(
+        &mut self,
+        size: usize,
+        align: usize,
+    ) -> Result<ConstantAllocation, RenderError> {
+        let offset = self.head.next_multiple_of(align);
+        let size = size.next_multiple_of(CONSTANT_ALIGNMENT);
+        if offset + size > self.frame_start + self.frame_size {
+            return Err(RenderError::ConstantsExhausted(size));
+        }
+        self.head = offset + size;
+        Ok(ConstantAllocation {
+            gpu_address: self.gpu_address + offset as u64,
+            data: unsafe { NonNull::new_unchecked(self.data.as_ptr().add(offset)) },
+            size,
+        })
+    }
+
+    pub fn write<T: Copy>(&mut self, value: &T) -> Result<ConstantAllocation, RenderError> {
+        let allocation = self.allocate(std::mem::size_of::<T>(), CONSTANT_ALIGNMENT)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                value as *const T as *const u8,
+                allocation.data.as_ptr(),
+                std::mem::size_of::<T>(),
+            )
+        };
+        Ok(allocation)
+    }
+}
@@ -0,0 +1,487 @@
+use std::path::Path;
+
+use gpu_allocator::MemoryLocation;
+use sovereign_ecs::World;
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    asset::{texture::TextureData, Assets},
+    compile_compute_shader,
+    device::Device,
+    error::RenderError,
+    id::{ImageId, SamplerId, ViewId},
+    texture::Texture,
+    upload_queue::UploadSource,
+    Renderer,
+};
+
+const SHADER_PATH: &str = "assets/shaders/cubemap.hlsl";
+const CUBEMAP_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16B16A16_FLOAT;
+// Faces loaded from 8-bit files are sRGB encoded.
+const FACE_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R8G8B8A8_UNORM_SRGB;
+const THREAD_GROUP_SIZE: u32 = 8;
+// Steps per quarter turn of the irradiance grid, and GGX samples per prefiltered texel.
+const IRRADIANCE_STEPS: u32 = 64;
+const PREFILTER_SAMPLES: u32 = 1024;
+// Finished cubemaps are read by pixel and compute shaders alike.
+const SHADER_RESOURCE_STATE: D3D12_RESOURCE_STATES = D3D12_RESOURCE_STATES(
+    D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE.0 | D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE.0,
+);
+
+// An HDR cube texture with a TextureCube view over all of its mips.
+#[derive(Debug)]
+pub struct Cubemap {
+    pub image: ImageId,
+    pub view: ViewId,
+    pub size: u32,
+    pub mip_levels: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CubemapFilter {
+    EquirectToCube,
+    Irradiance,
+    Prefilter,
+}
+
+impl CubemapFilter {
+    // Rougher mips of a prefiltered map, from mirror-like at the top to fully rough at the last.
+    fn roughness(self, mip: u32, mip_levels: u32) -> f32 {
+        match self {
+            CubemapFilter::Prefilter if mip_levels > 1 => mip as f32 / (mip_levels - 1) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn sample_count(self) -> u32 {
+        match self {
+            CubemapFilter::EquirectToCube => 1,
+            CubemapFilter::Irradiance => IRRADIANCE_STEPS,
+            CubemapFilter::Prefilter => PREFILTER_SAMPLES,
+        }
+    }
+}
+
+#[repr(C)]
+pub(crate) struct CubemapResources {
+    pub source_index: u32,
+    pub target_index: u32,
+    pub sampler_index: u32,
+    pub face_size: u32,
+    pub roughness: f32,
+    pub sample_count: u32,
+}
+
+// The compute steps behind skyboxes, probes and image based lighting, shared by all of them.
+pub(crate) struct CubemapPipelines {
+    pub equirect_to_cube: ID3D12PipelineState,
+    pub irradiance: ID3D12PipelineState,
+    pub prefilter: ID3D12PipelineState,
+    pub brdf_lut: ID3D12PipelineState,
+    pub sampler: SamplerId,
+}
+
+impl CubemapPipelines {
+    pub fn new(
+        device: &Device,
+        root_signature: &ID3D12RootSignature,
+        sampler: SamplerId,
+    ) -> Result<Self, RenderError> {
+        let create_pipeline = |entry_point: &str| {
+            let shader = compile_compute_shader(SHADER_PATH, entry_point)?;
+            device.create_compute_pipeline(&D3D12_COMPUTE_PIPELINE_STATE_DESC {
+                pRootSignature: unsafe { std::mem::transmute_copy(root_signature) },
+                CS: D3D12_SHADER_BYTECODE {
+                    pShaderBytecode: shader.as_ptr() as *const _,
+                    BytecodeLength: shader.len(),
+                },
+                ..Default::default()
+            })
+        };
+        Ok(Self {
+            equirect_to_cube: create_pipeline("EquirectToCubeMain")?,
+            irradiance: create_pipeline("IrradianceMain")?,
+            prefilter: create_pipeline("PrefilterMain")?,
+            brdf_lut: create_pipeline("BrdfLutMain")?,
+            sampler,
+        })
+    }
+
+    fn pipeline(&self, filter: CubemapFilter) -> &ID3D12PipelineState {
+        match filter {
+            CubemapFilter::EquirectToCube => &self.equirect_to_cube,
+            CubemapFilter::Irradiance => &self.irradiance,
+            CubemapFilter::Prefilter => &self.prefilter,
+        }
+    }
+}
+
+impl Renderer {
+    // Loads one image file per face, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn load_cubemap(&mut self, name: &str, faces: [&Path; 6]) -> Result<Cubemap, RenderError> {
+        let faces = faces
+            .iter()
+            .map(|path| Ok(image::open(path)?.into_rgba8()))
+            .collect::<Result<Vec<_>, RenderError>>()?;
+        let size = face_size(
+            &faces
+                .iter()
+                .map(|face| face.dimensions())
+                .collect::<Vec<_>>(),
+        )?;
+
+        let image = self.device.create_cubemap_image(
+            name,
+            size,
+            1,
+            FACE_FORMAT,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+        )?;
+        let (footprints, total_size) = self.device.copyable_footprints(image);
+        let staging_buffer = self.device.create_buffer(
+            &format!("{} (staging)", name),
+            total_size,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            MemoryLocation::CpuToGpu,
+        )?;
+        let staging_data = self.device.map_buffer::<u8>(staging_buffer)?.as_ptr();
+        for (face, footprint) in faces.iter().zip(&footprints) {
+            let row_size = footprint.row_size as usize;
+            let row_pitch = footprint.layout.Footprint.RowPitch as usize;
+            let offset = footprint.layout.Offset as usize;
+            for (row, texels) in face.chunks_exact(row_size).enumerate() {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        texels.as_ptr(),
+                        staging_data.add(offset + row * row_pitch),
+                        row_size,
+                    )
+                };
+            }
+        }
+        self.device.unmap_buffer(staging_buffer);
+        self.uploads.push_image(
+            UploadSource::Staging(staging_buffer),
+            image,
+            footprints,
+            SHADER_RESOURCE_STATE,
+        );
+
+        Ok(Cubemap {
+            image,
+            view: self.create_cubemap_view(name, image, FACE_FORMAT, 1)?,
+            size,
+            mip_levels: 1,
+        })
+    }
+
+    // Loads a latitude-longitude panorama such as an .hdr file and projects it onto a cube with
+    // `size` texels per edge.
+    pub fn load_cubemap_from_equirect(
+        &mut self,
+        world: &World,
+        path: &Path,
+        size: u32,
+    ) -> Result<Cubemap, RenderError> {
+        let panorama = image::open(path)?.into_rgba32f();
+        let (width, height) = panorama.dimensions();
+        let data = TextureData {
+            width,
+            height,
+            format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+            mips: vec![bytemuck::cast_slice(&panorama.into_raw()).to_vec()],
+        };
+        let name = path.display().to_string();
+        // The panorama is released with its handle by the next garbage collection.
+        let handle = self.create_texture(world, &name, &data)?;
+        let source = {
+            let mut textures_query = world.get_singleton::<Assets<Texture>>();
+            let (textures,) = textures_query.get().unwrap();
+            let texture = textures.get(&handle).unwrap();
+            (texture.image, texture.view)
+        };
+        self.filter_cubemap(&name, CubemapFilter::EquirectToCube, source, size, 1)
+    }
+
+    // Projects a latitude-longitude panorama onto the six faces of a cube.
+    pub fn create_cubemap_from_equirect(
+        &mut self,
+        name: &str,
+        equirect: &Texture,
+        size: u32,
+    ) -> Result<Cubemap, RenderError> {
+        self.filter_cubemap(
+            name,
+            CubemapFilter::EquirectToCube,
+            (equirect.image, equirect.view),
+            size,
+            1,
+        )
+    }
+
+    // Diffuse lighting for every normal direction. Irradiance varies slowly, so a small size
+    // such as 32 is plenty.
+    pub fn create_irradiance_map(
+        &mut self,
+        name: &str,
+        source: &Cubemap,
+        size: u32,
+    ) -> Result<Cubemap, RenderError> {
+        self.filter_cubemap(
+            name,
+            CubemapFilter::Irradiance,
+            (source.image, source.view),
+            size,
+            1,
+        )
+    }
+
+    // Specular lighting convolved with the GGX lobe, one roughness step per mip.
+    pub fn create_prefiltered_map(
+        &mut self,
+        name: &str,
+        source: &Cubemap,
+        size: u32,
+        mip_levels: u32,
+    ) -> Result<Cubemap, RenderError> {
+        let mip_levels = prefilter_mip_levels(size, mip_levels)?;
+        self.filter_cubemap(
+            name,
+            CubemapFilter::Prefilter,
+            (source.image, source.view),
+            size,
+            mip_levels,
+        )
+    }
+
+    pub fn destroy_cubemap(&mut self, cubemap: Cubemap) {
+        self.device.destroy_image(cubemap.image);
+        self.cbv_heap.free(cubemap.view);
+    }
+
+    fn filter_cubemap(
+        &mut self,
+        name: &str,
+        filter: CubemapFilter,
+        (source_image, source_view): (ImageId, ViewId),
+        size: u32,
+        mip_levels: u32,
+    ) -> Result<Cubemap, RenderError> {
+        check_size(size)?;
+        let image = self.device.create_cubemap_image(
+            name,
+            size,
+            mip_levels,
+            CUBEMAP_FORMAT,
+            D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS,
+            D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+        )?;
+        let resource = self.device.get_image(image).allocation.resource().clone();
+        let mip_views = (0..mip_levels)
+            .map(|mip| {
+                self.cbv_heap.create_uav(
+                    &format!("{} Mip {} UAV", name, mip),
+                    &resource,
+                    &uav_desc(mip),
+                )
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?;
+
+        self.immediate_submit(|r, encoder| {
+            // Plain textures rest in the pixel shader state, which compute shaders cannot read.
+            let source = r.device.get_image(source_image).allocation.resource();
+            if filter == CubemapFilter::EquirectToCube {
+                encoder.transition_image(
+                    source,
+                    D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                    SHADER_RESOURCE_STATE,
+                );
+            }
+            encoder.set_descriptor_heaps(&[Some(r.cbv_heap.get()), Some(r.sampler_heap.get())]);
+            encoder.set_compute_root_signature(&r.root_signature);
+            encoder.set_pipeline(r.cubemap_pipelines.pipeline(filter));
+            for (mip, view) in mip_views.iter().enumerate() {
+                let face_size = mip_face_size(size, mip as u32);
+                encoder.set_compute_root_constants(&CubemapResources {
+                    source_index: source_view.0 as u32,
+                    target_index: view.0 as u32,
+                    sampler_index: r.cubemap_pipelines.sampler.0 as u32,
+                    face_size,
+                    roughness: filter.roughness(mip as u32, mip_levels),
+                    sample_count: filter.sample_count(),
+                });
+                let [x, y, z] = dispatch_size(face_size);
+                encoder.dispatch(x, y, z);
+            }
+            encoder.transition_image(
+                &resource,
+                D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+                SHADER_RESOURCE_STATE,
+            );
+            if filter == CubemapFilter::EquirectToCube {
+                encoder.transition_image(
+                    source,
+                    SHADER_RESOURCE_STATE,
+                    D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                );
+            }
+        })?;
+        for view in mip_views {
+            self.cbv_heap.free(view);
+        }
+
+        Ok(Cubemap {
+            image,
+            view: self.create_cubemap_view(name, image, CUBEMAP_FORMAT, mip_levels)?,
+            size,
+            mip_levels,
+        })
+    }
+
+    fn create_cubemap_view(
+        &mut self,
+        name: &str,
+        image: ImageId,
+        format: DXGI_FORMAT,
+        mip_levels: u32,
+    ) -> Result<ViewId, RenderError> {
+        self.cbv_heap.create_srv(
+            &format!("{} SRV", name),
+            self.device.get_image(image).allocation.resource(),
+            &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: format,
+                ViewDimension: D3D12_SRV_DIMENSION_TEXTURECUBE,
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    TextureCube: D3D12_TEXCUBE_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: mip_levels,
+                        ResourceMinLODClamp: 0.0,
+                    },
+                },
+            },
+        )
+    }
+}
+
+fn check_size(size: u32) -> Result<(), RenderError> {
+    if size == 0 {
+        return Err(RenderError::InvalidTexture(
+            "cubemap faces need at least one texel".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// All six faces have to be square and share one size, which is the size of the cube.
+fn face_size(faces: &[(u32, u32)]) -> Result<u32, RenderError> {
+    let (size, _) = faces[0];
+    if let Some(&(width, height)) = faces.iter().find(|&&face| face != (size, size)) {
+        return Err(RenderError::InvalidTexture(format!(
+            "cubemap faces must be square and of one size, found {}x{} with {}x{}",
+            width, height, size, size
+        )));
+    }
+    check_size(size)?;
+    Ok(size)
+}
+
+// Mips stop at 1x1, so asking for more than the full chain gets the full chain.
+fn prefilter_mip_levels(size: u32, mip_levels: u32) -> Result<u32, RenderError> {
+    check_size(size)?;
+    Ok(mip_levels.clamp(1, 32 - size.leading_zeros()))
+}
+
+fn mip_face_size(size: u32, mip: u32) -> u32 {
+    (size >> mip).max(1)
+}
+
+// One thread per texel of a face, with a z slice per face.
+fn dispatch_size(face_size: u32) -> [u32; 3] {
+    let groups = face_size.div_ceil(THREAD_GROUP_SIZE);
+    [groups, groups, 6]
+}
+
+fn uav_desc(mip: u32) -> D3D12_UNORDERED_ACCESS_VIEW_DESC {
+    D3D12_UNORDERED_ACCESS_VIEW_DESC {
+        Format: CUBEMAP_FORMAT,
+        ViewDimension: D3D12_UAV_DIMENSION_TEXTURE2DARRAY,
+        Anonymous: D3D12_UNORDERED_ACCESS_VIEW_DESC_0 {
+            Texture2DArray: D3D12_TEX2D_ARRAY_UAV {
+                MipSlice: mip,
+                FirstArraySlice: 0,
+                ArraySize: 6,
+                PlaneSlice: 0,
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefilter_roughness_spans_the_mip_chain() {
+        let filter = CubemapFilter::Prefilter;
+        assert_eq!(filter.roughness(0, 5), 0.0);
+        assert_eq!(filter.roughness(2, 5), 0.5);
+        assert_eq!(filter.roughness(4, 5), 1.0);
+        // A single mip has nothing to spread over and stays mirror-like.
+        assert_eq!(filter.roughness(0, 1), 0.0);
+    }
+
+    #[test]
+    fn other_filters_ignore_roughness() {
+        assert_eq!(CubemapFilter::Irradiance.roughness(3, 5), 0.0);
+        assert_eq!(CubemapFilter::EquirectToCube.roughness(3, 5), 0.0);
+    }
+
+    #[test]
+    fn prefilter_mips_are_clamped_to_the_chain() {
+        assert_eq!(prefilter_mip_levels(256, 5).unwrap(), 5);
+        assert_eq!(prefilter_mip_levels(256, 20).unwrap(), 9);
+        assert_eq!(prefilter_mip_levels(256, 0).unwrap(), 1);
+        assert_eq!(prefilter_mip_levels(1, 4).unwrap(), 1);
+        assert_eq!(prefilter_mip_levels(100, 32).unwrap(), 7);
+    }
+
+    #[test]
+    fn zero_size_is_rejected() {
+        assert!(prefilter_mip_levels(0, 5).is_err());
+        assert!(check_size(0).is_err());
+        assert!(face_size(&[(0, 0); 6]).is_err());
+    }
+
+    #[test]
+    fn faces_must_be_square_and_matching() {
+        assert_eq!(face_size(&[(64, 64); 6]).unwrap(), 64);
+
+        let mut faces = [(64, 64); 6];
+        faces[3] = (32, 32);
+        assert!(face_size(&faces).is_err());
+        assert!(face_size(&[(64, 32); 6]).is_err());
+    }
+
+    #[test]
+    fn mips_cover_every_face() {
+        assert_eq!(mip_face_size(128, 0), 128);
+        assert_eq!(mip_face_size(128, 3), 16);
+        assert_eq!(mip_face_size(128, 10), 1);
+
+        assert_eq!(dispatch_size(128), [16, 16, 6]);
+        assert_eq!(dispatch_size(20), [3, 3, 6]);
+        assert_eq!(dispatch_size(1), [1, 1, 6]);
+
+        let desc = uav_desc(2);
+        let array = unsafe { desc.Anonymous.Texture2DArray };
+        assert_eq!(desc.ViewDimension, D3D12_UAV_DIMENSION_TEXTURE2DARRAY);
+        assert_eq!(
+            (array.MipSlice, array.FirstArraySlice, array.ArraySize),
+            (2, 0, 6)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use sovereign_ecs::World;
+use sovereign_math::{Aabb, Color};
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*};
+
+use crate::{
+    bounds::WorldBounds, camera::Projection, compile_pixel_shader, compile_shader_pair,
+    desc::DepthFormat, descriptor::DescriptorHeap, device::Device, error::RenderError,
+    gpu_buffer::GrowableGpuBuffer, graphics_pipeline_desc, Renderer, INVALID_INDEX,
+    SCENE_COLOR_FORMAT,
+};
+
+const MIN_VERTEX_CAPACITY: usize = 1024;
+const CIRCLE_SEGMENTS: usize = 32;
+const BOUNDS_COLOR: Color = Color::rgb(0.2, 1.0, 0.4);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct LineVertex {
+    pub position: Vec3,
+    // Linear, since lines are drawn into the HDR scene before tonemapping.
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+pub(crate) struct DebugDrawResources {
+    pub vertex_buffer_index: u32,
+    pub view_buffer_index: u32,
+}
+
+// Immediate-mode world-space lines. Shapes are queued during the frame, drawn after the main pass
+// depth tested against the scene, and cleared once drawn, so they have to be queued every frame.
+#[derive(Default)]
+pub struct DebugDraw {
+    // Outlines the world bounds of every mesh.
+    pub bounds: bool,
+    // Tints every mesh by which side of its triangles faces the camera, so flipped winding and
+    // inverted normals show up in red.
+    pub winding: bool,
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugDraw {
+    pub fn draw_line(&mut self, start: Vec3, end: Vec3, color: Color) {
+        let color = color.to_array();
+        self.vertices.extend_from_slice(&[
+            LineVertex {
+                position: start,
+                color,
+            },
+            LineVertex {
+                position: end,
+                color,
+            },
+        ]);
+    }
+
+    pub fn draw_aabb(&mut self, aabb: &Aabb, color: Color) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        };
+        // Every pair of corners that differs in exactly one axis is an edge.
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    self.draw_line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    // One circle around each axis.
+    pub fn draw_sphere(&mut self, center: Vec3, radius: f32, color: Color) {
+        let point = |segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            (sin * radius, cos * radius)
+        };
+        for segment in 0..CIRCLE_SEGMENTS {
+            let (s0, c0) = point(segment);
+            let (s1, c1) = point(segment + 1);
+            for (start, end) in [
+                (Vec3::new(0.0, s0, c0), Vec3::new(0.0, s1, c1)),
+                (Vec3::new(s0, 0.0, c0), Vec3::new(s1, 0.0, c1)),
+                (Vec3::new(s0, c0, 0.0), Vec3::new(s1, c1, 0.0)),
+            ] {
+                self.draw_line(center + start, center + end, color);
+            }
+        }
+    }
+
+    // The transform's X, Y and Z axes in red, green and blue, each `size` long.
+    pub fn draw_axes(&mut self, transform: &Mat4, size: f32) {
+        let origin = transform.w_axis.truncate();
+        for (axis, color) in [
+            (Vec3::X, Color::rgb(1.0, 0.0, 0.0)),
+            (Vec3::Y, Color::rgb(0.0, 1.0, 0.0)),
+            (Vec3::Z, Color::rgb(0.0, 0.0, 1.0)),
+        ] {
+            let end = origin + transform.transform_vector3(axis).normalize_or_zero() * size;
+            self.draw_line(origin, end, color);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+}
+
+pub(crate) struct DebugDrawPass {
+    pub pipeline: ID3D12PipelineState,
+    pub winding_pipeline: ID3D12PipelineState,
+    pub vertex_buffer: GrowableGpuBuffer<LineVertex>,
+}
+
+impl DebugDrawPass {
+    pub fn new(
+        device: &mut Device,
+        cbv_heap: &mut DescriptorHeap,
+        root_signature: &ID3D12RootSignature,
+        sample_count: u32,
+        depth_format: DepthFormat,
+    ) -> Result<Self, RenderError> {
+        Ok(Self {
+            pipeline: create_line_pipeline(device, root_signature, sample_count, depth_format)?,
+            winding_pipeline: create_winding_pipeline(
+                device,
+                root_signature,
+                sample_count,
+                depth_format,
+            )?,
+            vertex_buffer: GrowableGpuBuffer::new(
+                device,
+                cbv_heap,
+                "Debug Draw Vertex Buffer",
+                MIN_VERTEX_CAPACITY,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+            )?,
+        })
+    }
+}
+
+// Lines are tested against the depth the prepass left behind but never write it.
+pub(crate) fn create_line_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    sample_count: u32,
+    depth_format: DepthFormat,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/debug_draw.hlsl")?;
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        SCENE_COLOR_FORMAT,
+        sample_count,
+    );
+    pipeline_desc.DepthStencilState.DepthWriteMask = D3D12_DEPTH_WRITE_MASK_ZERO;
+    pipeline_desc.DepthStencilState.DepthFunc = Projection::DEPTH_COMPARE_EQUAL;
+    pipeline_desc.DSVFormat = depth_format.dsv_format();
+    pipeline_desc.PrimitiveTopologyType = D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE;
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+// Blended over the shaded meshes, shading only the surface the prepass kept. Meshes are drawn
+// without culling, so both sides of every triangle reach the pixel shader.
+pub(crate) fn create_winding_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    sample_count: u32,
+    depth_format: DepthFormat,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, _fragment_shader) = compile_shader_pair("assets/shaders/mesh.hlsl")?;
+    let fragment_shader = compile_pixel_shader("assets/shaders/winding.hlsl")?;
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        SCENE_COLOR_FORMAT,
+        sample_count,
+    );
+    pipeline_desc.DepthStencilState.DepthWriteMask = D3D12_DEPTH_WRITE_MASK_ZERO;
+    pipeline_desc.DepthStencilState.DepthFunc = Projection::DEPTH_COMPARE_EQUAL;
+    pipeline_desc.DSVFormat = depth_format.dsv_format();
+    let blend = &mut pipeline_desc.BlendState.RenderTarget[0];
+    blend.BlendEnable = true.into();
+    blend.SrcBlend = D3D12_BLEND_SRC_ALPHA;
+    blend.DestBlend = D3D12_BLEND_INV_SRC_ALPHA;
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+impl Renderer {
+    // Expects the scene color and depth targets of the main pass to still be bound.
+    pub(crate) fn draw_winding(&self) -> u32 {
+        self.render_command_encoder
+            .set_pipeline(&self.debug_draw_pass.winding_pipeline);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.draw_batches(&self.render_command_encoder, INVALID_INDEX)
+    }
+
+    // Uploads the lines queued this frame, returning how many vertices draw_debug_lines will draw.
+    pub(crate) fn upload_debug_lines(&mut self, world: &World) -> Result<u32, RenderError> {
+        if self.debug_draw.bounds {
+            for (_entity, bounds) in world.query::<&WorldBounds>().iter() {
+                self.debug_draw.draw_aabb(&bounds.0, BOUNDS_COLOR);
+            }
+        }
+        let count = self.debug_draw.vertices.len();
+        if count == 0 {
+            return Ok(0);
+        }
+        // render waits for the GPU before returning, so the buffer is free to overwrite here.
+        self.debug_draw_pass
+            .vertex_buffer
+            .reserve(&mut self.device, &mut self.cbv_heap, count)?;
+        self.debug_draw_pass
+            .vertex_buffer
+            .map(&self.device)?
+            .write_slice(&self.debug_draw.vertices);
+        self.debug_draw.clear();
+        Ok(count as u32)
+    }
+
+    // Expects the scene color and depth targets of the main pass to still be bound.
+    pub(crate) fn draw_debug_lines(&self, vertex_count: u32) -> u32 {
+        let vertex_buffer = self.debug_draw_pass.vertex_buffer.view();
+        self.render_command_encoder
+            .set_pipeline(&self.debug_draw_pass.pipeline);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_LINELIST);
+        self.render_command_encoder
+            .set_root_constants(&DebugDrawResources {
+                vertex_buffer_index: vertex_buffer.view.0 as u32,
+                view_buffer_index: self.view_buffer.0 as u32,
+            });
+        self.render_command_encoder
+            .draw_instanced(vertex_count, 1, 0, 0);
+        1
+    }
+}
@@ -0,0 +1,220 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec2, Vec3};
+use sovereign_ecs::World;
+use sovereign_math::Color;
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    bounds::WorldBounds,
+    camera::Camera,
+    compile_shader_pair,
+    descriptor::DescriptorHeap,
+    device::Device,
+    error::RenderError,
+    gpu_buffer::GrowableGpuBuffer,
+    graphics_pipeline_desc,
+    light::DirectionalLight,
+    text::{glyph, glyph_uv, GLYPH_SIZE},
+    transform::GlobalTransform,
+    Renderer,
+};
+
+const MIN_GLYPH_CAPACITY: usize = 1024;
+// Directional lights without a transform are labelled this far from the camera, towards the light.
+const LIGHT_LABEL_DISTANCE: f32 = 10.0;
+const ENTITY_ID_COLOR: Color = Color::rgba(1.0, 0.85, 0.3, 1.0);
+const DISTANCE_COLOR: Color = Color::rgba(0.6, 0.85, 1.0, 1.0);
+const LIGHT_COLOR: Color = Color::rgba(1.0, 1.0, 0.6, 1.0);
+
+// Text drawn at points in the world, facing the camera at a fixed size on screen. Each category
+// can be toggled on its own.
+pub struct DebugText {
+    pub entity_ids: bool,
+    pub distances: bool,
+    pub light_intensities: bool,
+    pub scale: f32,
+}
+
+impl Default for DebugText {
+    fn default() -> Self {
+        Self {
+            entity_ids: false,
+            distances: false,
+            light_intensities: false,
+            scale: 1.0,
+        }
+    }
+}
+
+impl DebugText {
+    pub fn any_enabled(&self) -> bool {
+        self.entity_ids || self.distances || self.light_intensities
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct TextGlyph {
+    pub position: Vec3,
+    pub color: u32,
+    // Top left corner in pixels, relative to the projected position.
+    pub offset: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+#[repr(C)]
+pub(crate) struct DebugTextResources {
+    pub glyph_buffer_index: u32,
+    pub font_texture_index: u32,
+    pub sampler_index: u32,
+    pub view_buffer_index: u32,
+    pub screen_size: Vec2,
+    pub glyph_size: Vec2,
+    pub scale: f32,
+}
+
+// Lines stack upwards from the anchor, each centered above it.
+fn push_text(glyphs: &mut Vec<TextGlyph>, position: Vec3, lines: &[(String, Color)]) {
+    for (row, (line, color)) in lines.iter().rev().enumerate() {
+        let width = line.chars().count() as f32 * GLYPH_SIZE.x;
+        let top = -(row as f32 + 1.0) * GLYPH_SIZE.y;
+        let color = u32::from_le_bytes(color.to_srgba_u8());
+        for (column, c) in line.chars().enumerate() {
+            let (uv_min, uv_max) = glyph_uv(glyph(c));
+            glyphs.push(TextGlyph {
+                position,
+                color,
+                offset: Vec2::new(column as f32 * GLYPH_SIZE.x - width * 0.5, top),
+                uv_min,
+                uv_max,
+            });
+        }
+    }
+}
+
+pub(crate) fn collect_glyphs(world: &World, text: &DebugText) -> Vec<TextGlyph> {
+    let mut glyphs = Vec::new();
+    let mut camera_query = world.query::<(&Camera, &GlobalTransform)>();
+    let Some((_entity, (_camera, camera_transform))) = camera_query.iter().next() else {
+        return glyphs;
+    };
+    let camera_position = camera_transform.transform.w_axis.truncate();
+
+    if text.entity_ids || text.distances {
+        for (entity, bounds) in world.query::<&WorldBounds>().iter() {
+            let center = bounds.0.center();
+            let mut lines = Vec::new();
+            if text.entity_ids {
+                lines.push((format!("#{}", entity.id()), ENTITY_ID_COLOR));
+            }
+            if text.distances {
+                let distance = center.distance(camera_position);
+                lines.push((format!("{:.1} m", distance), DISTANCE_COLOR));
+            }
+            push_text(&mut glyphs, center, &lines);
+        }
+    }
+
+    if text.light_intensities {
+        for (_entity, (light, transform)) in world
+            .query::<(&DirectionalLight, Option<&GlobalTransform>)>()
+            .iter()
+        {
+            let position = match transform {
+                Some(transform) => transform.transform.w_axis.truncate(),
+                None => camera_position + light.direction.normalize() * LIGHT_LABEL_DISTANCE,
+            };
+            let lines = [(format!("{:.2}", light.intensity), LIGHT_COLOR)];
+            push_text(&mut glyphs, position, &lines);
+        }
+    }
+    glyphs
+}
+
+pub(crate) struct DebugTextPass {
+    pub pipeline: ID3D12PipelineState,
+    pub glyph_buffer: GrowableGpuBuffer<TextGlyph>,
+}
+
+impl DebugTextPass {
+    pub fn new(
+        device: &mut Device,
+        cbv_heap: &mut DescriptorHeap,
+        root_signature: &ID3D12RootSignature,
+        format: DXGI_FORMAT,
+    ) -> Result<Self, RenderError> {
+        let (vertex_shader, fragment_shader) =
+            compile_shader_pair("assets/shaders/debug_text.hlsl")?;
+        let mut pipeline_desc =
+            graphics_pipeline_desc(root_signature, &vertex_shader, &fragment_shader, format, 1);
+        pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+            DepthEnable: false.into(),
+            DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+            ..Default::default()
+        };
+        pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+        let blend = &mut pipeline_desc.BlendState.RenderTarget[0];
+        blend.BlendEnable = true.into();
+        blend.SrcBlend = D3D12_BLEND_SRC_ALPHA;
+        blend.DestBlend = D3D12_BLEND_INV_SRC_ALPHA;
+        blend.DestBlendAlpha = D3D12_BLEND_INV_SRC_ALPHA;
+
+        Ok(Self {
+            pipeline: device.create_graphics_pipeline(&pipeline_desc)?,
+            glyph_buffer: GrowableGpuBuffer::new(
+                device,
+                cbv_heap,
+                "Debug Text Glyph Buffer",
+                MIN_GLYPH_CAPACITY,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+            )?,
+        })
+    }
+}
+
+impl Renderer {
+    // One instance per glyph; the vertex shader projects its anchor and expands it on screen.
+    pub(crate) fn draw_debug_text(&mut self, world: &World) -> Result<u32, RenderError> {
+        let glyphs = collect_glyphs(world, &self.debug_text);
+        let Some(font_view) = self.text_pass.font.as_ref().map(|(_font, view)| *view) else {
+            return Ok(0);
+        };
+        if glyphs.is_empty() {
+            return Ok(0);
+        }
+        // render waits for the GPU before returning, so the buffer is free to overwrite here.
+        self.debug_text_pass.glyph_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            glyphs.len(),
+        )?;
+        let glyph_buffer = self.debug_text_pass.glyph_buffer.view();
+        self.debug_text_pass
+            .glyph_buffer
+            .map(&self.device)?
+            .write_slice(&glyphs);
+
+        self.render_command_encoder
+            .set_pipeline(&self.debug_text_pass.pipeline);
+        self.render_command_encoder
+            .set_viewport(self.width, self.height);
+        self.render_command_encoder
+            .set_scissor(self.width, self.height);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .set_root_constants(&DebugTextResources {
+                glyph_buffer_index: glyph_buffer.view.0 as u32,
+                font_texture_index: font_view.0 as u32,
+                sampler_index: self.ui.sampler.0 as u32,
+                view_buffer_index: self.view_buffer.0 as u32,
+                screen_size: Vec2::new(self.width as f32, self.height as f32),
+                glyph_size: GLYPH_SIZE,
+                scale: self.debug_text.scale * self.ui_viewport().scale,
+            });
+        self.render_command_encoder
+            .draw_instanced(6, glyphs.len() as u32, 0, 0);
+        Ok(1)
+    }
+}
@@ -0,0 +1,197 @@
+use windows::Win32::Graphics::Dxgi::{Common::*, *};
+
+use crate::camera::Projection;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Rgba8,
+    Bgra8,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DepthFormat {
+    #[default]
+    D32Float,
+    D32FloatS8,
+}
+
+impl DepthFormat {
+    // Depth is created typeless so ambient occlusion can also sample it.
+    pub(crate) fn resource_format(self) -> DXGI_FORMAT {
+        match self {
+            DepthFormat::D32Float => DXGI_FORMAT_R32_TYPELESS,
+            DepthFormat::D32FloatS8 => DXGI_FORMAT_R32G8X24_TYPELESS,
+        }
+    }
+
+    pub(crate) fn dsv_format(self) -> DXGI_FORMAT {
+        match self {
+            DepthFormat::D32Float => DXGI_FORMAT_D32_FLOAT,
+            DepthFormat::D32FloatS8 => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        }
+    }
+
+    #[cfg(feature = "ambient-occlusion")]
+    pub(crate) fn srv_format(self) -> DXGI_FORMAT {
+        match self {
+            DepthFormat::D32Float => DXGI_FORMAT_R32_FLOAT,
+            DepthFormat::D32FloatS8 => DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS,
+        }
+    }
+}
+
+// Which adapter to prefer when a machine has more than one, such as a laptop with both an
+// integrated and a discrete GPU.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PowerPreference {
+    #[default]
+    HighPerformance,
+    LowPower,
+}
+
+impl PowerPreference {
+    pub(crate) fn gpu_preference(self) -> DXGI_GPU_PREFERENCE {
+        match self {
+            PowerPreference::HighPerformance => DXGI_GPU_PREFERENCE_HIGH_PERFORMANCE,
+            PowerPreference::LowPower => DXGI_GPU_PREFERENCE_MINIMUM_POWER,
+        }
+    }
+}
+
+// Whether to run on a hardware adapter, on WARP, the software rasterizer that ships with Windows,
+// or on WARP only when no hardware adapter can create a device, as in VMs and on CI machines.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AdapterPreference {
+    #[default]
+    Hardware,
+    HardwareOrWarp,
+    Warp,
+}
+
+// Descriptor counts of the heaps created with the renderer. Resources cover every texture, buffer
+// and constant view the scene uses, so large scenes need more than the default. Transient
+// resources are views recreated every frame, shared between the frames in flight. The default
+// render and depth targets fit every feature enabled at once: three swapchain buffers, scene and
+// MSAA color, the depth pyramid and occlusion images, the bloom chain and picking need 20 render
+// targets; the scene, picking and four shadow cascades need 6 depth targets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DescriptorHeapSizes {
+    pub render_targets: u32,
+    pub depth_targets: u32,
+    pub resources: u32,
+    pub transient_resources: u32,
+    pub samplers: u32,
+}
+
+impl Default for DescriptorHeapSizes {
+    fn default() -> Self {
+        Self {
+            render_targets: 32,
+            depth_targets: 8,
+            resources: 1000,
+            transient_resources: 256,
+            samplers: 1000,
+        }
+    }
+}
+
+// Fixed for the lifetime of a renderer, unlike RenderSettings which can change every frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererDesc {
+    // Swapchain buffers, 2 or 3. A third lets the CPU start the next frame while two are queued.
+    pub(crate) buffer_count: u32,
+    pub(crate) output_format: OutputFormat,
+    // Writes the output through an sRGB view so the hardware encodes it instead of the tonemap
+    // shader. Overlays drawn after tonemapping are then treated as linear too.
+    pub(crate) srgb_output: bool,
+    pub(crate) depth_format: DepthFormat,
+    pub(crate) power_preference: PowerPreference,
+    pub(crate) adapter_preference: AdapterPreference,
+    pub(crate) heap_sizes: DescriptorHeapSizes,
+    pub(crate) projection: Projection,
+}
+
+impl Default for RendererDesc {
+    fn default() -> Self {
+        Self {
+            buffer_count: 2,
+            output_format: OutputFormat::default(),
+            srgb_output: false,
+            depth_format: DepthFormat::default(),
+            power_preference: PowerPreference::default(),
+            adapter_preference: AdapterPreference::default(),
+            heap_sizes: DescriptorHeapSizes::default(),
+            projection: Projection::Perspective {
+                fov_y: 60.0f32.to_radians(),
+                near: 0.0001,
+                far: 10000.0,
+            },
+        }
+    }
+}
+
+impl RendererDesc {
+    pub fn with_buffer_count(mut self, buffer_count: u32) -> Self {
+        self.buffer_count = buffer_count;
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: OutputFormat, srgb: bool) -> Self {
+        self.output_format = output_format;
+        self.srgb_output = srgb;
+        self
+    }
+
+    pub fn with_depth_format(mut self, depth_format: DepthFormat) -> Self {
+        self.depth_format = depth_format;
+        self
+    }
+
+    pub fn with_power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn with_adapter_preference(mut self, adapter_preference: AdapterPreference) -> Self {
+        self.adapter_preference = adapter_preference;
+        self
+    }
+
+    pub fn with_heap_sizes(mut self, heap_sizes: DescriptorHeapSizes) -> Self {
+        self.heap_sizes = heap_sizes;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    // The projection new cameras should start with.
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub(crate) fn buffer_count(&self) -> u32 {
+        self.buffer_count.clamp(2, 3)
+    }
+
+    // Flip model swapchains cannot have sRGB formats, so the buffers always use the UNORM one.
+    pub(crate) fn buffer_format(&self) -> DXGI_FORMAT {
+        match self.output_format {
+            OutputFormat::Rgba8 => DXGI_FORMAT_R8G8B8A8_UNORM,
+            OutputFormat::Bgra8 => DXGI_FORMAT_B8G8R8A8_UNORM,
+        }
+    }
+
+    // Format of the views rendered into the output, and of the pipelines that draw there.
+    pub(crate) fn view_format(&self) -> DXGI_FORMAT {
+        match (self.output_format, self.srgb_output) {
+            (OutputFormat::Rgba8, false) => DXGI_FORMAT_R8G8B8A8_UNORM,
+            (OutputFormat::Rgba8, true) => DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            (OutputFormat::Bgra8, false) => DXGI_FORMAT_B8G8R8A8_UNORM,
+            (OutputFormat::Bgra8, true) => DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
+        }
+    }
+}
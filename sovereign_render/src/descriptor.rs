@@ -1,19 +1,177 @@
-use std::sync::Arc;
-use windows::Win32::Graphics::Direct3D12::{
-    ID3D12DescriptorHeap, ID3D12Device, ID3D12Resource, D3D12_CONSTANT_BUFFER_VIEW_DESC,
-    D3D12_CPU_DESCRIPTOR_HANDLE, D3D12_SAMPLER_DESC, D3D12_SHADER_RESOURCE_VIEW_DESC,
-};
+use std::{ops::Range, sync::Arc};
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::DXGI_FORMAT};
 
 use crate::{
-    device::AllocatedImage,
-    id::{SamplerId, ViewId},
+    device::{AllocatedImage, Device},
+    error::RenderError,
+    id::{ImageId, SamplerId, ViewId},
+    stats::Usage,
+    tracker::{ResourceKind, ResourceTracker},
 };
 
+// The part of an image a render target or depth view covers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TargetView {
+    // Overrides the image format, e.g. for a typed view of a typeless depth image.
+    pub format: Option<DXGI_FORMAT>,
+    pub mip_slice: u32,
+    // None covers every array slice.
+    pub array_slice: Option<u32>,
+}
+
+impl TargetView {
+    pub fn format(format: DXGI_FORMAT) -> Self {
+        Self {
+            format: Some(format),
+            ..Default::default()
+        }
+    }
+
+    pub fn mip(mip_slice: u32) -> Self {
+        Self {
+            mip_slice,
+            ..Default::default()
+        }
+    }
+
+    fn resolve(&self, image: &AllocatedImage) -> ResolvedTargetView {
+        let desc = unsafe { image.allocation.resource().GetDesc() };
+        let (first_array_slice, array_size) = match self.array_slice {
+            Some(slice) => (slice, 1),
+            None => (0, desc.DepthOrArraySize as u32),
+        };
+        ResolvedTargetView {
+            format: self.format.unwrap_or(image.format),
+            multisampled: desc.SampleDesc.Count > 1,
+            arrayed: self.array_slice.is_some() || desc.DepthOrArraySize > 1,
+            mip_slice: self.mip_slice,
+            first_array_slice,
+            array_size,
+        }
+    }
+}
+
+struct ResolvedTargetView {
+    format: DXGI_FORMAT,
+    multisampled: bool,
+    arrayed: bool,
+    mip_slice: u32,
+    first_array_slice: u32,
+    array_size: u32,
+}
+
+impl ResolvedTargetView {
+    fn rtv_desc(&self) -> D3D12_RENDER_TARGET_VIEW_DESC {
+        let (dimension, anonymous) = match (self.multisampled, self.arrayed) {
+            (false, false) => (
+                D3D12_RTV_DIMENSION_TEXTURE2D,
+                D3D12_RENDER_TARGET_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_RTV {
+                        MipSlice: self.mip_slice,
+                        PlaneSlice: 0,
+                    },
+                },
+            ),
+            (false, true) => (
+                D3D12_RTV_DIMENSION_TEXTURE2DARRAY,
+                D3D12_RENDER_TARGET_VIEW_DESC_0 {
+                    Texture2DArray: D3D12_TEX2D_ARRAY_RTV {
+                        MipSlice: self.mip_slice,
+                        FirstArraySlice: self.first_array_slice,
+                        ArraySize: self.array_size,
+                        PlaneSlice: 0,
+                    },
+                },
+            ),
+            (true, false) => (
+                D3D12_RTV_DIMENSION_TEXTURE2DMS,
+                D3D12_RENDER_TARGET_VIEW_DESC_0 {
+                    Texture2DMS: D3D12_TEX2DMS_RTV::default(),
+                },
+            ),
+            (true, true) => (
+                D3D12_RTV_DIMENSION_TEXTURE2DMSARRAY,
+                D3D12_RENDER_TARGET_VIEW_DESC_0 {
+                    Texture2DMSArray: D3D12_TEX2DMS_ARRAY_RTV {
+                        FirstArraySlice: self.first_array_slice,
+                        ArraySize: self.array_size,
+                    },
+                },
+            ),
+        };
+        D3D12_RENDER_TARGET_VIEW_DESC {
+            Format: self.format,
+            ViewDimension: dimension,
+            Anonymous: anonymous,
+        }
+    }
+
+    fn dsv_desc(&self) -> D3D12_DEPTH_STENCIL_VIEW_DESC {
+        let (dimension, anonymous) = match (self.multisampled, self.arrayed) {
+            (false, false) => (
+                D3D12_DSV_DIMENSION_TEXTURE2D,
+                D3D12_DEPTH_STENCIL_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_DSV {
+                        MipSlice: self.mip_slice,
+                    },
+                },
+            ),
+            (false, true) => (
+                D3D12_DSV_DIMENSION_TEXTURE2DARRAY,
+                D3D12_DEPTH_STENCIL_VIEW_DESC_0 {
+                    Texture2DArray: D3D12_TEX2D_ARRAY_DSV {
+                        MipSlice: self.mip_slice,
+                        FirstArraySlice: self.first_array_slice,
+                        ArraySize: self.array_size,
+                    },
+                },
+            ),
+            (true, false) => (
+                D3D12_DSV_DIMENSION_TEXTURE2DMS,
+                D3D12_DEPTH_STENCIL_VIEW_DESC_0 {
+                    Texture2DMS: D3D12_TEX2DMS_DSV::default(),
+                },
+            ),
+            (true, true) => (
+                D3D12_DSV_DIMENSION_TEXTURE2DMSARRAY,
+                D3D12_DEPTH_STENCIL_VIEW_DESC_0 {
+                    Texture2DMSArray: D3D12_TEX2DMS_ARRAY_DSV {
+                        FirstArraySlice: self.first_array_slice,
+                        ArraySize: self.array_size,
+                    },
+                },
+            ),
+        };
+        D3D12_DEPTH_STENCIL_VIEW_DESC {
+            Format: self.format,
+            ViewDimension: dimension,
+            Flags: D3D12_DSV_FLAG_NONE,
+            Anonymous: anonymous,
+        }
+    }
+}
+
+// Views only needed for one frame, bumped through a range at the end of the heap that holds a
+// region per frame in flight. Starting a frame rewinds to that frame's region, so views the GPU may
+// still be reading for another frame are left alone.
+struct TransientRange {
+    start: usize,
+    frame_size: usize,
+    head: usize,
+    end: usize,
+}
+
 pub struct DescriptorHeap {
     heap: ID3D12DescriptorHeap,
     device: Arc<ID3D12Device>,
     descriptor_size: u32,
+    capacity: usize,
     items: usize,
+    // Freed slots as sorted, non-adjacent ranges, so single views and whole tables reuse them
+    // alike.
+    free: Vec<Range<usize>>,
+    transient: Option<TransientRange>,
+    tracker: ResourceTracker,
 }
 
 impl DescriptorHeap {
@@ -21,15 +179,133 @@ impl DescriptorHeap {
         heap: ID3D12DescriptorHeap,
         device: Arc<ID3D12Device>,
         descriptor_size: u32,
+        capacity: usize,
     ) -> Self {
         Self {
             heap,
             device,
             descriptor_size,
+            capacity,
             items: 0,
+            free: Vec::new(),
+            transient: None,
+            tracker: ResourceTracker::default(),
+        }
+    }
+
+    // Sets aside the last `count` descriptors for per-frame views, split evenly between frames.
+    pub fn reserve_transient(&mut self, count: usize, frames_in_flight: usize) {
+        let frame_size = count / frames_in_flight;
+        let start = self.capacity - frame_size * frames_in_flight;
+        self.capacity = start;
+        self.transient = Some(TransientRange {
+            start,
+            frame_size,
+            head: start,
+            end: start + frame_size,
+        });
+    }
+
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        if let Some(transient) = &mut self.transient {
+            transient.head = transient.start + frame_index * transient.frame_size;
+            transient.end = transient.head + transient.frame_size;
+        }
+    }
+
+    fn allocate(&mut self) -> Result<usize, RenderError> {
+        self.allocate_range(1)
+    }
+
+    // Takes the first freed range that fits before growing into the unused end of the heap.
+    // Running past the persistent region would overwrite the transient views, so a full heap is an
+    // error rather than an assertion.
+    fn allocate_range(&mut self, count: usize) -> Result<usize, RenderError> {
+        if let Some(idx) = self.free.iter().position(|range| range.len() >= count) {
+            let range = &mut self.free[idx];
+            range.start += count;
+            let start = range.start - count;
+            if range.start == range.end {
+                self.free.remove(idx);
+            }
+            return Ok(start);
+        }
+        if self.items + count > self.capacity {
+            return Err(RenderError::DescriptorHeapFull(self.capacity));
+        }
+        self.items += count;
+        Ok(self.items - count)
+    }
+
+    fn allocate_transient(&mut self) -> Result<usize, RenderError> {
+        match &mut self.transient {
+            Some(transient) if transient.head < transient.end => {
+                transient.head += 1;
+                Ok(transient.head - 1)
+            }
+            _ => Err(RenderError::TransientDescriptorsExhausted),
+        }
+    }
+
+    pub fn free(&mut self, view: ViewId) {
+        self.tracker.untrack(ResourceKind::Descriptor, view.0);
+        self.release(view.0..view.0 + 1);
+    }
+
+    // Merges the range with its freed neighbours, and gives it back to the unused end of the heap
+    // if it reaches it.
+    fn release(&mut self, mut range: Range<usize>) {
+        let idx = self.free.partition_point(|free| free.start < range.start);
+        if self
+            .free
+            .get(idx)
+            .is_some_and(|next| next.start == range.end)
+        {
+            range.end = self.free.remove(idx).end;
+        }
+        match idx.checked_sub(1).map(|prev| &mut self.free[prev]) {
+            Some(prev) if prev.end == range.start => prev.end = range.end,
+            _ => self.free.insert(idx, range),
+        }
+        if let Some(last) = self.free.last().filter(|last| last.end == self.items) {
+            self.items = last.start;
+            self.free.pop();
         }
     }
 
+    pub(crate) fn tracker(&self) -> &ResourceTracker {
+        &self.tracker
+    }
+
+    // Persistent descriptors, not counting the transient range.
+    pub fn usage(&self) -> Usage {
+        Usage {
+            used: self.items - self.free.iter().map(Range::len).sum::<usize>(),
+            capacity: self.capacity,
+        }
+    }
+
+    // Fails up front when `count` more descriptors won't fit, so a set of views that belong
+    // together is created whole or not at all.
+    pub fn ensure_free(&self, count: usize) -> Result<(), RenderError> {
+        let usage = self.usage();
+        if usage.used + count > usage.capacity {
+            return Err(RenderError::DescriptorHeapFull(self.capacity));
+        }
+        Ok(())
+    }
+
+    // How much of the current frame's transient range has been handed out.
+    pub fn transient_usage(&self) -> Usage {
+        self.transient
+            .as_ref()
+            .map(|transient| Usage {
+                used: transient.head + transient.frame_size - transient.end,
+                capacity: transient.frame_size,
+            })
+            .unwrap_or_default()
+    }
+
     pub fn get(&self) -> ID3D12DescriptorHeap {
         self.heap.clone()
     }
@@ -41,38 +317,93 @@ impl DescriptorHeap {
         }
     }
 
-    pub fn create_rtv(&mut self, resource: &ID3D12Resource) {
-        let idx = self.items;
+    #[track_caller]
+    pub fn create_rtv(
+        &mut self,
+        name: &str,
+        resource: &ID3D12Resource,
+        desc: Option<&D3D12_RENDER_TARGET_VIEW_DESC>,
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate()?;
         unsafe {
             self.device.CreateRenderTargetView(
                 resource,
-                None,
+                desc.map(|desc| desc as *const _),
                 D3D12_CPU_DESCRIPTOR_HANDLE {
                     ptr: self.heap.GetCPUDescriptorHandleForHeapStart().ptr
                         + idx * self.descriptor_size as usize,
                 },
             );
         }
-        self.items += 1;
+        self.tracker.track(
+            ResourceKind::Descriptor,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(ViewId(idx))
     }
 
-    pub fn create_dsv(&mut self, image: &AllocatedImage) {
-        let idx = self.items;
+    #[track_caller]
+    pub fn create_dsv(
+        &mut self,
+        name: &str,
+        image: &AllocatedImage,
+        desc: Option<&D3D12_DEPTH_STENCIL_VIEW_DESC>,
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate()?;
         unsafe {
             self.device.CreateDepthStencilView(
                 image.allocation.resource(),
-                None,
+                desc.map(|desc| desc as *const _),
                 D3D12_CPU_DESCRIPTOR_HANDLE {
                     ptr: self.heap.GetCPUDescriptorHandleForHeapStart().ptr
                         + idx * self.descriptor_size as usize,
                 },
             );
         }
-        self.items += 1;
+        self.tracker.track(
+            ResourceKind::Descriptor,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(ViewId(idx))
+    }
+
+    #[track_caller]
+    pub fn create_image_rtv(
+        &mut self,
+        device: &Device,
+        name: &str,
+        image: ImageId,
+        view: TargetView,
+    ) -> Result<ViewId, RenderError> {
+        let image = device.get_image(image);
+        let desc = view.resolve(image).rtv_desc();
+        self.create_rtv(name, image.allocation.resource(), Some(&desc))
     }
 
-    pub fn create_sampler(&mut self, sampler: &D3D12_SAMPLER_DESC) -> SamplerId {
-        let idx = self.items;
+    #[track_caller]
+    pub fn create_image_dsv(
+        &mut self,
+        device: &Device,
+        name: &str,
+        image: ImageId,
+        view: TargetView,
+    ) -> Result<ViewId, RenderError> {
+        let image = device.get_image(image);
+        let desc = view.resolve(image).dsv_desc();
+        self.create_dsv(name, image, Some(&desc))
+    }
+
+    #[track_caller]
+    pub fn create_sampler(
+        &mut self,
+        name: &str,
+        sampler: &D3D12_SAMPLER_DESC,
+    ) -> Result<SamplerId, RenderError> {
+        let idx = self.allocate()?;
         unsafe {
             self.device.CreateSampler(
                 sampler,
@@ -82,16 +413,23 @@ impl DescriptorHeap {
                 },
             );
         }
-        self.items += 1;
-        SamplerId(idx)
+        self.tracker.track(
+            ResourceKind::Sampler,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(SamplerId(idx))
     }
 
+    #[track_caller]
     pub fn create_srv(
         &mut self,
+        name: &str,
         resource: &ID3D12Resource,
         desc: &D3D12_SHADER_RESOURCE_VIEW_DESC,
-    ) -> ViewId {
-        let idx = self.items;
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate()?;
         unsafe {
             self.device.CreateShaderResourceView(
                 resource,
@@ -102,12 +440,91 @@ impl DescriptorHeap {
                 },
             );
         }
-        self.items += 1;
-        ViewId(idx)
+        self.tracker.track(
+            ResourceKind::Descriptor,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(ViewId(idx))
+    }
+
+    // Creates the views in one contiguous range and returns the first, so the set can be addressed
+    // as a table from its base index.
+    #[track_caller]
+    pub fn create_srvs(
+        &mut self,
+        views: &[(&str, &ID3D12Resource, &D3D12_SHADER_RESOURCE_VIEW_DESC)],
+    ) -> Result<ViewId, RenderError> {
+        let base = self.allocate_range(views.len())?;
+        let start = unsafe { self.heap.GetCPUDescriptorHandleForHeapStart() }.ptr;
+        for (i, (name, resource, desc)) in views.iter().enumerate() {
+            let idx = base + i;
+            unsafe {
+                self.device.CreateShaderResourceView(
+                    *resource,
+                    Some(*desc as *const _),
+                    D3D12_CPU_DESCRIPTOR_HANDLE {
+                        ptr: start + idx * self.descriptor_size as usize,
+                    },
+                );
+            }
+            self.tracker.track(
+                ResourceKind::Descriptor,
+                idx,
+                name,
+                self.descriptor_size as u64,
+            );
+        }
+        Ok(ViewId(base))
+    }
+
+    #[track_caller]
+    pub fn create_uav(
+        &mut self,
+        name: &str,
+        resource: &ID3D12Resource,
+        desc: &D3D12_UNORDERED_ACCESS_VIEW_DESC,
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate()?;
+        unsafe {
+            self.device.CreateUnorderedAccessView(
+                resource,
+                None,
+                Some(desc as *const _),
+                self.get_handle(idx),
+            );
+        }
+        self.tracker.track(
+            ResourceKind::Descriptor,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(ViewId(idx))
     }
 
-    pub fn create_cbv(&mut self, desc: &D3D12_CONSTANT_BUFFER_VIEW_DESC) -> ViewId {
-        let idx = self.items;
+    // Only valid until the same frame index comes around again; not tracked, since it is gone by
+    // then anyway.
+    pub fn create_transient_cbv(
+        &mut self,
+        desc: &D3D12_CONSTANT_BUFFER_VIEW_DESC,
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate_transient()?;
+        unsafe {
+            self.device
+                .CreateConstantBufferView(Some(desc as *const _), self.get_handle(idx));
+        }
+        Ok(ViewId(idx))
+    }
+
+    #[track_caller]
+    pub fn create_cbv(
+        &mut self,
+        name: &str,
+        desc: &D3D12_CONSTANT_BUFFER_VIEW_DESC,
+    ) -> Result<ViewId, RenderError> {
+        let idx = self.allocate()?;
         unsafe {
             self.device.CreateConstantBufferView(
                 Some(desc as *const _),
@@ -117,7 +534,12 @@ impl DescriptorHeap {
                 },
             );
         }
-        self.items += 1;
-        ViewId(idx)
+        self.tracker.track(
+            ResourceKind::Descriptor,
+            idx,
+            name,
+            self.descriptor_size as u64,
+        );
+        Ok(ViewId(idx))
     }
 }
@@ -1,3 +1,4 @@
+use bytemuck::{cast_slice, Pod};
 use gpu_allocator::{
     d3d12::{
         Allocator, AllocatorCreateDesc, ID3D12DeviceVersion, Resource, ResourceCategory,
@@ -6,13 +7,12 @@ use gpu_allocator::{
     MemoryLocation,
 };
 use std::{
-    error::Error,
     ffi::c_void,
     ptr::{self, NonNull},
     sync::Arc,
 };
 use windows::{
-    core::{ComInterface, PCSTR},
+    core::{ComInterface, Interface, PCSTR},
     Win32::{
         Foundation::HWND,
         Graphics::{
@@ -24,29 +24,42 @@ use windows::{
 };
 
 use crate::{
+    capabilities::DeviceCapabilities,
     command_encoder::CommandEncoder,
+    desc::{AdapterPreference, PowerPreference, RendererDesc},
     descriptor::DescriptorHeap,
+    error::RenderError,
     id::{BufferId, ImageId},
     queue::Queue,
+    tracker::{ResourceKind, ResourceTracker},
+    upload_adapter::UploadAdapter,
 };
 
-pub type DeviceError = Box<dyn Error>;
-
 pub struct Device {
     factory: IDXGIFactory6,
     _physical_device: IDXGIAdapter1,
     device: Arc<ID3D12Device>,
     allocator: Allocator,
     _debug_callback: Option<ID3D12InfoQueue1>,
+    capabilities: DeviceCapabilities,
 
-    images: Vec<AllocatedImage>,
-    buffers: Vec<AllocatedBuffer>,
+    images: Vec<Option<AllocatedImage>>,
+    buffers: Vec<Option<AllocatedBuffer>>,
+    tracker: ResourceTracker,
 }
 
 pub struct AllocatedImage {
     pub allocation: Resource,
     pub width: u32,
     pub height: u32,
+    pub mip_levels: u32,
+    pub format: DXGI_FORMAT,
+}
+
+pub struct SubresourceFootprint {
+    pub layout: D3D12_PLACED_SUBRESOURCE_FOOTPRINT,
+    pub num_rows: u32,
+    pub row_size: u64,
 }
 
 pub struct AllocatedBuffer {
@@ -55,7 +68,7 @@ pub struct AllocatedBuffer {
 }
 
 impl Device {
-    pub fn new() -> Result<Self, DeviceError> {
+    pub fn new(desc: &RendererDesc) -> Result<Self, RenderError> {
         if cfg!(debug_assertions) {
             unsafe {
                 let mut debug: Option<ID3D12Debug1> = None;
@@ -70,11 +83,25 @@ impl Device {
         let factory_flags = DXGI_CREATE_FACTORY_DEBUG;
         let factory: IDXGIFactory6 = unsafe { CreateDXGIFactory2(factory_flags) }?;
 
-        let physical_device = get_physical_device(&factory)?;
+        let physical_device = match desc.adapter_preference {
+            AdapterPreference::Hardware => get_physical_device(&factory, desc.power_preference)?,
+            AdapterPreference::HardwareOrWarp => {
+                match get_physical_device(&factory, desc.power_preference) {
+                    Err(RenderError::NoAdapter) => {
+                        tracing::warn!("No hardware adapter found, falling back to WARP");
+                        get_warp_device(&factory)?
+                    }
+                    result => result?,
+                }
+            }
+            AdapterPreference::Warp => get_warp_device(&factory)?,
+        };
 
         let mut device: Option<ID3D12Device> = None;
-        unsafe { D3D12CreateDevice(&physical_device, D3D_FEATURE_LEVEL_11_0, &mut device) }?;
+        unsafe { D3D12CreateDevice(&physical_device, D3D_FEATURE_LEVEL_11_0, &mut device) }
+            .map_err(RenderError::DeviceCreation)?;
         let device = device.unwrap();
+        let capabilities = DeviceCapabilities::query(&device, &physical_device);
 
         let allocator = Allocator::new(&AllocatorCreateDesc {
             device: ID3D12DeviceVersion::Device(device.clone()),
@@ -115,23 +142,46 @@ impl Device {
             device: Arc::new(device),
             allocator,
             _debug_callback: info_queue,
+            capabilities,
             images: Vec::new(),
             buffers: Vec::new(),
+            tracker: ResourceTracker::default(),
         })
     }
 
+    pub fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    pub(crate) fn raw(&self) -> &ID3D12Device {
+        &self.device
+    }
+
+    pub(crate) fn create_upload_adapter(&self) -> Result<Option<UploadAdapter>, RenderError> {
+        UploadAdapter::new(&self.factory, &self.device)
+    }
+
     pub fn get_image(&self, image_id: ImageId) -> &AllocatedImage {
-        &self.images[image_id.0]
+        self.images[image_id.0].as_ref().unwrap()
     }
 
     pub fn get_buffer(&self, buffer_id: BufferId) -> &AllocatedBuffer {
-        &self.buffers[buffer_id.0]
+        self.buffers[buffer_id.0].as_ref().unwrap()
+    }
+
+    // None once the image has been destroyed; ids are never reused.
+    pub fn try_get_image(&self, image_id: ImageId) -> Option<&AllocatedImage> {
+        self.images.get(image_id.0)?.as_ref()
+    }
+
+    pub fn try_get_buffer(&self, buffer_id: BufferId) -> Option<&AllocatedBuffer> {
+        self.buffers.get(buffer_id.0)?.as_ref()
     }
 
     pub fn create_command_queue(
         &self,
         kind: D3D12_COMMAND_LIST_TYPE,
-    ) -> Result<Queue, DeviceError> {
+    ) -> Result<Queue, RenderError> {
         let desc = D3D12_COMMAND_QUEUE_DESC {
             Type: kind,
             ..Default::default()
@@ -147,16 +197,18 @@ impl Device {
         desc: &DXGI_SWAP_CHAIN_DESC1,
         queue: &Queue,
         hwnd: HWND,
-    ) -> Result<IDXGISwapChain3, DeviceError> {
+    ) -> Result<IDXGISwapChain3, RenderError> {
         let swapchain: IDXGISwapChain3 = unsafe {
             self.factory
                 .CreateSwapChainForHwnd(queue.get(), hwnd, desc, None, None)
-        }?
-        .cast()?;
+        }
+        .and_then(|swapchain| swapchain.cast())
+        .map_err(RenderError::swapchain)?;
 
         unsafe {
             self.factory
-                .MakeWindowAssociation(hwnd, DXGI_MWA_NO_ALT_ENTER)?;
+                .MakeWindowAssociation(hwnd, DXGI_MWA_NO_ALT_ENTER)
+                .map_err(RenderError::swapchain)?;
         }
 
         Ok(swapchain)
@@ -167,7 +219,7 @@ impl Device {
         kind: D3D12_DESCRIPTOR_HEAP_TYPE,
         count: u32,
         flags: D3D12_DESCRIPTOR_HEAP_FLAGS,
-    ) -> Result<DescriptorHeap, DeviceError> {
+    ) -> Result<DescriptorHeap, RenderError> {
         let desc = D3D12_DESCRIPTOR_HEAP_DESC {
             Type: kind,
             NumDescriptors: count,
@@ -176,67 +228,214 @@ impl Device {
         };
         let raw_heap: ID3D12DescriptorHeap = unsafe { self.device.CreateDescriptorHeap(&desc)? };
         let descriptor_size = unsafe { self.device.GetDescriptorHandleIncrementSize(kind) };
-        let heap = DescriptorHeap::new(raw_heap, self.device.clone(), descriptor_size);
+        let heap = DescriptorHeap::new(
+            raw_heap,
+            self.device.clone(),
+            descriptor_size,
+            count as usize,
+        );
 
         Ok(heap)
     }
 
+    #[track_caller]
     pub fn create_image(
         &mut self,
+        name: &str,
         width: u32,
         height: u32,
         format: DXGI_FORMAT,
         flags: D3D12_RESOURCE_FLAGS,
         state: D3D12_RESOURCE_STATES,
-    ) -> Result<ImageId, DeviceError> {
-        let resource_category = if format == DXGI_FORMAT_D32_FLOAT {
+    ) -> Result<ImageId, RenderError> {
+        let desc = image_desc(width, height, 1, format, flags);
+        self.create_image_from_desc(name, &desc, state)
+    }
+
+    #[track_caller]
+    pub fn create_texture_image(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        format: DXGI_FORMAT,
+    ) -> Result<ImageId, RenderError> {
+        let desc = image_desc(width, height, mip_levels, format, D3D12_RESOURCE_FLAG_NONE);
+        self.create_image_from_desc(name, &desc, D3D12_RESOURCE_STATE_COPY_DEST)
+    }
+
+    #[track_caller]
+    pub fn create_render_target(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        format: DXGI_FORMAT,
+        sample_count: u32,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<ImageId, RenderError> {
+        let mut desc = image_desc(width, height, 1, format, target_flags(format));
+        desc.SampleDesc.Count = sample_count;
+        self.create_image_from_desc(name, &desc, state)
+    }
+
+    #[track_caller]
+    pub fn create_render_target_mips(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        format: DXGI_FORMAT,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<ImageId, RenderError> {
+        let desc = image_desc(width, height, mip_levels, format, target_flags(format));
+        self.create_image_from_desc(name, &desc, state)
+    }
+
+    #[track_caller]
+    pub fn create_render_target_array(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        array_size: u32,
+        format: DXGI_FORMAT,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<ImageId, RenderError> {
+        let mut desc = image_desc(width, height, 1, format, target_flags(format));
+        desc.DepthOrArraySize = array_size as u16;
+        self.create_image_from_desc(name, &desc, state)
+    }
+
+    // Six faces in one array, in the D3D face order +X, -X, +Y, -Y, +Z, -Z, sampled as a cube.
+    #[track_caller]
+    pub fn create_cubemap_image(
+        &mut self,
+        name: &str,
+        size: u32,
+        mip_levels: u32,
+        format: DXGI_FORMAT,
+        flags: D3D12_RESOURCE_FLAGS,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<ImageId, RenderError> {
+        let mut desc = image_desc(size, size, mip_levels, format, flags);
+        desc.DepthOrArraySize = 6;
+        self.create_image_from_desc(name, &desc, state)
+    }
+
+    #[track_caller]
+    fn create_image_from_desc(
+        &mut self,
+        name: &str,
+        desc: &D3D12_RESOURCE_DESC,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<ImageId, RenderError> {
+        let target_flags =
+            D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET.0 | D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL.0;
+        let resource_category = if desc.Flags.0 & target_flags != 0 {
             ResourceCategory::RtvDsvTexture
         } else {
             ResourceCategory::OtherTexture
         };
-        let desc = D3D12_RESOURCE_DESC {
-            Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
-            Alignment: 0,
-            Width: width as u64,
-            Height: height,
-            DepthOrArraySize: 1,
-            MipLevels: 1,
-            Format: format,
-            SampleDesc: DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
-            Flags: flags,
-        };
         let allocation = self.allocator.create_resource(&ResourceCreateDesc {
-            name: "Image",
+            name,
             memory_location: MemoryLocation::GpuOnly,
             resource_category,
-            resource_desc: &desc,
+            resource_desc: desc,
             clear_value: None,
             initial_state_or_layout: ResourceStateOrBarrierLayout::ResourceState(state),
             resource_type: &ResourceType::Placed,
         })?;
 
         let idx = self.images.len();
-        self.images.push(AllocatedImage {
+        self.tracker
+            .track(ResourceKind::Image, idx, name, allocation.size);
+        self.images.push(Some(AllocatedImage {
             allocation,
-            width,
-            height,
-        });
+            width: desc.Width as u32,
+            height: desc.Height,
+            mip_levels: desc.MipLevels as u32,
+            format: desc.Format,
+        }));
 
         Ok(ImageId(idx))
     }
 
+    pub fn copyable_footprints(&self, id: ImageId) -> (Vec<SubresourceFootprint>, u64) {
+        let image = self.get_image(id);
+        let desc = unsafe { image.allocation.resource().GetDesc() };
+        // Subresources of array images run through every mip of one slice before the next.
+        let count = image.mip_levels as usize * desc.DepthOrArraySize as usize;
+        let mut layouts = vec![D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default(); count];
+        let mut num_rows = vec![0; count];
+        let mut row_sizes = vec![0; count];
+        let mut total_size = 0;
+        unsafe {
+            self.device.GetCopyableFootprints(
+                &desc,
+                0,
+                count as u32,
+                0,
+                Some(layouts.as_mut_ptr()),
+                Some(num_rows.as_mut_ptr()),
+                Some(row_sizes.as_mut_ptr()),
+                Some(&mut total_size),
+            );
+        }
+        let footprints = layouts
+            .into_iter()
+            .zip(num_rows)
+            .zip(row_sizes)
+            .map(|((layout, num_rows), row_size)| SubresourceFootprint {
+                layout,
+                num_rows,
+                row_size,
+            })
+            .collect();
+        (footprints, total_size)
+    }
+
+    // Layout of the first subresource of any texture resource, such as a swapchain buffer.
+    pub fn resource_footprint(&self, resource: &ID3D12Resource) -> (SubresourceFootprint, u64) {
+        let desc = unsafe { resource.GetDesc() };
+        let mut layout = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+        let mut num_rows = 0;
+        let mut row_size = 0;
+        let mut total_size = 0;
+        unsafe {
+            self.device.GetCopyableFootprints(
+                &desc,
+                0,
+                1,
+                0,
+                Some(&mut layout),
+                Some(&mut num_rows),
+                Some(&mut row_size),
+                Some(&mut total_size),
+            );
+        }
+        (
+            SubresourceFootprint {
+                layout,
+                num_rows,
+                row_size,
+            },
+            total_size,
+        )
+    }
+
+    #[track_caller]
     pub fn create_buffer(
         &mut self,
+        name: &str,
         size: u64,
         format: DXGI_FORMAT,
         flags: D3D12_RESOURCE_FLAGS,
         state: D3D12_RESOURCE_STATES,
         location: MemoryLocation,
-    ) -> Result<BufferId, DeviceError> {
+    ) -> Result<BufferId, RenderError> {
         let desc = D3D12_RESOURCE_DESC {
             Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
             Alignment: 0,
@@ -253,7 +452,7 @@ impl Device {
             Flags: flags,
         };
         let allocation = self.allocator.create_resource(&ResourceCreateDesc {
-            name: "Buffer",
+            name,
             memory_location: location,
             resource_category: ResourceCategory::Buffer,
             resource_desc: &desc,
@@ -263,15 +462,126 @@ impl Device {
         })?;
 
         let idx = self.buffers.len();
-        self.buffers.push(AllocatedBuffer { allocation, size });
+        self.tracker.track(ResourceKind::Buffer, idx, name, size);
+        self.buffers
+            .push(Some(AllocatedBuffer { allocation, size }));
 
         Ok(BufferId(idx))
     }
 
+    // Returns the buffer, still in COPY_DEST, and a staging buffer holding the data to copy into
+    // it.
+    #[track_caller]
+    pub fn create_buffer_with_data<T: Pod>(
+        &mut self,
+        name: &str,
+        data: &[T],
+    ) -> Result<(BufferId, BufferId), RenderError> {
+        let bytes: &[u8] = cast_slice(data);
+        let staging_buffer = self.create_buffer(
+            &format!("{} (staging)", name),
+            bytes.len() as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            MemoryLocation::CpuToGpu,
+        )?;
+        let buffer = self.create_buffer(
+            name,
+            bytes.len() as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            MemoryLocation::GpuOnly,
+        )?;
+        {
+            let data = self.map_buffer::<T>(staging_buffer)?;
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), data.as_ptr(), bytes.len()) };
+            self.unmap_buffer(staging_buffer);
+        }
+
+        Ok((buffer, staging_buffer))
+    }
+
+    // Writes the items straight into a new upload buffer, for callers that would otherwise
+    // collect them only to copy them again. Slots the iterator doesn't reach are zeroed.
+    pub fn create_staging_buffer_from_iter<T: Pod>(
+        &mut self,
+        name: &str,
+        len: usize,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<BufferId, RenderError> {
+        let staging_buffer = self.create_buffer(
+            name,
+            (len * std::mem::size_of::<T>()) as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+            MemoryLocation::CpuToGpu,
+        )?;
+        let data = self.map_buffer::<T>(staging_buffer)?.cast::<T>();
+        let mut written = 0;
+        for item in items.into_iter().take(len) {
+            unsafe { data.as_ptr().add(written).write(item) };
+            written += 1;
+        }
+        unsafe { ptr::write_bytes(data.as_ptr().add(written), 0, len - written) };
+        self.unmap_buffer(staging_buffer);
+
+        Ok(staging_buffer)
+    }
+
+    pub fn destroy_image(&mut self, id: ImageId) {
+        if let Some(image) = self.images[id.0].take() {
+            let _ = self.allocator.free_resource(image.allocation);
+            self.tracker.untrack(ResourceKind::Image, id.0);
+        }
+    }
+
+    pub fn destroy_buffer(&mut self, id: BufferId) {
+        if let Some(buffer) = self.buffers[id.0].take() {
+            let _ = self.allocator.free_resource(buffer.allocation);
+            self.tracker.untrack(ResourceKind::Buffer, id.0);
+        }
+    }
+
+    pub(crate) fn tracker(&self) -> &ResourceTracker {
+        &self.tracker
+    }
+
+    pub fn live_buffers(&self) -> usize {
+        self.buffers
+            .iter()
+            .filter(|buffer| buffer.is_some())
+            .count()
+    }
+
+    pub fn live_images(&self) -> usize {
+        self.images.iter().filter(|image| image.is_some()).count()
+    }
+
+    // Looks up the name a resource was created with from its raw COM pointer.
+    pub(crate) fn resource_name(&self, resource: usize) -> Option<&str> {
+        let matches = |allocation: &Resource| allocation.resource().as_raw() as usize == resource;
+        if let Some(idx) = self.images.iter().position(|image| {
+            image
+                .as_ref()
+                .is_some_and(|image| matches(&image.allocation))
+        }) {
+            return self.tracker.name(ResourceKind::Image, idx);
+        }
+        let idx = self.buffers.iter().position(|buffer| {
+            buffer
+                .as_ref()
+                .is_some_and(|buffer| matches(&buffer.allocation))
+        })?;
+        self.tracker.name(ResourceKind::Buffer, idx)
+    }
+
     pub fn create_command_encoder(
         &self,
         kind: D3D12_COMMAND_LIST_TYPE,
-    ) -> Result<CommandEncoder, DeviceError> {
+    ) -> Result<CommandEncoder, RenderError> {
         let allocator: ID3D12CommandAllocator =
             unsafe { self.device.CreateCommandAllocator(kind) }?;
         let list: ID3D12GraphicsCommandList =
@@ -284,7 +594,7 @@ impl Device {
         &self,
         flags: D3D12_ROOT_SIGNATURE_FLAGS,
         parameters: &[D3D12_ROOT_PARAMETER],
-    ) -> Result<ID3D12RootSignature, DeviceError> {
+    ) -> Result<ID3D12RootSignature, RenderError> {
         let desc = D3D12_ROOT_SIGNATURE_DESC {
             Flags: flags,
             NumParameters: parameters.len() as u32,
@@ -312,19 +622,42 @@ impl Device {
     pub fn create_graphics_pipeline(
         &self,
         desc: &D3D12_GRAPHICS_PIPELINE_STATE_DESC,
-    ) -> Result<ID3D12PipelineState, DeviceError> {
+    ) -> Result<ID3D12PipelineState, RenderError> {
         let pipeline = unsafe { self.device.CreateGraphicsPipelineState(desc)? };
         Ok(pipeline)
     }
 
-    pub fn create_fence(&self) -> Result<ID3D12Fence, DeviceError> {
+    pub fn create_compute_pipeline(
+        &self,
+        desc: &D3D12_COMPUTE_PIPELINE_STATE_DESC,
+    ) -> Result<ID3D12PipelineState, RenderError> {
+        let pipeline = unsafe { self.device.CreateComputePipelineState(desc)? };
+        Ok(pipeline)
+    }
+
+    pub fn create_query_heap(
+        &self,
+        kind: D3D12_QUERY_HEAP_TYPE,
+        count: u32,
+    ) -> Result<ID3D12QueryHeap, RenderError> {
+        let desc = D3D12_QUERY_HEAP_DESC {
+            Type: kind,
+            Count: count,
+            NodeMask: 0,
+        };
+        let mut query_heap: Option<ID3D12QueryHeap> = None;
+        unsafe { self.device.CreateQueryHeap(&desc, &mut query_heap) }?;
+        Ok(query_heap.unwrap())
+    }
+
+    pub fn create_fence(&self) -> Result<ID3D12Fence, RenderError> {
         let fence = unsafe { self.device.CreateFence(0, D3D12_FENCE_FLAG_NONE) }?;
         Ok(fence)
     }
 
-    pub fn map_buffer<T>(&self, id: BufferId) -> Result<NonNull<u8>, DeviceError> {
+    pub fn map_buffer<T>(&self, id: BufferId) -> Result<NonNull<u8>, RenderError> {
         let mut data = ptr::null_mut();
-        let buffer = &self.buffers[id.0];
+        let buffer = self.get_buffer(id);
         unsafe {
             buffer.allocation.resource().Map(0, None, Some(&mut data))?;
             let ptr = NonNull::new(data.cast::<u8>()).unwrap();
@@ -334,27 +667,36 @@ impl Device {
 
     pub fn unmap_buffer(&self, id: BufferId) {
         unsafe {
-            self.buffers[id.0].allocation.resource().Unmap(0, None);
+            self.get_buffer(id).allocation.resource().Unmap(0, None);
         }
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        for image in self.images.drain(..) {
+        for image in self.images.drain(..).flatten() {
             let _ = self.allocator.free_resource(image.allocation);
         }
 
-        for buffer in self.buffers.drain(..) {
+        for buffer in self.buffers.drain(..).flatten() {
             let _ = self.allocator.free_resource(buffer.allocation);
         }
     }
 }
 
-fn get_physical_device(factory: &IDXGIFactory6) -> Result<IDXGIAdapter1, Box<dyn Error>> {
+// Adapters come back ordered by the preference, so the first one that can create a device wins.
+fn get_physical_device(
+    factory: &IDXGIFactory6,
+    power_preference: PowerPreference,
+) -> Result<IDXGIAdapter1, RenderError> {
     for i in 0.. {
-        let physical_device: IDXGIAdapter1 =
-            unsafe { factory.EnumAdapterByGpuPreference(i, DXGI_GPU_PREFERENCE_HIGH_PERFORMANCE) }?;
+        let physical_device: IDXGIAdapter1 = match unsafe {
+            factory.EnumAdapterByGpuPreference(i, power_preference.gpu_preference())
+        } {
+            Ok(physical_device) => physical_device,
+            Err(err) if err.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(err) => return Err(RenderError::DeviceCreation(err)),
+        };
 
         let mut desc = Default::default();
         unsafe { physical_device.GetDesc1(&mut desc) }?;
@@ -377,7 +719,21 @@ fn get_physical_device(factory: &IDXGIFactory6) -> Result<IDXGIAdapter1, Box<dyn
         }
     }
 
-    unreachable!()
+    Err(RenderError::NoAdapter)
+}
+
+fn get_warp_device(factory: &IDXGIFactory6) -> Result<IDXGIAdapter1, RenderError> {
+    let physical_device: IDXGIAdapter1 =
+        unsafe { factory.EnumWarpAdapter() }.map_err(RenderError::DeviceCreation)?;
+    unsafe {
+        D3D12CreateDevice(
+            &physical_device,
+            D3D_FEATURE_LEVEL_11_0,
+            std::ptr::null_mut::<Option<ID3D12Device>>(),
+        )
+    }
+    .map_err(RenderError::DeviceCreation)?;
+    Ok(physical_device)
 }
 
 unsafe extern "system" fn message_callback(
@@ -389,3 +745,41 @@ unsafe extern "system" fn message_callback(
 ) {
     tracing::warn!("{}", description.display());
 }
+
+fn target_flags(format: DXGI_FORMAT) -> D3D12_RESOURCE_FLAGS {
+    if matches!(
+        format,
+        DXGI_FORMAT_D32_FLOAT
+            | DXGI_FORMAT_R32_TYPELESS
+            | DXGI_FORMAT_D32_FLOAT_S8X24_UINT
+            | DXGI_FORMAT_R32G8X24_TYPELESS
+    ) {
+        D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL
+    } else {
+        D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET
+    }
+}
+
+fn image_desc(
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    format: DXGI_FORMAT,
+    flags: D3D12_RESOURCE_FLAGS,
+) -> D3D12_RESOURCE_DESC {
+    D3D12_RESOURCE_DESC {
+        Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+        Alignment: 0,
+        Width: width as u64,
+        Height: height,
+        DepthOrArraySize: 1,
+        MipLevels: mip_levels as u16,
+        Format: format,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+        Flags: flags,
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use sovereign_ecs::SnapshotRegistry;
+use sovereign_math::Aabb;
+
+use crate::{
+    asset::{Assets, Handle},
+    bounds::{BoundsOverride, NeverCull, SkinnedBounds, WorldBounds},
+    camera::Camera,
+    light::{DirectionalLight, Sky},
+    material::{GPUMaterial, Material, MaterialOverride},
+    mesh::{GPUMesh, Mesh, MorphWeights},
+    settings::RenderSettings,
+    shadow::{CastsShadows, ReceivesShadows},
+    stats::RenderStats,
+    texture::Texture,
+    time_of_day::TimeOfDay,
+    transform::{GPUTransform, GlobalTransform, Parent, Transform},
+};
+
+pub struct FrameBudget {
+    pub cpu_budget: Duration,
+    pub gpu_budget: Duration,
+    pub top_passes: usize,
+    pub min_log_interval: Duration,
+    last_log: Option<Instant>,
+    suppressed: u32,
+}
+
+impl FrameBudget {
+    pub fn new(cpu_budget: Duration, gpu_budget: Duration) -> Self {
+        Self {
+            cpu_budget,
+            gpu_budget,
+            top_passes: 3,
+            min_log_interval: Duration::from_secs(1),
+            last_log: None,
+            suppressed: 0,
+        }
+    }
+
+    pub fn check(&mut self, frame_time: Duration, stats: &RenderStats) -> bool {
+        let over_cpu = frame_time > self.cpu_budget;
+        let over_gpu = stats.gpu_wait_time > self.gpu_budget;
+        if !over_cpu && !over_gpu {
+            return false;
+        }
+
+        let now = Instant::now();
+        if let Some(last_log) = self.last_log {
+            if now - last_log < self.min_log_interval {
+                self.suppressed += 1;
+                return true;
+            }
+        }
+        self.last_log = Some(now);
+
+        let mut passes = stats.passes.iter().collect::<Vec<_>>();
+        passes.sort_unstable_by_key(|pass| std::cmp::Reverse(pass.cpu_time));
+        let top_passes = passes
+            .iter()
+            .take(self.top_passes)
+            .map(|pass| {
+                format!(
+                    "{}={:.2}ms",
+                    pass.name,
+                    pass.cpu_time.as_secs_f64() * 1000.0
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        tracing::warn!(
+            frame = stats.frame,
+            frame_ms = frame_time.as_secs_f64() * 1000.0,
+            cpu_budget_ms = self.cpu_budget.as_secs_f64() * 1000.0,
+            render_cpu_ms = stats.cpu_time.as_secs_f64() * 1000.0,
+            gpu_wait_ms = stats.gpu_wait_time.as_secs_f64() * 1000.0,
+            gpu_budget_ms = self.gpu_budget.as_secs_f64() * 1000.0,
+            draw_calls = stats.draw_calls,
+            instances = stats.instances,
+            culled = stats.culled,
+            suppressed = self.suppressed,
+            top_passes = %top_passes,
+            "Frame over budget"
+        );
+        self.suppressed = 0;
+        true
+    }
+}
+
+// Registers the renderer's components for world snapshots. GPU components record where their
+// data lives, so a diff shows entities that keep GPU state after losing what it was built from.
+pub fn register_snapshot_components(registry: &mut SnapshotRegistry) {
+    registry
+        .register_with::<Transform>(|transform| format!("{:?}", transform.transform.w_axis))
+        .register_with::<GlobalTransform>(|transform| format!("{:?}", transform.transform.w_axis))
+        .register_with::<Parent>(|parent| format!("{:?}", parent.0))
+        .register_with::<Camera>(|camera| format!("{:?}", camera.projection))
+        .register_debug::<Handle<Mesh>>()
+        .register_debug::<Handle<Material>>()
+        .register_debug::<Aabb>()
+        .register_debug::<WorldBounds>()
+        .register_debug::<BoundsOverride>()
+        .register::<NeverCull>()
+        .register_with::<SkinnedBounds>(|bounds| format!("{} joints", bounds.joints.len()))
+        .register_debug::<MorphWeights>()
+        .register_debug::<MaterialOverride>()
+        .register_debug::<CastsShadows>()
+        .register_debug::<ReceivesShadows>()
+        .register_debug::<DirectionalLight>()
+        .register_debug::<Sky>()
+        .register_with::<TimeOfDay>(|time_of_day| format!("{:.2}", time_of_day.hour))
+        .register_with::<GPUMesh>(|mesh| format!("{:?}", mesh.vertex_buffer.buffer))
+        .register_with::<GPUMaterial>(|material| format!("offset {}", material.offset))
+        .register_with::<GPUTransform>(|transform| format!("offset {}", transform.offset))
+        .register_with::<Assets<Mesh>>(|assets| format!("{} loaded", assets.len()))
+        .register_with::<Assets<Material>>(|assets| format!("{} loaded", assets.len()))
+        .register_with::<Assets<Texture>>(|assets| format!("{} loaded", assets.len()))
+        .register::<RenderSettings>();
+    #[cfg(feature = "labels")]
+    registry.register_with::<crate::label::Label>(|label| label.text.clone());
+}
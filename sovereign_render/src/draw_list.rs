@@ -0,0 +1,159 @@
+use crate::{material::GPUMaterial, mesh::GPUMesh};
+
+#[derive(Clone, Copy)]
+pub struct DrawItem {
+    pub mesh: GPUMesh,
+    pub material: GPUMaterial,
+    pub transform_offset: u32,
+    pub morph_weight_offset: u32,
+    // False for casters outside the camera's view, kept only for the shadow pass.
+    pub visible: bool,
+    pub casts_shadows: bool,
+    pub receives_shadows: bool,
+}
+
+impl DrawItem {
+    // Draws with the same key share an instanced batch.
+    // The flags are part of the key, so every pass can take or skip whole batches.
+    fn batch_key(&self) -> (usize, usize, usize, u32, bool, bool, bool) {
+        (
+            self.mesh.vertex_buffer.view.0,
+            self.material.buffer.view.0,
+            self.material.offset,
+            self.morph_weight_offset,
+            self.visible,
+            self.casts_shadows,
+            self.receives_shadows,
+        )
+    }
+}
+
+pub fn sort_draws(draws: &mut [DrawItem]) {
+    draws.sort_unstable_by_key(DrawItem::batch_key);
+}
+
+// Expects draws sorted with `sort_draws` so every batch is contiguous.
+pub fn batches(draws: &[DrawItem]) -> impl Iterator<Item = &[DrawItem]> {
+    draws.chunk_by(|a, b| a.batch_key() == b.batch_key())
+}
+
+// Every batch with the offset of its first instance, which is where its draws start in the
+// sorted list and so in the instance buffer.
+pub fn instanced_batches(draws: &[DrawItem]) -> impl Iterator<Item = (u32, &[DrawItem])> {
+    batches(draws).scan(0, |offset, batch| {
+        let first = *offset;
+        *offset += batch.len() as u32;
+        Some((first, batch))
+    })
+}
+
+// The batches the camera sees.
+pub fn visible_batches(draws: &[DrawItem]) -> impl Iterator<Item = (u32, &[DrawItem])> {
+    instanced_batches(draws).filter(|(_offset, batch)| batch[0].visible)
+}
+
+// The batches the shadow pass draws into the cascades.
+pub fn shadow_batches(draws: &[DrawItem]) -> impl Iterator<Item = (u32, &[DrawItem])> {
+    instanced_batches(draws).filter(|(_offset, batch)| batch[0].casts_shadows)
+}
+
+// Nothing to draw, or only the skybox. Without mesh entities the draw list is never built, so
+// none of the buffers that feed it are mapped; with every mesh culled it holds at most casters
+// outside the view, whose shadows would fall on nothing.
+pub fn is_empty_scene(has_meshes: bool, draws: &[DrawItem]) -> bool {
+    !has_meshes || !draws.iter().any(|draw| draw.visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        id::{BufferId, ViewId},
+        BufferView,
+    };
+
+    fn draw(vertex_view: usize, visible: bool, casts_shadows: bool) -> DrawItem {
+        let view = |view| BufferView {
+            buffer: BufferId(view),
+            view: ViewId(view),
+        };
+        DrawItem {
+            mesh: GPUMesh {
+                vertex_buffer: view(vertex_view),
+                index_buffer: BufferId(0),
+                index_buffer_view: Default::default(),
+                index_count: 3,
+                morph_targets: None,
+                morph_target_count: 0,
+            },
+            material: GPUMaterial {
+                buffer: view(0),
+                offset: 0,
+            },
+            transform_offset: 0,
+            morph_weight_offset: u32::MAX,
+            visible,
+            casts_shadows,
+            receives_shadows: true,
+        }
+    }
+
+    #[test]
+    fn world_without_meshes_is_empty() {
+        assert!(is_empty_scene(false, &[]));
+    }
+
+    #[test]
+    fn fully_culled_world_is_empty() {
+        assert!(is_empty_scene(true, &[]));
+    }
+
+    #[test]
+    fn visible_draws_are_not_empty() {
+        assert!(!is_empty_scene(true, &[draw(1, true, true)]));
+    }
+
+    #[test]
+    fn world_with_only_offscreen_casters_is_empty() {
+        assert!(is_empty_scene(true, &[draw(1, false, true)]));
+    }
+
+    #[test]
+    fn empty_draw_list_has_no_batches() {
+        assert_eq!(batches(&[]).count(), 0);
+        assert_eq!(visible_batches(&[]).count(), 0);
+        assert_eq!(shadow_batches(&[]).count(), 0);
+    }
+
+    #[test]
+    fn batches_split_by_mesh_and_shadow_casting() {
+        let mut draws = vec![
+            draw(2, true, true),
+            draw(1, true, true),
+            draw(2, true, true),
+            draw(1, true, false),
+        ];
+        sort_draws(&mut draws);
+        let sizes = batches(&draws).map(<[DrawItem]>::len).collect::<Vec<_>>();
+        assert_eq!(sizes, [1, 1, 2]);
+        assert_eq!(shadow_batches(&draws).count(), 2);
+    }
+
+    #[test]
+    fn filtered_batches_keep_their_instance_offsets() {
+        let mut draws = vec![
+            draw(1, true, false),
+            draw(2, false, true),
+            draw(2, false, true),
+            draw(3, true, true),
+        ];
+        sort_draws(&mut draws);
+        let offsets = |batches: &mut dyn Iterator<Item = (u32, &[DrawItem])>| {
+            batches
+                .map(|(offset, batch)| (offset, batch.len()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(&mut visible_batches(&draws)), [(0, 1), (3, 1)]);
+        assert_eq!(offsets(&mut shadow_batches(&draws)), [(1, 2), (3, 1)]);
+    }
+}
@@ -0,0 +1,67 @@
+use windows::Win32::{
+    Foundation::E_OUTOFMEMORY,
+    Graphics::Dxgi::{DXGI_ERROR_DEVICE_HUNG, DXGI_ERROR_DEVICE_REMOVED, DXGI_ERROR_DEVICE_RESET},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("failed to create device: {0}")]
+    DeviceCreation(windows::core::Error),
+    #[error("no suitable adapter found")]
+    NoAdapter,
+    #[error("unsupported device: {0}")]
+    UnsupportedDevice(String),
+    #[error("device removed: {0}")]
+    DeviceRemoved(windows::core::Error),
+    #[error("out of memory: {0}")]
+    OutOfMemory(windows::core::Error),
+    #[error("allocation failed: {0}")]
+    Allocation(#[from] gpu_allocator::AllocationError),
+    #[error("shader compilation failed: {0}")]
+    ShaderCompilation(#[from] hassle_rs::HassleError),
+    #[error("swapchain error: {0}")]
+    Swapchain(windows::core::Error),
+    #[error("invalid window handle: {0}")]
+    Window(#[from] raw_window_handle::HandleError),
+    #[error("unsupported window handle")]
+    UnsupportedWindow,
+    #[error("graphics API error: {0}")]
+    Graphics(windows::core::Error),
+    #[error("per-frame constants exhausted ({0} bytes requested)")]
+    ConstantsExhausted(usize),
+    #[error("descriptor heap is full ({0} descriptors)")]
+    DescriptorHeapFull(usize),
+    #[error("per-frame descriptors exhausted")]
+    TransientDescriptorsExhausted,
+    #[error("invalid texture: {0}")]
+    InvalidTexture(String),
+    #[error("failed to decode image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl RenderError {
+    pub fn is_device_lost(&self) -> bool {
+        matches!(self, RenderError::DeviceRemoved(_))
+    }
+
+    pub(crate) fn swapchain(err: windows::core::Error) -> Self {
+        match RenderError::from(err) {
+            RenderError::Graphics(err) => RenderError::Swapchain(err),
+            err => err,
+        }
+    }
+}
+
+impl From<windows::core::Error> for RenderError {
+    fn from(err: windows::core::Error) -> Self {
+        match err.code() {
+            DXGI_ERROR_DEVICE_REMOVED | DXGI_ERROR_DEVICE_RESET | DXGI_ERROR_DEVICE_HUNG => {
+                RenderError::DeviceRemoved(err)
+            }
+            E_OUTOFMEMORY => RenderError::OutOfMemory(err),
+            _ => RenderError::Graphics(err),
+        }
+    }
+}
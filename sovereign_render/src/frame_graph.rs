@@ -0,0 +1,162 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use crate::barrier_trace::BarrierTrace;
+
+#[derive(Debug)]
+pub struct GraphPass {
+    pub name: String,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+// The pass that used the resource after another pass wrote it.
+#[derive(Debug, PartialEq)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub resource: String,
+}
+
+// Passes of one frame in submission order with the resources each declared, built from a barrier
+// trace. Every use of a resource depends on the last pass that wrote it.
+#[derive(Debug, Default)]
+pub struct FrameGraph {
+    pub passes: Vec<GraphPass>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl FrameGraph {
+    pub fn from_trace(trace: &BarrierTrace) -> Self {
+        let mut graph = Self::default();
+        let mut last_writer = HashMap::new();
+        // Segments between passes only restore resting states and declare nothing.
+        for pass in trace.passes.iter().filter(|pass| !pass.uses.is_empty()) {
+            let idx = graph.passes.len();
+            let mut graph_pass = GraphPass {
+                name: pass.name.clone(),
+                reads: Vec::new(),
+                writes: Vec::new(),
+            };
+            for resource_use in &pass.uses {
+                let resource = &resource_use.resource;
+                if let Some(&writer) = last_writer.get(resource) {
+                    let edge = GraphEdge {
+                        from: writer,
+                        to: idx,
+                        resource: resource.clone(),
+                    };
+                    if writer != idx && !graph.edges.contains(&edge) {
+                        graph.edges.push(edge);
+                    }
+                }
+                let list = if resource_use.is_write() {
+                    &mut graph_pass.writes
+                } else {
+                    &mut graph_pass.reads
+                };
+                if !list.contains(resource) {
+                    list.push(resource.clone());
+                }
+            }
+            for resource in &graph_pass.writes {
+                last_writer.insert(resource.clone(), idx);
+            }
+            graph.passes.push(graph_pass);
+        }
+        graph
+    }
+
+    // Graphviz source with one node per pass, laid out left to right in submission order.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frame {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (idx, pass) in self.passes.iter().enumerate() {
+            let _ = writeln!(dot, "    p{} [label={}];", idx, quote(&pass.name));
+        }
+        // Keeps passes without a dependency between them in order.
+        for idx in 1..self.passes.len() {
+            let _ = writeln!(dot, "    p{} -> p{} [style=invis];", idx - 1, idx);
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                dot,
+                "    p{} -> p{} [label={}];",
+                edge.from,
+                edge.to,
+                quote(&edge.resource)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // One pass or edge per line, so a change in pass order shows up as a small diff.
+    pub fn to_json(&self) -> String {
+        let list = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| quote(name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let passes = self
+            .passes
+            .iter()
+            .map(|pass| {
+                format!(
+                    "    {{\"name\": {}, \"reads\": [{}], \"writes\": [{}]}}",
+                    quote(&pass.name),
+                    list(&pass.reads),
+                    list(&pass.writes)
+                )
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .edges
+            .iter()
+            .map(|edge| {
+                format!(
+                    "    {{\"from\": {}, \"to\": {}, \"resource\": {}}}",
+                    edge.from,
+                    edge.to,
+                    quote(&edge.resource)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\n  \"passes\": [\n{}\n  ],\n  \"edges\": [\n{}\n  ]\n}}\n",
+            passes.join(",\n"),
+            edges.join(",\n")
+        )
+    }
+
+    // Writes JSON for a .json path and Graphviz source for anything else.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let contents = if is_json {
+            self.to_json()
+        } else {
+            self.to_dot()
+        };
+        std::fs::write(path, contents)
+    }
+}
+
+// A double-quoted string that is valid in both JSON and DOT.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,66 @@
+use glam::{Mat4, Vec2, Vec3};
+
+const AXIS_SCALE: f32 = 0.8;
+const PICK_RADIUS: f32 = 12.0;
+const AXES: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::Y,
+    Vec3::Z,
+    Vec3::NEG_X,
+    Vec3::NEG_Y,
+    Vec3::NEG_Z,
+];
+
+#[repr(C)]
+pub(crate) struct GizmoResources {
+    pub view_buffer_index: u32,
+}
+
+pub struct OrientationGizmo {
+    pub enabled: bool,
+    pub size: u32,
+    pub margin: u32,
+}
+
+impl Default for OrientationGizmo {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 96,
+            margin: 16,
+        }
+    }
+}
+
+impl OrientationGizmo {
+    pub fn viewport(&self, width: u32, height: u32) -> (u32, u32, u32) {
+        let size = self.size.min(width).min(height);
+        let x = width.saturating_sub(size + self.margin);
+        (x, self.margin.min(height - size), size)
+    }
+
+    pub fn pick(&self, cursor: Vec2, view: &Mat4, width: u32, height: u32) -> Option<Vec3> {
+        if !self.enabled {
+            return None;
+        }
+        let (x, y, size) = self.viewport(width, height);
+        let origin = Vec2::new(x as f32, y as f32);
+        let size = size as f32;
+
+        AXES.iter()
+            .map(|axis| {
+                let projected = view.transform_vector3(*axis) * AXIS_SCALE;
+                let tip =
+                    origin + Vec2::new(0.5 + projected.x * 0.5, 0.5 - projected.y * 0.5) * size;
+                (*axis, tip.distance(cursor))
+            })
+            .filter(|(_axis, distance)| *distance <= PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(axis, _distance)| axis)
+    }
+}
+
+pub fn axis_aligned_transform(position: Vec3, axis: Vec3) -> Mat4 {
+    let up = if axis.y.abs() > 0.5 { Vec3::Z } else { Vec3::Y };
+    Mat4::look_to_lh(position, -axis, up).inverse()
+}
@@ -0,0 +1,169 @@
+use gpu_allocator::MemoryLocation;
+use std::{marker::PhantomData, ptr::NonNull};
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::DXGI_FORMAT_UNKNOWN};
+
+use crate::{
+    descriptor::DescriptorHeap, device::Device, error::RenderError, id::BufferId, BufferView,
+};
+
+// A CPU-writable structured buffer that is reallocated with spare room once it runs out of
+// elements. Growing copies the old contents and creates a new SRV, so anything that kept the old
+// view has to pick up the new one.
+pub struct GrowableGpuBuffer<T> {
+    name: &'static str,
+    view: BufferView,
+    capacity: usize,
+    state: D3D12_RESOURCE_STATES,
+    _element: PhantomData<T>,
+}
+
+impl<T: Copy> GrowableGpuBuffer<T> {
+    pub fn new(
+        device: &mut Device,
+        heap: &mut DescriptorHeap,
+        name: &'static str,
+        capacity: usize,
+        state: D3D12_RESOURCE_STATES,
+    ) -> Result<Self, RenderError> {
+        let capacity = capacity.max(1);
+        Ok(Self {
+            name,
+            view: create_buffer::<T>(device, heap, name, capacity, state)?,
+            capacity,
+            state,
+            _element: PhantomData,
+        })
+    }
+
+    pub fn view(&self) -> BufferView {
+        self.view
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Makes room for `len` elements and returns whether the buffer moved. The GPU must be done
+    // with the buffer, since the old one is destroyed right away.
+    pub fn reserve(
+        &mut self,
+        device: &mut Device,
+        heap: &mut DescriptorHeap,
+        len: usize,
+    ) -> Result<bool, RenderError> {
+        if len <= self.capacity {
+            return Ok(false);
+        }
+        let capacity = len.next_power_of_two();
+        let view = create_buffer::<T>(device, heap, self.name, capacity, self.state)?;
+        let src = device.map_buffer::<T>(self.view.buffer)?;
+        let dst = device.map_buffer::<T>(view.buffer)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                src.as_ptr(),
+                dst.as_ptr(),
+                self.capacity * std::mem::size_of::<T>(),
+            )
+        };
+        device.unmap_buffer(view.buffer);
+        device.unmap_buffer(self.view.buffer);
+        device.destroy_buffer(self.view.buffer);
+        heap.free(self.view.view);
+        tracing::debug!(buffer = self.name, capacity, "Grew GPU buffer");
+
+        self.view = view;
+        self.capacity = capacity;
+        Ok(true)
+    }
+
+    pub fn map<'a>(&self, device: &'a Device) -> Result<MappedBuffer<'a, T>, RenderError> {
+        Ok(MappedBuffer {
+            device,
+            buffer: self.view.buffer,
+            data: device.map_buffer::<T>(self.view.buffer)?,
+            capacity: self.capacity,
+            _element: PhantomData,
+        })
+    }
+}
+
+// Unmaps on drop.
+pub struct MappedBuffer<'a, T> {
+    device: &'a Device,
+    buffer: BufferId,
+    data: NonNull<u8>,
+    capacity: usize,
+    _element: PhantomData<T>,
+}
+
+impl<T: Copy> MappedBuffer<'_, T> {
+    pub fn write(&mut self, idx: usize, value: &T) {
+        assert!(
+            idx < self.capacity,
+            "write past the end of a GPU buffer; reserve first"
+        );
+        unsafe {
+            self.data
+                .as_ptr()
+                .cast::<T>()
+                .add(idx)
+                .write_unaligned(*value)
+        };
+    }
+
+    // Copies `values` in from the start of the buffer.
+    pub fn write_slice(&mut self, values: &[T]) {
+        assert!(
+            values.len() <= self.capacity,
+            "write past the end of a GPU buffer; reserve first"
+        );
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                values.as_ptr().cast::<u8>(),
+                self.data.as_ptr(),
+                std::mem::size_of_val(values),
+            )
+        };
+    }
+}
+
+impl<T> Drop for MappedBuffer<'_, T> {
+    fn drop(&mut self) {
+        self.device.unmap_buffer(self.buffer);
+    }
+}
+
+fn create_buffer<T>(
+    device: &mut Device,
+    heap: &mut DescriptorHeap,
+    name: &str,
+    capacity: usize,
+    state: D3D12_RESOURCE_STATES,
+) -> Result<BufferView, RenderError> {
+    let buffer = device.create_buffer(
+        name,
+        (capacity * std::mem::size_of::<T>()) as u64,
+        DXGI_FORMAT_UNKNOWN,
+        D3D12_RESOURCE_FLAG_NONE,
+        state,
+        MemoryLocation::CpuToGpu,
+    )?;
+    let view = heap.create_srv(
+        &format!("{} SRV", name),
+        device.get_buffer(buffer).allocation.resource(),
+        &D3D12_SHADER_RESOURCE_VIEW_DESC {
+            Format: DXGI_FORMAT_UNKNOWN,
+            ViewDimension: D3D12_SRV_DIMENSION_BUFFER,
+            Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+            Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                Buffer: D3D12_BUFFER_SRV {
+                    FirstElement: 0,
+                    NumElements: capacity as u32,
+                    StructureByteStride: std::mem::size_of::<T>() as u32,
+                    Flags: D3D12_BUFFER_SRV_FLAG_NONE,
+                },
+            },
+        },
+    )?;
+    Ok(BufferView { buffer, view })
+}
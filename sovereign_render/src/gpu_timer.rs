@@ -0,0 +1,164 @@
+use std::{cell::RefCell, ptr::NonNull, time::Duration};
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::DXGI_FORMAT_UNKNOWN};
+
+use crate::{
+    device::Device, error::RenderError, id::BufferId, queue::Queue, stats::GpuPassTiming,
+    MemoryLocation,
+};
+
+const MAX_QUERIES: u32 = 64;
+
+struct GpuScope {
+    name: &'static str,
+    begin: u32,
+    end: Option<u32>,
+}
+
+// Breadcrumbs mirror the timestamp queries: one u32 per query, set by the GPU as it passes the
+// matching scope boundary. They live in a mapped readback buffer the CPU can still read after the
+// device is removed, which tells which passes the GPU got through.
+pub struct GpuTimer {
+    query_heap: ID3D12QueryHeap,
+    readback: BufferId,
+    breadcrumb_address: u64,
+    breadcrumbs: NonNull<u32>,
+    frequency: u64,
+    pub markers: bool,
+    scopes: RefCell<Vec<GpuScope>>,
+    open: RefCell<Vec<usize>>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &mut Device, queue: &Queue, markers: bool) -> Result<Self, RenderError> {
+        let query_heap = device.create_query_heap(D3D12_QUERY_HEAP_TYPE_TIMESTAMP, MAX_QUERIES)?;
+        let readback = device.create_buffer(
+            "Timestamp Readback Buffer",
+            std::mem::size_of::<u64>() as u64 * MAX_QUERIES as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            MemoryLocation::GpuToCpu,
+        )?;
+        let breadcrumb_buffer = device.create_buffer(
+            "Breadcrumb Buffer",
+            std::mem::size_of::<u32>() as u64 * MAX_QUERIES as u64,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            MemoryLocation::GpuToCpu,
+        )?;
+        let breadcrumb_address = unsafe {
+            device
+                .get_buffer(breadcrumb_buffer)
+                .allocation
+                .resource()
+                .GetGPUVirtualAddress()
+        };
+        let breadcrumbs = device.map_buffer::<u32>(breadcrumb_buffer)?.cast::<u32>();
+        Ok(Self {
+            query_heap,
+            readback,
+            breadcrumb_address,
+            breadcrumbs,
+            frequency: queue.timestamp_frequency()?,
+            markers,
+            scopes: RefCell::new(Vec::new()),
+            open: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn query_heap(&self) -> &ID3D12QueryHeap {
+        &self.query_heap
+    }
+
+    pub fn readback(&self) -> BufferId {
+        self.readback
+    }
+
+    // The previous frame has finished on the GPU by now, so its breadcrumbs can be cleared.
+    pub fn reset(&self) {
+        self.scopes.borrow_mut().clear();
+        self.open.borrow_mut().clear();
+        unsafe { std::ptr::write_bytes(self.breadcrumbs.as_ptr(), 0, MAX_QUERIES as usize) };
+    }
+
+    pub(crate) fn breadcrumb_address(&self, query: u32) -> u64 {
+        self.breadcrumb_address + (query as usize * std::mem::size_of::<u32>()) as u64
+    }
+
+    // The last scope the GPU finished and the scopes it had started but not finished, innermost
+    // last. Only meaningful once the GPU has stopped, such as after a device removal.
+    pub fn breadcrumbs(&self) -> (Option<&'static str>, Vec<&'static str>) {
+        let reached = |query: u32| unsafe { *self.breadcrumbs.as_ptr().add(query as usize) != 0 };
+        let scopes = self.scopes.borrow();
+        let last_completed = scopes
+            .iter()
+            .filter(|scope| scope.end.is_some_and(reached))
+            .max_by_key(|scope| scope.end)
+            .map(|scope| scope.name);
+        let in_flight = scopes
+            .iter()
+            .filter(|scope| reached(scope.begin) && !scope.end.is_some_and(reached))
+            .map(|scope| scope.name)
+            .collect();
+        (last_completed, in_flight)
+    }
+
+    pub fn query_count(&self) -> u32 {
+        self.scopes
+            .borrow()
+            .iter()
+            .map(|scope| scope.begin.max(scope.end.unwrap_or(0)) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn begin_scope(&self, name: &'static str) -> Option<u32> {
+        let mut scopes = self.scopes.borrow_mut();
+        let begin = scopes.len() as u32 * 2;
+        if begin + 1 >= MAX_QUERIES {
+            return None;
+        }
+        self.open.borrow_mut().push(scopes.len());
+        scopes.push(GpuScope {
+            name,
+            begin,
+            end: None,
+        });
+        Some(begin)
+    }
+
+    pub(crate) fn end_scope(&self) -> Option<u32> {
+        let idx = self.open.borrow_mut().pop()?;
+        let mut scopes = self.scopes.borrow_mut();
+        let scope = &mut scopes[idx];
+        let end = scope.begin + 1;
+        scope.end = Some(end);
+        Some(end)
+    }
+
+    pub fn read(&self, device: &Device) -> Result<Vec<GpuPassTiming>, RenderError> {
+        let count = self.query_count() as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let data = device.map_buffer::<u64>(self.readback)?;
+        let timestamps = unsafe { std::slice::from_raw_parts(data.as_ptr().cast::<u64>(), count) };
+        let timings = self
+            .scopes
+            .borrow()
+            .iter()
+            .filter_map(|scope| {
+                let begin = timestamps[scope.begin as usize];
+                let end = timestamps[scope.end? as usize];
+                let ticks = end.saturating_sub(begin);
+                Some(GpuPassTiming {
+                    name: scope.name,
+                    gpu_time: Duration::from_secs_f64(ticks as f64 / self.frequency as f64),
+                })
+            })
+            .collect();
+        device.unmap_buffer(self.readback);
+        Ok(timings)
+    }
+}
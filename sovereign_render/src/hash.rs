@@ -0,0 +1,247 @@
+use bytemuck::{bytes_of, cast_slice};
+use windows::Win32::Graphics::Direct3D12::D3D12_SAMPLER_DESC;
+
+use crate::{
+    asset::{texture::TextureData, Assets},
+    id::SamplerId,
+    material::Material,
+    mesh::Mesh,
+    sampler::sampler_key,
+    texture::Texture,
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// FNV-1a, so hashes stay stable across runs, platforms and compiler versions and
+// can be persisted as cache keys, unlike std's DefaultHasher.
+#[derive(Clone, Copy, Debug)]
+pub struct ContentHasher {
+    state: u64,
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Self {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+pub trait ContentHash {
+    fn content_hash(&self) -> u64;
+}
+
+impl ContentHash for Mesh {
+    fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        // Staged vertices were hashed as they were written, the same way as vertices on the CPU.
+        let vertex_hash = match &self.staged_vertices {
+            Some(staged) => staged.hash,
+            None => hash_bytes(cast_slice(&self.vertices)),
+        };
+        hasher.write_u64(self.vertex_count() as u64);
+        hasher.write_u64(vertex_hash);
+        hasher.write_u64(self.indices.len() as u64);
+        hasher.write(self.indices.as_bytes());
+        hasher.write_u64(self.morph_targets.len() as u64);
+        for target in &self.morph_targets {
+            hasher.write(cast_slice(target));
+        }
+        hasher.finish()
+    }
+}
+
+// Format is part of the hash so one image used as both sRGB and linear stays two textures.
+impl ContentHash for TextureData {
+    fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.write_u64(self.width as u64);
+        hasher.write_u64(self.height as u64);
+        hasher.write_u64(self.format.0 as u64);
+        hasher.write_u64(self.mips.len() as u64);
+        for mip in &self.mips {
+            hasher.write(mip);
+        }
+        hasher.finish()
+    }
+}
+
+// Texture and sampler slots depend on load order, so a material hashes the texture content and
+// sampler description behind them instead. Pass `Renderer::sampler_desc` for sampler_desc.
+pub fn material_hash(
+    material: &Material,
+    textures: &Assets<Texture>,
+    sampler_desc: impl Fn(SamplerId) -> Option<D3D12_SAMPLER_DESC>,
+) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.write(bytes_of(&material.uniform));
+    for texture in material.textures() {
+        match texture {
+            Some(texture) => {
+                hasher.write_u64(1);
+                let content = textures.get(&texture.texture);
+                hasher.write_u64(content.map_or(0, |texture| texture.content_hash));
+                let desc = sampler_desc(texture.sampler);
+                hasher.write_u64(desc.map_or(0, |desc| sampler_key(&desc)));
+            }
+            None => hasher.write_u64(0),
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use glam::{Vec2, Vec4};
+    use windows::Win32::Graphics::Dxgi::Common::{
+        DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+    };
+
+    use super::*;
+    use crate::{
+        asset::Handle,
+        id::{BufferId, ImageId, ViewId},
+        material::{MaterialTexture, MaterialUniform},
+        mesh::{Indices, StagedVertices, Vertex},
+        sampler::{LINEAR_WRAP, POINT_CLAMP},
+    };
+
+    fn mesh(x: f32) -> Mesh {
+        let vertex = |position: Vec4| Vertex {
+            position,
+            normal: Vec4::Z,
+            color: Vec4::ONE,
+            uv: Vec2::ZERO,
+            pad: Vec2::ZERO,
+        };
+        Mesh {
+            vertices: vec![vertex(Vec4::W), vertex(Vec4::new(x, 0.0, 0.0, 1.0))],
+            staged_vertices: None,
+            indices: Indices::from_u32(vec![0, 1, 0]),
+            morph_targets: Vec::new(),
+        }
+    }
+
+    fn texture(view: usize, content_hash: u64) -> Texture {
+        Texture {
+            image: ImageId(view),
+            view: ViewId(view),
+            width: 1,
+            height: 1,
+            mip_levels: 1,
+            format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            content_hash,
+        }
+    }
+
+    fn material(texture: &Handle<Texture>, sampler: SamplerId) -> Material {
+        Material {
+            uniform: MaterialUniform::zeroed(),
+            base_color_texture: Some(MaterialTexture {
+                texture: texture.clone(),
+                sampler,
+            }),
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+        }
+    }
+
+    fn samplers(sampler: SamplerId) -> Option<D3D12_SAMPLER_DESC> {
+        match sampler.0 {
+            0 | 1 => Some(LINEAR_WRAP),
+            _ => Some(POINT_CLAMP),
+        }
+    }
+
+    #[test]
+    fn equal_meshes_hash_equal() {
+        assert_eq!(mesh(1.0).content_hash(), mesh(1.0).content_hash());
+        assert_ne!(mesh(1.0).content_hash(), mesh(2.0).content_hash());
+    }
+
+    #[test]
+    fn staged_meshes_hash_like_their_vertices() {
+        let cpu = mesh(1.0);
+        let staged = Mesh {
+            vertices: Vec::new(),
+            staged_vertices: Some(StagedVertices {
+                buffer: BufferId(0),
+                len: cpu.vertices.len(),
+                hash: hash_bytes(cast_slice(&cpu.vertices)),
+            }),
+            ..mesh(1.0)
+        };
+        assert_eq!(staged.content_hash(), cpu.content_hash());
+
+        // Recycled buffers don't make different vertices collide.
+        let other = Mesh {
+            staged_vertices: Some(StagedVertices {
+                buffer: BufferId(0),
+                len: cpu.vertices.len(),
+                hash: hash_bytes(cast_slice(&mesh(2.0).vertices)),
+            }),
+            ..mesh(1.0)
+        };
+        assert_ne!(other.content_hash(), cpu.content_hash());
+    }
+
+    #[test]
+    fn texture_format_is_part_of_the_hash() {
+        let linear = TextureData::rgba8(1, 1, vec![255; 4]);
+        let srgb = TextureData {
+            format: DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+            ..TextureData::rgba8(1, 1, vec![255; 4])
+        };
+        assert_eq!(
+            linear.content_hash(),
+            TextureData::rgba8(1, 1, vec![255; 4]).content_hash()
+        );
+        assert_ne!(linear.content_hash(), srgb.content_hash());
+    }
+
+    #[test]
+    fn materials_hash_texture_content_not_slots() {
+        let mut textures = Assets::new();
+        let first = textures.push(texture(0, 7));
+        // Same content in another slot, as after loading in a different order.
+        let copy = textures.push(texture(1, 7));
+        let other = textures.push(texture(2, 8));
+
+        let hash =
+            |texture, sampler| material_hash(&material(texture, sampler), &textures, samplers);
+        assert_eq!(hash(&first, SamplerId(0)), hash(&copy, SamplerId(1)));
+        assert_ne!(hash(&first, SamplerId(0)), hash(&other, SamplerId(0)));
+        assert_ne!(hash(&first, SamplerId(0)), hash(&first, SamplerId(2)));
+    }
+}
@@ -0,0 +1,179 @@
+use bytemuck::{Pod, Zeroable};
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    cubemap::{Cubemap, CubemapResources},
+    error::RenderError,
+    id::{ImageId, ViewId},
+    Renderer, INVALID_INDEX,
+};
+
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 256;
+const PREFILTERED_MIP_LEVELS: u32 = 6;
+const BRDF_LUT_SIZE: u32 = 256;
+const BRDF_LUT_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16_FLOAT;
+const BRDF_LUT_SAMPLES: u32 = 1024;
+const THREAD_GROUP_SIZE: u32 = 8;
+
+// The cubemap drawn behind the scene, and the diffuse and specular lighting derived from it.
+#[derive(Debug)]
+pub struct Environment {
+    pub skybox: Cubemap,
+    pub irradiance: Cubemap,
+    pub prefiltered: Cubemap,
+    // Scales the lighting, not the skybox.
+    pub intensity: f32,
+}
+
+// Where the mesh shader finds the environment lighting; the views are INVALID_INDEX without an
+// environment.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct EnvironmentUniform {
+    pub irradiance_index: u32,
+    pub prefiltered_index: u32,
+    pub brdf_lut_index: u32,
+    pub sampler_index: u32,
+    // Mip of the prefiltered map that holds fully rough reflections.
+    pub max_mip: f32,
+    pub intensity: f32,
+    pub pad: [f32; 2],
+}
+
+impl EnvironmentUniform {
+    pub const NONE: Self = Self {
+        irradiance_index: INVALID_INDEX,
+        prefiltered_index: INVALID_INDEX,
+        brdf_lut_index: INVALID_INDEX,
+        sampler_index: INVALID_INDEX,
+        max_mip: 0.0,
+        intensity: 0.0,
+        pad: [0.0; 2],
+    };
+}
+
+impl Renderer {
+    // Draws the cubemap behind the scene and lights materials with it, replacing and destroying
+    // any previous environment.
+    pub fn set_environment(&mut self, skybox: Cubemap) -> Result<(), RenderError> {
+        let irradiance =
+            self.create_irradiance_map("Environment Irradiance", &skybox, IRRADIANCE_SIZE)?;
+        let prefiltered = self.create_prefiltered_map(
+            "Environment Prefiltered",
+            &skybox,
+            PREFILTERED_SIZE,
+            PREFILTERED_MIP_LEVELS,
+        )?;
+        // The split sum lookup table is the same for every environment, so it is made once.
+        if self.brdf_lut.is_none() {
+            self.brdf_lut = Some(self.create_brdf_lut()?);
+        }
+        self.clear_environment();
+        self.environment = Some(Environment {
+            skybox,
+            irradiance,
+            prefiltered,
+            intensity: 1.0,
+        });
+        Ok(())
+    }
+
+    pub fn clear_environment(&mut self) {
+        if let Some(environment) = self.environment.take() {
+            self.destroy_cubemap(environment.skybox);
+            self.destroy_cubemap(environment.irradiance);
+            self.destroy_cubemap(environment.prefiltered);
+        }
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    pub fn environment_mut(&mut self) -> Option<&mut Environment> {
+        self.environment.as_mut()
+    }
+
+    pub(crate) fn environment_uniform(&self) -> EnvironmentUniform {
+        let (Some(environment), Some((_image, brdf_lut))) = (&self.environment, &self.brdf_lut)
+        else {
+            return EnvironmentUniform::NONE;
+        };
+        EnvironmentUniform {
+            irradiance_index: environment.irradiance.view.0 as u32,
+            prefiltered_index: environment.prefiltered.view.0 as u32,
+            brdf_lut_index: brdf_lut.0 as u32,
+            sampler_index: self.environment_sampler.0 as u32,
+            max_mip: (environment.prefiltered.mip_levels - 1) as f32,
+            intensity: environment.intensity,
+            pad: [0.0; 2],
+        }
+    }
+
+    fn create_brdf_lut(&mut self) -> Result<(ImageId, ViewId), RenderError> {
+        let image = self.device.create_image(
+            "BRDF LUT",
+            BRDF_LUT_SIZE,
+            BRDF_LUT_SIZE,
+            BRDF_LUT_FORMAT,
+            D3D12_RESOURCE_FLAG_ALLOW_UNORDERED_ACCESS,
+            D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+        )?;
+        let resource = self.device.get_image(image).allocation.resource().clone();
+        let uav = self.cbv_heap.create_uav(
+            "BRDF LUT UAV",
+            &resource,
+            &D3D12_UNORDERED_ACCESS_VIEW_DESC {
+                Format: BRDF_LUT_FORMAT,
+                ViewDimension: D3D12_UAV_DIMENSION_TEXTURE2D,
+                Anonymous: D3D12_UNORDERED_ACCESS_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_UAV {
+                        MipSlice: 0,
+                        PlaneSlice: 0,
+                    },
+                },
+            },
+        )?;
+        self.immediate_submit(|r, encoder| {
+            encoder.set_descriptor_heaps(&[Some(r.cbv_heap.get()), Some(r.sampler_heap.get())]);
+            encoder.set_compute_root_signature(&r.root_signature);
+            encoder.set_pipeline(&r.cubemap_pipelines.brdf_lut);
+            encoder.set_compute_root_constants(&CubemapResources {
+                source_index: INVALID_INDEX,
+                target_index: uav.0 as u32,
+                sampler_index: INVALID_INDEX,
+                face_size: BRDF_LUT_SIZE,
+                roughness: 0.0,
+                sample_count: BRDF_LUT_SAMPLES,
+            });
+            let groups = BRDF_LUT_SIZE.div_ceil(THREAD_GROUP_SIZE);
+            encoder.dispatch(groups, groups, 1);
+            encoder.transition_image(
+                &resource,
+                D3D12_RESOURCE_STATE_UNORDERED_ACCESS,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+        })?;
+        self.cbv_heap.free(uav);
+
+        let view = self.cbv_heap.create_srv(
+            "BRDF LUT SRV",
+            &resource,
+            &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: BRDF_LUT_FORMAT,
+                ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                        PlaneSlice: 0,
+                        ResourceMinLODClamp: 0.0,
+                    },
+                },
+            },
+        )?;
+        Ok((image, view))
+    }
+}
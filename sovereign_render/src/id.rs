@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct SamplerId(pub usize);
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
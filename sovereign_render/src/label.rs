@@ -0,0 +1,141 @@
+use glam::{Vec2, Vec3};
+use sovereign_ecs::{Entity, World};
+use sovereign_math::Color;
+
+use crate::{
+    camera::Camera, light::DirectionalLight, settings::UiViewport, text::GLYPH_SIZE,
+    transform::GlobalTransform,
+};
+
+// Drawn centered on a light's anchor in place of a marker.
+const LIGHT_ICON: &str = "(*)";
+// Lights without a transform are placed this far along their direction from the camera.
+const LIGHT_ICON_DISTANCE: f32 = 10.0;
+// Gap between a marker and its text, in pixels.
+const TEXT_GAP: f32 = 4.0;
+
+pub struct Label {
+    pub text: String,
+    pub color: Color,
+    pub offset: Vec3,
+}
+
+impl Label {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: Color::WHITE,
+            offset: Vec3::ZERO,
+        }
+    }
+}
+
+pub struct LabelOverlay {
+    pub enabled: bool,
+    pub marker_size: u32,
+    pub light_icons: bool,
+}
+
+impl Default for LabelOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            marker_size: 6,
+            light_icons: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScreenLabel {
+    pub entity: Entity,
+    pub text: String,
+    pub position: Vec2,
+    pub color: Color,
+    // Labels get a marker with their text to its right; light icons are text centered on the
+    // anchor.
+    pub marker: bool,
+}
+
+impl ScreenLabel {
+    // Top left corner of the text in UI units, given the marker size in pixels.
+    pub(crate) fn text_origin(&self, marker_size: f32, viewport: &UiViewport) -> Vec2 {
+        let text_size = Vec2::new(self.text.chars().count() as f32, 1.0) * GLYPH_SIZE;
+        let anchor = (self.position - viewport.offset) / viewport.scale;
+        if self.marker {
+            let gap = (marker_size * 0.5 + TEXT_GAP) / viewport.scale;
+            Vec2::new(anchor.x + gap, anchor.y - text_size.y * 0.5)
+        } else {
+            anchor - text_size * 0.5
+        }
+    }
+}
+
+#[repr(C)]
+pub(crate) struct MarkerResources {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub color: u32,
+}
+
+impl MarkerResources {
+    pub fn new(label: &ScreenLabel, size: f32, viewport: Vec2) -> Self {
+        let half_size = Vec2::splat((size * 0.5).round().max(1.0));
+        let to_ndc = |pixel: Vec2| Vec2::new(pixel.x, viewport.y - pixel.y) / viewport * 2.0 - 1.0;
+        Self {
+            min: to_ndc(label.position - half_size),
+            max: to_ndc(label.position + half_size),
+            color: u32::from_le_bytes(label.color.to_srgba_u8()),
+        }
+    }
+}
+
+pub fn project_labels(world: &World, viewport: Vec2, light_icons: bool) -> Vec<ScreenLabel> {
+    let mut camera_query = world.query::<(&Camera, &GlobalTransform)>();
+    let Some((_entity, (camera, camera_transform))) = camera_query.iter().next() else {
+        return Vec::new();
+    };
+    let to_screen = |anchor: Vec3| {
+        let position = camera.world_to_viewport(&camera_transform.transform, viewport, anchor)?;
+        let on_screen = position.cmpge(Vec2::ZERO).all() && position.cmplt(viewport).all();
+        on_screen.then_some(position)
+    };
+
+    let mut labels = world
+        .query::<(&Label, &GlobalTransform)>()
+        .iter()
+        .filter_map(|(entity, (label, transform))| {
+            let position = to_screen(transform.transform.transform_point3(label.offset))?;
+            Some(ScreenLabel {
+                entity,
+                text: label.text.clone(),
+                position,
+                color: label.color,
+                marker: true,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if light_icons {
+        let camera_position = camera_transform.transform.w_axis.truncate();
+        labels.extend(
+            world
+                .query::<(&DirectionalLight, Option<&GlobalTransform>)>()
+                .iter()
+                .filter_map(|(entity, (light, transform))| {
+                    let anchor = match transform {
+                        Some(transform) => transform.transform.w_axis.truncate(),
+                        None => camera_position + light.direction.normalize() * LIGHT_ICON_DISTANCE,
+                    };
+                    Some(ScreenLabel {
+                        entity,
+                        text: LIGHT_ICON.to_owned(),
+                        position: to_screen(anchor)?,
+                        color: light.color,
+                        marker: false,
+                    })
+                }),
+        );
+    }
+    labels
+}
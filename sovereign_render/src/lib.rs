@@ -1,31 +1,126 @@
+#[cfg(feature = "ambient-occlusion")]
+mod ao;
 pub mod asset;
+pub mod barrier_trace;
+mod bloom;
+pub mod bounds;
 pub mod camera;
+pub mod capabilities;
 mod command_encoder;
+mod constant_ring;
+pub mod cubemap;
+pub mod debug_draw;
+#[cfg(feature = "debug-ui")]
+pub mod debug_text;
+pub mod desc;
 mod descriptor;
 mod device;
+pub mod diagnostics;
+pub mod draw_list;
+mod error;
+pub mod frame_graph;
+pub mod gizmo;
+mod gpu_buffer;
+mod gpu_timer;
+pub mod hash;
+pub mod ibl;
 pub mod id;
+#[cfg(feature = "labels")]
+pub mod label;
+pub mod light;
 pub mod material;
 pub mod mesh;
+mod picking;
 mod queue;
+pub mod readback;
+pub mod sampler;
+pub mod settings;
+pub mod shadow;
+mod skybox;
+pub mod stats;
+pub mod text;
+pub mod texture;
+pub mod time_of_day;
+mod tonemap;
+pub mod tracker;
 pub mod transform;
+#[cfg(feature = "debug-ui")]
+pub mod ui;
+mod upload_adapter;
+mod upload_queue;
 
-use asset::{Assets, Handle};
-use camera::{Camera, ViewUniform};
-use command_encoder::CommandEncoder;
-use descriptor::DescriptorHeap;
-use device::Device;
+#[cfg(feature = "ambient-occlusion")]
+use ao::{AoPipelines, AoTargets};
+use asset::{
+    texture::{load_dds, load_ktx2, TextureData},
+    Assets, Handle,
+};
+use barrier_trace::{BarrierRecord, BarrierTrace, PassTrace, ResourceUse};
+use bloom::{BloomPipelines, BloomTargets};
+use bounds::{NeverCull, WorldBounds};
+use camera::{Camera, Projection, ViewUniform};
+use command_encoder::{CommandEncoder, TracedPasses};
+use constant_ring::{ConstantRing, CONSTANT_ALIGNMENT};
+use cubemap::CubemapPipelines;
+use debug_draw::{DebugDraw, DebugDrawPass};
+#[cfg(feature = "debug-ui")]
+use debug_text::{DebugText, DebugTextPass};
+use desc::{DepthFormat, RendererDesc};
+use descriptor::{DescriptorHeap, TargetView};
+use device::{Device, SubresourceFootprint};
+use draw_list::{is_empty_scene, sort_draws, visible_batches, DrawItem};
+pub use error::RenderError;
+use frame_graph::FrameGraph;
+use gizmo::{GizmoResources, OrientationGizmo};
+use glam::{Mat4, Vec2};
+use gpu_buffer::GrowableGpuBuffer;
+use gpu_timer::GpuTimer;
+use hash::{ContentHash, ContentHasher};
 use hassle_rs::{compile_hlsl, fake_sign_dxil_in_place};
-use id::{BufferId, ImageId, ViewId};
-use material::{GPUMaterial, Material, MaterialUniform};
-use mesh::{GPUMesh, Mesh, Vertex};
+use ibl::Environment;
+use id::{BufferId, ImageId, SamplerId, ViewId};
+#[cfg(feature = "labels")]
+use label::{LabelOverlay, MarkerResources};
+use light::{DirectionalLight, Sky};
+use material::{GPUMaterial, GPUMaterialData, Material, MaterialOverride};
+use mesh::{GPUMesh, Mesh, MorphDelta, MorphWeights, StagedVertices, Vertex};
+use picking::PickPass;
 use queue::Queue;
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
-use sovereign_ecs::{CommandBuffer, PreparedQuery, World};
-use std::error::Error;
+use readback::{ReadbackImage, ReadbackRequest};
+use sampler::SamplerCache;
+use settings::{AmbientOcclusion, FullscreenMode, RenderSettings, UiViewport};
+use shadow::{
+    fit_cascades, visible_depth_range, CastsShadows, ReceivesShadows, ShadowCascade, ShadowMap,
+    ShadowPass,
+};
+use skybox::SkyboxPass;
+use sovereign_ecs::{
+    CommandBuffer, Despawned, Entity, EventReader, Events, TracedQuery, Without, World,
+};
+use sovereign_math::{Color, Frustum};
+use stats::{CapacityStats, RenderStats, Usage};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use text::TextPass;
+use texture::Texture;
+use tonemap::TonemapResources;
+use tracker::ResourceReport;
 use transform::{GPUTransform, GlobalTransform};
-use windows::Win32::{
-    Foundation::{HANDLE, HWND},
-    System::Threading::{CreateEventA, WaitForSingleObject},
+#[cfg(feature = "debug-ui")]
+use ui::UiPass;
+use upload_adapter::{StagedUpload, UploadAdapter};
+use upload_queue::{UploadQueue, UploadSource};
+use windows::{
+    core::{Interface, PCWSTR},
+    Win32::{
+        Foundation::{BOOL, DXGI_STATUS_OCCLUDED, HANDLE, HWND},
+        Graphics::Gdi::{EnumDisplaySettingsW, DEVMODEW, ENUM_CURRENT_SETTINGS},
+        System::Threading::{CreateEventA, WaitForSingleObject},
+    },
 };
 
 pub use gpu_allocator::MemoryLocation;
@@ -39,10 +134,16 @@ pub use windows::Win32::Graphics::{
 pub struct RenderResources {
     pub vertex_buffer_id: u32,
     pub transform_buffer_id: u32,
-    pub transform_offset: u32,
+    pub instance_buffer_id: u32,
+    pub instance_offset: u32,
     pub view_buffer_index: u32,
     pub material_buffer_index: u32,
     pub material_offset: u32,
+    pub ao_texture_index: u32,
+    pub morph_target_buffer_index: u32,
+    pub morph_weight_buffer_index: u32,
+    pub morph_weight_offset: u32,
+    pub shadow_buffer_index: u32,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -51,43 +152,124 @@ pub struct BufferView {
     pub view: ViewId,
 }
 
+type MeshQuery = (
+    &'static GPUMesh,
+    &'static GPUMaterial,
+    &'static GPUTransform,
+    Option<&'static WorldBounds>,
+    Option<&'static NeverCull>,
+    Option<&'static CastsShadows>,
+    Option<&'static ReceivesShadows>,
+    Option<&'static MorphWeights>,
+    Option<(&'static MaterialOverride, &'static Handle<Material>)>,
+);
+
 pub struct Renderer {
     width: u32,
     height: u32,
 
     pub device: Device,
     graphics_queue: Queue,
-    swapchain: IDXGISwapChain3,
+    // None when rendering offscreen.
+    swapchain: Option<IDXGISwapChain3>,
+    offscreen_target: Option<ImageId>,
     rtv_heap: DescriptorHeap,
     dsv_heap: DescriptorHeap,
     pub cbv_heap: DescriptorHeap,
     pub sampler_heap: DescriptorHeap,
     root_signature: ID3D12RootSignature,
+    depth_pipeline: ID3D12PipelineState,
     pipeline: ID3D12PipelineState,
+    #[cfg(feature = "ambient-occlusion")]
+    ao_pipelines: AoPipelines,
+    bloom_pipelines: BloomPipelines,
+    gizmo_pipeline: ID3D12PipelineState,
+    tonemap_pipeline: ID3D12PipelineState,
+    #[cfg(feature = "labels")]
+    marker_pipeline: ID3D12PipelineState,
+    tonemap_sampler: SamplerId,
+    cubemap_pipelines: CubemapPipelines,
+    skybox_pass: SkyboxPass,
+    shadow_pass: ShadowPass,
+    environment: Option<Environment>,
+    brdf_lut: Option<(ImageId, ViewId)>,
+    environment_sampler: SamplerId,
+    debug_draw_pass: DebugDrawPass,
+    pub debug_draw: DebugDraw,
+    text_pass: TextPass,
+    #[cfg(feature = "debug-ui")]
+    ui: UiPass,
+    #[cfg(feature = "debug-ui")]
+    debug_text_pass: DebugTextPass,
+    #[cfg(feature = "debug-ui")]
+    pub debug_text: DebugText,
+    pub gizmo: OrientationGizmo,
+    #[cfg(feature = "labels")]
+    pub labels: LabelOverlay,
     render_targets: Vec<ID3D12Resource>,
+    swapchain_rtvs: Vec<ViewId>,
+    fullscreen: FullscreenMode,
+    occluded: bool,
+    scale_factor: f32,
+    targets: RenderTargets,
+    settings: RenderSettings,
+    desc: RendererDesc,
     frame_index: usize,
 
     render_command_encoder: CommandEncoder,
     immediate_command_encoder: CommandEncoder,
+    gpu_timer: GpuTimer,
+    upload_adapter: Option<UploadAdapter>,
+    uploads: UploadQueue,
 
     fence: ID3D12Fence,
     fence_value: u64,
     fence_event: HANDLE,
 
-    pub checkerboard_image: ImageId,
-
-    view_buffer: BufferView,
-    transform_buffer: BufferView,
-    material_buffer: BufferView,
-    mesh_query: PreparedQuery<(
-        &'static GPUMesh,
-        &'static GPUMaterial,
-        &'static GPUTransform,
-    )>,
-    prepare_mesh_query: PreparedQuery<(&'static Handle<Mesh>,)>,
-    prepare_transform_query: PreparedQuery<(&'static GlobalTransform,)>,
-    prepare_material_query: PreparedQuery<(&'static Handle<Material>,)>,
-    render_prepare_camera_query: PreparedQuery<(&'static Camera, &'static GlobalTransform)>,
+    checkerboard: Option<Handle<Texture>>,
+
+    constants: ConstantRing,
+    view_buffer: ViewId,
+    transform_buffer: GrowableGpuBuffer<GlobalTransform>,
+    material_buffer: GrowableGpuBuffer<GPUMaterialData>,
+    // Rewritten every frame with one slot per draw that has a MaterialOverride.
+    material_override_buffer: GrowableGpuBuffer<GPUMaterialData>,
+    instance_buffer: GrowableGpuBuffer<u32>,
+    morph_weight_buffer: GrowableGpuBuffer<f32>,
+    gpu_meshes: HashMap<usize, GPUMesh>,
+    // Meshes with equal content share one upload, keyed by content hash with its user count.
+    mesh_uploads: HashMap<u64, (GPUMesh, usize)>,
+    mesh_hashes: HashMap<usize, u64>,
+    samplers: SamplerCache,
+    draws: Vec<DrawItem>,
+    frustum: Option<Frustum>,
+    capture_barriers: bool,
+    frame_graph_path: Option<PathBuf>,
+    readback_request: Option<ReadbackRequest>,
+    readback_image: Option<ReadbackImage>,
+    shadow_cascades: Vec<ShadowCascade>,
+    stats: RenderStats,
+    mesh_query: TracedQuery<MeshQuery>,
+    // CPU copy of every transform slot as last written to the transform buffer.
+    uploaded_transforms: Vec<Mat4>,
+    // The entity each transform slot was uploaded for, to resolve picks.
+    transform_entities: Vec<Entity>,
+    // Slots of despawned entities, handed to the next new transforms.
+    free_transform_slots: Vec<usize>,
+    despawned_reader: EventReader<Despawned>,
+    pick_pass: Option<PickPass>,
+    prepare_mesh_query: TracedQuery<Without<(&'static Handle<Mesh>,), &'static GPUMesh>>,
+    changed_mesh_query: TracedQuery<(&'static Handle<Mesh>, &'static GPUMesh)>,
+    prepare_transform_query:
+        TracedQuery<Without<(&'static GlobalTransform,), &'static GPUTransform>>,
+    changed_transform_query: TracedQuery<(&'static GlobalTransform, &'static GPUTransform)>,
+    prepare_material_query:
+        TracedQuery<Without<(&'static Handle<Material>,), &'static GPUMaterial>>,
+    changed_material_query: TracedQuery<(&'static Handle<Material>, &'static GPUMaterial)>,
+    render_prepare_camera_query: TracedQuery<(&'static Camera, &'static GlobalTransform)>,
+    light_query: TracedQuery<(&'static DirectionalLight,)>,
+    sky_query: TracedQuery<(&'static Sky,)>,
+    clear_color: Color,
 }
 
 impl Renderer {
@@ -96,78 +278,123 @@ impl Renderer {
         height: u32,
         window: &dyn HasWindowHandle,
         world: &mut World,
-    ) -> Result<Self, Box<dyn Error>> {
-        let mut device = Device::new()?;
-        let graphics_queue = device.create_command_queue(D3D12_COMMAND_LIST_TYPE_DIRECT)?;
-
+        desc: RendererDesc,
+    ) -> Result<Self, RenderError> {
         let hwnd = match window.window_handle()?.as_raw() {
             RawWindowHandle::Win32(win) => HWND(win.hwnd.get()),
-            _ => unreachable!(),
+            _ => return Err(RenderError::UnsupportedWindow),
         };
+        Self::create(width, height, Some(hwnd), world, desc)
+    }
+
+    // Renders into an offscreen target instead of a window, for tests and tools that only read the
+    // frames back.
+    pub fn new_offscreen(
+        width: u32,
+        height: u32,
+        world: &mut World,
+        desc: RendererDesc,
+    ) -> Result<Self, RenderError> {
+        Self::create(width, height, None, world, desc)
+    }
+
+    fn create(
+        width: u32,
+        height: u32,
+        hwnd: Option<HWND>,
+        world: &mut World,
+        desc: RendererDesc,
+    ) -> Result<Self, RenderError> {
+        let mut device = Device::new(&desc)?;
+        let capabilities = device.capabilities();
+        tracing::info!("{}", capabilities);
+        if !capabilities.supports_bindless() {
+            return Err(RenderError::UnsupportedDevice(format!(
+                "{} lacks shader model 6.6 with resource binding tier 3",
+                capabilities.adapter
+            )));
+        }
+        let graphics_queue = device.create_command_queue(D3D12_COMMAND_LIST_TYPE_DIRECT)?;
+
         let swapchain_desc = DXGI_SWAP_CHAIN_DESC1 {
             Width: width,
             Height: height,
-            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            Format: desc.buffer_format(),
             SampleDesc: DXGI_SAMPLE_DESC {
                 Count: 1,
                 Quality: 0,
             },
             BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
-            BufferCount: 2,
+            BufferCount: desc.buffer_count(),
             SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
             ..Default::default()
         };
-        let swapchain = device.create_swapchain(&swapchain_desc, &graphics_queue, hwnd)?;
+        let swapchain = hwnd
+            .map(|hwnd| device.create_swapchain(&swapchain_desc, &graphics_queue, hwnd))
+            .transpose()?;
 
         let mut rtv_heap = device.create_descriptor_heap(
             D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
-            2,
+            desc.heap_sizes.render_targets,
             D3D12_DESCRIPTOR_HEAP_FLAG_NONE,
         )?;
         let mut dsv_heap = device.create_descriptor_heap(
             D3D12_DESCRIPTOR_HEAP_TYPE_DSV,
-            1,
+            desc.heap_sizes.depth_targets,
             D3D12_DESCRIPTOR_HEAP_FLAG_NONE,
         )?;
         let mut cbv_heap = device.create_descriptor_heap(
             D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
-            1000,
+            desc.heap_sizes.resources + desc.heap_sizes.transient_resources,
             D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
         )?;
-        let sampler_heap = device.create_descriptor_heap(
+        cbv_heap.reserve_transient(
+            desc.heap_sizes.transient_resources as usize,
+            desc.buffer_count() as usize,
+        );
+        let mut sampler_heap = device.create_descriptor_heap(
             D3D12_DESCRIPTOR_HEAP_TYPE_SAMPLER,
-            1000,
+            desc.heap_sizes.samplers,
             D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
         )?;
 
-        let mut render_targets = Vec::new();
-        for i in 0..2 {
-            let render_target: ID3D12Resource = unsafe { swapchain.GetBuffer(i)? };
-            rtv_heap.create_rtv(&render_target);
-            render_targets.push(render_target);
-        }
+        let (render_targets, swapchain_rtvs, offscreen_target) = match &swapchain {
+            Some(swapchain) => {
+                let (render_targets, views) =
+                    create_swapchain_views(swapchain, &mut rtv_heap, &desc)?;
+                (render_targets, views, None)
+            }
+            None => {
+                let (render_target, view, image) =
+                    create_offscreen_target(&mut device, &mut rtv_heap, &desc, width, height)?;
+                (vec![render_target], vec![view], Some(image))
+            }
+        };
 
-        let depth_texture = device.create_image(
-            width,
-            height,
-            DXGI_FORMAT_D32_FLOAT,
-            D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL,
-            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        let mut settings = RenderSettings::default();
+        settings.clamp_to(device.capabilities());
+        let targets = RenderTargets::new(
+            &mut device,
+            &mut rtv_heap,
+            &mut dsv_heap,
+            &mut cbv_heap,
+            (width, height),
+            desc.depth_format,
+            &settings,
         )?;
-        dsv_heap.create_dsv(device.get_image(depth_texture));
+        let mut samplers = SamplerCache::new(&mut sampler_heap)?;
+        let tonemap_sampler =
+            samplers.get_or_create(&mut sampler_heap, "Tonemap Sampler", &sampler::LINEAR_CLAMP)?;
 
         let render_command_encoder =
             device.create_command_encoder(D3D12_COMMAND_LIST_TYPE_DIRECT)?;
         let immediate_command_encoder =
             device.create_command_encoder(D3D12_COMMAND_LIST_TYPE_DIRECT)?;
-
-        let shader_code = std::fs::read_to_string("assets/shaders/mesh.hlsl")?;
-        let mut vertex_shader =
-            compile_hlsl("mesh.hlsl", &shader_code, "VSMain", "vs_6_6", &[], &[])?;
-        let mut fragment_shader =
-            compile_hlsl("mesh.hlsl", &shader_code, "PSMain", "ps_6_6", &[], &[])?;
-        fake_sign_dxil_in_place(&mut vertex_shader);
-        fake_sign_dxil_in_place(&mut fragment_shader);
+        let gpu_timer = GpuTimer::new(&mut device, &graphics_queue, settings.gpu_markers)?;
+        for render_target in &render_targets {
+            render_command_encoder.track_resource(render_target, D3D12_RESOURCE_STATE_PRESENT);
+        }
+        targets.track(&device, &render_command_encoder);
 
         let constants = D3D12_ROOT_PARAMETER {
             ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
@@ -182,156 +409,173 @@ impl Renderer {
             },
         };
         let root_signature = device.create_root_signature(
-            D3D12_ROOT_SIGNATURE_FLAG_CBV_SRV_UAV_HEAP_DIRECTLY_INDEXED,
+            D3D12_ROOT_SIGNATURE_FLAG_CBV_SRV_UAV_HEAP_DIRECTLY_INDEXED
+                | D3D12_ROOT_SIGNATURE_FLAG_SAMPLER_HEAP_DIRECTLY_INDEXED,
             &[constants],
         )?;
-        let mut pipeline_desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
-            pRootSignature: unsafe { std::mem::transmute_copy(&root_signature) },
-            VS: D3D12_SHADER_BYTECODE {
-                pShaderBytecode: vertex_shader.as_ptr() as *const _,
-                BytecodeLength: vertex_shader.len(),
-            },
-            PS: D3D12_SHADER_BYTECODE {
-                pShaderBytecode: fragment_shader.as_ptr() as *const _,
-                BytecodeLength: fragment_shader.len(),
-            },
-            RasterizerState: D3D12_RASTERIZER_DESC {
-                FillMode: D3D12_FILL_MODE_SOLID,
-                CullMode: D3D12_CULL_MODE_NONE,
-                ..Default::default()
-            },
-            BlendState: D3D12_BLEND_DESC {
-                AlphaToCoverageEnable: false.into(),
-                IndependentBlendEnable: false.into(),
-                RenderTarget: [
-                    D3D12_RENDER_TARGET_BLEND_DESC {
-                        BlendEnable: false.into(),
-                        LogicOpEnable: false.into(),
-                        SrcBlend: D3D12_BLEND_ONE,
-                        DestBlend: D3D12_BLEND_ZERO,
-                        BlendOp: D3D12_BLEND_OP_ADD,
-                        SrcBlendAlpha: D3D12_BLEND_ONE,
-                        DestBlendAlpha: D3D12_BLEND_ZERO,
-                        BlendOpAlpha: D3D12_BLEND_OP_ADD,
-                        LogicOp: D3D12_LOGIC_OP_NOOP,
-                        RenderTargetWriteMask: D3D12_COLOR_WRITE_ENABLE_ALL.0 as u8,
-                    },
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                    D3D12_RENDER_TARGET_BLEND_DESC::default(),
-                ],
-            },
-            DepthStencilState: D3D12_DEPTH_STENCIL_DESC {
-                DepthEnable: true.into(),
-                DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ALL,
-                DepthFunc: D3D12_COMPARISON_FUNC_GREATER,
-                ..Default::default()
-            },
-            DSVFormat: DXGI_FORMAT_D32_FLOAT,
-            SampleMask: u32::MAX,
-            PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
-            NumRenderTargets: 1,
-            SampleDesc: DXGI_SAMPLE_DESC {
-                Count: 1,
-                Quality: 0,
-            },
-            ..Default::default()
-        };
-        pipeline_desc.RTVFormats[0] = DXGI_FORMAT_R8G8B8A8_UNORM;
-        let pipeline = device.create_graphics_pipeline(&pipeline_desc)?;
+        let (depth_pipeline, pipeline) = create_mesh_pipelines(
+            &device,
+            &root_signature,
+            settings.anti_aliasing.sample_count(),
+            desc.depth_format,
+        )?;
+        let gizmo_pipeline = create_gizmo_pipeline(&device, &root_signature, desc.view_format())?;
+        let tonemap_pipeline = create_fullscreen_pipeline(
+            &device,
+            &root_signature,
+            "assets/shaders/tonemap.hlsl",
+            desc.view_format(),
+        )?;
+        #[cfg(feature = "labels")]
+        let marker_pipeline = create_fullscreen_pipeline(
+            &device,
+            &root_signature,
+            "assets/shaders/marker.hlsl",
+            desc.view_format(),
+        )?;
+        #[cfg(feature = "ambient-occlusion")]
+        let ao_pipelines = AoPipelines::new(
+            &device,
+            &root_signature,
+            samplers.get_or_create(&mut sampler_heap, "AO Sampler", &sampler::POINT_CLAMP)?,
+        )?;
+        let bloom_pipelines = BloomPipelines::new(
+            &device,
+            &root_signature,
+            samplers.get_or_create(&mut sampler_heap, "Bloom Sampler", &sampler::LINEAR_CLAMP)?,
+        )?;
+        let debug_draw_pass = DebugDrawPass::new(
+            &mut device,
+            &mut cbv_heap,
+            &root_signature,
+            settings.anti_aliasing.sample_count(),
+            desc.depth_format,
+        )?;
+        let skybox_pass = SkyboxPass::new(
+            &device,
+            &root_signature,
+            settings.anti_aliasing.sample_count(),
+            desc.depth_format,
+        )?;
+        let environment_sampler = samplers.get_or_create(
+            &mut sampler_heap,
+            "Environment Sampler",
+            &sampler::LINEAR_CLAMP,
+        )?;
+        let shadow_map = ShadowMap::new(
+            &mut device,
+            &mut dsv_heap,
+            &mut cbv_heap,
+            settings.shadow_quality,
+        )?;
+        if let Some(shadow_map) = &shadow_map {
+            shadow_map.track(&device, &render_command_encoder);
+        }
+        let shadow_pass = ShadowPass::new(
+            &device,
+            &root_signature,
+            samplers.get_or_create(&mut sampler_heap, "Shadow Sampler", &shadow::SHADOW_SAMPLER)?,
+            shadow_map,
+        )?;
+        let cubemap_pipelines = CubemapPipelines::new(
+            &device,
+            &root_signature,
+            samplers.get_or_create(&mut sampler_heap, "Cubemap Sampler", &sampler::LINEAR_WRAP)?,
+        )?;
+        let text_pass = TextPass::new(
+            &mut device,
+            &mut cbv_heap,
+            &root_signature,
+            desc.view_format(),
+            samplers.get_or_create(&mut sampler_heap, "Font Sampler", &sampler::POINT_CLAMP)?,
+        )?;
+        #[cfg(feature = "debug-ui")]
+        let debug_text_pass = DebugTextPass::new(
+            &mut device,
+            &mut cbv_heap,
+            &root_signature,
+            desc.view_format(),
+        )?;
+        #[cfg(feature = "debug-ui")]
+        let ui = UiPass::new(
+            &mut device,
+            &mut cbv_heap,
+            &root_signature,
+            desc.view_format(),
+            samplers.get_or_create(&mut sampler_heap, "UI Sampler", &sampler::LINEAR_CLAMP)?,
+        )?;
 
         let fence = device.create_fence()?;
         let fence_value = 1;
         let fence_event = unsafe { CreateEventA(None, false, false, None) }?;
 
-        let frame_index = unsafe { swapchain.GetCurrentBackBufferIndex() } as usize;
+        let frame_index = current_backbuffer(swapchain.as_ref());
 
-        let view_buffer = device.create_buffer(
-            256,
-            DXGI_FORMAT_UNKNOWN,
-            D3D12_RESOURCE_FLAG_NONE,
-            D3D12_RESOURCE_STATE_COMMON,
-            MemoryLocation::CpuToGpu,
-        )?;
-        let view_buffer_resource = device.get_buffer(view_buffer);
-        let view_buffer_view_desc = D3D12_CONSTANT_BUFFER_VIEW_DESC {
-            BufferLocation: unsafe {
-                view_buffer_resource
-                    .allocation
-                    .resource()
-                    .GetGPUVirtualAddress()
-                    + view_buffer_resource
-                        .allocation
-                        .allocation
-                        .as_ref()
-                        .unwrap()
-                        .offset()
-            },
-            SizeInBytes: device.get_buffer(view_buffer).size as u32,
-        };
-        let view_buffer_view = cbv_heap.create_cbv(&view_buffer_view_desc);
+        let mut constants = ConstantRing::new(
+            &mut device,
+            CONSTANTS_PER_FRAME,
+            desc.buffer_count() as usize,
+        )?;
+        // Replaced with a view of the frame's own copy every frame; this one only stands in until
+        // there is a camera.
+        let view_allocation =
+            constants.allocate(std::mem::size_of::<ViewUniform>(), CONSTANT_ALIGNMENT)?;
+        let view_buffer_view = cbv_heap.create_transient_cbv(&D3D12_CONSTANT_BUFFER_VIEW_DESC {
+            BufferLocation: view_allocation.gpu_address,
+            SizeInBytes: view_allocation.size as u32,
+        })?;
 
-        let transform_buffer = device.create_buffer(
-            std::mem::size_of::<GlobalTransform>() as u64 * 1000,
-            DXGI_FORMAT_UNKNOWN,
-            D3D12_RESOURCE_FLAG_NONE,
+        let transform_buffer = GrowableGpuBuffer::new(
+            &mut device,
+            &mut cbv_heap,
+            "Transform Buffer",
+            1024,
             D3D12_RESOURCE_STATE_COMMON,
-            MemoryLocation::CpuToGpu,
         )?;
-        let transform_buffer_view_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
-            Format: DXGI_FORMAT_UNKNOWN,
-            ViewDimension: D3D12_SRV_DIMENSION_BUFFER,
-            Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
-            Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
-                Buffer: D3D12_BUFFER_SRV {
-                    FirstElement: 0,
-                    NumElements: 1000,
-                    StructureByteStride: std::mem::size_of::<GlobalTransform>() as u32,
-                    Flags: D3D12_BUFFER_SRV_FLAG_NONE,
-                },
-            },
-        };
-        let transform_buffer_view = cbv_heap.create_srv(
-            device.get_buffer(transform_buffer).allocation.resource(),
-            &transform_buffer_view_desc,
-        );
-
-        let material_buffer = device.create_buffer(
-            std::mem::size_of::<MaterialUniform>() as u64 * 200,
-            DXGI_FORMAT_UNKNOWN,
-            D3D12_RESOURCE_FLAG_NONE,
+        let material_buffer = GrowableGpuBuffer::new(
+            &mut device,
+            &mut cbv_heap,
+            "Material Buffer",
+            256,
             D3D12_RESOURCE_STATE_COMMON,
-            MemoryLocation::CpuToGpu,
         )?;
-        let material_buffer_view_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
-            Format: DXGI_FORMAT_UNKNOWN,
-            ViewDimension: D3D12_SRV_DIMENSION_BUFFER,
-            Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
-            Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
-                Buffer: D3D12_BUFFER_SRV {
-                    FirstElement: 0,
-                    NumElements: 100,
-                    StructureByteStride: std::mem::size_of::<MaterialUniform>() as u32,
-                    Flags: D3D12_BUFFER_SRV_FLAG_NONE,
-                },
-            },
-        };
-        let material_buffer_view = cbv_heap.create_srv(
-            device.get_buffer(material_buffer).allocation.resource(),
-            &material_buffer_view_desc,
-        );
+        let material_override_buffer = GrowableGpuBuffer::new(
+            &mut device,
+            &mut cbv_heap,
+            "Material Override Buffer",
+            64,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )?;
+        let instance_buffer = GrowableGpuBuffer::new(
+            &mut device,
+            &mut cbv_heap,
+            "Instance Buffer",
+            1024,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )?;
+
+        let morph_weight_buffer = GrowableGpuBuffer::new(
+            &mut device,
+            &mut cbv_heap,
+            "Morph Weight Buffer",
+            4096,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )?;
 
         world.set_singleton(Assets::<Mesh>::new());
         world.set_singleton(Assets::<Material>::new());
-        let mesh_query = PreparedQuery::new();
-        let prepare_mesh_query = PreparedQuery::new();
-        let prepare_transform_query = PreparedQuery::new();
-        let prepare_material_query = PreparedQuery::new();
-        let render_prepare_camera_query = PreparedQuery::new();
+        world.set_singleton(Assets::<Texture>::new());
+        world.set_singleton(settings.clone());
+        let mesh_query = TracedQuery::new("mesh_query");
+        let prepare_mesh_query = TracedQuery::new("prepare_mesh_query");
+        let changed_mesh_query = TracedQuery::new("changed_mesh_query");
+        let prepare_transform_query = TracedQuery::new("prepare_transform_query");
+        let changed_transform_query = TracedQuery::new("changed_transform_query");
+        let prepare_material_query = TracedQuery::new("prepare_material_query");
+        let changed_material_query = TracedQuery::new("changed_material_query");
+        let render_prepare_camera_query = TracedQuery::new("render_prepare_camera_query");
+        let light_query = TracedQuery::new("light_query");
+        let sky_query = TracedQuery::new("sky_query");
 
         let mut renderer = Self {
             width,
@@ -339,37 +583,93 @@ impl Renderer {
             device,
             graphics_queue,
             swapchain,
+            offscreen_target,
             rtv_heap,
             dsv_heap,
             cbv_heap,
             sampler_heap,
             render_targets,
+            swapchain_rtvs,
+            fullscreen: FullscreenMode::Windowed,
+            occluded: false,
+            scale_factor: 1.0,
+            targets,
+            settings,
+            desc,
             frame_index,
             render_command_encoder,
             immediate_command_encoder,
+            gpu_timer,
+            upload_adapter: None,
+            uploads: UploadQueue::default(),
             root_signature,
+            depth_pipeline,
             pipeline,
+            #[cfg(feature = "ambient-occlusion")]
+            ao_pipelines,
+            bloom_pipelines,
+            gizmo_pipeline,
+            tonemap_pipeline,
+            #[cfg(feature = "labels")]
+            marker_pipeline,
+            tonemap_sampler,
+            cubemap_pipelines,
+            skybox_pass,
+            shadow_pass,
+            environment: None,
+            brdf_lut: None,
+            environment_sampler,
+            debug_draw_pass,
+            debug_draw: DebugDraw::default(),
+            text_pass,
+            #[cfg(feature = "debug-ui")]
+            ui,
+            #[cfg(feature = "debug-ui")]
+            debug_text_pass,
+            #[cfg(feature = "debug-ui")]
+            debug_text: DebugText::default(),
+            gizmo: OrientationGizmo::default(),
+            #[cfg(feature = "labels")]
+            labels: LabelOverlay::default(),
             fence,
             fence_event,
             fence_value,
-            checkerboard_image: ImageId(0),
-            view_buffer: BufferView {
-                buffer: view_buffer,
-                view: view_buffer_view,
-            },
-            transform_buffer: BufferView {
-                buffer: transform_buffer,
-                view: transform_buffer_view,
-            },
-            material_buffer: BufferView {
-                buffer: material_buffer,
-                view: material_buffer_view,
-            },
+            checkerboard: None,
+            constants,
+            view_buffer: view_buffer_view,
+            transform_buffer,
+            material_buffer,
+            material_override_buffer,
+            instance_buffer,
+            morph_weight_buffer,
+            gpu_meshes: HashMap::new(),
+            mesh_uploads: HashMap::new(),
+            mesh_hashes: HashMap::new(),
+            samplers,
+            draws: Vec::new(),
+            frustum: None,
+            capture_barriers: false,
+            frame_graph_path: None,
+            readback_request: None,
+            readback_image: None,
+            shadow_cascades: Vec::new(),
+            stats: RenderStats::default(),
             mesh_query,
+            uploaded_transforms: Vec::new(),
+            transform_entities: Vec::new(),
+            free_transform_slots: Vec::new(),
+            despawned_reader: EventReader::new(),
+            pick_pass: None,
             prepare_mesh_query,
+            changed_mesh_query,
             prepare_transform_query,
+            changed_transform_query,
             prepare_material_query,
+            changed_material_query,
             render_prepare_camera_query,
+            light_query,
+            sky_query,
+            clear_color: Color::BLACK,
         };
 
         let magenta = 0xFFFF00FFu32;
@@ -384,355 +684,1717 @@ impl Renderer {
                 };
             }
         }
-        let error_checkboard_image_id = renderer.device.create_image(
-            16,
-            16,
-            DXGI_FORMAT_R8G8B8A8_UNORM,
-            D3D12_RESOURCE_FLAG_NONE,
-            D3D12_RESOURCE_STATE_COPY_DEST,
-        )?;
-        renderer.checkerboard_image = error_checkboard_image_id;
-
-        let buffer_id = renderer
-            .device
-            .create_buffer(
-                16 * 16 * 4,
-                DXGI_FORMAT_UNKNOWN,
-                D3D12_RESOURCE_FLAG_NONE,
-                D3D12_RESOURCE_STATE_COMMON,
-                MemoryLocation::CpuToGpu,
-            )
-            .unwrap();
-        {
-            let data = renderer.device.map_buffer::<u8>(buffer_id)?;
-            unsafe {
-                std::ptr::copy_nonoverlapping(
-                    pixels.as_ptr() as *const u8,
-                    data.as_ptr(),
-                    std::mem::size_of::<u32>() * pixels.len(),
-                )
-            };
-            renderer.device.unmap_buffer(buffer_id);
-        }
-        renderer.immediate_submit(|r, encoder| {
-            let buffer = r.device.get_buffer(buffer_id);
-            let error_checkboard_image = r.device.get_image(error_checkboard_image_id);
-            encoder.copy_buffer_to_image(buffer, error_checkboard_image);
-            encoder.transition_image(
-                error_checkboard_image.allocation.resource(),
-                D3D12_RESOURCE_STATE_COPY_DEST,
-                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
-            );
-        })?;
+        let checkerboard =
+            renderer.create_texture_from_rgba8(world, bytemuck::cast_slice(&pixels), 16, 16)?;
+        renderer.checkerboard = Some(checkerboard);
+        renderer.load_font(world)?;
+        renderer.update_refresh_rate();
 
         Ok(renderer)
     }
 
-    pub fn prepare(&mut self, world: &mut World) -> Result<(), Box<dyn Error>> {
+    pub fn collect_garbage(&mut self, world: &World) {
+        let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
+        let (meshes,) = meshes_query.get().unwrap();
+        // Staging of meshes dropped before their upload; uploaded ones already freed theirs.
+        for mesh in meshes.collect_garbage() {
+            if let Some(staged) = mesh.staged_vertices {
+                self.device.destroy_buffer(staged.buffer);
+            }
+        }
+        for handle in meshes.drain_removed() {
+            let hash = self.mesh_hashes.remove(&handle.idx());
+            if let Some(gpu_mesh) = self.gpu_meshes.remove(&handle.idx()) {
+                // Shared buffers go with the last mesh using them.
+                let hash = hash.unwrap();
+                let (_, users) = self.mesh_uploads.get_mut(&hash).unwrap();
+                *users -= 1;
+                if *users > 0 {
+                    continue;
+                }
+                self.mesh_uploads.remove(&hash);
+                self.device.destroy_buffer(gpu_mesh.vertex_buffer.buffer);
+                self.device.destroy_buffer(gpu_mesh.index_buffer);
+                self.cbv_heap.free(gpu_mesh.vertex_buffer.view);
+                if let Some(morph_targets) = gpu_mesh.morph_targets {
+                    self.device.destroy_buffer(morph_targets.buffer);
+                    self.cbv_heap.free(morph_targets.view);
+                }
+            }
+        }
+
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (materials,) = materials_query.get().unwrap();
+        materials.collect_garbage();
+        materials.drain_removed().for_each(drop);
+
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+        for texture in textures.collect_garbage() {
+            self.destroy_texture(texture);
+        }
+        textures.drain_removed().for_each(drop);
+    }
+
+    // Despawned entities take their GPU components with them; their transform slots are all that
+    // is left to reclaim. Mesh buffers go once the last handle to the mesh is dropped.
+    fn free_despawned_transforms(&mut self, world: &World) {
+        let mut events_query = world.get_singleton::<Events<Despawned>>();
+        let (events,) = events_query.get().unwrap();
+        let despawned = self
+            .despawned_reader
+            .read(events)
+            .map(|Despawned(entity)| *entity)
+            .collect::<HashSet<_>>();
+        if despawned.is_empty() {
+            return;
+        }
+        for (slot, entity) in self.transform_entities.iter_mut().enumerate() {
+            if despawned.contains(entity) {
+                *entity = Entity::DANGLING;
+                self.free_transform_slots.push(slot);
+            }
+        }
+    }
+
+    pub fn prepare(&mut self, world: &mut World) -> Result<(), RenderError> {
+        self.collect_garbage(world);
+
         let mut meshes_query = world.get_singleton::<Assets<Mesh>>();
         let (meshes,) = meshes_query.get().unwrap();
 
         let mut materials_query = world.get_singleton::<Assets<Material>>();
         let (materials,) = materials_query.get().unwrap();
 
-        self.immediate_command_encoder.reset()?;
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+
+        let mut pending_meshes = Vec::new();
+        for (entity, (mesh_handle,)) in self.prepare_mesh_query.query(world.get()).iter() {
+            pending_meshes.push((entity, mesh_handle.downgrade()));
+        }
+        for (entity, (mesh_handle, gpu_mesh)) in self.changed_mesh_query.query(world.get()).iter() {
+            let cached = self.gpu_meshes.get(&mesh_handle.idx());
+            if cached.map(|mesh| mesh.vertex_buffer.view) != Some(gpu_mesh.vertex_buffer.view) {
+                pending_meshes.push((entity, mesh_handle.downgrade()));
+            }
+        }
+
+        let span = tracing::debug_span!("upload_meshes", entities = pending_meshes.len()).entered();
         let mut commands = CommandBuffer::new();
-        self.prepare_mesh_query
-            .query(world.get())
-            .iter()
-            .for_each(|(entity, (mesh_handle,))| {
-                let mesh = meshes.get(*mesh_handle).unwrap();
-                let staging_vertex_buffer = self
-                    .device
-                    .create_buffer(
-                        mesh.vertices.len() as u64 * std::mem::size_of::<Vertex>() as u64,
-                        DXGI_FORMAT_UNKNOWN,
-                        D3D12_RESOURCE_FLAG_NONE,
-                        D3D12_RESOURCE_STATE_COPY_SOURCE,
-                        MemoryLocation::CpuToGpu,
-                    )
-                    .unwrap();
-                let vertex_buffer = self
-                    .device
-                    .create_buffer(
-                        mesh.vertices.len() as u64 * std::mem::size_of::<Vertex>() as u64,
+        let mut prepared_meshes = Vec::new();
+        let mut new_meshes = Vec::new();
+        let mut mesh_srvs = Vec::new();
+        // Entities sharing a mesh often arrive together; the mesh is still uploaded once, which
+        // staged vertices rely on since their staging is freed after the copy.
+        let mut queued_meshes = HashSet::new();
+        let mut queued_hashes = HashSet::new();
+        let mut shared_meshes = Vec::new();
+        for (entity, mesh_handle) in pending_meshes {
+            if self.gpu_meshes.contains_key(&mesh_handle.idx())
+                || queued_meshes.contains(&mesh_handle.idx())
+            {
+                prepared_meshes.push((entity, mesh_handle.idx()));
+                continue;
+            }
+
+            let Some(mesh) = meshes.get(mesh_handle) else {
+                commands.remove_one::<GPUMesh>(entity);
+                continue;
+            };
+            queued_meshes.insert(mesh_handle.idx());
+            prepared_meshes.push((entity, mesh_handle.idx()));
+            // A mesh equal to one already uploaded, or queued this frame, reuses its buffers.
+            let hash = mesh.content_hash();
+            self.mesh_hashes.insert(mesh_handle.idx(), hash);
+            if self.mesh_uploads.contains_key(&hash) || !queued_hashes.insert(hash) {
+                shared_meshes.push((mesh_handle.idx(), hash));
+                continue;
+            }
+
+            let (vertex_buffer, staging_vertex_buffer) = match &mesh.staged_vertices {
+                Some(staged) => (
+                    self.device.create_buffer(
+                        &format!("Mesh {} Vertices", mesh_handle.idx()),
+                        (staged.len * std::mem::size_of::<Vertex>()) as u64,
                         DXGI_FORMAT_UNKNOWN,
                         D3D12_RESOURCE_FLAG_NONE,
                         D3D12_RESOURCE_STATE_COPY_DEST,
                         MemoryLocation::GpuOnly,
-                    )
-                    .unwrap();
-                {
-                    let data = self
-                        .device
-                        .map_buffer::<Vertex>(staging_vertex_buffer)
-                        .unwrap();
-                    unsafe {
-                        std::ptr::copy_nonoverlapping(
-                            mesh.vertices.as_ptr() as *const u8,
-                            data.as_ptr(),
-                            std::mem::size_of::<Vertex>() * mesh.vertices.len(),
-                        )
-                    };
-                    self.device.unmap_buffer(staging_vertex_buffer);
-                }
-                let vbv_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
+                    )?,
+                    staged.buffer,
+                ),
+                None => self.device.create_buffer_with_data(
+                    &format!("Mesh {} Vertices", mesh_handle.idx()),
+                    &mesh.vertices,
+                )?,
+            };
+            self.uploads.push_buffer(
+                staging_vertex_buffer,
+                vertex_buffer,
+                D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE,
+            );
+            let vbv_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: DXGI_FORMAT_UNKNOWN,
+                ViewDimension: D3D12_SRV_DIMENSION_BUFFER,
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Buffer: D3D12_BUFFER_SRV {
+                        FirstElement: 0,
+                        NumElements: mesh.vertex_count() as u32,
+                        StructureByteStride: std::mem::size_of::<Vertex>() as u32,
+                        Flags: D3D12_BUFFER_SRV_FLAG_NONE,
+                    },
+                },
+            };
+            mesh_srvs.push((
+                format!("Mesh {} Vertices SRV", mesh_handle.idx()),
+                vertex_buffer,
+                vbv_desc,
+            ));
+
+            let (index_buffer, staging_index_buffer) = self.device.create_buffer_with_data(
+                &format!("Mesh {} Indices", mesh_handle.idx()),
+                mesh.indices.as_bytes(),
+            )?;
+            self.uploads.push_buffer(
+                staging_index_buffer,
+                index_buffer,
+                D3D12_RESOURCE_STATE_INDEX_BUFFER,
+            );
+
+            // Deltas are laid out target-major, indexed as target * vertex count + vertex.
+            let morph_buffer = if mesh.morph_targets.is_empty() {
+                None
+            } else {
+                let deltas = mesh.morph_targets.concat();
+                let (morph_buffer, staging_morph_buffer) = self.device.create_buffer_with_data(
+                    &format!("Mesh {} Morph Targets", mesh_handle.idx()),
+                    &deltas,
+                )?;
+                self.uploads.push_buffer(
+                    staging_morph_buffer,
+                    morph_buffer,
+                    D3D12_RESOURCE_STATE_NON_PIXEL_SHADER_RESOURCE,
+                );
+                let morph_view_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
                     Format: DXGI_FORMAT_UNKNOWN,
                     ViewDimension: D3D12_SRV_DIMENSION_BUFFER,
                     Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
                     Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
                         Buffer: D3D12_BUFFER_SRV {
                             FirstElement: 0,
-                            NumElements: mesh.vertices.len() as u32,
-                            StructureByteStride: std::mem::size_of::<Vertex>() as u32,
+                            NumElements: deltas.len() as u32,
+                            StructureByteStride: std::mem::size_of::<MorphDelta>() as u32,
                             Flags: D3D12_BUFFER_SRV_FLAG_NONE,
                         },
                     },
                 };
-                let vbv = self.cbv_heap.create_srv(
-                    self.device.get_buffer(vertex_buffer).allocation.resource(),
-                    &vbv_desc,
-                );
-                self.immediate_command_encoder.copy_buffer_to_buffer(
-                    self.device.get_buffer(staging_vertex_buffer),
-                    self.device.get_buffer(vertex_buffer),
-                );
-
-                let staging_index_buffer = self
-                    .device
-                    .create_buffer(
-                        mesh.indices.len() as u64 * std::mem::size_of::<u32>() as u64,
-                        DXGI_FORMAT_UNKNOWN,
-                        D3D12_RESOURCE_FLAG_NONE,
-                        D3D12_RESOURCE_STATE_COPY_SOURCE,
-                        MemoryLocation::CpuToGpu,
-                    )
-                    .unwrap();
-                let index_buffer = self
-                    .device
-                    .create_buffer(
-                        mesh.indices.len() as u64 * std::mem::size_of::<u32>() as u64,
-                        DXGI_FORMAT_UNKNOWN,
-                        D3D12_RESOURCE_FLAG_NONE,
-                        D3D12_RESOURCE_STATE_COPY_DEST,
-                        MemoryLocation::CpuToGpu,
-                    )
-                    .unwrap();
-                {
-                    let data = self.device.map_buffer::<u32>(staging_index_buffer).unwrap();
-                    unsafe {
-                        std::ptr::copy_nonoverlapping(
-                            mesh.indices.as_ptr() as *const u8,
-                            data.as_ptr(),
-                            std::mem::size_of::<u32>() * mesh.indices.len(),
-                        )
-                    };
-                    self.device.unmap_buffer(staging_index_buffer);
-                }
-                self.immediate_command_encoder.copy_buffer_to_buffer(
-                    self.device.get_buffer(staging_index_buffer),
-                    self.device.get_buffer(index_buffer),
-                );
-                self.immediate_command_encoder.transition_buffer(
-                    self.device.get_buffer(index_buffer),
-                    D3D12_RESOURCE_STATE_COPY_DEST,
-                    D3D12_RESOURCE_STATE_INDEX_BUFFER,
-                );
+                mesh_srvs.push((
+                    format!("Mesh {} Morph Targets SRV", mesh_handle.idx()),
+                    morph_buffer,
+                    morph_view_desc,
+                ));
+                Some(morph_buffer)
+            };
 
-                commands.insert_one(
-                    entity,
-                    GPUMesh {
-                        vertex_buffer: BufferView {
-                            buffer: vertex_buffer,
-                            view: vbv,
-                        },
-                        index_buffer,
-                        index_count: mesh.indices.len(),
+            new_meshes.push(NewMesh {
+                idx: mesh_handle.idx(),
+                hash,
+                vertex_buffer,
+                index_buffer,
+                index_buffer_view: D3D12_INDEX_BUFFER_VIEW {
+                    BufferLocation: unsafe {
+                        self.device
+                            .get_buffer(index_buffer)
+                            .allocation
+                            .resource()
+                            .GetGPUVirtualAddress()
                     },
-                );
+                    SizeInBytes: (mesh.indices.len() * mesh.indices.format().size()) as u32,
+                    Format: mesh.indices.format().dxgi_format(),
+                },
+                index_count: mesh.indices.len(),
+                morph_buffer,
+                morph_target_count: mesh.morph_targets.len(),
             });
+        }
+        // New meshes get their views from one contiguous range, handed out in the order the views
+        // were queued: the vertices, then the morph targets if the mesh has any.
+        if !mesh_srvs.is_empty() {
+            let views = mesh_srvs
+                .iter()
+                .map(|(name, buffer, desc)| {
+                    (
+                        name.as_str(),
+                        self.device.get_buffer(*buffer).allocation.resource(),
+                        desc,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let base = self.cbv_heap.create_srvs(&views)?;
+            let mut views = (base.0..).map(ViewId);
+            for new_mesh in new_meshes {
+                let gpu_mesh = GPUMesh {
+                    vertex_buffer: BufferView {
+                        buffer: new_mesh.vertex_buffer,
+                        view: views.next().unwrap(),
+                    },
+                    index_buffer: new_mesh.index_buffer,
+                    index_buffer_view: new_mesh.index_buffer_view,
+                    index_count: new_mesh.index_count,
+                    morph_targets: new_mesh.morph_buffer.map(|buffer| BufferView {
+                        buffer,
+                        view: views.next().unwrap(),
+                    }),
+                    morph_target_count: new_mesh.morph_target_count,
+                };
+                self.gpu_meshes.insert(new_mesh.idx, gpu_mesh);
+                self.mesh_uploads.insert(new_mesh.hash, (gpu_mesh, 1));
+            }
+        }
+        for (idx, hash) in shared_meshes {
+            let (gpu_mesh, users) = self.mesh_uploads.get_mut(&hash).unwrap();
+            *users += 1;
+            self.gpu_meshes.insert(idx, *gpu_mesh);
+        }
+        for (entity, idx) in prepared_meshes {
+            commands.insert_one(entity, self.gpu_meshes[&idx]);
+        }
+        span.exit();
 
-        let transform_data = self
-            .device
-            .map_buffer::<GlobalTransform>(self.transform_buffer.buffer)
-            .unwrap();
-        self.prepare_transform_query
+        self.free_despawned_transforms(world);
+        let new_transforms = self
+            .prepare_transform_query
             .query(world.get())
             .iter()
-            .enumerate()
-            .for_each(|(idx, (entity, (transform,)))| {
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        transform as *const _ as *const u8,
-                        transform_data
-                            .as_ptr()
-                            .offset((idx * std::mem::size_of::<GlobalTransform>()) as isize),
-                        std::mem::size_of::<GlobalTransform>(),
-                    )
+            .map(|(entity, (transform,))| (entity, *transform))
+            .collect::<Vec<_>>();
+        let span =
+            tracing::debug_span!("upload_transforms", entities = new_transforms.len()).entered();
+        if self.transform_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            self.uploaded_transforms.len() + new_transforms.len(),
+        )? {
+            for (_entity, gpu_transform) in world.query::<&mut GPUTransform>().iter() {
+                gpu_transform.buffer = self.transform_buffer.view();
+            }
+        }
+        // Only transforms that moved since their last upload are written. The GPU is idle between
+        // frames, so one buffer is enough.
+        let mut changed_transforms = Vec::new();
+        for (_entity, (transform, gpu_transform)) in
+            self.changed_transform_query.query(world.get()).iter()
+        {
+            let uploaded = &mut self.uploaded_transforms[gpu_transform.offset];
+            if *uploaded != transform.transform {
+                *uploaded = transform.transform;
+                changed_transforms.push((gpu_transform.offset, *transform));
+            }
+        }
+        // A static scene leaves the buffer unmapped.
+        if !new_transforms.is_empty() || !changed_transforms.is_empty() {
+            let mut transform_data = self.transform_buffer.map(&self.device)?;
+            for (offset, transform) in changed_transforms {
+                transform_data.write(offset, &transform);
+            }
+            for (entity, transform) in new_transforms {
+                let offset = match self.free_transform_slots.pop() {
+                    Some(slot) => {
+                        self.uploaded_transforms[slot] = transform.transform;
+                        self.transform_entities[slot] = entity;
+                        slot
+                    }
+                    None => {
+                        self.uploaded_transforms.push(transform.transform);
+                        self.transform_entities.push(entity);
+                        self.uploaded_transforms.len() - 1
+                    }
                 };
+                transform_data.write(offset, &transform);
                 commands.insert_one(
                     entity,
                     GPUTransform {
-                        buffer: self.transform_buffer,
-                        offset: idx,
+                        buffer: self.transform_buffer.view(),
+                        offset,
                     },
-                )
-            });
-        self.device.unmap_buffer(self.transform_buffer.buffer);
+                );
+            }
+        }
+        span.exit();
 
-        let material_data = self
-            .device
-            .map_buffer::<MaterialUniform>(self.material_buffer.buffer)
-            .unwrap();
-        self.prepare_material_query
-            .query(world.get())
+        let mut pending_materials = Vec::new();
+        for (entity, (material_handle,)) in self.prepare_material_query.query(world.get()).iter() {
+            pending_materials.push((entity, material_handle.downgrade()));
+        }
+        for (entity, (material_handle, gpu_material)) in
+            self.changed_material_query.query(world.get()).iter()
+        {
+            if gpu_material.offset != material_handle.idx() {
+                pending_materials.push((entity, material_handle.downgrade()));
+            }
+        }
+
+        let span =
+            tracing::debug_span!("upload_materials", entities = pending_materials.len()).entered();
+        // Materials live at their asset index, so the buffer has to cover the highest one.
+        let material_count = pending_materials
             .iter()
-            .enumerate()
-            .for_each(|(idx, (entity, (material_idx,)))| {
-                let material = materials.get(*material_idx).unwrap();
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        &material.uniform as *const _ as *const u8,
-                        material_data
-                            .as_ptr()
-                            .offset((idx * std::mem::size_of::<MaterialUniform>()) as isize),
-                        std::mem::size_of::<MaterialUniform>(),
-                    )
+            .map(|(_entity, handle)| handle.idx() + 1)
+            .max()
+            .unwrap_or(0);
+        if self
+            .material_buffer
+            .reserve(&mut self.device, &mut self.cbv_heap, material_count)?
+        {
+            for (_entity, gpu_material) in world.query::<&mut GPUMaterial>().iter() {
+                gpu_material.buffer = self.material_buffer.view();
+            }
+        }
+        if !pending_materials.is_empty() {
+            let mut material_data = self.material_buffer.map(&self.device)?;
+            for (entity, material_handle) in pending_materials {
+                let Some(material) = materials.get(material_handle) else {
+                    commands.remove_one::<GPUMaterial>(entity);
+                    continue;
                 };
+                let idx = material_handle.idx();
+                material_data.write(idx, &material.gpu_data(textures, None));
                 commands.insert_one(
                     entity,
                     GPUMaterial {
-                        buffer: self.material_buffer,
+                        buffer: self.material_buffer.view(),
                         offset: idx,
                     },
                 );
-            });
-        self.device.unmap_buffer(self.material_buffer.buffer);
+            }
+        }
+        span.exit();
 
         drop(meshes_query);
         drop(materials_query);
+        drop(textures_query);
 
         commands.run_on(world.get_mut());
 
-        let command_list = self.immediate_command_encoder.finish()?;
-        self.graphics_queue
-            .execute_command_lists(&[Some(command_list)]);
-
-        self.wait_for_previous_frame()?;
-        Ok(())
+        // Mesh data goes out with any textures created since the last frame.
+        self.flush_uploads()
     }
 
-    pub fn prepare_render(&mut self, world: &World) -> Result<(), Box<dyn Error>> {
-        self.render_prepare_camera_query
+    pub fn prepare_render(&mut self, world: &World) -> Result<(), RenderError> {
+        self.stats.begin_frame();
+        let start = Instant::now();
+
+        self.apply_settings(world)?;
+
+        let light = self
+            .light_query
             .query(world.get())
             .iter()
-            .for_each(|(_entity, (camera, transform))| {
-                let view = ViewUniform {
-                    projection: camera.projection,
-                    view: transform.transform.inverse(),
-                    position: transform.transform.w_axis,
-                };
-                {
-                    let data = self
-                        .device
-                        .map_buffer::<ViewUniform>(self.view_buffer.buffer).unwrap();
-                    unsafe {
-                        std::ptr::copy_nonoverlapping(
-                            &view as *const _ as *const u8,
-                            data.as_ptr(),
-                            std::mem::size_of::<ViewUniform>(),
-                        )
-                    };
-                    self.device.unmap_buffer(self.view_buffer.buffer);
+            .next()
+            .map(|(_entity, (light,))| *light)
+            .unwrap_or_default();
+        let sky = self
+            .sky_query
+            .query(world.get())
+            .iter()
+            .next()
+            .map(|(_entity, (sky,))| *sky)
+            .unwrap_or_default();
+        self.clear_color = sky.horizon_color;
+
+        self.constants.begin_frame(self.frame_index);
+        self.cbv_heap.begin_frame(self.frame_index);
+        let environment = self.environment_uniform();
+        self.shadow_pass.begin_frame();
+        let mut shadow_view = None;
+        for (_entity, (camera, transform)) in
+            self.render_prepare_camera_query.query(world.get()).iter()
+        {
+            let view = ViewUniform {
+                projection: camera.projection_matrix(),
+                view: transform.transform.inverse(),
+                position: camera.view_position(&transform.transform),
+                sun_direction: light.direction.normalize().extend(light.intensity),
+                sun_color: light.color.to_vec4(),
+                ambient_color: sky
+                    .zenith_color
+                    .lerp(sky.horizon_color, 0.5)
+                    .with_alpha(sky.ambient_intensity)
+                    .to_vec4(),
+                environment,
+            };
+            let frustum = Frustum::from_view_projection(&(view.projection * view.view));
+            // Only shadow receivers actually in view decide the depth range. Never-culled meshes
+            // get no exception, since a skybox would stretch the cascades over its whole extent.
+            let mut bounds_query = world.query::<(&WorldBounds, Option<&ReceivesShadows>)>();
+            let visible_bounds = bounds_query
+                .iter()
+                .filter(|(_entity, (_bounds, receives))| receives.copied().unwrap_or_default().0)
+                .map(|(_entity, (bounds, _receives))| &bounds.0)
+                .filter(|bounds| frustum.intersects_aabb(bounds));
+            self.shadow_cascades = visible_depth_range(&view.view, visible_bounds)
+                .map(|depth_range| {
+                    fit_cascades(
+                        camera,
+                        &transform.transform,
+                        light.direction,
+                        depth_range,
+                        self.settings.shadow_quality,
+                    )
+                })
+                .unwrap_or_default();
+            shadow_view = Some(view);
+            self.frustum = Some(frustum);
+            let allocation = self.constants.write(&view)?;
+            self.view_buffer =
+                self.cbv_heap
+                    .create_transient_cbv(&D3D12_CONSTANT_BUFFER_VIEW_DESC {
+                        BufferLocation: allocation.gpu_address,
+                        SizeInBytes: allocation.size as u32,
+                    })?;
+        }
+        if let Some(view) = shadow_view {
+            self.prepare_shadows(&view)?;
+        }
+
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (materials,) = materials_query.get().unwrap();
+        let modified = materials.drain_modified().collect::<Vec<_>>();
+        if !modified.is_empty() {
+            let _span =
+                tracing::debug_span!("upload_modified_materials", materials = modified.len())
+                    .entered();
+            let mut textures_query = world.get_singleton::<Assets<Texture>>();
+            let (textures,) = textures_query.get().unwrap();
+            let material_count = modified.iter().map(|handle| handle.idx() + 1).max();
+            if self.material_buffer.reserve(
+                &mut self.device,
+                &mut self.cbv_heap,
+                material_count.unwrap_or(0),
+            )? {
+                for (_entity, gpu_material) in world.query::<&mut GPUMaterial>().iter() {
+                    gpu_material.buffer = self.material_buffer.view();
                 }
-            });
+            }
+            let mut material_data = self.material_buffer.map(&self.device)?;
+            for handle in modified {
+                material_data.write(
+                    handle.idx(),
+                    &materials.get(handle).unwrap().gpu_data(textures, None),
+                );
+            }
+        }
 
+        self.stats.record_pass("prepare_render", start.elapsed());
         Ok(())
     }
 
-    pub fn render(&mut self, world: &World) -> Result<(), Box<dyn Error>> {
-        self.render_command_encoder.reset()?;
+    pub fn render(&mut self, world: &World) -> Result<(), RenderError> {
+        let result = self.render_frame(world);
+        if result.as_ref().is_err_and(RenderError::is_device_lost) {
+            let (last_completed, in_flight) = self.gpu_timer.breadcrumbs();
+            tracing::error!(
+                ?last_completed,
+                ?in_flight,
+                "GPU breadcrumbs at device removal"
+            );
+        }
+        result
+    }
 
+    fn render_frame(&mut self, world: &World) -> Result<(), RenderError> {
+        if let (true, Some(swapchain)) = (self.occluded, &self.swapchain) {
+            // Nothing is visible while occluded, so skip the frame until a test present succeeds.
+            if unsafe { swapchain.Present(0, DXGI_PRESENT_TEST) } == DXGI_STATUS_OCCLUDED {
+                return Ok(());
+            }
+            self.occluded = false;
+            self.restore_fullscreen()?;
+        }
+
+        // Catches textures created after `prepare`.
+        self.flush_uploads()?;
+
+        let start = Instant::now();
+        self.render_command_encoder.reset()?;
+        if self.capture_barriers || self.frame_graph_path.is_some() {
+            self.render_command_encoder.begin_trace();
+        }
+
+        self.gpu_timer.reset();
         self.render_command_encoder
-            .set_descriptor_heaps(&[Some(self.cbv_heap.get()), Some(self.sampler_heap.get())]);
-        self.render_command_encoder
-            .set_root_signature(&self.root_signature);
-        self.render_command_encoder.set_pipeline(&self.pipeline);
-        self.render_command_encoder
-            .set_viewport(self.width, self.height);
+            .begin_scope(&self.gpu_timer, "frame");
         self.render_command_encoder
-            .set_scissor(self.width, self.height);
+            .set_descriptor_heaps(&[Some(self.cbv_heap.get()), Some(self.sampler_heap.get())]);
+        self.render_command_encoder
+            .set_root_signature(&self.root_signature);
+        self.render_command_encoder
+            .set_viewport(self.targets.width, self.targets.height);
+        self.render_command_encoder
+            .set_scissor(self.targets.width, self.targets.height);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
 
-        self.render_command_encoder.transition_image(
-            &self.render_targets[self.frame_index],
-            D3D12_RESOURCE_STATE_PRESENT,
-            D3D12_RESOURCE_STATE_RENDER_TARGET,
-        );
+        self.draws.clear();
+        let has_meshes = self.mesh_query.query(world.get()).iter().next().is_some();
+        if has_meshes {
+            self.build_draw_list(world)?;
+        }
+        // An empty scene keeps its clears and the passes that don't depend on geometry.
+        self.stats.empty_scene = is_empty_scene(has_meshes, &self.draws);
+        let line_vertex_count = self.upload_debug_lines(world)?;
+
+        let depth = self
+            .device
+            .get_image(self.targets.depth)
+            .allocation
+            .resource();
+        let dsv_handle = self.dsv_heap.get_handle(self.targets.depth_view.0);
+        if !self.stats.empty_scene {
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "depth_prepass");
+            self.render_command_encoder
+                .require_state(depth, D3D12_RESOURCE_STATE_DEPTH_WRITE);
+            self.render_command_encoder.set_depth_target(dsv_handle);
+            self.render_command_encoder
+                .clear_depth_target(dsv_handle, Projection::DEPTH_CLEAR);
+            self.render_command_encoder
+                .set_pipeline(&self.depth_pipeline);
+            self.stats.draw_calls += self.draw_batches(&self.render_command_encoder, INVALID_INDEX);
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("depth_prepass", start.elapsed());
+            self.stats.draw_calls += self.render_shadows();
+        }
+
+        // Occlusion of an empty depth buffer is none at all.
+        #[cfg(feature = "ambient-occlusion")]
+        let ao_texture_index = if self.stats.empty_scene {
+            INVALID_INDEX
+        } else {
+            self.render_ambient_occlusion()
+        };
+        #[cfg(not(feature = "ambient-occlusion"))]
+        let ao_texture_index = INVALID_INDEX;
 
-        let rtv_handle = self.rtv_heap.get_handle(self.frame_index);
-        let dsv_handle = self.dsv_heap.get_handle(0);
+        let main_start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "main");
+        let scene_color = self
+            .device
+            .get_image(self.targets.scene_color)
+            .allocation
+            .resource();
+        let (color_target, rtv_handle) = match self.targets.msaa_color {
+            Some((image, view)) => (
+                self.device.get_image(image).allocation.resource(),
+                self.rtv_heap.get_handle(view.0),
+            ),
+            None => (
+                scene_color,
+                self.rtv_heap.get_handle(self.targets.scene_color_rtv.0),
+            ),
+        };
+        self.render_command_encoder
+            .require_state(color_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        self.render_command_encoder.require_state(
+            self.device
+                .get_image(self.targets.depth)
+                .allocation
+                .resource(),
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        );
         self.render_command_encoder
             .set_render_target(rtv_handle, Some(&dsv_handle));
-
         self.render_command_encoder
-            .clear_render_target(rtv_handle, &[0.0, 0.0, 0.0, 1.0]);
+            .set_viewport(self.targets.width, self.targets.height);
+        self.render_command_encoder
+            .set_scissor(self.targets.width, self.targets.height);
         self.render_command_encoder
-            .clear_depth_target(dsv_handle, 0.0);
+            .clear_render_target(rtv_handle, &self.clear_color.to_array());
+        if self.stats.empty_scene {
+            // Without a prepass the skybox still needs a cleared depth buffer to test against.
+            self.render_command_encoder
+                .clear_depth_target(dsv_handle, Projection::DEPTH_CLEAR);
+        } else {
+            self.render_command_encoder.set_pipeline(&self.pipeline);
+            self.stats.draw_calls +=
+                self.draw_batches(&self.render_command_encoder, ao_texture_index);
+        }
+        self.stats.draw_calls += self.draw_skybox();
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats.record_pass("main", main_start.elapsed());
 
+        if self.debug_draw.winding {
+            let winding_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "winding");
+            self.render_command_encoder
+                .require_state(color_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+            self.stats.draw_calls += self.draw_winding();
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("winding", winding_start.elapsed());
+        }
+
+        if line_vertex_count > 0 {
+            let debug_draw_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "debug_draw");
+            self.render_command_encoder
+                .require_state(color_target, D3D12_RESOURCE_STATE_RENDER_TARGET);
+            self.stats.draw_calls += self.draw_debug_lines(line_vertex_count);
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats
+                .record_pass("debug_draw", debug_draw_start.elapsed());
+        }
+
+        if self.targets.msaa_color.is_some() {
+            let resolve_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "resolve");
+            self.render_command_encoder
+                .require_state(color_target, D3D12_RESOURCE_STATE_RESOLVE_SOURCE);
+            self.render_command_encoder
+                .require_state(scene_color, D3D12_RESOURCE_STATE_RESOLVE_DEST);
+            self.render_command_encoder.resolve_image(
+                color_target,
+                scene_color,
+                SCENE_COLOR_FORMAT,
+            );
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("resolve", resolve_start.elapsed());
+        }
+
+        let bloom_index = self.render_bloom();
+
+        let tonemap_start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "tonemap");
+        // Fetched again since bloom borrowed the renderer mutably.
+        let scene_color = self
+            .device
+            .get_image(self.targets.scene_color)
+            .allocation
+            .resource();
+        let backbuffer = &self.render_targets[self.frame_index];
+        self.render_command_encoder
+            .require_state(scene_color, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+        self.render_command_encoder
+            .require_state(backbuffer, D3D12_RESOURCE_STATE_RENDER_TARGET);
+        self.render_command_encoder.set_render_target(
+            self.rtv_heap
+                .get_handle(self.swapchain_rtvs[self.frame_index].0),
+            None,
+        );
+        self.render_command_encoder
+            .set_pipeline(&self.tonemap_pipeline);
+        self.render_command_encoder
+            .set_viewport(self.width, self.height);
+        self.render_command_encoder
+            .set_scissor(self.width, self.height);
         self.render_command_encoder
             .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .set_root_constants(&TonemapResources::new(
+                self.targets.scene_color_srv.0 as u32,
+                self.tonemap_sampler.0 as u32,
+                bloom_index,
+                &self.settings.post,
+                !self.desc.srgb_output,
+            ));
+        self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+        self.stats.draw_calls += 1;
+        self.end_bloom();
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats.record_pass("tonemap", tonemap_start.elapsed());
 
-        self.mesh_query.query(world.get()).iter().for_each(
-            |(_entity, (mesh, material, transform))| {
-                let render_resources = RenderResources {
-                    vertex_buffer_id: mesh.vertex_buffer.view.0 as u32,
-                    transform_buffer_id: transform.buffer.view.0 as u32,
-                    transform_offset: transform.offset as u32,
-                    view_buffer_index: self.view_buffer.view.0 as u32,
-                    material_buffer_index: material.buffer.view.0 as u32,
-                    material_offset: material.offset as u32,
-                };
-                self.render_command_encoder
-                    .set_root_constants(&render_resources);
-                self.render_command_encoder
-                    .bind_index_buffer(&D3D12_INDEX_BUFFER_VIEW {
-                        BufferLocation: unsafe {
-                            self.device
-                                .get_buffer(mesh.index_buffer)
-                                .allocation
-                                .resource()
-                                .GetGPUVirtualAddress()
-                        },
-                        SizeInBytes: (mesh.index_count * std::mem::size_of::<u32>()) as u32,
-                        Format: DXGI_FORMAT_R32_UINT,
-                    });
-                self.render_command_encoder.draw_indexed_instanced(
-                    mesh.index_count as u32,
-                    1,
-                    0,
-                    0,
+        #[cfg(feature = "labels")]
+        if self.labels.enabled {
+            let labels_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "labels");
+            self.require_backbuffer();
+            let viewport = Vec2::new(self.width as f32, self.height as f32);
+            let labels = label::project_labels(world, viewport, self.labels.light_icons);
+            let ui_viewport = self.ui_viewport();
+            let marker_size = self.labels.marker_size as f32 * ui_viewport.scale;
+            self.render_command_encoder
+                .set_pipeline(&self.marker_pipeline);
+            for label in &labels {
+                // The text joins the rest of the frame's text, drawn after the UI.
+                self.queue_text(
+                    label.text_origin(marker_size, &ui_viewport),
+                    &label.text,
+                    label.color,
                 );
-            },
-        );
+                if !label.marker {
+                    continue;
+                }
+                self.render_command_encoder
+                    .set_root_constants(&MarkerResources::new(label, marker_size, viewport));
+                self.render_command_encoder.draw_instanced(6, 1, 0, 0);
+                self.stats.draw_calls += 1;
+            }
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("labels", labels_start.elapsed());
+        }
+
+        #[cfg(feature = "debug-ui")]
+        if self.debug_text.any_enabled() {
+            let debug_text_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "debug_text");
+            self.require_backbuffer();
+            self.stats.draw_calls += self.draw_debug_text(world)?;
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats
+                .record_pass("debug_text", debug_text_start.elapsed());
+        }
+
+        if self.gizmo.enabled {
+            let gizmo_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "gizmo");
+            self.require_backbuffer();
+            let (x, y, size) = self.gizmo.viewport(self.width, self.height);
+            self.render_command_encoder
+                .set_pipeline(&self.gizmo_pipeline);
+            self.render_command_encoder
+                .set_viewport_rect(x, y, size, size);
+            self.render_command_encoder
+                .set_scissor_rect(x, y, size, size);
+            self.render_command_encoder
+                .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_LINELIST);
+            self.render_command_encoder
+                .set_root_constants(&GizmoResources {
+                    view_buffer_index: self.view_buffer.0 as u32,
+                });
+            self.render_command_encoder.draw_instanced(12, 1, 0, 0);
+            self.stats.draw_calls += 1;
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("gizmo", gizmo_start.elapsed());
+        }
+
+        #[cfg(feature = "debug-ui")]
+        if !self.ui.draws.is_empty() {
+            let ui_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "ui");
+            self.require_backbuffer();
+            self.stats.draw_calls += self.draw_ui();
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("ui", ui_start.elapsed());
+        }
 
-        self.render_command_encoder.transition_image(
+        if !self.text_pass.glyphs.is_empty() {
+            let text_start = Instant::now();
+            self.render_command_encoder
+                .begin_scope(&self.gpu_timer, "text");
+            self.require_backbuffer();
+            self.stats.draw_calls += self.draw_queued_text()?;
+            self.render_command_encoder.end_scope(&self.gpu_timer);
+            self.stats.record_pass("text", text_start.elapsed());
+        }
+
+        let readback = match self.readback_request.take() {
+            Some(request) => Some((request, self.copy_backbuffer()?)),
+            None => None,
+        };
+        self.render_command_encoder.require_state(
             &self.render_targets[self.frame_index],
-            D3D12_RESOURCE_STATE_RENDER_TARGET,
             D3D12_RESOURCE_STATE_PRESENT,
         );
 
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.render_command_encoder.resolve_timer(
+            &self.gpu_timer,
+            self.device.get_buffer(self.gpu_timer.readback()),
+        );
+
+        let submit_start = Instant::now();
         let command_list = self.render_command_encoder.finish()?;
         self.graphics_queue
             .execute_command_lists(&[Some(command_list)]);
+        if let Some(passes) = self.render_command_encoder.take_trace() {
+            let trace = self.name_barriers(passes);
+            if std::mem::take(&mut self.capture_barriers) {
+                tracing::info!("{}", trace);
+            }
+            if let Some(path) = self.frame_graph_path.take() {
+                match FrameGraph::from_trace(&trace).save(&path) {
+                    Ok(()) => tracing::info!(path = %path.display(), "Saved frame graph"),
+                    Err(err) => {
+                        tracing::error!(path = %path.display(), %err, "Failed to save frame graph")
+                    }
+                }
+            }
+        }
+
+        if let Some(swapchain) = &self.swapchain {
+            let status = unsafe { swapchain.Present(self.settings.vsync as u32, 0) };
+            status.ok().map_err(RenderError::swapchain)?;
+            self.occluded = status == DXGI_STATUS_OCCLUDED;
+            let mut frame_statistics = DXGI_FRAME_STATISTICS::default();
+            // Statistics are unavailable until the first synced present and while occluded.
+            if unsafe { swapchain.GetFrameStatistics(&mut frame_statistics) }.is_ok() {
+                self.stats.present.record(
+                    frame_statistics.PresentCount,
+                    frame_statistics.PresentRefreshCount,
+                    frame_statistics.SyncRefreshCount,
+                    self.settings.vsync,
+                );
+            }
+        }
+        self.stats.record_pass("submit", submit_start.elapsed());
+
+        let wait_start = Instant::now();
+        self.wait_for_previous_frame()?;
+        self.stats.gpu_wait_time = wait_start.elapsed();
+
+        if let Some((request, (buffer, footprint))) = readback {
+            let image = self.read_backbuffer(buffer, &footprint);
+            self.device.destroy_buffer(buffer);
+            match request {
+                ReadbackRequest::Screenshot(path) => {
+                    match image.and_then(|image| image.save_png(&path)) {
+                        Ok(()) => tracing::info!(path = %path.display(), "Saved screenshot"),
+                        Err(err) => {
+                            tracing::error!(path = %path.display(), %err, "Failed to save screenshot")
+                        }
+                    }
+                }
+                ReadbackRequest::Image => self.readback_image = Some(image?),
+            }
+        }
+
+        for timing in self.gpu_timer.read(&self.device)? {
+            if timing.name == "frame" {
+                self.stats.gpu_time = timing.gpu_time;
+            } else {
+                self.stats.gpu_passes.push(timing);
+            }
+        }
+        let capacity = self.capacity_stats(world);
+        self.stats.record_capacity(capacity);
+
+        Ok(())
+    }
+
+    // Fills `draws` from every visible mesh, along with the per-draw override, morph weight and
+    // instance data the batches read.
+    fn build_draw_list(&mut self, world: &World) -> Result<(), RenderError> {
+        let override_count = world.query::<&MaterialOverride>().iter().count();
+        self.material_override_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            override_count,
+        )?;
+        // Sized for every morphed mesh, culled or not, like the overrides.
+        let morph_weight_capacity = world
+            .query::<(&GPUMesh, &MorphWeights)>()
+            .iter()
+            .filter(|(_entity, (mesh, _weights))| mesh.morph_targets.is_some())
+            .map(|(_entity, (mesh, _weights))| mesh.morph_target_count)
+            .sum();
+        self.morph_weight_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            morph_weight_capacity,
+        )?;
+        let material_override_view = self.material_override_buffer.view();
+        let mut override_data = self.material_override_buffer.map(&self.device)?;
+        let mut override_count = 0;
+        let mut materials_query = world.get_singleton::<Assets<Material>>();
+        let (materials,) = materials_query.get().unwrap();
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+        let mut morph_weight_data = self.morph_weight_buffer.map(&self.device)?;
+        let mut morph_weight_count = 0;
+        self.mesh_query.query(world.get()).iter().for_each(
+            |(
+                _entity,
+                (
+                    mesh,
+                    material,
+                    transform,
+                    bounds,
+                    never_cull,
+                    casts_shadows,
+                    receives_shadows,
+                    weights,
+                    material_override,
+                ),
+            )| {
+                let casts_shadows = casts_shadows.copied().unwrap_or_default().0;
+                let mut visible = true;
+                if let (Some(frustum), Some(bounds), None) = (&self.frustum, bounds, never_cull) {
+                    if !frustum.intersects_aabb(&bounds.0) {
+                        if !casts_shadows || !self.shadow_pass.casts_into_view(&bounds.0) {
+                            self.stats.culled += 1;
+                            return;
+                        }
+                        visible = false;
+                    }
+                }
+                // Morphed draws get their own weights, so they never share an instanced batch.
+                let mut morph_weight_offset = INVALID_INDEX;
+                if let (Some(weights), true) = (weights, mesh.morph_targets.is_some()) {
+                    let count = mesh.morph_target_count;
+                    for target in 0..count {
+                        let weight = weights.0.get(target).copied().unwrap_or(0.0);
+                        morph_weight_data.write(morph_weight_count + target, &weight);
+                    }
+                    morph_weight_offset = morph_weight_count as u32;
+                    morph_weight_count += count;
+                }
+                // Overridden draws get their own material slot, merged from the asset here.
+                let mut material = *material;
+                if let Some((material_override, handle)) = material_override {
+                    if let Some(base) = materials.get(handle) {
+                        let data = base.gpu_data(textures, Some(material_override));
+                        override_data.write(override_count, &data);
+                        material = GPUMaterial {
+                            buffer: material_override_view,
+                            offset: override_count,
+                        };
+                        override_count += 1;
+                    }
+                }
+                self.draws.push(DrawItem {
+                    mesh: *mesh,
+                    material,
+                    transform_offset: transform.offset as u32,
+                    morph_weight_offset,
+                    visible,
+                    casts_shadows,
+                    receives_shadows: receives_shadows.copied().unwrap_or_default().0,
+                });
+            },
+        );
+        drop(morph_weight_data);
+        drop(override_data);
+        drop(materials_query);
+        drop(textures_query);
+        sort_draws(&mut self.draws);
+
+        self.instance_buffer
+            .reserve(&mut self.device, &mut self.cbv_heap, self.draws.len())?;
+        let mut instance_data = self.instance_buffer.map(&self.device)?;
+        for (idx, draw) in self.draws.iter().enumerate() {
+            instance_data.write(idx, &draw.transform_offset);
+        }
+        drop(instance_data);
+        self.stats.instances = self.draws.len() as u32;
+        Ok(())
+    }
+
+    // Draws what the camera sees from its view.
+    fn draw_batches(&self, encoder: &CommandEncoder, ao_texture_index: u32) -> u32 {
+        self.draw_batch_list(
+            encoder,
+            visible_batches(&self.draws),
+            self.view_buffer,
+            ao_texture_index,
+            self.shadow_pass.uniform,
+        )
+    }
+
+    fn draw_batch_list<'a>(
+        &self,
+        encoder: &CommandEncoder,
+        batches: impl Iterator<Item = (u32, &'a [DrawItem])>,
+        view_buffer: ViewId,
+        ao_texture_index: u32,
+        shadow_buffer: Option<ViewId>,
+    ) -> u32 {
+        let mut draw_calls = 0;
+        for (instance_offset, batch) in batches {
+            let mesh = &batch[0].mesh;
+            let material = &batch[0].material;
+            let morph_weight_offset = batch[0].morph_weight_offset;
+            let render_resources = RenderResources {
+                vertex_buffer_id: mesh.vertex_buffer.view.0 as u32,
+                transform_buffer_id: self.transform_buffer.view().view.0 as u32,
+                instance_buffer_id: self.instance_buffer.view().view.0 as u32,
+                instance_offset,
+                view_buffer_index: view_buffer.0 as u32,
+                material_buffer_index: material.buffer.view.0 as u32,
+                material_offset: material.offset as u32,
+                ao_texture_index,
+                morph_target_buffer_index: match mesh.morph_targets {
+                    Some(morph_targets) if morph_weight_offset != INVALID_INDEX => {
+                        morph_targets.view.0 as u32
+                    }
+                    _ => INVALID_INDEX,
+                },
+                morph_weight_buffer_index: self.morph_weight_buffer.view().view.0 as u32,
+                morph_weight_offset,
+                shadow_buffer_index: match shadow_buffer {
+                    Some(shadow_buffer) if batch[0].receives_shadows => shadow_buffer.0 as u32,
+                    _ => INVALID_INDEX,
+                },
+            };
+            encoder.set_root_constants(&render_resources);
+            encoder.bind_index_buffer(&mesh.index_buffer_view);
+            encoder.draw_indexed_instanced(mesh.index_count as u32, batch.len() as u32, 0, 0);
+            draw_calls += 1;
+        }
+        draw_calls
+    }
 
-        unsafe { self.swapchain.Present(1, 0) }.ok()?;
+    fn apply_settings(&mut self, world: &World) -> Result<(), RenderError> {
+        let settings = {
+            let mut settings_query = world.get_singleton::<RenderSettings>();
+            let (settings,) = settings_query.get().unwrap();
+            if *settings == self.settings {
+                return Ok(());
+            }
+            settings.clamp_to(self.device.capabilities());
+            if *settings == self.settings {
+                return Ok(());
+            }
+            settings.clone()
+        };
+
+        if settings.anti_aliasing != self.settings.anti_aliasing {
+            (self.depth_pipeline, self.pipeline) = create_mesh_pipelines(
+                &self.device,
+                &self.root_signature,
+                settings.anti_aliasing.sample_count(),
+                self.desc.depth_format,
+            )?;
+            self.debug_draw_pass.pipeline = debug_draw::create_line_pipeline(
+                &self.device,
+                &self.root_signature,
+                settings.anti_aliasing.sample_count(),
+                self.desc.depth_format,
+            )?;
+            self.debug_draw_pass.winding_pipeline = debug_draw::create_winding_pipeline(
+                &self.device,
+                &self.root_signature,
+                settings.anti_aliasing.sample_count(),
+                self.desc.depth_format,
+            )?;
+            self.skybox_pass.pipeline = skybox::create_skybox_pipeline(
+                &self.device,
+                &self.root_signature,
+                settings.anti_aliasing.sample_count(),
+                self.desc.depth_format,
+            )?;
+        }
+        let ao_toggled = (settings.ambient_occlusion == AmbientOcclusion::Off)
+            != (self.settings.ambient_occlusion == AmbientOcclusion::Off);
+        if settings.anti_aliasing != self.settings.anti_aliasing
+            || settings.resolution_scale != self.settings.resolution_scale
+            || ao_toggled
+            || settings.post.bloom != self.settings.post.bloom
+            || settings.ambient_occlusion_resolution != self.settings.ambient_occlusion_resolution
+        {
+            self.recreate_targets(&settings)?;
+        }
 
+        if settings.shadow_quality != self.settings.shadow_quality {
+            if let Some(shadow_map) = self.shadow_pass.map.take() {
+                shadow_map.destroy(
+                    &mut self.device,
+                    &mut self.dsv_heap,
+                    &mut self.cbv_heap,
+                    &self.render_command_encoder,
+                );
+            }
+            self.shadow_pass.map = ShadowMap::new(
+                &mut self.device,
+                &mut self.dsv_heap,
+                &mut self.cbv_heap,
+                settings.shadow_quality,
+            )?;
+            if let Some(shadow_map) = &self.shadow_pass.map {
+                shadow_map.track(&self.device, &self.render_command_encoder);
+            }
+        }
+        if settings.texture_quality != self.settings.texture_quality {
+            tracing::info!(
+                quality = ?settings.texture_quality,
+                "Texture quality applies to textures created from now on"
+            );
+        }
+        if settings.upload_adapter != self.settings.upload_adapter {
+            // Uploads staged on the old adapter are copied out before its heap goes away.
+            self.flush_uploads()?;
+            self.upload_adapter = if settings.upload_adapter {
+                self.device.create_upload_adapter()?
+            } else {
+                None
+            };
+            match &self.upload_adapter {
+                Some(upload_adapter) => {
+                    tracing::info!(adapter = %upload_adapter.name, "Staging uploads on secondary adapter")
+                }
+                None if settings.upload_adapter => {
+                    tracing::warn!("No secondary adapter found for upload staging")
+                }
+                None => {}
+            }
+        }
+        self.gpu_timer.markers = settings.gpu_markers;
+        tracing::info!(?settings, "Applied render settings");
+        self.settings = settings;
+        Ok(())
+    }
+
+    fn recreate_targets(&mut self, settings: &RenderSettings) -> Result<(), RenderError> {
+        self.targets.destroy(
+            &mut self.device,
+            &mut self.rtv_heap,
+            &mut self.dsv_heap,
+            &mut self.cbv_heap,
+            &self.render_command_encoder,
+        );
+        self.targets = RenderTargets::new(
+            &mut self.device,
+            &mut self.rtv_heap,
+            &mut self.dsv_heap,
+            &mut self.cbv_heap,
+            (self.width, self.height),
+            self.desc.depth_format,
+            settings,
+        )?;
+        self.targets
+            .track(&self.device, &self.render_command_encoder);
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // The window's DPI scale, followed by UiScaling::Dpi.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    // Where the screen-space layers are laid out this frame, under the current UiScaling.
+    pub fn ui_viewport(&self) -> UiViewport {
+        self.settings.ui_scaling.viewport(
+            Vec2::new(self.width as f32, self.height as f32),
+            self.scale_factor,
+        )
+    }
+
+    pub fn desc(&self) -> &RendererDesc {
+        &self.desc
+    }
+
+    pub fn fullscreen(&self) -> FullscreenMode {
+        self.fullscreen
+    }
+
+    pub fn refresh_rate(&self) -> Option<f32> {
+        self.stats.present.refresh_rate
+    }
+
+    // The swapchain can end up on another output whenever the window moves.
+    pub fn update_refresh_rate(&mut self) {
+        let refresh_rate = self.swapchain.as_ref().and_then(query_refresh_rate);
+        if refresh_rate != self.stats.present.refresh_rate {
+            tracing::info!(?refresh_rate, "Display refresh rate");
+            self.stats.present.refresh_rate = refresh_rate;
+        }
+    }
+
+    pub fn is_occluded(&self) -> bool {
+        self.occluded
+    }
+
+    // A zero-sized window is minimized; the old buffers are kept until it is restored.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
+        if width == 0 || height == 0 || (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        self.resize_output(width, height)
+    }
+
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) -> Result<(), RenderError> {
+        let Some(swapchain) = self.swapchain.clone() else {
+            return Ok(());
+        };
+        if mode == self.fullscreen {
+            return Ok(());
+        }
         self.wait_for_previous_frame()?;
+        unsafe { swapchain.SetFullscreenState(mode == FullscreenMode::Exclusive, None) }
+            .map_err(RenderError::swapchain)?;
+        self.fullscreen = mode;
+        tracing::info!(?mode, "Changed fullscreen mode");
+        // Zero lets DXGI match the buffers to the window, or to the output in exclusive mode.
+        self.resize_output(0, 0)
+    }
 
+    // Exclusive mode is dropped by DXGI when the window loses focus, so re-enter it once visible.
+    fn restore_fullscreen(&mut self) -> Result<(), RenderError> {
+        let Some(swapchain) = &self.swapchain else {
+            return Ok(());
+        };
+        if self.fullscreen != FullscreenMode::Exclusive {
+            return Ok(());
+        }
+        let mut fullscreen = BOOL::default();
+        unsafe { swapchain.GetFullscreenState(Some(&mut fullscreen), None) }
+            .map_err(RenderError::swapchain)?;
+        if !fullscreen.as_bool() {
+            self.fullscreen = FullscreenMode::Windowed;
+            self.set_fullscreen(FullscreenMode::Exclusive)?;
+        }
+        Ok(())
+    }
+
+    fn resize_output(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
+        self.wait_for_previous_frame()?;
+        for (render_target, view) in self
+            .render_targets
+            .drain(..)
+            .zip(self.swapchain_rtvs.drain(..))
+        {
+            self.render_command_encoder.untrack_resource(&render_target);
+            self.rtv_heap.free(view);
+        }
+        match &self.swapchain {
+            Some(swapchain) => {
+                let resized = unsafe {
+                    swapchain.ResizeBuffers(
+                        self.desc.buffer_count(),
+                        width,
+                        height,
+                        DXGI_FORMAT_UNKNOWN,
+                        0,
+                    )
+                }
+                .and_then(|()| {
+                    let mut desc = DXGI_SWAP_CHAIN_DESC1::default();
+                    unsafe { swapchain.GetDesc1(&mut desc) }?;
+                    Ok(desc)
+                })
+                .map_err(RenderError::swapchain);
+
+                // The views come back even when the resize failed, so the next frame still has
+                // the old buffers to render into.
+                (self.render_targets, self.swapchain_rtvs) =
+                    create_swapchain_views(swapchain, &mut self.rtv_heap, &self.desc)?;
+                let desc = resized?;
+                self.width = desc.Width;
+                self.height = desc.Height;
+            }
+            None => {
+                if let Some(image) = self.offscreen_target.take() {
+                    self.device.destroy_image(image);
+                }
+                self.width = width;
+                self.height = height;
+                let (render_target, view, image) = create_offscreen_target(
+                    &mut self.device,
+                    &mut self.rtv_heap,
+                    &self.desc,
+                    width,
+                    height,
+                )?;
+                self.render_targets = vec![render_target];
+                self.swapchain_rtvs = vec![view];
+                self.offscreen_target = Some(image);
+            }
+        }
+        for render_target in &self.render_targets {
+            self.render_command_encoder
+                .track_resource(render_target, D3D12_RESOURCE_STATE_PRESENT);
+        }
+        self.frame_index = current_backbuffer(self.swapchain.as_ref());
+
+        let settings = self.settings.clone();
+        self.recreate_targets(&settings)?;
+        self.update_refresh_rate();
+        tracing::info!(width = self.width, height = self.height, "Resized output");
         Ok(())
     }
 
-    pub fn wait_for_previous_frame(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn checkerboard(&self) -> Handle<Texture> {
+        self.checkerboard.clone().unwrap()
+    }
+
+    // Writes vertices straight into upload memory, for a mesh's `staged_vertices`. The mesh takes
+    // ownership of the staging; it is freed once uploaded or when the mesh is dropped.
+    pub fn stage_vertices(
+        &mut self,
+        len: usize,
+        vertices: impl IntoIterator<Item = Vertex>,
+    ) -> Result<StagedVertices, RenderError> {
+        let mut hasher = ContentHasher::new();
+        let mut written = 0;
+        let vertices = vertices.into_iter().inspect(|vertex| {
+            hasher.write(bytemuck::bytes_of(vertex));
+            written += 1;
+        });
+        let buffer =
+            self.device
+                .create_staging_buffer_from_iter("Staged Vertices", len, vertices)?;
+        // Vertices the iterator ran out of are zeroed in the buffer, and hashed the same way.
+        for _ in written..len {
+            hasher.write(bytemuck::bytes_of(&<Vertex as bytemuck::Zeroable>::zeroed()));
+        }
+        Ok(StagedVertices {
+            buffer,
+            len,
+            hash: hasher.finish(),
+        })
+    }
+
+    pub fn create_texture_from_rgba8(
+        &mut self,
+        world: &World,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Handle<Texture>, RenderError> {
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(RenderError::InvalidTexture(format!(
+                "{}x{} RGBA8 texture needs {} bytes, got {}",
+                width,
+                height,
+                expected,
+                data.len()
+            )));
+        }
+        self.create_texture(
+            world,
+            &format!("Texture {}x{}", width, height),
+            &TextureData::rgba8(width, height, data.to_vec()),
+        )
+    }
+
+    pub fn create_texture_from_file(
+        &mut self,
+        world: &World,
+        path: &Path,
+    ) -> Result<Handle<Texture>, RenderError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let data = match extension.as_deref() {
+            Some("dds") => load_dds(&std::fs::read(path)?)?,
+            Some("ktx2") => load_ktx2(&std::fs::read(path)?)?,
+            _ => {
+                let image = image::open(path)?.into_rgba8();
+                let (width, height) = image.dimensions();
+                TextureData::rgba8(width, height, image.into_raw())
+            }
+        };
+        self.create_texture(world, &path.display().to_string(), &data)
+    }
+
+    pub fn create_texture(
+        &mut self,
+        world: &World,
+        name: &str,
+        data: &TextureData,
+    ) -> Result<Handle<Texture>, RenderError> {
+        let texture = self.create_texture_resource(name, data)?;
+        let mut textures_query = world.get_singleton::<Assets<Texture>>();
+        let (textures,) = textures_query.get().unwrap();
+        Ok(textures.push(texture))
+    }
+
+    // The image and view behind a texture, for textures owned outside the Assets store.
+    pub(crate) fn create_texture_resource(
+        &mut self,
+        name: &str,
+        data: &TextureData,
+    ) -> Result<Texture, RenderError> {
+        let skip = data.droppable_mips(self.settings.texture_quality.mip_bias());
+        let mips = &data.mips[skip..];
+        let width = data.width >> skip;
+        let height = data.height >> skip;
+        let mip_levels = mips.len() as u32;
+        let image =
+            self.device
+                .create_texture_image(name, width, height, mip_levels, data.format)?;
+
+        let (footprints, total_size) = self.device.copyable_footprints(image);
+        let write_mips = |staging_data: *mut u8| {
+            for (mip, footprint) in mips.iter().zip(&footprints) {
+                let row_size = footprint.row_size as usize;
+                let row_pitch = footprint.layout.Footprint.RowPitch as usize;
+                let offset = footprint.layout.Offset as usize;
+                for (row, texels) in mip
+                    .chunks_exact(row_size)
+                    .take(footprint.num_rows as usize)
+                    .enumerate()
+                {
+                    unsafe {
+                        std::ptr::copy_nonoverlapping(
+                            texels.as_ptr(),
+                            staging_data.add(offset + row * row_pitch),
+                            row_size,
+                        )
+                    };
+                }
+            }
+        };
+
+        if self.upload_adapter.is_some() {
+            let staged = self.stage_on_adapter(total_size, &write_mips)?;
+            let primary_fence = self.upload_adapter.as_ref().unwrap().primary_fence();
+            self.graphics_queue
+                .wait(primary_fence, staged.fence_value)?;
+            // Footprints were laid out from zero; the upload sits at its offset in the shared heap.
+            let footprints = footprints
+                .into_iter()
+                .map(|mut footprint| {
+                    footprint.layout.Offset += staged.offset;
+                    footprint
+                })
+                .collect();
+            self.uploads.push_image(
+                UploadSource::Shared(staged.buffer),
+                image,
+                footprints,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+        } else {
+            let staging_buffer = self.device.create_buffer(
+                &format!("{} (staging)", name),
+                total_size,
+                DXGI_FORMAT_UNKNOWN,
+                D3D12_RESOURCE_FLAG_NONE,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+                MemoryLocation::CpuToGpu,
+            )?;
+            write_mips(self.device.map_buffer::<u8>(staging_buffer)?.as_ptr());
+            self.device.unmap_buffer(staging_buffer);
+            self.uploads.push_image(
+                UploadSource::Staging(staging_buffer),
+                image,
+                footprints,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+        }
+
+        let view_desc = D3D12_SHADER_RESOURCE_VIEW_DESC {
+            Format: data.format,
+            ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+            Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+            Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                Texture2D: D3D12_TEX2D_SRV {
+                    MostDetailedMip: 0,
+                    MipLevels: mip_levels,
+                    PlaneSlice: 0,
+                    ResourceMinLODClamp: 0.0,
+                },
+            },
+        };
+        let view = self.cbv_heap.create_srv(
+            &format!("{} SRV", name),
+            self.device.get_image(image).allocation.resource(),
+            &view_desc,
+        )?;
+
+        Ok(Texture {
+            image,
+            view,
+            width,
+            height,
+            mip_levels,
+            format: data.format,
+            content_hash: data.content_hash(),
+        })
+    }
+
+    pub fn destroy_texture(&mut self, texture: Texture) {
+        self.device.destroy_image(texture.image);
+        self.cbv_heap.free(texture.view);
+    }
+
+    // Returns the existing sampler when one with the same description was created before.
+    pub fn create_sampler(
+        &mut self,
+        name: &str,
+        desc: &D3D12_SAMPLER_DESC,
+    ) -> Result<SamplerId, RenderError> {
+        self.samplers
+            .get_or_create(&mut self.sampler_heap, name, desc)
+    }
+
+    pub fn sampler_desc(&self, sampler: SamplerId) -> Option<D3D12_SAMPLER_DESC> {
+        self.samplers.desc(sampler)
+    }
+
+    // Linear filtering with wrapping, created with the renderer, for when a sampler of its own
+    // can't be.
+    pub fn default_sampler(&self) -> SamplerId {
+        self.samplers
+            .get(&sampler::LINEAR_WRAP)
+            .expect("default samplers are created with the renderer")
+    }
+
+    pub fn frame_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    fn capacity_stats(&self, world: &World) -> CapacityStats {
+        let material_count = {
+            let mut materials_query = world.get_singleton::<Assets<Material>>();
+            let (materials,) = materials_query.get().unwrap();
+            materials.len()
+        };
+        CapacityStats {
+            resource_descriptors: self.cbv_heap.usage(),
+            transient_descriptors: self.cbv_heap.transient_usage(),
+            render_target_descriptors: self.rtv_heap.usage(),
+            depth_target_descriptors: self.dsv_heap.usage(),
+            sampler_descriptors: self.sampler_heap.usage(),
+            transforms: Usage {
+                used: self.uploaded_transforms.len() - self.free_transform_slots.len(),
+                capacity: self.transform_buffer.capacity(),
+            },
+            materials: Usage {
+                used: material_count,
+                capacity: self.material_buffer.capacity(),
+            },
+            buffers: self.device.live_buffers(),
+            images: self.device.live_images(),
+        }
+    }
+
+    // Logs the passes, resource transitions and redundant barriers of the next rendered frame.
+    pub fn capture_barriers(&mut self) {
+        self.capture_barriers = true;
+    }
+
+    // Overlays draw onto the backbuffer tonemap left bound; this only declares it for the frame
+    // graph.
+    fn require_backbuffer(&self) {
+        self.render_command_encoder.require_state(
+            &self.render_targets[self.frame_index],
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        );
+    }
+
+    // Saves the passes of the next frame and the resources passed between them, as JSON for a
+    // .json path and as Graphviz source otherwise.
+    pub fn capture_frame_graph(&mut self, path: impl Into<PathBuf>) {
+        self.frame_graph_path = Some(path.into());
+    }
+
+    // Saves what the next frame presents as a PNG.
+    pub fn capture_screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.readback_request = Some(ReadbackRequest::Screenshot(path.into()));
+    }
+
+    // Reads back what the next frame presents; the pixels are available from `take_readback` once
+    // that frame has rendered.
+    pub fn request_readback(&mut self) {
+        self.readback_request = Some(ReadbackRequest::Image);
+    }
+
+    pub fn take_readback(&mut self) -> Option<ReadbackImage> {
+        self.readback_image.take()
+    }
+
+    fn copy_backbuffer(&mut self) -> Result<(BufferId, SubresourceFootprint), RenderError> {
+        let backbuffer = &self.render_targets[self.frame_index];
+        let (footprint, size) = self.device.resource_footprint(backbuffer);
+        let buffer = self.device.create_buffer(
+            "Backbuffer Readback",
+            size,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            MemoryLocation::GpuToCpu,
+        )?;
+        self.render_command_encoder
+            .require_state(backbuffer, D3D12_RESOURCE_STATE_COPY_SOURCE);
+        self.render_command_encoder.copy_image_to_buffer(
+            backbuffer,
+            self.device.get_buffer(buffer).allocation.resource(),
+            &footprint,
+        );
+        Ok((buffer, footprint))
+    }
+
+    fn read_backbuffer(
+        &self,
+        buffer: BufferId,
+        footprint: &SubresourceFootprint,
+    ) -> Result<ReadbackImage, RenderError> {
+        let size = self.device.get_buffer(buffer).size as usize;
+        let data = self.device.map_buffer::<u8>(buffer)?;
+        let image = ReadbackImage::from_footprint(
+            unsafe { std::slice::from_raw_parts(data.as_ptr(), size) },
+            footprint,
+        );
+        self.device.unmap_buffer(buffer);
+        image
+    }
+
+    fn name_barriers(&self, passes: TracedPasses) -> BarrierTrace {
+        let name = |resource: usize| {
+            if let Some(idx) = self
+                .render_targets
+                .iter()
+                .position(|target| target.as_raw() as usize == resource)
+            {
+                return format!("Swapchain {}", idx);
+            }
+            self.device
+                .resource_name(resource)
+                .map_or_else(|| format!("{:#x}", resource), str::to_string)
+        };
+        BarrierTrace {
+            passes: passes
+                .into_iter()
+                .map(|pass| PassTrace {
+                    name: pass.name,
+                    uses: pass
+                        .uses
+                        .into_iter()
+                        .map(|(resource, state)| ResourceUse {
+                            resource: name(resource),
+                            state,
+                        })
+                        .collect(),
+                    barriers: pass
+                        .barriers
+                        .into_iter()
+                        .map(|barrier| BarrierRecord {
+                            resource: name(barrier.resource),
+                            subresource: barrier.subresource,
+                            before: barrier.before,
+                            after: barrier.after,
+                            redundant: barrier.redundant,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn shadow_cascades(&self) -> &[ShadowCascade] {
+        &self.shadow_cascades
+    }
+
+    pub fn resource_report(&self) -> ResourceReport<'_> {
+        ResourceReport::new(
+            self.device
+                .tracker()
+                .records()
+                .chain(self.rtv_heap.tracker().records())
+                .chain(self.dsv_heap.tracker().records())
+                .chain(self.cbv_heap.tracker().records())
+                .chain(self.sampler_heap.tracker().records()),
+        )
+    }
+
+    pub fn wait_for_previous_frame(&mut self) -> Result<(), RenderError> {
         let fence_value = self.fence_value;
         self.graphics_queue.signal(&self.fence, fence_value)?;
         self.fence_value += 1;
@@ -745,16 +2407,20 @@ impl Renderer {
             }
         }
 
-        self.frame_index = unsafe { self.swapchain.GetCurrentBackBufferIndex() } as usize;
+        self.frame_index = current_backbuffer(self.swapchain.as_ref());
         Ok(())
     }
 
+    // Queued uploads are recorded ahead of `f`, so whatever it reads has its data.
     pub fn immediate_submit(
         &mut self,
         f: impl Fn(&Renderer, &CommandEncoder),
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), RenderError> {
         self.immediate_command_encoder.reset()?;
 
+        let staging_buffers = self
+            .uploads
+            .record(&self.device, &self.immediate_command_encoder);
         f(self, &self.immediate_command_encoder);
 
         let command_list = self.immediate_command_encoder.finish()?;
@@ -762,6 +2428,523 @@ impl Renderer {
             .execute_command_lists(&[Some(command_list)]);
 
         self.wait_for_previous_frame()?;
+        for buffer in staging_buffers {
+            self.device.destroy_buffer(buffer);
+        }
+        if let Some(upload_adapter) = &mut self.upload_adapter {
+            upload_adapter.reset();
+        }
         Ok(())
     }
+
+    // Textures staged on the upload adapter share its heap and are copied out in the next flush,
+    // which has to happen early once the heap is full.
+    fn stage_on_adapter(
+        &mut self,
+        size: u64,
+        write: &dyn Fn(*mut u8),
+    ) -> Result<StagedUpload, RenderError> {
+        let upload_adapter = self.upload_adapter.as_mut().unwrap();
+        if let Some(staged) = upload_adapter.stage(self.device.raw(), size, write)? {
+            return Ok(staged);
+        }
+        self.flush_uploads()?;
+        let upload_adapter = self.upload_adapter.as_mut().unwrap();
+        Ok(upload_adapter
+            .stage(self.device.raw(), size, write)?
+            .expect("empty staging heap is grown to fit"))
+    }
+
+    // Submits every queued upload at once. Textures and meshes created since the last call can't
+    // be drawn before it; `prepare` and `render` call it every frame.
+    pub fn flush_uploads(&mut self) -> Result<(), RenderError> {
+        if self.uploads.is_empty() {
+            return Ok(());
+        }
+        self.immediate_submit(|_r, _encoder| {})
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        // DXGI refuses to release a swapchain that is still in exclusive fullscreen.
+        if let (FullscreenMode::Exclusive, Some(swapchain)) = (self.fullscreen, &self.swapchain) {
+            let _ = unsafe { swapchain.SetFullscreenState(false, None) };
+        }
+    }
+}
+
+fn query_refresh_rate(swapchain: &IDXGISwapChain3) -> Option<f32> {
+    let output = unsafe { swapchain.GetContainingOutput() }.ok()?;
+    let mut desc = DXGI_OUTPUT_DESC::default();
+    unsafe { output.GetDesc(&mut desc) }.ok()?;
+    let mut mode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    unsafe {
+        EnumDisplaySettingsW(
+            PCWSTR(desc.DeviceName.as_ptr()),
+            ENUM_CURRENT_SETTINGS,
+            &mut mode,
+        )
+    }
+    .as_bool()
+    .then_some(mode.dmDisplayFrequency as f32)
+}
+
+// Offscreen output has a single target that is never presented.
+fn current_backbuffer(swapchain: Option<&IDXGISwapChain3>) -> usize {
+    swapchain.map_or(
+        0,
+        |swapchain| unsafe { swapchain.GetCurrentBackBufferIndex() } as usize,
+    )
+}
+
+// Stands in for the swapchain buffers when rendering offscreen. It is left in the same state a
+// presented buffer would be, so the frame transitions it exactly like a swapchain buffer.
+fn create_offscreen_target(
+    device: &mut Device,
+    rtv_heap: &mut DescriptorHeap,
+    desc: &RendererDesc,
+    width: u32,
+    height: u32,
+) -> Result<(ID3D12Resource, ViewId, ImageId), RenderError> {
+    let image = device.create_render_target(
+        "Offscreen Target",
+        width,
+        height,
+        desc.buffer_format(),
+        1,
+        D3D12_RESOURCE_STATE_PRESENT,
+    )?;
+    let view = rtv_heap.create_image_rtv(
+        device,
+        "Offscreen RTV",
+        image,
+        TargetView::format(desc.view_format()),
+    )?;
+    Ok((
+        device.get_image(image).allocation.resource().clone(),
+        view,
+        image,
+    ))
+}
+
+fn create_swapchain_views(
+    swapchain: &IDXGISwapChain3,
+    rtv_heap: &mut DescriptorHeap,
+    desc: &RendererDesc,
+) -> Result<(Vec<ID3D12Resource>, Vec<ViewId>), RenderError> {
+    let mut render_targets = Vec::new();
+    let mut views = Vec::new();
+    let view_desc = D3D12_RENDER_TARGET_VIEW_DESC {
+        Format: desc.view_format(),
+        ViewDimension: D3D12_RTV_DIMENSION_TEXTURE2D,
+        Anonymous: D3D12_RENDER_TARGET_VIEW_DESC_0 {
+            Texture2D: D3D12_TEX2D_RTV::default(),
+        },
+    };
+    for i in 0..desc.buffer_count() {
+        let render_target: ID3D12Resource = unsafe { swapchain.GetBuffer(i)? };
+        views.push(rtv_heap.create_rtv("Swapchain RTV", &render_target, Some(&view_desc))?);
+        render_targets.push(render_target);
+    }
+    Ok((render_targets, views))
+}
+
+const SCENE_COLOR_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R16G16B16A16_FLOAT;
+const INVALID_INDEX: u32 = u32::MAX;
+// Room for the view and other per-frame constants, per swapchain buffer.
+const CONSTANTS_PER_FRAME: usize = 64 * 1024;
+
+// A mesh uploaded this frame, waiting for its views before it becomes a `GPUMesh`.
+struct NewMesh {
+    idx: usize,
+    hash: u64,
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
+    index_buffer_view: D3D12_INDEX_BUFFER_VIEW,
+    index_count: usize,
+    morph_buffer: Option<BufferId>,
+    morph_target_count: usize,
+}
+
+struct RenderTargets {
+    width: u32,
+    height: u32,
+    depth: ImageId,
+    depth_view: ViewId,
+    scene_color: ImageId,
+    scene_color_rtv: ViewId,
+    scene_color_srv: ViewId,
+    msaa_color: Option<(ImageId, ViewId)>,
+    #[cfg(feature = "ambient-occlusion")]
+    ao: Option<AoTargets>,
+    bloom: Option<BloomTargets>,
+}
+
+impl RenderTargets {
+    fn new(
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        dsv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        (window_width, window_height): (u32, u32),
+        depth_format: DepthFormat,
+        settings: &RenderSettings,
+    ) -> Result<Self, RenderError> {
+        let (width, height) = settings.scaled_size(window_width, window_height);
+        let sample_count = settings.anti_aliasing.sample_count();
+        let multisampled = sample_count > 1;
+        rtv_heap.ensure_free(Self::rtv_count(width, height, settings))?;
+        dsv_heap.ensure_free(1)?;
+
+        let depth = device.create_render_target(
+            "Depth Texture",
+            width,
+            height,
+            depth_format.resource_format(),
+            sample_count,
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        )?;
+        let depth_view = dsv_heap.create_image_dsv(
+            device,
+            "Depth DSV",
+            depth,
+            TargetView::format(depth_format.dsv_format()),
+        )?;
+
+        let scene_color = device.create_render_target(
+            "Scene Color Target",
+            width,
+            height,
+            SCENE_COLOR_FORMAT,
+            1,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        )?;
+        let scene_color_rtv = rtv_heap.create_image_rtv(
+            device,
+            "Scene Color RTV",
+            scene_color,
+            TargetView::default(),
+        )?;
+        let scene_color_resource = device.get_image(scene_color).allocation.resource();
+        let scene_color_srv = cbv_heap.create_srv(
+            "Scene Color SRV",
+            scene_color_resource,
+            &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: SCENE_COLOR_FORMAT,
+                ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2D,
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D12_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                        PlaneSlice: 0,
+                        ResourceMinLODClamp: 0.0,
+                    },
+                },
+            },
+        )?;
+
+        let msaa_color = if multisampled {
+            let image = device.create_render_target(
+                "MSAA Color Target",
+                width,
+                height,
+                SCENE_COLOR_FORMAT,
+                sample_count,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            )?;
+            let view = rtv_heap.create_image_rtv(
+                device,
+                "MSAA Color RTV",
+                image,
+                TargetView::default(),
+            )?;
+            Some((image, view))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "ambient-occlusion")]
+        let ao = if settings.ambient_occlusion != AmbientOcclusion::Off {
+            Some(AoTargets::new(
+                device,
+                rtv_heap,
+                cbv_heap,
+                depth,
+                depth_format.srv_format(),
+                settings,
+            )?)
+        } else {
+            None
+        };
+
+        let bloom = if settings.post.bloom {
+            Some(BloomTargets::new(
+                device, rtv_heap, cbv_heap, width, height,
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            width,
+            height,
+            depth,
+            depth_view,
+            scene_color,
+            scene_color_rtv,
+            scene_color_srv,
+            msaa_color,
+            #[cfg(feature = "ambient-occlusion")]
+            ao,
+            bloom,
+        })
+    }
+
+    // Scene color, MSAA color and whichever of the ambient occlusion and bloom targets the settings
+    // enable.
+    fn rtv_count(width: u32, height: u32, settings: &RenderSettings) -> usize {
+        let mut count = 1;
+        if settings.anti_aliasing.sample_count() > 1 {
+            count += 1;
+        }
+        #[cfg(feature = "ambient-occlusion")]
+        if settings.ambient_occlusion != AmbientOcclusion::Off {
+            count += AoTargets::rtv_count(width, height, settings);
+        }
+        if settings.post.bloom {
+            count += BloomTargets::rtv_count(width, height);
+        }
+        count
+    }
+
+    fn track(&self, device: &Device, encoder: &CommandEncoder) {
+        encoder.track_resource(
+            device.get_image(self.depth).allocation.resource(),
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        );
+        encoder.track_resource(
+            device.get_image(self.scene_color).allocation.resource(),
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        );
+        if let Some((image, _view)) = self.msaa_color {
+            encoder.track_resource(
+                device.get_image(image).allocation.resource(),
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+        }
+        #[cfg(feature = "ambient-occlusion")]
+        if let Some(ao) = &self.ao {
+            ao.track(device, encoder);
+        }
+    }
+
+    fn destroy(
+        &self,
+        device: &mut Device,
+        rtv_heap: &mut DescriptorHeap,
+        dsv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        encoder: &CommandEncoder,
+    ) {
+        encoder.untrack_resource(device.get_image(self.depth).allocation.resource());
+        encoder.untrack_resource(device.get_image(self.scene_color).allocation.resource());
+        if let Some((image, _view)) = self.msaa_color {
+            encoder.untrack_resource(device.get_image(image).allocation.resource());
+        }
+        device.destroy_image(self.depth);
+        dsv_heap.free(self.depth_view);
+        device.destroy_image(self.scene_color);
+        rtv_heap.free(self.scene_color_rtv);
+        cbv_heap.free(self.scene_color_srv);
+        if let Some((image, view)) = self.msaa_color {
+            device.destroy_image(image);
+            rtv_heap.free(view);
+        }
+        #[cfg(feature = "ambient-occlusion")]
+        if let Some(ao) = &self.ao {
+            ao.destroy(device, rtv_heap, cbv_heap, encoder);
+        }
+        if let Some(bloom) = &self.bloom {
+            bloom.destroy(device, rtv_heap, cbv_heap);
+        }
+    }
+}
+
+fn compile_shader_pair(path: &str) -> Result<(Vec<u8>, Vec<u8>), RenderError> {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    let shader_code = std::fs::read_to_string(path)?;
+    let mut vertex_shader = compile_hlsl(name, &shader_code, "VSMain", "vs_6_6", &[], &[])?;
+    let mut fragment_shader = compile_hlsl(name, &shader_code, "PSMain", "ps_6_6", &[], &[])?;
+    fake_sign_dxil_in_place(&mut vertex_shader);
+    fake_sign_dxil_in_place(&mut fragment_shader);
+    Ok((vertex_shader, fragment_shader))
+}
+
+// For passes that reuse the vertex shader of another pipeline.
+fn compile_pixel_shader(path: &str) -> Result<Vec<u8>, RenderError> {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    let shader_code = std::fs::read_to_string(path)?;
+    let mut shader = compile_hlsl(name, &shader_code, "PSMain", "ps_6_6", &[], &[])?;
+    fake_sign_dxil_in_place(&mut shader);
+    Ok(shader)
+}
+
+fn compile_compute_shader(path: &str, entry_point: &str) -> Result<Vec<u8>, RenderError> {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    let shader_code = std::fs::read_to_string(path)?;
+    let mut shader = compile_hlsl(name, &shader_code, entry_point, "cs_6_6", &[], &[])?;
+    fake_sign_dxil_in_place(&mut shader);
+    Ok(shader)
+}
+
+fn graphics_pipeline_desc(
+    root_signature: &ID3D12RootSignature,
+    vertex_shader: &[u8],
+    fragment_shader: &[u8],
+    format: DXGI_FORMAT,
+    sample_count: u32,
+) -> D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+    let mut pipeline_desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+        pRootSignature: unsafe { std::mem::transmute_copy(root_signature) },
+        VS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: vertex_shader.as_ptr() as *const _,
+            BytecodeLength: vertex_shader.len(),
+        },
+        PS: D3D12_SHADER_BYTECODE {
+            pShaderBytecode: fragment_shader.as_ptr() as *const _,
+            BytecodeLength: fragment_shader.len(),
+        },
+        RasterizerState: D3D12_RASTERIZER_DESC {
+            FillMode: D3D12_FILL_MODE_SOLID,
+            CullMode: D3D12_CULL_MODE_NONE,
+            MultisampleEnable: (sample_count > 1).into(),
+            ..Default::default()
+        },
+        BlendState: D3D12_BLEND_DESC {
+            AlphaToCoverageEnable: false.into(),
+            IndependentBlendEnable: false.into(),
+            RenderTarget: [
+                D3D12_RENDER_TARGET_BLEND_DESC {
+                    BlendEnable: false.into(),
+                    LogicOpEnable: false.into(),
+                    SrcBlend: D3D12_BLEND_ONE,
+                    DestBlend: D3D12_BLEND_ZERO,
+                    BlendOp: D3D12_BLEND_OP_ADD,
+                    SrcBlendAlpha: D3D12_BLEND_ONE,
+                    DestBlendAlpha: D3D12_BLEND_ZERO,
+                    BlendOpAlpha: D3D12_BLEND_OP_ADD,
+                    LogicOp: D3D12_LOGIC_OP_NOOP,
+                    RenderTargetWriteMask: D3D12_COLOR_WRITE_ENABLE_ALL.0 as u8,
+                },
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+                D3D12_RENDER_TARGET_BLEND_DESC::default(),
+            ],
+        },
+        DepthStencilState: D3D12_DEPTH_STENCIL_DESC {
+            DepthEnable: true.into(),
+            DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ALL,
+            DepthFunc: Projection::DEPTH_COMPARE,
+            ..Default::default()
+        },
+        DSVFormat: DXGI_FORMAT_D32_FLOAT,
+        SampleMask: u32::MAX,
+        PrimitiveTopologyType: D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+        NumRenderTargets: 1,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: sample_count,
+            Quality: 0,
+        },
+        ..Default::default()
+    };
+    pipeline_desc.RTVFormats[0] = format;
+    pipeline_desc
+}
+
+fn create_mesh_pipelines(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    sample_count: u32,
+    depth_format: DepthFormat,
+) -> Result<(ID3D12PipelineState, ID3D12PipelineState), RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/mesh.hlsl")?;
+    let mut depth_pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        DXGI_FORMAT_UNKNOWN,
+        sample_count,
+    );
+    depth_pipeline_desc.DSVFormat = depth_format.dsv_format();
+    depth_pipeline_desc.PS = D3D12_SHADER_BYTECODE::default();
+    depth_pipeline_desc.NumRenderTargets = 0;
+    let depth_pipeline = device.create_graphics_pipeline(&depth_pipeline_desc)?;
+
+    // The prepass already wrote the final depth, so the main pass only shades the visible surface.
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        SCENE_COLOR_FORMAT,
+        sample_count,
+    );
+    pipeline_desc.DepthStencilState.DepthWriteMask = D3D12_DEPTH_WRITE_MASK_ZERO;
+    pipeline_desc.DepthStencilState.DepthFunc = Projection::DEPTH_COMPARE_EQUAL;
+    pipeline_desc.DSVFormat = depth_format.dsv_format();
+    let pipeline = device.create_graphics_pipeline(&pipeline_desc)?;
+    Ok((depth_pipeline, pipeline))
+}
+
+fn create_gizmo_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    format: DXGI_FORMAT,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/gizmo.hlsl")?;
+    let mut pipeline_desc =
+        graphics_pipeline_desc(root_signature, &vertex_shader, &fragment_shader, format, 1);
+    pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+        DepthEnable: false.into(),
+        DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+        ..Default::default()
+    };
+    pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+    pipeline_desc.PrimitiveTopologyType = D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE;
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+fn create_fullscreen_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    path: &str,
+    format: DXGI_FORMAT,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair(path)?;
+    let mut pipeline_desc =
+        graphics_pipeline_desc(root_signature, &vertex_shader, &fragment_shader, format, 1);
+    pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+        DepthEnable: false.into(),
+        DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+        ..Default::default()
+    };
+    pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+    device.create_graphics_pipeline(&pipeline_desc)
 }
@@ -0,0 +1,36 @@
+use glam::Vec3;
+use sovereign_math::Color;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-1.0, 1.0, -1.0).normalize(),
+            color: Color::WHITE,
+            intensity: 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    pub zenith_color: Color,
+    pub horizon_color: Color,
+    pub ambient_intensity: f32,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            zenith_color: Color::BLACK,
+            horizon_color: Color::BLACK,
+            ambient_intensity: 0.03,
+        }
+    }
+}
@@ -1,28 +1,205 @@
 use bytemuck::{Pod, Zeroable};
 use glam::Vec4;
+use half::f16;
+use sovereign_math::Color;
 
 use crate::{
-    id::{ImageId, SamplerId},
+    asset::{Assets, Handle},
+    id::SamplerId,
+    texture::Texture,
     BufferView,
 };
 
 #[derive(Debug)]
 pub struct Material {
     pub uniform: MaterialUniform,
-    pub color_image: Option<ImageId>,
-    pub color_sampler: Option<SamplerId>,
+    pub base_color_texture: Option<MaterialTexture>,
+    pub metallic_roughness_texture: Option<MaterialTexture>,
+    pub normal_texture: Option<MaterialTexture>,
+    pub occlusion_texture: Option<MaterialTexture>,
+    pub emissive_texture: Option<MaterialTexture>,
+}
+
+impl Material {
+    pub fn textures(&self) -> [Option<&MaterialTexture>; 5] {
+        [
+            self.base_color_texture.as_ref(),
+            self.metallic_roughness_texture.as_ref(),
+            self.normal_texture.as_ref(),
+            self.occlusion_texture.as_ref(),
+            self.emissive_texture.as_ref(),
+        ]
+    }
+
+    // In the same order as textures.
+    pub fn textures_mut(&mut self) -> [&mut Option<MaterialTexture>; 5] {
+        [
+            &mut self.base_color_texture,
+            &mut self.metallic_roughness_texture,
+            &mut self.normal_texture,
+            &mut self.occlusion_texture,
+            &mut self.emissive_texture,
+        ]
+    }
+
+    pub(crate) fn gpu_data(
+        &self,
+        textures: &Assets<Texture>,
+        material_override: Option<&MaterialOverride>,
+    ) -> GPUMaterialData {
+        let bind = |texture: &Option<MaterialTexture>| {
+            texture
+                .as_ref()
+                .and_then(|texture| {
+                    let view = textures.get(&texture.texture)?.view;
+                    Some(TextureBinding {
+                        texture: view.0 as u32,
+                        sampler: texture.sampler.0 as u32,
+                    })
+                })
+                .unwrap_or(TextureBinding::NONE)
+        };
+        let mut uniform = self.uniform;
+        if let Some(material_override) = material_override {
+            material_override.apply(&mut uniform);
+        }
+        GPUMaterialData {
+            uniform: uniform.pack(),
+            textures: MaterialTextures {
+                base_color: bind(&self.base_color_texture),
+                metallic_roughness: bind(&self.metallic_roughness_texture),
+                normal: bind(&self.normal_texture),
+                occlusion: bind(&self.occlusion_texture),
+                emissive: bind(&self.emissive_texture),
+            },
+            pad: [0; 2],
+        }
+    }
+}
+
+// Per-entity adjustments on top of the entity's material, for variation such as damage flashes or
+// team colors without a material asset per variant. Overridden draws are not instanced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialOverride {
+    // Multiplies the base color factors.
+    pub tint: Color,
+    // Added to the emissive factors.
+    pub emissive: Color,
+}
+
+impl Default for MaterialOverride {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            emissive: Color::BLACK,
+        }
+    }
+}
+
+impl MaterialOverride {
+    pub(crate) fn apply(&self, uniform: &mut MaterialUniform) {
+        uniform.base_color_factors =
+            (Vec4::from(uniform.base_color_factors) * Vec4::from(self.tint)).into();
+        uniform.emissive_factors = (Vec4::from(uniform.emissive_factors)
+            + Vec4::from(self.emissive).truncate().extend(0.0))
+        .into();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MaterialTexture {
+    pub texture: Handle<Texture>,
+    pub sampler: SamplerId,
 }
 
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 #[repr(C)]
 pub struct MaterialUniform {
-    pub base_color_factors: Vec4,
+    pub base_color_factors: Color,
+    pub emissive_factors: Color,
+    // Scales the emissive factors past 1 for surfaces bright enough to bloom.
+    pub emissive_strength: f32,
+    pub normal_scale: f32,
     pub perceptual_roughness: f32,
     pub metallic: f32,
     pub reflectance: f32,
-    pub pad: f32,
+    pub occlusion_strength: f32,
+}
+
+impl MaterialUniform {
+    pub(crate) fn pack(&self) -> PackedMaterialUniform {
+        let [r, g, b, _] = self
+            .emissive_factors
+            .to_array()
+            .map(|c| c * self.emissive_strength);
+        PackedMaterialUniform {
+            base_color: pack_unorm8(self.base_color_factors.to_array()),
+            emissive_normal_scale: [pack_half2(r, g), pack_half2(b, self.normal_scale)],
+            surface: pack_unorm8([
+                self.perceptual_roughness,
+                self.metallic,
+                self.reflectance,
+                self.occlusion_strength,
+            ]),
+        }
+    }
+}
+
+// The layout mesh.hlsl reads and unpacks in UnpackMaterial, so both have to change together.
+// Factors that stay within [0, 1] are unorm8 with the first value in the lowest byte; emissive and
+// the normal scale can exceed 1 and are half floats, two per u32 with the first in the low bits.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub(crate) struct PackedMaterialUniform {
+    pub base_color: u32,
+    // Emissive red and green, then emissive blue and the normal scale.
+    pub emissive_normal_scale: [u32; 2],
+    // Perceptual roughness, metallic, reflectance and occlusion strength.
+    pub surface: u32,
+}
+
+fn pack_unorm8(values: [f32; 4]) -> u32 {
+    u32::from_le_bytes(values.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+fn pack_half2(low: f32, high: f32) -> u32 {
+    f16::from_f32(low).to_bits() as u32 | (f16::from_f32(high).to_bits() as u32) << 16
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct TextureBinding {
+    pub texture: u32,
+    pub sampler: u32,
+}
+
+impl TextureBinding {
+    pub const NONE: Self = Self {
+        texture: u32::MAX,
+        sampler: u32::MAX,
+    };
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct MaterialTextures {
+    pub base_color: TextureBinding,
+    pub metallic_roughness: TextureBinding,
+    pub normal: TextureBinding,
+    pub occlusion: TextureBinding,
+    pub emissive: TextureBinding,
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub(crate) struct GPUMaterialData {
+    pub uniform: PackedMaterialUniform,
+    pub textures: MaterialTextures,
+    // Rounds the stride up to 64 bytes.
+    pub pad: [u32; 2],
 }
 
+#[derive(Clone, Copy)]
 pub struct GPUMaterial {
     pub buffer: BufferView,
     pub offset: usize,
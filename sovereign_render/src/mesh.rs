@@ -1,8 +1,15 @@
-use bytemuck::{Pod, Zeroable};
-use glam::{Vec2, Vec4};
+use bytemuck::{cast_slice, Pod, Zeroable};
+use glam::{Vec2, Vec4, Vec4Swizzles};
+use sovereign_math::Aabb;
+use windows::Win32::Graphics::{
+    Direct3D12::D3D12_INDEX_BUFFER_VIEW,
+    Dxgi::Common::{DXGI_FORMAT, DXGI_FORMAT_R16_UINT, DXGI_FORMAT_R32_UINT},
+};
 
 use crate::{id::BufferId, BufferView};
 
+pub mod shapes;
+
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 #[repr(C)]
 pub struct Vertex {
@@ -13,15 +20,131 @@ pub struct Vertex {
     pub pad: Vec2,
 }
 
+// Offsets added to a vertex, scaled by the target's weight.
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct MorphDelta {
+    pub position: Vec4,
+    pub normal: Vec4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    U16,
+    U32,
+}
+
+impl IndexFormat {
+    pub fn size(self) -> usize {
+        match self {
+            IndexFormat::U16 => std::mem::size_of::<u16>(),
+            IndexFormat::U32 => std::mem::size_of::<u32>(),
+        }
+    }
+
+    pub fn dxgi_format(self) -> DXGI_FORMAT {
+        match self {
+            IndexFormat::U16 => DXGI_FORMAT_R16_UINT,
+            IndexFormat::U32 => DXGI_FORMAT_R32_UINT,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    // Narrows to 16-bit indices whenever every index fits, which covers most meshes.
+    pub fn from_u32(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|&index| index <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Indices::U16(_) => IndexFormat::U16,
+            Indices::U32(_) => IndexFormat::U32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => cast_slice(indices),
+            Indices::U32(indices) => cast_slice(indices),
+        }
+    }
+}
+
+// Vertices already written to upload memory by `Renderer::stage_vertices`; the upload copies
+// them to the GPU as they are.
+#[derive(Debug)]
+pub struct StagedVertices {
+    pub(crate) buffer: BufferId,
+    pub(crate) len: usize,
+    // Of the vertex bytes, taken while they were written since upload memory is slow to read.
+    pub(crate) hash: u64,
+}
+
+impl StagedVertices {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
 #[derive(Debug)]
 pub struct Mesh {
+    // Empty when the vertices are staged instead.
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u32>,
+    pub staged_vertices: Option<StagedVertices>,
+    pub indices: Indices,
+    // One delta per vertex for every target.
+    pub morph_targets: Vec<Vec<MorphDelta>>,
 }
 
-#[derive(Debug)]
+impl Mesh {
+    pub fn vertex_count(&self) -> usize {
+        self.staged_vertices
+            .as_ref()
+            .map_or(self.vertices.len(), StagedVertices::len)
+    }
+
+    // Staged vertices can't be read back, so those meshes need bounds from their source.
+    pub fn compute_bounds(&self) -> Aabb {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position.xyz()))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphWeights(pub Vec<f32>);
+
+#[derive(Clone, Copy, Debug)]
 pub struct GPUMesh {
     pub vertex_buffer: BufferView,
     pub index_buffer: BufferId,
+    // Resolved once at upload so binding it per draw needs no lookups.
+    pub index_buffer_view: D3D12_INDEX_BUFFER_VIEW,
     pub index_count: usize,
+    pub morph_targets: Option<BufferView>,
+    pub morph_target_count: usize,
 }
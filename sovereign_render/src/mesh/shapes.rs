@@ -0,0 +1,183 @@
+use glam::{Vec2, Vec3, Vec4};
+use std::f32::consts::{PI, TAU};
+
+use super::{Indices, Mesh, Vertex};
+
+// Procedural meshes centred on the origin. Triangles wind counter-clockwise seen from outside,
+// like glTF, and texture coordinates start at the top left.
+
+pub fn cube(size: f32) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    for normal in [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ] {
+        let tangent = normal.any_orthonormal_vector();
+        let bitangent = normal.cross(tangent);
+        let base = builder.next_index();
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let position =
+                (normal + tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0)) * size * 0.5;
+            builder.vertex(position, normal, Vec2::new(u, v));
+        }
+        builder.quad(base, base + 1, base + 3, base + 2);
+    }
+    builder.build()
+}
+
+pub fn uv_sphere(radius: f32, rings: u32, segments: u32) -> Mesh {
+    let rings = rings.max(2);
+    let segments = segments.max(3);
+    let mut builder = MeshBuilder::default();
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+            let normal = Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+            builder.vertex(normal * radius, normal, Vec2::new(u, v));
+        }
+    }
+    builder.grid(rings, segments);
+    builder.build()
+}
+
+// A square in the XZ plane facing +Y, split into `subdivisions` quads along each side.
+pub fn plane(size: f32, subdivisions: u32) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+    let mut builder = MeshBuilder::default();
+    for x in 0..=subdivisions {
+        for z in 0..=subdivisions {
+            let uv = Vec2::new(x as f32, z as f32) / subdivisions as f32;
+            let position = Vec3::new(uv.x - 0.5, 0.0, uv.y - 0.5) * size;
+            builder.vertex(position, Vec3::Y, uv);
+        }
+    }
+    builder.grid(subdivisions, subdivisions);
+    builder.build()
+}
+
+// Capped cylinder along the Y axis.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half_height = height * 0.5;
+    let mut builder = MeshBuilder::default();
+
+    // The side is a grid of two rings; the seam is duplicated so UVs wrap cleanly.
+    for (y, v) in [(half_height, 0.0), (-half_height, 1.0)] {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            let normal = Vec3::new(cos, 0.0, sin);
+            builder.vertex(normal * radius + Vec3::Y * y, normal, Vec2::new(u, v));
+        }
+    }
+    builder.grid(1, segments);
+
+    for (y, normal) in [(half_height, Vec3::Y), (-half_height, Vec3::NEG_Y)] {
+        let center = builder.next_index();
+        builder.vertex(Vec3::Y * y, normal, Vec2::splat(0.5));
+        for segment in 0..segments {
+            let (sin, cos) = (segment as f32 / segments as f32 * TAU).sin_cos();
+            builder.vertex(
+                Vec3::new(cos * radius, y, sin * radius),
+                normal,
+                Vec2::new(0.5 + cos * 0.5, 0.5 + sin * 0.5),
+            );
+        }
+        for segment in 0..segments {
+            let a = center + 1 + segment;
+            let b = center + 1 + (segment + 1) % segments;
+            if normal.y > 0.0 {
+                builder.triangle(center, b, a);
+            } else {
+                builder.triangle(center, a, b);
+            }
+        }
+    }
+    builder.build()
+}
+
+// Ring in the XZ plane; `minor_radius` is the radius of the tube.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Mesh {
+    let major_segments = major_segments.max(3);
+    let minor_segments = minor_segments.max(3);
+    let mut builder = MeshBuilder::default();
+    for major in 0..=major_segments {
+        let u = major as f32 / major_segments as f32;
+        let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+        for minor in 0..=minor_segments {
+            let v = minor as f32 / minor_segments as f32;
+            let (sin_theta, cos_theta) = (v * TAU).sin_cos();
+            let normal = Vec3::new(cos_theta * cos_phi, sin_theta, cos_theta * sin_phi);
+            let center = Vec3::new(cos_phi, 0.0, sin_phi) * major_radius;
+            builder.vertex(center + normal * minor_radius, normal, Vec2::new(u, v));
+        }
+    }
+    builder.grid(major_segments, minor_segments);
+    builder.build()
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn next_index(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    fn vertex(&mut self, position: Vec3, normal: Vec3, uv: Vec2) {
+        self.vertices.push(Vertex {
+            position: position.extend(1.0),
+            normal: normal.extend(1.0),
+            color: Vec4::ONE,
+            uv,
+            pad: Vec2::ZERO,
+        });
+    }
+
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend([a, b, c]);
+    }
+
+    // `a`-`b` runs along one edge and `c`-`d` along the opposite one.
+    fn quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.triangle(a, b, c);
+        self.triangle(b, d, c);
+    }
+
+    // Stitches `rows + 1` rows of `columns + 1` vertices, starting at index 0. The surface faces
+    // the side where the column direction crossed with the row direction points.
+    fn grid(&mut self, rows: u32, columns: u32) {
+        let stride = columns + 1;
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = row * stride + column;
+                let b = a + stride;
+                self.quad(a, a + 1, b, b + 1);
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh {
+            vertices: self.vertices,
+            staged_vertices: None,
+            indices: Indices::from_u32(self.indices),
+            morph_targets: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use glam::Vec2;
+use gpu_allocator::MemoryLocation;
+use sovereign_ecs::{Entity, World};
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    camera::Projection,
+    compile_shader_pair,
+    descriptor::TargetView,
+    device::SubresourceFootprint,
+    error::RenderError,
+    graphics_pipeline_desc,
+    id::{BufferId, ImageId, ViewId},
+    Renderer, INVALID_INDEX,
+};
+
+const PICK_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R32_UINT;
+
+// Renders transform slots instead of colors into a single texel. The viewport is shifted so the
+// picked pixel of the scene lands on that texel, which keeps the pass independent of the scene size.
+pub(crate) struct PickPass {
+    pipeline: ID3D12PipelineState,
+    target: (ImageId, ViewId),
+    depth: (ImageId, ViewId),
+    readback: BufferId,
+    footprint: SubresourceFootprint,
+}
+
+impl Renderer {
+    // The entity whose mesh covers `cursor`, in window pixels, as of the last rendered frame.
+    // Renders and reads back on the spot, so it waits for the GPU.
+    pub fn pick(&mut self, world: &World, cursor: Vec2) -> Result<Option<Entity>, RenderError> {
+        if self.draws.is_empty() || self.width == 0 || self.height == 0 {
+            return Ok(None);
+        }
+        if self.pick_pass.is_none() {
+            self.pick_pass = Some(self.create_pick_pass()?);
+        }
+        // The scene renders at the scaled resolution, not the window's.
+        let scale = Vec2::new(
+            self.targets.width as f32 / self.width as f32,
+            self.targets.height as f32 / self.height as f32,
+        );
+        let pixel = (cursor * scale).floor();
+        if pixel.x < 0.0
+            || pixel.y < 0.0
+            || pixel.x >= self.targets.width as f32
+            || pixel.y >= self.targets.height as f32
+        {
+            return Ok(None);
+        }
+
+        self.immediate_submit(|r, encoder| {
+            let pass = r.pick_pass.as_ref().unwrap();
+            let target = r.device.get_image(pass.target.0).allocation.resource();
+            let rtv = r.rtv_heap.get_handle(pass.target.1 .0);
+            let dsv = r.dsv_heap.get_handle(pass.depth.1 .0);
+            encoder.set_descriptor_heaps(&[Some(r.cbv_heap.get()), Some(r.sampler_heap.get())]);
+            encoder.set_root_signature(&r.root_signature);
+            encoder.set_render_target(rtv, Some(&dsv));
+            encoder.clear_render_target(rtv, &[0.0; 4]);
+            encoder.clear_depth_target(dsv, Projection::DEPTH_CLEAR);
+            encoder.set_viewport_at(-pixel.x, -pixel.y, r.targets.width, r.targets.height);
+            encoder.set_scissor(1, 1);
+            encoder.set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            encoder.set_pipeline(&pass.pipeline);
+            r.draw_batches(encoder, INVALID_INDEX);
+            encoder.transition_image(
+                target,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+                D3D12_RESOURCE_STATE_COPY_SOURCE,
+            );
+            encoder.copy_image_to_buffer(
+                target,
+                r.device.get_buffer(pass.readback).allocation.resource(),
+                &pass.footprint,
+            );
+            encoder.transition_image(
+                target,
+                D3D12_RESOURCE_STATE_COPY_SOURCE,
+                D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+        })?;
+
+        let pass = self.pick_pass.as_ref().unwrap();
+        let data = self.device.map_buffer::<u32>(pass.readback)?;
+        let id = unsafe {
+            data.as_ptr()
+                .add(pass.footprint.layout.Offset as usize)
+                .cast::<u32>()
+                .read_unaligned()
+        };
+        self.device.unmap_buffer(pass.readback);
+
+        let Some(slot) = id.checked_sub(1) else {
+            return Ok(None);
+        };
+        // Slots of despawned entities are cleared, but the entity may have been despawned since.
+        Ok(self
+            .transform_entities
+            .get(slot as usize)
+            .copied()
+            .filter(|entity| world.get().contains(*entity)))
+    }
+
+    // The pass needs a view in the render and depth target heaps on top of the scene's; the
+    // default heap sizes leave room for both.
+    fn create_pick_pass(&mut self) -> Result<PickPass, RenderError> {
+        self.rtv_heap.ensure_free(1)?;
+        self.dsv_heap.ensure_free(1)?;
+        let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/pick.hlsl")?;
+        let mut pipeline_desc = graphics_pipeline_desc(
+            &self.root_signature,
+            &vertex_shader,
+            &fragment_shader,
+            PICK_FORMAT,
+            1,
+        );
+        pipeline_desc.DSVFormat = self.desc.depth_format.dsv_format();
+        let pipeline = self.device.create_graphics_pipeline(&pipeline_desc)?;
+
+        let target = self.device.create_render_target(
+            "Pick Target",
+            1,
+            1,
+            PICK_FORMAT,
+            1,
+            D3D12_RESOURCE_STATE_RENDER_TARGET,
+        )?;
+        let target_view = self.rtv_heap.create_image_rtv(
+            &self.device,
+            "Pick RTV",
+            target,
+            TargetView::default(),
+        )?;
+        let depth = self.device.create_render_target(
+            "Pick Depth",
+            1,
+            1,
+            self.desc.depth_format.resource_format(),
+            1,
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        )?;
+        let depth_view = self.dsv_heap.create_image_dsv(
+            &self.device,
+            "Pick DSV",
+            depth,
+            TargetView::format(self.desc.depth_format.dsv_format()),
+        )?;
+        let (footprint, size) = self
+            .device
+            .resource_footprint(self.device.get_image(target).allocation.resource());
+        let readback = self.device.create_buffer(
+            "Pick Readback",
+            size,
+            DXGI_FORMAT_UNKNOWN,
+            D3D12_RESOURCE_FLAG_NONE,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+            MemoryLocation::GpuToCpu,
+        )?;
+        Ok(PickPass {
+            pipeline,
+            target: (target, target_view),
+            depth: (depth, depth_view),
+            readback,
+            footprint,
+        })
+    }
+}
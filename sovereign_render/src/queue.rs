@@ -1,6 +1,7 @@
-use std::error::Error;
 use windows::Win32::Graphics::Direct3D12::{ID3D12CommandList, ID3D12CommandQueue, ID3D12Fence};
 
+use crate::error::RenderError;
+
 pub struct Queue {
     queue: ID3D12CommandQueue,
 }
@@ -20,7 +21,17 @@ impl Queue {
         }
     }
 
-    pub fn signal(&self, fence: &ID3D12Fence, value: u64) -> Result<(), Box<dyn Error>> {
+    pub fn timestamp_frequency(&self) -> Result<u64, RenderError> {
+        let frequency = unsafe { self.queue.GetTimestampFrequency() }?;
+        Ok(frequency)
+    }
+
+    pub fn wait(&self, fence: &ID3D12Fence, value: u64) -> Result<(), RenderError> {
+        unsafe { self.queue.Wait(fence, value) }?;
+        Ok(())
+    }
+
+    pub fn signal(&self, fence: &ID3D12Fence, value: u64) -> Result<(), RenderError> {
         unsafe { self.queue.Signal(fence, value) }?;
         Ok(())
     }
@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+use windows::Win32::Graphics::Dxgi::Common::*;
+
+use crate::{device::SubresourceFootprint, error::RenderError};
+
+pub(crate) enum ReadbackRequest {
+    Screenshot(PathBuf),
+    Image,
+}
+
+// Tightly packed RGBA8 pixels read back from the GPU, top row first.
+#[derive(Clone, Debug)]
+pub struct ReadbackImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ReadbackImage {
+    // Strips the row padding of a copied 8-bit RGBA or BGRA image. Alpha is forced to opaque,
+    // since what ends up there in a swapchain buffer is not meant to be seen.
+    pub(crate) fn from_footprint(
+        data: &[u8],
+        footprint: &SubresourceFootprint,
+    ) -> Result<Self, RenderError> {
+        let layout = &footprint.layout;
+        let swap_red_blue = match layout.Footprint.Format {
+            DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => false,
+            DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => true,
+            format => {
+                return Err(RenderError::InvalidTexture(format!(
+                    "cannot read back format {}",
+                    format.0
+                )))
+            }
+        };
+        let width = layout.Footprint.Width;
+        let row_pitch = layout.Footprint.RowPitch as usize;
+        let row_size = width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_size * footprint.num_rows as usize);
+        for row in 0..footprint.num_rows as usize {
+            let start = layout.Offset as usize + row * row_pitch;
+            for pixel in data[start..start + row_size].chunks_exact(4) {
+                if swap_red_blue {
+                    pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], u8::MAX]);
+                } else {
+                    pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], u8::MAX]);
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            height: footprint.num_rows,
+            pixels,
+        })
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), RenderError> {
+        image::save_buffer_with_format(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use windows::Win32::Graphics::Direct3D12::*;
+
+use crate::{descriptor::DescriptorHeap, error::RenderError, hash::hash_bytes, id::SamplerId};
+
+pub const LINEAR_WRAP: D3D12_SAMPLER_DESC = sampler_desc(
+    D3D12_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+);
+pub const LINEAR_CLAMP: D3D12_SAMPLER_DESC = sampler_desc(
+    D3D12_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+);
+pub const POINT_WRAP: D3D12_SAMPLER_DESC = sampler_desc(
+    D3D12_FILTER_MIN_MAG_MIP_POINT,
+    D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+);
+pub const POINT_CLAMP: D3D12_SAMPLER_DESC = sampler_desc(
+    D3D12_FILTER_MIN_MAG_MIP_POINT,
+    D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+);
+
+const DEFAULT_SAMPLERS: &[(&str, D3D12_SAMPLER_DESC)] = &[
+    ("Linear Wrap Sampler", LINEAR_WRAP),
+    ("Linear Clamp Sampler", LINEAR_CLAMP),
+    ("Point Wrap Sampler", POINT_WRAP),
+    ("Point Clamp Sampler", POINT_CLAMP),
+];
+
+const fn sampler_desc(
+    filter: D3D12_FILTER,
+    address_mode: D3D12_TEXTURE_ADDRESS_MODE,
+) -> D3D12_SAMPLER_DESC {
+    D3D12_SAMPLER_DESC {
+        Filter: filter,
+        AddressU: address_mode,
+        AddressV: address_mode,
+        AddressW: address_mode,
+        MipLODBias: 0.0,
+        MaxAnisotropy: 0,
+        ComparisonFunc: D3D12_COMPARISON_FUNC(0),
+        BorderColor: [0.0; 4],
+        MinLOD: 0.0,
+        MaxLOD: f32::MAX,
+    }
+}
+
+// Samplers keyed by their description, so every scene and pass asking for the same sampler shares
+// one slot in the sampler heap instead of filling it up with copies.
+pub(crate) struct SamplerCache {
+    samplers: HashMap<u64, SamplerId>,
+    descs: HashMap<SamplerId, D3D12_SAMPLER_DESC>,
+}
+
+impl SamplerCache {
+    // Creates the default samplers up front so they keep their own names however they are reached.
+    pub fn new(heap: &mut DescriptorHeap) -> Result<Self, RenderError> {
+        let mut cache = Self {
+            samplers: HashMap::new(),
+            descs: HashMap::new(),
+        };
+        for (name, desc) in DEFAULT_SAMPLERS {
+            cache.get_or_create(heap, name, desc)?;
+        }
+        Ok(cache)
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        heap: &mut DescriptorHeap,
+        name: &str,
+        desc: &D3D12_SAMPLER_DESC,
+    ) -> Result<SamplerId, RenderError> {
+        if let Some(sampler) = self.get(desc) {
+            return Ok(sampler);
+        }
+        let sampler = heap.create_sampler(name, desc)?;
+        self.samplers.insert(sampler_key(desc), sampler);
+        self.descs.insert(sampler, *desc);
+        Ok(sampler)
+    }
+
+    pub fn get(&self, desc: &D3D12_SAMPLER_DESC) -> Option<SamplerId> {
+        self.samplers.get(&sampler_key(desc)).copied()
+    }
+
+    pub fn desc(&self, sampler: SamplerId) -> Option<D3D12_SAMPLER_DESC> {
+        self.descs.get(&sampler).copied()
+    }
+}
+
+pub(crate) fn sampler_key(desc: &D3D12_SAMPLER_DESC) -> u64 {
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            desc as *const _ as *const u8,
+            std::mem::size_of::<D3D12_SAMPLER_DESC>(),
+        )
+    };
+    hash_bytes(bytes)
+}
@@ -0,0 +1,279 @@
+use glam::Vec2;
+
+use crate::capabilities::DeviceCapabilities;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ShadowQuality {
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AntiAliasing {
+    None,
+    Msaa2x,
+    #[default]
+    Msaa4x,
+    Msaa8x,
+}
+
+impl AntiAliasing {
+    pub fn lower(&self) -> Self {
+        match self {
+            AntiAliasing::None | AntiAliasing::Msaa2x => AntiAliasing::None,
+            AntiAliasing::Msaa4x => AntiAliasing::Msaa2x,
+            AntiAliasing::Msaa8x => AntiAliasing::Msaa4x,
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            AntiAliasing::None => 1,
+            AntiAliasing::Msaa2x => 2,
+            AntiAliasing::Msaa4x => 4,
+            AntiAliasing::Msaa8x => 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TextureQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl TextureQuality {
+    // Number of top mip levels left out when a texture is uploaded. The dropped levels are never
+    // kept, so a change only reaches textures created afterwards; reload assets to apply it.
+    pub fn mip_bias(&self) -> u32 {
+        match self {
+            TextureQuality::Low => 2,
+            TextureQuality::Medium => 1,
+            TextureQuality::High => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AmbientOcclusion {
+    Off,
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl AmbientOcclusion {
+    // Low runs basic SSAO; Medium and High run GTAO, which also outputs the bent normal the
+    // lighting uses for specular occlusion.
+    pub fn uses_gtao(&self) -> bool {
+        matches!(self, AmbientOcclusion::Medium | AmbientOcclusion::High)
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            AmbientOcclusion::Low => 8,
+            _ => 0,
+        }
+    }
+
+    pub fn slice_count(&self) -> u32 {
+        match self {
+            AmbientOcclusion::Off | AmbientOcclusion::Low => 0,
+            AmbientOcclusion::Medium => 2,
+            AmbientOcclusion::High => 3,
+        }
+    }
+
+    pub fn step_count(&self) -> u32 {
+        match self {
+            AmbientOcclusion::Off | AmbientOcclusion::Low => 0,
+            AmbientOcclusion::Medium => 2,
+            AmbientOcclusion::High => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EffectResolution {
+    #[default]
+    Full,
+    Half,
+}
+
+impl EffectResolution {
+    pub fn mip_level(&self) -> u32 {
+        match self {
+            EffectResolution::Full => 0,
+            EffectResolution::Half => 1,
+        }
+    }
+}
+
+// Borderless is owned by the windowing layer; the renderer only leaves exclusive mode and
+// follows the window size through Renderer::resize.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Exclusive,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Tonemapping {
+    None,
+    Reinhard,
+    #[default]
+    Aces,
+}
+
+// How the screen-space layers (debug UI, labels and debug text) map their units to output pixels.
+// Pixel keeps one unit per pixel, Dpi follows the window's scale factor, and Reference lays out
+// on a fixed canvas scaled uniformly to fit the output, centered with the leftover space as bars.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UiScaling {
+    #[default]
+    Pixel,
+    Dpi,
+    Reference {
+        width: f32,
+        height: f32,
+    },
+}
+
+impl UiScaling {
+    pub fn viewport(&self, output: Vec2, scale_factor: f32) -> UiViewport {
+        let scale = match *self {
+            UiScaling::Pixel => 1.0,
+            UiScaling::Dpi => scale_factor,
+            UiScaling::Reference { width, height } => {
+                (output / Vec2::new(width, height).max(Vec2::ONE)).min_element()
+            }
+        }
+        .max(0.01);
+        let size = match *self {
+            UiScaling::Reference { width, height } => Vec2::new(width, height).max(Vec2::ONE),
+            _ => output / scale,
+        };
+        UiViewport {
+            offset: ((output - size * scale) * 0.5).round(),
+            scale,
+            size,
+        }
+    }
+}
+
+// Where UI space lands in the output: a UI point p covers the pixel at offset + p * scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiViewport {
+    pub offset: Vec2,
+    pub scale: f32,
+    // Extent of UI space that is visible, in UI units.
+    pub size: Vec2,
+}
+
+impl UiViewport {
+    pub fn to_ui(&self, pixel: Vec2) -> Vec2 {
+        (pixel - self.offset) / self.scale
+    }
+
+    pub fn to_pixels(&self, point: Vec2) -> Vec2 {
+        self.offset + point * self.scale
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostProcessSettings {
+    pub tonemapping: Tonemapping,
+    pub exposure: f32,
+    pub bloom: bool,
+    // Scene luminance where bloom starts; everything brighter spreads into its surroundings.
+    pub bloom_threshold: f32,
+    // How much of the blurred bloom chain is added back to the scene before tonemapping.
+    pub bloom_intensity: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            tonemapping: Tonemapping::default(),
+            exposure: 1.0,
+            bloom: false,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.15,
+        }
+    }
+}
+
+// Shadow quality sets the cascade count and shadow map size; changing it rebuilds the map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub shadow_quality: ShadowQuality,
+    pub anti_aliasing: AntiAliasing,
+    pub texture_quality: TextureQuality,
+    pub ambient_occlusion: AmbientOcclusion,
+    pub ambient_occlusion_resolution: EffectResolution,
+    // World-space distance occluders are searched over.
+    pub ambient_occlusion_radius: f32,
+    // Exponent on the visibility; above 1 darkens occluded areas, 0 turns occlusion off.
+    pub ambient_occlusion_intensity: f32,
+    pub resolution_scale: f32,
+    pub post: PostProcessSettings,
+    pub ui_scaling: UiScaling,
+    pub vsync: bool,
+    pub gpu_markers: bool,
+    // Stage texture uploads through a second adapter's cross-adapter heap when one is present.
+    pub upload_adapter: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            shadow_quality: ShadowQuality::default(),
+            anti_aliasing: AntiAliasing::default(),
+            texture_quality: TextureQuality::default(),
+            ambient_occlusion: AmbientOcclusion::default(),
+            ambient_occlusion_resolution: EffectResolution::default(),
+            ambient_occlusion_radius: 0.5,
+            ambient_occlusion_intensity: 1.0,
+            resolution_scale: 1.0,
+            post: PostProcessSettings::default(),
+            ui_scaling: UiScaling::default(),
+            vsync: true,
+            gpu_markers: cfg!(debug_assertions),
+            upload_adapter: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    // Steps anything the device cannot run down to the nearest supported level, so the
+    // pipelines and targets built from these settings never hit an unsupported configuration.
+    pub fn clamp_to(&mut self, capabilities: &DeviceCapabilities) {
+        let requested = self.anti_aliasing;
+        while !capabilities.supports_sample_count(self.anti_aliasing.sample_count()) {
+            self.anti_aliasing = self.anti_aliasing.lower();
+        }
+        if self.anti_aliasing != requested {
+            tracing::warn!(
+                ?requested,
+                supported = ?self.anti_aliasing,
+                "MSAA sample count is not supported by the device"
+            );
+        }
+    }
+
+    pub fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = self.resolution_scale.clamp(0.25, 2.0);
+        (
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+        )
+    }
+}
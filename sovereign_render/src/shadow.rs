@@ -0,0 +1,460 @@
+use std::time::Instant;
+
+use glam::{Mat4, Vec3};
+use sovereign_math::{Aabb, Frustum};
+use windows::Win32::Graphics::{Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    camera::{orthographic, Camera, Projection, ViewUniform},
+    command_encoder::CommandEncoder,
+    compile_shader_pair,
+    descriptor::{DescriptorHeap, TargetView},
+    device::Device,
+    draw_list::shadow_batches,
+    error::RenderError,
+    graphics_pipeline_desc,
+    id::{ImageId, SamplerId, ViewId},
+    settings::ShadowQuality,
+    Renderer,
+};
+
+pub(crate) const MAX_CASCADES: usize = 4;
+const SHADOW_MAP_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R32_TYPELESS;
+
+// Blend between uniform and logarithmic cascade splits.
+const SPLIT_LAMBDA: f32 = 0.75;
+// Casters between the light and a cascade still have to land in its depth range.
+const CASTER_DISTANCE: f32 = 2.0;
+// Nothing closer than this gets a cascade of its own, or the first split would be vanishingly thin.
+const MIN_DEPTH: f32 = 0.01;
+
+impl ShadowQuality {
+    pub fn cascade_count(&self) -> usize {
+        match self {
+            ShadowQuality::Off => 0,
+            ShadowQuality::Low => 2,
+            ShadowQuality::Medium => 3,
+            ShadowQuality::High => 4,
+        }
+    }
+
+    pub fn resolution(&self) -> u32 {
+        match self {
+            ShadowQuality::Off => 0,
+            ShadowQuality::Low => 1024,
+            ShadowQuality::Medium | ShadowQuality::High => 2048,
+        }
+    }
+}
+
+// Whether a mesh is drawn into the shadow cascades. Meshes without it cast shadows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CastsShadows(pub bool);
+
+impl Default for CastsShadows {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+// Whether the lighting darkens a mesh where casters block the sun. Meshes that opt out are also
+// left out when fitting the cascades, so a large ground plane or a UI mesh doesn't stretch them.
+// Meshes without it receive shadows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReceivesShadows(pub bool);
+
+impl Default for ReceivesShadows {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowCascade {
+    pub view_projection: Mat4,
+    // View-space depth where this cascade hands over to the next one.
+    pub split_depth: f32,
+    pub texel_size: f32,
+}
+
+// View-space depth range covered by the given world bounds in front of the camera. Fitting
+// cascades to this instead of a fixed distance keeps their resolution on what is on screen.
+pub fn visible_depth_range<'a>(
+    view: &Mat4,
+    bounds: impl IntoIterator<Item = &'a Aabb>,
+) -> Option<(f32, f32)> {
+    bounds
+        .into_iter()
+        .map(|bounds| bounds.transformed(view))
+        .filter(|bounds| bounds.max.z > MIN_DEPTH)
+        .map(|bounds| (bounds.min.z.max(MIN_DEPTH), bounds.max.z))
+        .reduce(|(min, max), (near, far)| (min.min(near), max.max(far)))
+}
+
+// Splits `depth_range` into cascades and fits an orthographic light projection around each one.
+// Cascades are sized by the bounding sphere of their slice so the extent does not change as the
+// camera turns, and their origin is snapped to whole shadow map texels so it does not crawl as the
+// camera moves; together that keeps shadow edges from shimmering.
+pub fn fit_cascades(
+    camera: &Camera,
+    camera_transform: &Mat4,
+    light_direction: Vec3,
+    depth_range: (f32, f32),
+    quality: ShadowQuality,
+) -> Vec<ShadowCascade> {
+    let count = quality.cascade_count();
+    let (near, far) = depth_range;
+    if count == 0 || far <= near || near <= 0.0 {
+        return Vec::new();
+    }
+    // Quarter-octave steps, so the splits only move when the visible range changes noticeably.
+    let near = ((near.log2() * 4.0).floor() / 4.0).exp2();
+    let far = ((far.log2() * 4.0).ceil() / 4.0).exp2();
+
+    let toward_light = light_direction.normalize();
+    let up = if toward_light.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    // Anchored at the origin so the snapping lattice is the same every frame.
+    let light_view = Mat4::look_to_lh(Vec3::ZERO, -toward_light, up);
+    let resolution = quality.resolution() as f32;
+
+    let mut split_near = near;
+    (1..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let uniform = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            let split_far = uniform + (logarithmic - uniform) * SPLIT_LAMBDA;
+
+            let corners = [split_near, split_far].into_iter().flat_map(|depth| {
+                let extents = camera.half_extents(depth);
+                [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, y)| {
+                    camera_transform.transform_point3(Vec3::new(
+                        x * extents.x,
+                        y * extents.y,
+                        depth,
+                    ))
+                })
+            });
+            let corners = corners.collect::<Vec<_>>();
+            let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0f32, f32::max);
+            // Rounded up so float noise in the corners cannot resize the cascade frame to frame.
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let texel_size = radius * 2.0 / resolution;
+            let light_center = light_view.transform_point3(center);
+            let snapped_x = (light_center.x / texel_size).floor() * texel_size;
+            let snapped_y = (light_center.y / texel_size).floor() * texel_size;
+            let projection = orthographic(
+                snapped_x - radius,
+                snapped_x + radius,
+                snapped_y - radius,
+                snapped_y + radius,
+                light_center.z - radius * (1.0 + CASTER_DISTANCE),
+                light_center.z + radius,
+            );
+
+            split_near = split_far;
+            ShadowCascade {
+                view_projection: projection * light_view,
+                split_depth: split_far,
+                texel_size,
+            }
+        })
+        .collect()
+}
+
+// What the lighting needs to find and sample a surface's cascade. Matches ShadowUniform in
+// mesh.hlsl, where the per-cascade floats are packed into float4s.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub(crate) struct ShadowUniform {
+    pub view_projections: [Mat4; MAX_CASCADES],
+    pub split_depths: [f32; MAX_CASCADES],
+    pub texel_sizes: [f32; MAX_CASCADES],
+    pub cascade_count: u32,
+    pub shadow_map_index: u32,
+    pub sampler_index: u32,
+    pub pad: u32,
+}
+
+impl ShadowUniform {
+    fn new(cascades: &[ShadowCascade], shadow_map: &ShadowMap, sampler: SamplerId) -> Self {
+        let mut uniform = Self {
+            view_projections: [Mat4::IDENTITY; MAX_CASCADES],
+            split_depths: [0.0; MAX_CASCADES],
+            texel_sizes: [0.0; MAX_CASCADES],
+            cascade_count: cascades.len().min(MAX_CASCADES) as u32,
+            shadow_map_index: shadow_map.srv.0 as u32,
+            sampler_index: sampler.0 as u32,
+            pad: 0,
+        };
+        for (i, cascade) in cascades.iter().take(MAX_CASCADES).enumerate() {
+            uniform.view_projections[i] = cascade.view_projection;
+            uniform.split_depths[i] = cascade.split_depth;
+            uniform.texel_sizes[i] = cascade.texel_size;
+        }
+        uniform
+    }
+}
+
+// One depth slice per cascade, sized by the shadow quality.
+pub(crate) struct ShadowMap {
+    pub image: ImageId,
+    pub cascade_dsvs: Vec<ViewId>,
+    pub srv: ViewId,
+    pub resolution: u32,
+}
+
+impl ShadowMap {
+    // None when shadows are off.
+    pub fn new(
+        device: &mut Device,
+        dsv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        quality: ShadowQuality,
+    ) -> Result<Option<Self>, RenderError> {
+        let cascade_count = quality.cascade_count() as u32;
+        if cascade_count == 0 {
+            return Ok(None);
+        }
+        dsv_heap.ensure_free(cascade_count as usize)?;
+        let resolution = quality.resolution();
+        let image = device.create_render_target_array(
+            "Shadow Map",
+            resolution,
+            resolution,
+            cascade_count,
+            SHADOW_MAP_FORMAT,
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        )?;
+        let cascade_dsvs = (0..cascade_count)
+            .map(|slice| {
+                dsv_heap.create_image_dsv(
+                    device,
+                    "Shadow Cascade DSV",
+                    image,
+                    TargetView {
+                        format: Some(DXGI_FORMAT_D32_FLOAT),
+                        mip_slice: 0,
+                        array_slice: Some(slice),
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?;
+        let srv = cbv_heap.create_srv(
+            "Shadow Map SRV",
+            device.get_image(image).allocation.resource(),
+            &D3D12_SHADER_RESOURCE_VIEW_DESC {
+                Format: DXGI_FORMAT_R32_FLOAT,
+                ViewDimension: D3D12_SRV_DIMENSION_TEXTURE2DARRAY,
+                Shader4ComponentMapping: D3D12_DEFAULT_SHADER_4_COMPONENT_MAPPING,
+                Anonymous: D3D12_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2DArray: D3D12_TEX2D_ARRAY_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                        FirstArraySlice: 0,
+                        ArraySize: cascade_count,
+                        PlaneSlice: 0,
+                        ResourceMinLODClamp: 0.0,
+                    },
+                },
+            },
+        )?;
+        Ok(Some(Self {
+            image,
+            cascade_dsvs,
+            srv,
+            resolution,
+        }))
+    }
+
+    pub fn track(&self, device: &Device, encoder: &CommandEncoder) {
+        encoder.track_resource(
+            device.get_image(self.image).allocation.resource(),
+            D3D12_RESOURCE_STATE_DEPTH_WRITE,
+        );
+    }
+
+    pub fn destroy(
+        &self,
+        device: &mut Device,
+        dsv_heap: &mut DescriptorHeap,
+        cbv_heap: &mut DescriptorHeap,
+        encoder: &CommandEncoder,
+    ) {
+        encoder.untrack_resource(device.get_image(self.image).allocation.resource());
+        device.destroy_image(self.image);
+        for dsv in &self.cascade_dsvs {
+            dsv_heap.free(*dsv);
+        }
+        cbv_heap.free(self.srv);
+    }
+}
+
+pub(crate) struct ShadowPass {
+    pub pipeline: ID3D12PipelineState,
+    pub sampler: SamplerId,
+    pub map: Option<ShadowMap>,
+    // Rewritten every frame: the lighting's view of the cascades, a view per cascade to render
+    // it from, and the cascade volumes casters are culled against.
+    pub uniform: Option<ViewId>,
+    pub cascade_views: Vec<ViewId>,
+    pub cascade_frusta: Vec<Frustum>,
+}
+
+impl ShadowPass {
+    pub fn new(
+        device: &Device,
+        root_signature: &ID3D12RootSignature,
+        sampler: SamplerId,
+        map: Option<ShadowMap>,
+    ) -> Result<Self, RenderError> {
+        Ok(Self {
+            pipeline: create_shadow_pipeline(device, root_signature)?,
+            sampler,
+            map,
+            uniform: None,
+            cascade_views: Vec::new(),
+            cascade_frusta: Vec::new(),
+        })
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.uniform = None;
+        self.cascade_views.clear();
+        self.cascade_frusta.clear();
+    }
+
+    // Casters outside the camera's view still shadow what is in it.
+    pub fn casts_into_view(&self, bounds: &Aabb) -> bool {
+        self.cascade_frusta
+            .iter()
+            .any(|frustum| frustum.intersects_aabb(bounds))
+    }
+}
+
+// Greater-equal against the reversed depth: lit wherever the surface is at least as close to the
+// light as the nearest caster.
+pub(crate) const SHADOW_SAMPLER: D3D12_SAMPLER_DESC = D3D12_SAMPLER_DESC {
+    Filter: D3D12_FILTER_COMPARISON_MIN_MAG_LINEAR_MIP_POINT,
+    AddressU: D3D12_TEXTURE_ADDRESS_MODE_BORDER,
+    AddressV: D3D12_TEXTURE_ADDRESS_MODE_BORDER,
+    AddressW: D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+    MipLODBias: 0.0,
+    MaxAnisotropy: 0,
+    ComparisonFunc: D3D12_COMPARISON_FUNC_GREATER_EQUAL,
+    // The cleared depth, so everything outside a cascade is lit.
+    BorderColor: [Projection::DEPTH_CLEAR; 4],
+    MinLOD: 0.0,
+    MaxLOD: 0.0,
+};
+
+// Depth only, from the mesh vertex shader. The bias pushes casters away from the light, which
+// under reversed depth means towards zero.
+fn create_shadow_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/mesh.hlsl")?;
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        DXGI_FORMAT_UNKNOWN,
+        1,
+    );
+    pipeline_desc.PS = D3D12_SHADER_BYTECODE::default();
+    pipeline_desc.NumRenderTargets = 0;
+    pipeline_desc.DSVFormat = DXGI_FORMAT_D32_FLOAT;
+    pipeline_desc.RasterizerState.DepthBias = -16;
+    pipeline_desc.RasterizerState.SlopeScaledDepthBias = -2.0;
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+impl Renderer {
+    // Writes the views the shadow pass and the lighting read for the cascades fitted this frame.
+    pub(crate) fn prepare_shadows(&mut self, view: &ViewUniform) -> Result<(), RenderError> {
+        let Some(map) = &self.shadow_pass.map else {
+            return Ok(());
+        };
+        if self.shadow_cascades.is_empty() {
+            return Ok(());
+        }
+        let uniform = ShadowUniform::new(&self.shadow_cascades, map, self.shadow_pass.sampler);
+        for cascade in &self.shadow_cascades {
+            let allocation = self.constants.write(&ViewUniform {
+                projection: cascade.view_projection,
+                view: Mat4::IDENTITY,
+                ..*view
+            })?;
+            let cascade_view =
+                self.cbv_heap
+                    .create_transient_cbv(&D3D12_CONSTANT_BUFFER_VIEW_DESC {
+                        BufferLocation: allocation.gpu_address,
+                        SizeInBytes: allocation.size as u32,
+                    })?;
+            self.shadow_pass.cascade_views.push(cascade_view);
+            self.shadow_pass
+                .cascade_frusta
+                .push(Frustum::from_view_projection(&cascade.view_projection));
+        }
+        let allocation = self.constants.write(&uniform)?;
+        self.shadow_pass.uniform = Some(self.cbv_heap.create_transient_cbv(
+            &D3D12_CONSTANT_BUFFER_VIEW_DESC {
+                BufferLocation: allocation.gpu_address,
+                SizeInBytes: allocation.size as u32,
+            },
+        )?);
+        Ok(())
+    }
+
+    // Draws the shadow casters into every cascade, leaving the map ready for the lighting to
+    // sample. Returns the draw calls.
+    pub(crate) fn render_shadows(&mut self) -> u32 {
+        let Some(map) = &self.shadow_pass.map else {
+            return 0;
+        };
+        if self.shadow_pass.uniform.is_none() {
+            return 0;
+        }
+        let start = Instant::now();
+        self.render_command_encoder
+            .begin_scope(&self.gpu_timer, "shadows");
+        let shadow_map = self.device.get_image(map.image).allocation.resource();
+        self.render_command_encoder
+            .require_state(shadow_map, D3D12_RESOURCE_STATE_DEPTH_WRITE);
+        self.render_command_encoder
+            .set_pipeline(&self.shadow_pass.pipeline);
+        self.render_command_encoder
+            .set_viewport(map.resolution, map.resolution);
+        self.render_command_encoder
+            .set_scissor(map.resolution, map.resolution);
+        let mut draw_calls = 0;
+        for (dsv, view) in map.cascade_dsvs.iter().zip(&self.shadow_pass.cascade_views) {
+            let dsv_handle = self.dsv_heap.get_handle(dsv.0);
+            self.render_command_encoder.set_depth_target(dsv_handle);
+            self.render_command_encoder
+                .clear_depth_target(dsv_handle, Projection::DEPTH_CLEAR);
+            draw_calls += self.draw_batch_list(
+                &self.render_command_encoder,
+                shadow_batches(&self.draws),
+                *view,
+                crate::INVALID_INDEX,
+                None,
+            );
+        }
+        self.render_command_encoder
+            .require_state(shadow_map, D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE);
+        self.render_command_encoder.end_scope(&self.gpu_timer);
+        self.stats.record_pass("shadows", start.elapsed());
+        draw_calls
+    }
+}
@@ -0,0 +1,72 @@
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*};
+
+use crate::{
+    camera::Projection, compile_shader_pair, desc::DepthFormat, device::Device, error::RenderError,
+    graphics_pipeline_desc, Renderer, SCENE_COLOR_FORMAT,
+};
+
+#[repr(C)]
+pub(crate) struct SkyboxResources {
+    pub cubemap_index: u32,
+    pub sampler_index: u32,
+    pub view_buffer_index: u32,
+}
+
+pub(crate) struct SkyboxPass {
+    pub pipeline: ID3D12PipelineState,
+}
+
+impl SkyboxPass {
+    pub fn new(
+        device: &Device,
+        root_signature: &ID3D12RootSignature,
+        sample_count: u32,
+        depth_format: DepthFormat,
+    ) -> Result<Self, RenderError> {
+        Ok(Self {
+            pipeline: create_skybox_pipeline(device, root_signature, sample_count, depth_format)?,
+        })
+    }
+}
+
+// A fullscreen triangle at the far plane, so it only covers pixels no geometry was drawn to.
+pub(crate) fn create_skybox_pipeline(
+    device: &Device,
+    root_signature: &ID3D12RootSignature,
+    sample_count: u32,
+    depth_format: DepthFormat,
+) -> Result<ID3D12PipelineState, RenderError> {
+    let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/skybox.hlsl")?;
+    let mut pipeline_desc = graphics_pipeline_desc(
+        root_signature,
+        &vertex_shader,
+        &fragment_shader,
+        SCENE_COLOR_FORMAT,
+        sample_count,
+    );
+    pipeline_desc.DepthStencilState.DepthWriteMask = D3D12_DEPTH_WRITE_MASK_ZERO;
+    pipeline_desc.DepthStencilState.DepthFunc = Projection::DEPTH_COMPARE_EQUAL;
+    pipeline_desc.DSVFormat = depth_format.dsv_format();
+    device.create_graphics_pipeline(&pipeline_desc)
+}
+
+impl Renderer {
+    // Expects the scene color and depth targets of the main pass to still be bound.
+    pub(crate) fn draw_skybox(&self) -> u32 {
+        let Some(environment) = &self.environment else {
+            return 0;
+        };
+        self.render_command_encoder
+            .set_pipeline(&self.skybox_pass.pipeline);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .set_root_constants(&SkyboxResources {
+                cubemap_index: environment.skybox.view.0 as u32,
+                sampler_index: self.cubemap_pipelines.sampler.0 as u32,
+                view_buffer_index: self.view_buffer.0 as u32,
+            });
+        self.render_command_encoder.draw_instanced(3, 1, 0, 0);
+        1
+    }
+}
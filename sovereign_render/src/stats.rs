@@ -0,0 +1,186 @@
+use std::{fmt, time::Duration};
+
+#[derive(Clone, Debug)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub cpu_time: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct GpuPassTiming {
+    pub name: &'static str,
+    pub gpu_time: Duration,
+}
+
+// Counters come from the swapchain's DXGI frame statistics and keep running across frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PresentStats {
+    pub present_count: u32,
+    pub present_refresh_count: u32,
+    pub sync_refresh_count: u32,
+    pub missed_refreshes: u64,
+    pub refresh_rate: Option<f32>,
+}
+
+impl PresentStats {
+    pub(crate) fn record(
+        &mut self,
+        present_count: u32,
+        present_refresh_count: u32,
+        sync_refresh_count: u32,
+        synced: bool,
+    ) {
+        // Every synced present takes one vblank, so any extra refreshes between two presents
+        // were frames that missed their slot. Unsynced presents have no slot to miss.
+        if synced && self.present_count != 0 && present_count != self.present_count {
+            let presents = present_count.wrapping_sub(self.present_count);
+            let refreshes = present_refresh_count.wrapping_sub(self.present_refresh_count);
+            self.missed_refreshes += refreshes.saturating_sub(presents) as u64;
+        }
+        self.present_count = present_count;
+        self.present_refresh_count = present_refresh_count;
+        self.sync_refresh_count = sync_refresh_count;
+    }
+}
+
+// How much of a fixed or growable allocation is in use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub used: usize,
+    pub capacity: usize,
+}
+
+impl Usage {
+    // Past this fraction a fixed heap is close enough to running out to warn about.
+    const NEARLY_FULL: f32 = 0.9;
+
+    pub fn fraction(&self) -> f32 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.used as f32 / self.capacity as f32
+    }
+
+    pub fn is_nearly_full(&self) -> bool {
+        self.fraction() >= Self::NEARLY_FULL
+    }
+}
+
+// Live counts sampled at the end of each frame. Descriptor heaps have a fixed size and overflowing
+// one corrupts other views, so those are the ones to watch; the transform and material buffers
+// grow on their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CapacityStats {
+    pub resource_descriptors: Usage,
+    // The per-frame range of the resource heap, used by this frame alone.
+    pub transient_descriptors: Usage,
+    pub render_target_descriptors: Usage,
+    pub depth_target_descriptors: Usage,
+    pub sampler_descriptors: Usage,
+    pub transforms: Usage,
+    pub materials: Usage,
+    pub buffers: usize,
+    pub images: usize,
+}
+
+impl CapacityStats {
+    pub fn heaps(&self) -> [(&'static str, Usage); 5] {
+        [
+            ("resource descriptors", self.resource_descriptors),
+            ("transient descriptors", self.transient_descriptors),
+            ("render target descriptors", self.render_target_descriptors),
+            ("depth target descriptors", self.depth_target_descriptors),
+            ("sampler descriptors", self.sampler_descriptors),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    pub frame: u64,
+    pub cpu_time: Duration,
+    pub gpu_time: Duration,
+    pub gpu_wait_time: Duration,
+    pub passes: Vec<PassTiming>,
+    pub gpu_passes: Vec<GpuPassTiming>,
+    pub draw_calls: u32,
+    pub instances: u32,
+    pub culled: u32,
+    // Set when no mesh was drawn, so the frame skipped the depth prepass, ambient occlusion and
+    // the instance upload.
+    pub empty_scene: bool,
+    pub present: PresentStats,
+    pub capacity: CapacityStats,
+}
+
+impl RenderStats {
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.cpu_time = Duration::ZERO;
+        self.gpu_time = Duration::ZERO;
+        self.gpu_wait_time = Duration::ZERO;
+        self.passes.clear();
+        self.gpu_passes.clear();
+        self.draw_calls = 0;
+        self.instances = 0;
+        self.culled = 0;
+        self.empty_scene = false;
+    }
+
+    // Warns once as each heap crosses into nearly full, rather than every frame it stays there.
+    pub(crate) fn record_capacity(&mut self, capacity: CapacityStats) {
+        for ((name, usage), (_name, previous)) in
+            capacity.heaps().into_iter().zip(self.capacity.heaps())
+        {
+            if usage.is_nearly_full() && !previous.is_nearly_full() {
+                tracing::warn!(
+                    heap = name,
+                    used = usage.used,
+                    capacity = usage.capacity,
+                    "Descriptor heap is nearly full"
+                );
+            }
+        }
+        self.capacity = capacity;
+    }
+
+    pub(crate) fn record_pass(&mut self, name: &'static str, cpu_time: Duration) {
+        self.cpu_time += cpu_time;
+        self.passes.push(PassTiming { name, cpu_time });
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CPU {:.2}ms, GPU {:.2}ms, {} draws",
+            self.cpu_time.as_secs_f64() * 1000.0,
+            self.gpu_time.as_secs_f64() * 1000.0,
+            self.draw_calls,
+        )?;
+        if self.empty_scene {
+            write!(f, " (empty scene)")?;
+        }
+        if let Some(refresh_rate) = self.present.refresh_rate {
+            write!(f, ", {:.0}Hz", refresh_rate)?;
+        }
+        if self.present.missed_refreshes > 0 {
+            write!(f, ", {} missed vblanks", self.present.missed_refreshes)?;
+        }
+        for (name, usage) in self.capacity.heaps() {
+            if usage.is_nearly_full() {
+                write!(f, ", {} {:.0}% full", name, usage.fraction() * 100.0)?;
+            }
+        }
+        for pass in &self.gpu_passes {
+            write!(
+                f,
+                " | {} {:.2}ms",
+                pass.name,
+                pass.gpu_time.as_secs_f64() * 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+use sovereign_ecs::World;
+use sovereign_math::Color;
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    asset::{Assets, Handle},
+    compile_shader_pair,
+    descriptor::DescriptorHeap,
+    device::Device,
+    error::RenderError,
+    gpu_buffer::GrowableGpuBuffer,
+    graphics_pipeline_desc,
+    id::{SamplerId, ViewId},
+    texture::Texture,
+    Renderer,
+};
+
+const FONT_PATH: &str = "assets/fonts/debug_font.png";
+pub(crate) const GLYPH_SIZE: Vec2 = Vec2::new(9.0, 17.0);
+const FONT_COLUMNS: u32 = 16;
+const FONT_ROWS: u32 = 6;
+const FIRST_GLYPH: u8 = b' ';
+// The atlas keeps a fully opaque cell in the DEL slot so solid quads share the text pipeline.
+pub(crate) const SOLID_GLYPH: u8 = 0x7F;
+const MIN_GLYPH_CAPACITY: usize = 1024;
+const TEXT_COLOR: Color = Color::WHITE;
+
+pub(crate) fn glyph(c: char) -> u8 {
+    u8::try_from(c)
+        .ok()
+        .filter(|glyph| (FIRST_GLYPH..SOLID_GLYPH).contains(glyph))
+        .unwrap_or(b'?')
+}
+
+pub(crate) fn glyph_uv(glyph: u8) -> (Vec2, Vec2) {
+    let index = (glyph - FIRST_GLYPH) as u32;
+    let cell = Vec2::new((index % FONT_COLUMNS) as f32, (index / FONT_COLUMNS) as f32);
+    let atlas = Vec2::new(FONT_COLUMNS as f32, FONT_ROWS as f32);
+    (cell / atlas, (cell + 1.0) / atlas)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct TextQuad {
+    // Top left corner in UI units.
+    pub position: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+    pub color: u32,
+}
+
+#[repr(C)]
+pub(crate) struct TextResources {
+    pub glyph_buffer_index: u32,
+    pub font_texture_index: u32,
+    pub sampler_index: u32,
+    pub screen_size: Vec2,
+    pub glyph_size: Vec2,
+    pub offset: Vec2,
+    pub scale: f32,
+}
+
+// Screen-space text in the fixed-size bitmap font, batched into one instanced draw over the
+// finished frame. The atlas is shared with the debug UI and debug text.
+pub(crate) struct TextPass {
+    pub pipeline: ID3D12PipelineState,
+    pub sampler: SamplerId,
+    pub font: Option<(Handle<Texture>, ViewId)>,
+    pub glyphs: Vec<TextQuad>,
+    pub glyph_buffer: GrowableGpuBuffer<TextQuad>,
+}
+
+impl TextPass {
+    pub fn new(
+        device: &mut Device,
+        cbv_heap: &mut DescriptorHeap,
+        root_signature: &ID3D12RootSignature,
+        format: DXGI_FORMAT,
+        sampler: SamplerId,
+    ) -> Result<Self, RenderError> {
+        let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/text.hlsl")?;
+        let mut pipeline_desc =
+            graphics_pipeline_desc(root_signature, &vertex_shader, &fragment_shader, format, 1);
+        pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+            DepthEnable: false.into(),
+            DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+            ..Default::default()
+        };
+        pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+        let blend = &mut pipeline_desc.BlendState.RenderTarget[0];
+        blend.BlendEnable = true.into();
+        blend.SrcBlend = D3D12_BLEND_SRC_ALPHA;
+        blend.DestBlend = D3D12_BLEND_INV_SRC_ALPHA;
+        blend.DestBlendAlpha = D3D12_BLEND_INV_SRC_ALPHA;
+
+        Ok(Self {
+            pipeline: device.create_graphics_pipeline(&pipeline_desc)?,
+            sampler,
+            font: None,
+            glyphs: Vec::new(),
+            glyph_buffer: GrowableGpuBuffer::new(
+                device,
+                cbv_heap,
+                "Text Glyph Buffer",
+                MIN_GLYPH_CAPACITY,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+            )?,
+        })
+    }
+}
+
+impl Renderer {
+    pub(crate) fn load_font(&mut self, world: &World) -> Result<(), RenderError> {
+        let font = self.create_texture_from_file(world, Path::new(FONT_PATH))?;
+        let view = {
+            let mut textures_query = world.get_singleton::<Assets<Texture>>();
+            let (textures,) = textures_query.get().unwrap();
+            textures.get(&font).unwrap().view
+        };
+        self.text_pass.font = Some((font, view));
+        Ok(())
+    }
+
+    // Queues text with its top left corner at (x, y) in UI units, so it follows the UI scaling
+    // policy. Text is drawn by the next call to render and has to be queued again every frame.
+    pub fn draw_text(&mut self, x: f32, y: f32, text: &str) {
+        self.queue_text(Vec2::new(x, y), text, TEXT_COLOR);
+    }
+
+    pub(crate) fn queue_text(&mut self, origin: Vec2, text: &str, color: Color) {
+        let origin = origin.floor();
+        let color = u32::from_le_bytes(color.to_srgba_u8());
+        let mut pen = origin;
+        for c in text.chars() {
+            if c == '\n' {
+                pen = Vec2::new(origin.x, pen.y + GLYPH_SIZE.y);
+                continue;
+            }
+            if c != ' ' {
+                let (uv_min, uv_max) = glyph_uv(glyph(c));
+                self.text_pass.glyphs.push(TextQuad {
+                    position: pen,
+                    uv_min,
+                    uv_max,
+                    color,
+                });
+            }
+            pen.x += GLYPH_SIZE.x;
+        }
+    }
+
+    // One instance per glyph, expanded to a quad by the vertex shader.
+    pub(crate) fn draw_queued_text(&mut self) -> Result<u32, RenderError> {
+        let glyphs = std::mem::take(&mut self.text_pass.glyphs);
+        let Some(font_view) = self.text_pass.font.as_ref().map(|(_font, view)| *view) else {
+            return Ok(0);
+        };
+        if glyphs.is_empty() {
+            return Ok(0);
+        }
+        // render waits for the GPU before returning, so the buffer is free to overwrite here.
+        self.text_pass
+            .glyph_buffer
+            .reserve(&mut self.device, &mut self.cbv_heap, glyphs.len())?;
+        let glyph_buffer = self.text_pass.glyph_buffer.view();
+        self.text_pass
+            .glyph_buffer
+            .map(&self.device)?
+            .write_slice(&glyphs);
+
+        let viewport = self.ui_viewport();
+        self.render_command_encoder
+            .set_pipeline(&self.text_pass.pipeline);
+        self.render_command_encoder
+            .set_viewport(self.width, self.height);
+        self.render_command_encoder
+            .set_scissor(self.width, self.height);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .set_root_constants(&TextResources {
+                glyph_buffer_index: glyph_buffer.view.0 as u32,
+                font_texture_index: font_view.0 as u32,
+                sampler_index: self.text_pass.sampler.0 as u32,
+                screen_size: Vec2::new(self.width as f32, self.height as f32),
+                glyph_size: GLYPH_SIZE,
+                offset: viewport.offset,
+                scale: viewport.scale,
+            });
+        self.render_command_encoder
+            .draw_instanced(6, glyphs.len() as u32, 0, 0);
+
+        // Keep the allocation for next frame's text.
+        self.text_pass.glyphs = glyphs;
+        self.text_pass.glyphs.clear();
+        Ok(1)
+    }
+}
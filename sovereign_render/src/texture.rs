@@ -0,0 +1,15 @@
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::id::{ImageId, ViewId};
+
+#[derive(Debug)]
+pub struct Texture {
+    pub image: ImageId,
+    pub view: ViewId,
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub format: DXGI_FORMAT,
+    // Of the data it was created from, so materials can hash what they show rather than a slot.
+    pub content_hash: u64,
+}
@@ -0,0 +1,95 @@
+use glam::{Quat, Vec3};
+use sovereign_ecs::World;
+use sovereign_math::Color;
+
+use crate::light::{DirectionalLight, Sky};
+
+const SUN_TILT: f32 = 0.5;
+const SUN_INTENSITY: f32 = 3.0;
+const NIGHT_ZENITH: Color = Color::rgb(0.005, 0.008, 0.02);
+const DAY_ZENITH: Color = Color::rgb(0.18, 0.36, 0.8);
+const NIGHT_HORIZON: Color = Color::rgb(0.01, 0.012, 0.025);
+const DAY_HORIZON: Color = Color::rgb(0.6, 0.72, 0.9);
+const SUNSET_HORIZON: Color = Color::rgb(0.9, 0.45, 0.2);
+const SUNSET_SUN: Color = Color::rgb(1.0, 0.5, 0.25);
+const NOON_SUN: Color = Color::rgb(1.0, 0.96, 0.9);
+
+pub struct TimeOfDay {
+    pub hour: f32,
+    pub day_length: f32,
+    pub paused: bool,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: f32, day_length: f32) -> Self {
+        Self {
+            hour: hour.rem_euclid(24.0),
+            day_length,
+            paused: false,
+        }
+    }
+
+    pub fn set_hour(&mut self, hour: f32) {
+        self.hour = hour.rem_euclid(24.0);
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        if self.paused || self.day_length <= 0.0 {
+            return;
+        }
+        self.set_hour(self.hour + dt / self.day_length * 24.0);
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.hour - 6.0) / 12.0 * std::f32::consts::PI;
+        Quat::from_rotation_x(-SUN_TILT) * Vec3::new(-angle.cos(), angle.sin(), 0.0)
+    }
+
+    pub fn sun(&self) -> DirectionalLight {
+        let direction = self.sun_direction();
+        let day = smoothstep(-0.1, 0.2, direction.y);
+        let height = smoothstep(0.0, 0.5, direction.y);
+        DirectionalLight {
+            direction,
+            color: SUNSET_SUN.lerp(NOON_SUN, height),
+            intensity: SUN_INTENSITY * day,
+        }
+    }
+
+    pub fn sky(&self) -> Sky {
+        let elevation = self.sun_direction().y;
+        let day = smoothstep(-0.2, 0.2, elevation);
+        let sunset = 1.0 - smoothstep(0.0, 0.3, elevation.abs());
+        Sky {
+            zenith_color: NIGHT_ZENITH.lerp(DAY_ZENITH, day),
+            horizon_color: NIGHT_HORIZON
+                .lerp(DAY_HORIZON, day)
+                .lerp(SUNSET_HORIZON, sunset * 0.6),
+            ambient_intensity: 0.02 + 0.25 * day,
+        }
+    }
+}
+
+pub fn update_time_of_day(world: &World, dt: f32) {
+    for (_entity, (time_of_day, light, sky)) in world
+        .query::<(
+            &mut TimeOfDay,
+            Option<&mut DirectionalLight>,
+            Option<&mut Sky>,
+        )>()
+        .iter()
+    {
+        time_of_day.advance(dt);
+        if let Some(light) = light {
+            *light = time_of_day.sun();
+        }
+        if let Some(sky) = sky {
+            *sky = time_of_day.sky();
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,38 @@
+use crate::settings::{PostProcessSettings, Tonemapping};
+
+#[repr(C)]
+pub(crate) struct TonemapResources {
+    pub scene_color_index: u32,
+    pub sampler_index: u32,
+    pub bloom_index: u32,
+    pub bloom_intensity: f32,
+    pub exposure: f32,
+    pub tonemapping: u32,
+    pub encode_srgb: u32,
+}
+
+impl TonemapResources {
+    // `encode_srgb` is off when the output view already encodes to sRGB. `bloom_index` is
+    // INVALID_INDEX when there is no bloom to add.
+    pub fn new(
+        scene_color_index: u32,
+        sampler_index: u32,
+        bloom_index: u32,
+        post: &PostProcessSettings,
+        encode_srgb: bool,
+    ) -> Self {
+        Self {
+            scene_color_index,
+            sampler_index,
+            bloom_index,
+            bloom_intensity: post.bloom_intensity,
+            exposure: post.exposure,
+            tonemapping: match post.tonemapping {
+                Tonemapping::None => 0,
+                Tonemapping::Reinhard => 1,
+                Tonemapping::Aces => 2,
+            },
+            encode_srgb: encode_srgb as u32,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    collections::BTreeMap,
+    fmt,
+    panic::Location,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    Sampler,
+    Descriptor,
+}
+
+pub struct ResourceRecord {
+    pub kind: ResourceKind,
+    pub index: usize,
+    pub name: String,
+    pub size: u64,
+    pub location: &'static Location<'static>,
+    pub backtrace: Option<Backtrace>,
+}
+
+#[derive(Default)]
+pub(crate) struct ResourceTracker {
+    records: BTreeMap<(ResourceKind, usize), ResourceRecord>,
+}
+
+impl ResourceTracker {
+    #[track_caller]
+    pub(crate) fn track(&mut self, kind: ResourceKind, index: usize, name: &str, size: u64) {
+        let backtrace = if cfg!(debug_assertions) {
+            Some(Backtrace::capture()).filter(|bt| bt.status() == BacktraceStatus::Captured)
+        } else {
+            None
+        };
+        self.records.insert(
+            (kind, index),
+            ResourceRecord {
+                kind,
+                index,
+                name: name.to_string(),
+                size,
+                location: Location::caller(),
+                backtrace,
+            },
+        );
+    }
+
+    pub(crate) fn untrack(&mut self, kind: ResourceKind, index: usize) {
+        self.records.remove(&(kind, index));
+    }
+
+    pub(crate) fn name(&self, kind: ResourceKind, index: usize) -> Option<&str> {
+        self.records
+            .get(&(kind, index))
+            .map(|record| record.name.as_str())
+    }
+
+    pub(crate) fn records(&self) -> impl Iterator<Item = &ResourceRecord> {
+        self.records.values()
+    }
+}
+
+pub struct ResourceReport<'a> {
+    records: Vec<&'a ResourceRecord>,
+}
+
+impl<'a> ResourceReport<'a> {
+    pub(crate) fn new(records: impl IntoIterator<Item = &'a ResourceRecord>) -> Self {
+        let mut records = records.into_iter().collect::<Vec<_>>();
+        records.sort_by_key(|record| (record.kind, record.index));
+        Self { records }
+    }
+
+    pub fn count(&self, kind: ResourceKind) -> usize {
+        self.records
+            .iter()
+            .filter(|record| record.kind == kind)
+            .count()
+    }
+
+    pub fn total_size(&self, kind: ResourceKind) -> u64 {
+        self.records
+            .iter()
+            .filter(|record| record.kind == kind)
+            .map(|record| record.size)
+            .sum()
+    }
+
+    pub fn records(&self) -> &[&'a ResourceRecord] {
+        &self.records
+    }
+}
+
+impl fmt::Display for ResourceReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Live GPU resources: {} buffers ({} bytes), {} images ({} bytes), {} samplers, {} descriptors",
+            self.count(ResourceKind::Buffer),
+            self.total_size(ResourceKind::Buffer),
+            self.count(ResourceKind::Image),
+            self.total_size(ResourceKind::Image),
+            self.count(ResourceKind::Sampler),
+            self.count(ResourceKind::Descriptor),
+        )?;
+        for record in &self.records {
+            writeln!(
+                f,
+                "  {:?} #{} \"{}\" {} bytes, created at {}",
+                record.kind, record.index, record.name, record.size, record.location
+            )?;
+            if let Some(backtrace) = &record.backtrace {
+                for line in backtrace.to_string().lines() {
+                    writeln!(f, "      {}", line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
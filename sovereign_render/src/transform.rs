@@ -1,11 +1,21 @@
 use glam::Mat4;
+use sovereign_ecs::{CommandBuffer, Entity, ParentOf, World};
+use std::collections::{HashMap, HashSet};
 
-use crate::BufferView;
+use crate::{bounds::update_world_bounds, BufferView};
 
 pub struct Transform {
     pub transform: Mat4,
 }
 
+pub struct Parent(pub Entity);
+
+impl ParentOf for Parent {
+    fn parent(&self) -> Entity {
+        self.0
+    }
+}
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct GlobalTransform {
@@ -16,3 +26,133 @@ pub struct GPUTransform {
     pub buffer: BufferView,
     pub offset: usize,
 }
+
+// What propagation last saw of an entity. Entities that still match it are skipped unless an
+// ancestor moved.
+struct Propagated {
+    local: Option<Mat4>,
+    parent: Option<Entity>,
+    global: Mat4,
+}
+
+struct Node {
+    local: Option<Mat4>,
+    parent: Option<Entity>,
+    previous: Option<Mat4>,
+}
+
+// Only subtrees under a changed Transform, a new parent or a GlobalTransform written directly are
+// recomputed. Bounds are refreshed for every entity whose GlobalTransform moved, however it moved.
+pub fn propagate_transforms(world: &mut World) {
+    let mut nodes = HashMap::new();
+    let mut children = HashMap::<Entity, Vec<Entity>>::new();
+    let mut changed = HashSet::new();
+    for (entity, (global, transform, parent, propagated)) in world
+        .query::<(
+            &GlobalTransform,
+            Option<&Transform>,
+            Option<&Parent>,
+            Option<&Propagated>,
+        )>()
+        .iter()
+    {
+        let local = transform.map(|transform| transform.transform);
+        let parent = local.and(parent.map(|parent| parent.0));
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(entity);
+        }
+        let unchanged = propagated.is_some_and(|propagated| {
+            propagated.local == local
+                && propagated.parent == parent
+                && propagated.global == global.transform
+        });
+        if !unchanged {
+            changed.insert(entity);
+        }
+        let previous = propagated.map(|propagated| propagated.global);
+        nodes.insert(
+            entity,
+            Node {
+                local,
+                parent,
+                previous,
+            },
+        );
+    }
+
+    // Changed entities under a changed ancestor are reached from that ancestor instead.
+    let mut stack = changed
+        .iter()
+        .filter_map(|entity| match changed_ancestor(*entity, &nodes, &changed) {
+            Some(true) => None,
+            Some(false) => Some((*entity, false)),
+            None => {
+                tracing::warn!(?entity, "Transform hierarchy contains a cycle");
+                Some((*entity, true))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut commands = CommandBuffer::new();
+    let mut visited = HashSet::new();
+    let mut moved = HashSet::new();
+    while let Some((entity, detached)) = stack.pop() {
+        if !visited.insert(entity) {
+            continue;
+        }
+        let node = &nodes[&entity];
+        // Read before borrowing the entity's own GlobalTransform, which may share its archetype.
+        let parent_global = match node.parent {
+            Some(parent) if !detached => world
+                .get()
+                .get::<&GlobalTransform>(parent)
+                .map_or(Mat4::IDENTITY, |global| global.transform),
+            _ => Mat4::IDENTITY,
+        };
+        let global = {
+            let mut global = world.get().get::<&mut GlobalTransform>(entity).unwrap();
+            if let Some(local) = node.local {
+                global.transform = parent_global * local;
+            }
+            global.transform
+        };
+
+        if node.previous != Some(global) {
+            moved.insert(entity);
+        }
+        let propagated = Propagated {
+            local: node.local,
+            parent: node.parent,
+            global,
+        };
+        match world.get().get::<&mut Propagated>(entity) {
+            Ok(mut previous) => *previous = propagated,
+            Err(_) => commands.insert_one(entity, propagated),
+        }
+        if let Some(children) = children.get(&entity) {
+            stack.extend(children.iter().map(|child| (*child, false)));
+        }
+    }
+    commands.run_on(world.get_mut());
+
+    update_world_bounds(world, &moved);
+}
+
+// Whether any ancestor of entity changed, or None if its parents form a cycle.
+fn changed_ancestor(
+    entity: Entity,
+    nodes: &HashMap<Entity, Node>,
+    changed: &HashSet<Entity>,
+) -> Option<bool> {
+    let mut visited = HashSet::from([entity]);
+    let mut current = entity;
+    let mut found = false;
+    while let Some(parent) = nodes.get(&current).and_then(|node| node.parent) {
+        if !visited.insert(parent) {
+            return None;
+        }
+        found |= changed.contains(&parent);
+        current = parent;
+    }
+    Some(found)
+}
@@ -0,0 +1,409 @@
+use std::{collections::HashMap, time::Instant};
+
+use bytemuck::{Pod, Zeroable};
+pub use egui;
+use egui::{
+    epaint::{ImageDelta, Primitive},
+    ClippedPrimitive, Color32, ImageData, TextureId,
+};
+use glam::Vec2;
+use windows::Win32::Graphics::{Direct3D::*, Direct3D12::*, Dxgi::Common::*};
+
+use crate::{
+    asset::texture::TextureData, compile_shader_pair, descriptor::DescriptorHeap, device::Device,
+    error::RenderError, gpu_buffer::GrowableGpuBuffer, graphics_pipeline_desc, id::SamplerId,
+    settings::UiViewport, texture::Texture, Renderer,
+};
+
+const MIN_VERTEX_CAPACITY: usize = 1024;
+const MIN_INDEX_CAPACITY: usize = 3 * MIN_VERTEX_CAPACITY;
+
+// egui's vertex: position in UI points, UV, and premultiplied sRGBA color.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub(crate) struct UiVertex {
+    pub position: Vec2,
+    pub uv: Vec2,
+    pub color: u32,
+}
+
+// Pointer state in window pixels, as tracked by the application.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UiInput {
+    pub cursor: Vec2,
+    pub mouse_down: bool,
+}
+
+// An egui context fed from UiInput and laid out in UI space, so it follows the UI scaling policy.
+// Every frame the application runs it and hands the output to Renderer::render_ui.
+pub struct UiContext {
+    context: egui::Context,
+    start: Instant,
+    input: UiInput,
+    output: Option<egui::FullOutput>,
+}
+
+impl UiContext {
+    pub fn new() -> Self {
+        Self {
+            context: egui::Context::default(),
+            start: Instant::now(),
+            input: UiInput::default(),
+            output: None,
+        }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    pub fn run(
+        &mut self,
+        input: UiInput,
+        viewport: &UiViewport,
+        build: impl FnOnce(&egui::Context),
+    ) {
+        let pointer = viewport.to_ui(input.cursor);
+        let pointer = egui::pos2(pointer.x, pointer.y);
+        let mut events = Vec::new();
+        if input.cursor != self.input.cursor {
+            events.push(egui::Event::PointerMoved(pointer));
+        }
+        if input.mouse_down != self.input.mouse_down {
+            events.push(egui::Event::PointerButton {
+                pos: pointer,
+                button: egui::PointerButton::Primary,
+                pressed: input.mouse_down,
+                modifiers: egui::Modifiers::default(),
+            });
+        }
+        self.input = input;
+
+        let mut raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(viewport.size.x, viewport.size.y),
+            )),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events,
+            ..Default::default()
+        };
+        raw_input
+            .viewports
+            .entry(egui::ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(viewport.scale);
+        let mut output = self.context.run(raw_input, build);
+        // A frame that was never rendered still owes its texture updates.
+        if let Some(mut skipped) = self.output.take() {
+            skipped.textures_delta.append(output.textures_delta);
+            output.textures_delta = skipped.textures_delta;
+        }
+        self.output = Some(output);
+    }
+
+    pub fn wants_input(&self) -> bool {
+        self.context.wants_pointer_input() || self.context.is_pointer_over_area()
+    }
+}
+
+impl Default for UiContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(C)]
+pub(crate) struct UiResources {
+    pub vertex_buffer_index: u32,
+    pub texture_index: u32,
+    pub sampler_index: u32,
+    pub screen_size: Vec2,
+    pub offset: Vec2,
+    pub scale: f32,
+}
+
+// A managed egui texture. The pixels are kept so partial updates can patch them and re-upload.
+pub(crate) struct UiTexture {
+    pub texture: Texture,
+    pub size: [usize; 2],
+    pub pixels: Vec<Color32>,
+}
+
+pub(crate) struct UiDraw {
+    pub texture_index: u32,
+    // Scissor rectangle in pixels: x, y, width, height.
+    pub clip: [u32; 4],
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+pub(crate) struct UiPass {
+    pub pipeline: ID3D12PipelineState,
+    pub sampler: SamplerId,
+    pub textures: HashMap<TextureId, UiTexture>,
+    // Freed by egui this frame but possibly still drawn, so destroyed on the next upload.
+    pub freed: Vec<TextureId>,
+    pub vertices: Vec<UiVertex>,
+    pub indices: Vec<u32>,
+    pub draws: Vec<UiDraw>,
+    pub vertex_buffer: GrowableGpuBuffer<UiVertex>,
+    pub index_buffer: GrowableGpuBuffer<u32>,
+}
+
+impl UiPass {
+    pub fn new(
+        device: &mut Device,
+        cbv_heap: &mut DescriptorHeap,
+        root_signature: &ID3D12RootSignature,
+        format: DXGI_FORMAT,
+        sampler: SamplerId,
+    ) -> Result<Self, RenderError> {
+        let (vertex_shader, fragment_shader) = compile_shader_pair("assets/shaders/ui.hlsl")?;
+        let mut pipeline_desc =
+            graphics_pipeline_desc(root_signature, &vertex_shader, &fragment_shader, format, 1);
+        pipeline_desc.DepthStencilState = D3D12_DEPTH_STENCIL_DESC {
+            DepthEnable: false.into(),
+            DepthWriteMask: D3D12_DEPTH_WRITE_MASK_ZERO,
+            ..Default::default()
+        };
+        pipeline_desc.DSVFormat = DXGI_FORMAT_UNKNOWN;
+        pipeline_desc.RasterizerState.CullMode = D3D12_CULL_MODE_NONE;
+        // egui colors are premultiplied.
+        let blend = &mut pipeline_desc.BlendState.RenderTarget[0];
+        blend.BlendEnable = true.into();
+        blend.SrcBlend = D3D12_BLEND_ONE;
+        blend.DestBlend = D3D12_BLEND_INV_SRC_ALPHA;
+        blend.SrcBlendAlpha = D3D12_BLEND_INV_DEST_ALPHA;
+        blend.DestBlendAlpha = D3D12_BLEND_ONE;
+
+        Ok(Self {
+            pipeline: device.create_graphics_pipeline(&pipeline_desc)?,
+            sampler,
+            textures: HashMap::new(),
+            freed: Vec::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            vertex_buffer: GrowableGpuBuffer::new(
+                device,
+                cbv_heap,
+                "UI Vertex Buffer",
+                MIN_VERTEX_CAPACITY,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+            )?,
+            index_buffer: GrowableGpuBuffer::new(
+                device,
+                cbv_heap,
+                "UI Index Buffer",
+                MIN_INDEX_CAPACITY,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+            )?,
+        })
+    }
+}
+
+impl Renderer {
+    // Uploads the UI last run on `ui`; it is drawn over the scene by the next call to render.
+    // TextureId::User ids are bindless SRV indices, so any texture view can be shown in the UI.
+    pub fn render_ui(&mut self, ui: &mut UiContext) -> Result<(), RenderError> {
+        self.ui.draws.clear();
+        // render waits for the GPU before returning, so last frame's textures are no longer read.
+        for id in std::mem::take(&mut self.ui.freed) {
+            if let Some(texture) = self.ui.textures.remove(&id) {
+                self.destroy_texture(texture.texture);
+            }
+        }
+        let Some(output) = ui.output.take() else {
+            return Ok(());
+        };
+        for (id, delta) in &output.textures_delta.set {
+            self.set_ui_texture(*id, delta)?;
+        }
+        self.ui.freed = output.textures_delta.free;
+
+        let primitives = ui
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.build_ui_draws(&primitives);
+        if self.ui.draws.is_empty() {
+            return Ok(());
+        }
+
+        // render waits for the GPU before returning, so the buffers are free to overwrite here.
+        self.ui.vertex_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            self.ui.vertices.len(),
+        )?;
+        self.ui.index_buffer.reserve(
+            &mut self.device,
+            &mut self.cbv_heap,
+            self.ui.indices.len(),
+        )?;
+        self.ui
+            .vertex_buffer
+            .map(&self.device)?
+            .write_slice(&self.ui.vertices);
+        self.ui
+            .index_buffer
+            .map(&self.device)?
+            .write_slice(&self.ui.indices);
+        Ok(())
+    }
+
+    fn set_ui_texture(&mut self, id: TextureId, delta: &ImageDelta) -> Result<(), RenderError> {
+        let (size, pixels) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.clone()),
+            ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect()),
+        };
+        let previous = self.ui.textures.remove(&id);
+        let (size, pixels) = match (delta.pos, previous) {
+            (Some([x, y]), Some(mut previous)) => {
+                let width = previous.size[0];
+                for (row, patch) in pixels.chunks_exact(size[0]).enumerate() {
+                    let start = (y + row) * width + x;
+                    previous.pixels[start..start + size[0]].copy_from_slice(patch);
+                }
+                self.destroy_texture(previous.texture);
+                (previous.size, previous.pixels)
+            }
+            (_, previous) => {
+                if let Some(previous) = previous {
+                    self.destroy_texture(previous.texture);
+                }
+                (size, pixels)
+            }
+        };
+
+        let data = TextureData::rgba8(
+            size[0] as u32,
+            size[1] as u32,
+            pixels.iter().flat_map(Color32::to_array).collect(),
+        );
+        let texture = self.create_texture_resource(&format!("UI Texture {:?}", id), &data)?;
+        self.ui.textures.insert(
+            id,
+            UiTexture {
+                texture,
+                size,
+                pixels,
+            },
+        );
+        Ok(())
+    }
+
+    fn build_ui_draws(&mut self, primitives: &[ClippedPrimitive]) {
+        self.ui.vertices.clear();
+        self.ui.indices.clear();
+        // Letterboxed layouts are clipped to their canvas, leaving the bars to the scene.
+        let viewport = self.ui_viewport();
+        let canvas_min = viewport.offset.max(Vec2::ZERO);
+        let canvas_max = viewport
+            .to_pixels(viewport.size)
+            .min(Vec2::new(self.width as f32, self.height as f32));
+
+        for primitive in primitives {
+            let Primitive::Mesh(mesh) = &primitive.primitive else {
+                continue;
+            };
+            let texture_index = match mesh.texture_id {
+                TextureId::Managed(_) => match self.ui.textures.get(&mesh.texture_id) {
+                    Some(texture) => texture.texture.view.0 as u32,
+                    None => continue,
+                },
+                TextureId::User(index) => index as u32,
+            };
+            let rect = primitive.clip_rect;
+            let min = viewport
+                .to_pixels(Vec2::new(rect.min.x, rect.min.y))
+                .max(canvas_min)
+                .round();
+            let max = viewport
+                .to_pixels(Vec2::new(rect.max.x, rect.max.y))
+                .min(canvas_max)
+                .round();
+            if mesh.indices.is_empty() || min.cmpge(max).any() {
+                continue;
+            }
+
+            let base_vertex = self.ui.vertices.len() as u32;
+            self.ui
+                .vertices
+                .extend(mesh.vertices.iter().map(|vertex| UiVertex {
+                    position: Vec2::new(vertex.pos.x, vertex.pos.y),
+                    uv: Vec2::new(vertex.uv.x, vertex.uv.y),
+                    color: u32::from_le_bytes(vertex.color.to_array()),
+                }));
+            let first_index = self.ui.indices.len() as u32;
+            self.ui
+                .indices
+                .extend(mesh.indices.iter().map(|index| base_vertex + index));
+            self.ui.draws.push(UiDraw {
+                texture_index,
+                clip: [
+                    min.x as u32,
+                    min.y as u32,
+                    (max.x - min.x) as u32,
+                    (max.y - min.y) as u32,
+                ],
+                first_index,
+                index_count: mesh.indices.len() as u32,
+            });
+        }
+    }
+
+    pub(crate) fn draw_ui(&mut self) -> u32 {
+        let draws = std::mem::take(&mut self.ui.draws);
+        if draws.is_empty() {
+            return 0;
+        }
+
+        let viewport = self.ui_viewport();
+        let vertex_buffer = self.ui.vertex_buffer.view();
+        let index_buffer = self.ui.index_buffer.view();
+        self.render_command_encoder.set_pipeline(&self.ui.pipeline);
+        self.render_command_encoder
+            .set_viewport(self.width, self.height);
+        self.render_command_encoder
+            .set_primitive_topology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        self.render_command_encoder
+            .bind_index_buffer(&D3D12_INDEX_BUFFER_VIEW {
+                BufferLocation: unsafe {
+                    self.device
+                        .get_buffer(index_buffer.buffer)
+                        .allocation
+                        .resource()
+                        .GetGPUVirtualAddress()
+                },
+                SizeInBytes: (self.ui.index_buffer.capacity() * std::mem::size_of::<u32>()) as u32,
+                Format: DXGI_FORMAT_R32_UINT,
+            });
+        for draw in &draws {
+            let [x, y, width, height] = draw.clip;
+            self.render_command_encoder
+                .set_scissor_rect(x, y, width, height);
+            self.render_command_encoder
+                .set_root_constants(&UiResources {
+                    vertex_buffer_index: vertex_buffer.view.0 as u32,
+                    texture_index: draw.texture_index,
+                    sampler_index: self.ui.sampler.0 as u32,
+                    screen_size: Vec2::new(self.width as f32, self.height as f32),
+                    offset: viewport.offset,
+                    scale: viewport.scale,
+                });
+            self.render_command_encoder.draw_indexed_instanced(
+                draw.index_count,
+                1,
+                draw.first_index,
+                0,
+            );
+        }
+
+        let draw_count = draws.len() as u32;
+        // Keep the allocation for next frame's UI.
+        self.ui.draws = draws;
+        self.ui.draws.clear();
+        draw_count
+    }
+}
@@ -0,0 +1,319 @@
+use std::ptr;
+
+use windows::{
+    core::ComInterface,
+    Win32::{
+        Foundation::{CloseHandle, GENERIC_ALL, LUID},
+        Graphics::{
+            Direct3D::*,
+            Direct3D12::*,
+            Dxgi::{Common::*, *},
+        },
+        System::Threading::{CreateEventA, WaitForSingleObject},
+    },
+};
+
+use crate::{capabilities::DeviceCapabilities, error::RenderError};
+
+const MIN_STAGING_SIZE: u64 = 16 * 1024 * 1024;
+
+// Stages uploads through a second adapter (usually the integrated GPU on a dual-GPU laptop):
+// its copy queue moves data into a cross-adapter heap that the primary device reads directly,
+// keeping the copy out of system memory off the primary's queue.
+pub(crate) struct UploadAdapter {
+    pub name: String,
+    device: ID3D12Device,
+    queue: ID3D12CommandQueue,
+    allocator: ID3D12CommandAllocator,
+    list: ID3D12GraphicsCommandList,
+    fence: ID3D12Fence,
+    primary_fence: ID3D12Fence,
+    fence_value: u64,
+    staging: Option<SharedStaging>,
+    // Bytes of the shared heap holding copies the primary queue has not run yet.
+    used: u64,
+}
+
+pub(crate) struct StagedUpload {
+    pub buffer: ID3D12Resource,
+    pub offset: u64,
+    pub fence_value: u64,
+}
+
+struct SharedStaging {
+    size: u64,
+    upload: ID3D12Resource,
+    shared: ID3D12Resource,
+    primary: ID3D12Resource,
+}
+
+impl UploadAdapter {
+    pub fn new(
+        factory: &IDXGIFactory6,
+        primary: &ID3D12Device,
+    ) -> Result<Option<Self>, RenderError> {
+        let Some(adapter) = find_secondary_adapter(factory, unsafe { primary.GetAdapterLuid() })?
+        else {
+            return Ok(None);
+        };
+
+        let mut device: Option<ID3D12Device> = None;
+        unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }
+            .map_err(RenderError::DeviceCreation)?;
+        let device = device.unwrap();
+        let name = DeviceCapabilities::query(&device, &adapter).adapter;
+
+        let queue: ID3D12CommandQueue = unsafe {
+            device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: D3D12_COMMAND_LIST_TYPE_COPY,
+                ..Default::default()
+            })
+        }?;
+        let allocator: ID3D12CommandAllocator =
+            unsafe { device.CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_COPY) }?;
+        let list: ID3D12GraphicsCommandList =
+            unsafe { device.CreateCommandList(0, D3D12_COMMAND_LIST_TYPE_COPY, &allocator, None) }?;
+        unsafe { list.Close() }?;
+
+        let fence: ID3D12Fence = unsafe {
+            device.CreateFence(
+                0,
+                D3D12_FENCE_FLAG_SHARED | D3D12_FENCE_FLAG_SHARED_CROSS_ADAPTER,
+            )
+        }?;
+        let primary_fence = open_shared(&device, primary, &fence)?;
+
+        Ok(Some(Self {
+            name,
+            device,
+            queue,
+            allocator,
+            list,
+            fence,
+            primary_fence,
+            fence_value: 0,
+            staging: None,
+            used: 0,
+        }))
+    }
+
+    // Writes `size` bytes through `write` after the uploads already staged and copies them into
+    // the cross-adapter heap. The primary queue has to wait on `primary_fence()` for the returned
+    // fence value before reading them. Returns None if the heap has no room left; the primary has
+    // to run the staged copies and call `reset` first.
+    pub fn stage(
+        &mut self,
+        primary: &ID3D12Device,
+        size: u64,
+        write: impl FnOnce(*mut u8),
+    ) -> Result<Option<StagedUpload>, RenderError> {
+        self.wait()?;
+        let mut offset = self
+            .used
+            .next_multiple_of(D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64);
+        if self
+            .staging
+            .as_ref()
+            .map_or(true, |staging| offset + size > staging.size)
+        {
+            if self.used > 0 {
+                return Ok(None);
+            }
+            self.staging =
+                Some(self.create_staging(primary, size.next_power_of_two().max(MIN_STAGING_SIZE))?);
+            offset = 0;
+        }
+        let staging = self.staging.as_ref().unwrap();
+
+        let mut data = ptr::null_mut();
+        unsafe { staging.upload.Map(0, None, Some(&mut data)) }?;
+        write(unsafe { data.cast::<u8>().add(offset as usize) });
+        unsafe { staging.upload.Unmap(0, None) };
+
+        unsafe {
+            self.allocator.Reset()?;
+            self.list.Reset(&self.allocator, None)?;
+            self.list
+                .CopyBufferRegion(&staging.shared, offset, &staging.upload, offset, size);
+            self.list.Close()?;
+            self.queue
+                .ExecuteCommandLists(&[Some(self.list.cast::<ID3D12CommandList>()?)]);
+        }
+        self.fence_value += 1;
+        unsafe { self.queue.Signal(&self.fence, self.fence_value) }?;
+        self.used = offset + size;
+
+        Ok(Some(StagedUpload {
+            buffer: staging.primary.clone(),
+            offset,
+            fence_value: self.fence_value,
+        }))
+    }
+
+    // Called once the primary queue has finished every copy out of the staged uploads.
+    pub fn reset(&mut self) {
+        self.used = 0;
+    }
+
+    pub fn primary_fence(&self) -> &ID3D12Fence {
+        &self.primary_fence
+    }
+
+    fn wait(&self) -> Result<(), RenderError> {
+        if unsafe { self.fence.GetCompletedValue() } >= self.fence_value {
+            return Ok(());
+        }
+        unsafe {
+            let event = CreateEventA(None, false, false, None)?;
+            self.fence.SetEventOnCompletion(self.fence_value, event)?;
+            WaitForSingleObject(event, u32::MAX);
+            CloseHandle(event)?;
+        }
+        Ok(())
+    }
+
+    fn create_staging(
+        &self,
+        primary: &ID3D12Device,
+        size: u64,
+    ) -> Result<SharedStaging, RenderError> {
+        let mut desc = buffer_desc(size, D3D12_RESOURCE_FLAG_NONE);
+        let mut upload: Option<ID3D12Resource> = None;
+        unsafe {
+            self.device.CreateCommittedResource(
+                &D3D12_HEAP_PROPERTIES {
+                    Type: D3D12_HEAP_TYPE_UPLOAD,
+                    ..Default::default()
+                },
+                D3D12_HEAP_FLAG_NONE,
+                &desc,
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+                None,
+                &mut upload,
+            )
+        }?;
+
+        let mut heap: Option<ID3D12Heap> = None;
+        unsafe {
+            self.device.CreateHeap(
+                &D3D12_HEAP_DESC {
+                    SizeInBytes: size,
+                    Properties: D3D12_HEAP_PROPERTIES {
+                        Type: D3D12_HEAP_TYPE_DEFAULT,
+                        ..Default::default()
+                    },
+                    Alignment: 0,
+                    Flags: D3D12_HEAP_FLAG_SHARED | D3D12_HEAP_FLAG_SHARED_CROSS_ADAPTER,
+                },
+                &mut heap,
+            )
+        }?;
+        let heap = heap.unwrap();
+        let primary_heap: ID3D12Heap = open_shared(&self.device, primary, &heap)?;
+
+        desc.Flags = D3D12_RESOURCE_FLAG_ALLOW_CROSS_ADAPTER;
+        let mut shared: Option<ID3D12Resource> = None;
+        let mut primary_buffer: Option<ID3D12Resource> = None;
+        unsafe {
+            self.device.CreatePlacedResource(
+                &heap,
+                0,
+                &desc,
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                None,
+                &mut shared,
+            )?;
+            primary.CreatePlacedResource(
+                &primary_heap,
+                0,
+                &desc,
+                D3D12_RESOURCE_STATE_COPY_SOURCE,
+                None,
+                &mut primary_buffer,
+            )?;
+        }
+
+        Ok(SharedStaging {
+            size,
+            upload: upload.unwrap(),
+            shared: shared.unwrap(),
+            primary: primary_buffer.unwrap(),
+        })
+    }
+}
+
+impl Drop for UploadAdapter {
+    fn drop(&mut self) {
+        let _ = self.wait();
+    }
+}
+
+fn find_secondary_adapter(
+    factory: &IDXGIFactory6,
+    primary: LUID,
+) -> Result<Option<IDXGIAdapter1>, RenderError> {
+    for i in 0.. {
+        let adapter: IDXGIAdapter1 = match unsafe {
+            factory.EnumAdapterByGpuPreference(i, DXGI_GPU_PREFERENCE_MINIMUM_POWER)
+        } {
+            Ok(adapter) => adapter,
+            Err(err) if err.code() == DXGI_ERROR_NOT_FOUND => break,
+            Err(err) => return Err(RenderError::DeviceCreation(err)),
+        };
+
+        let mut desc = Default::default();
+        unsafe { adapter.GetDesc1(&mut desc) }?;
+        if (DXGI_ADAPTER_FLAG(desc.Flags as i32) & DXGI_ADAPTER_FLAG_SOFTWARE)
+            != DXGI_ADAPTER_FLAG_NONE
+            || desc.AdapterLuid == primary
+        {
+            continue;
+        }
+
+        if unsafe {
+            D3D12CreateDevice(
+                &adapter,
+                D3D_FEATURE_LEVEL_11_0,
+                std::ptr::null_mut::<Option<ID3D12Device>>(),
+            )
+        }
+        .is_ok()
+        {
+            return Ok(Some(adapter));
+        }
+    }
+
+    Ok(None)
+}
+
+fn open_shared<T: ComInterface>(
+    device: &ID3D12Device,
+    primary: &ID3D12Device,
+    object: &T,
+) -> Result<T, RenderError> {
+    let child: ID3D12DeviceChild = object.cast()?;
+    let handle = unsafe { device.CreateSharedHandle(&child, None, GENERIC_ALL.0, None) }?;
+    let mut opened: Option<T> = None;
+    let result = unsafe { primary.OpenSharedHandle(handle, &mut opened) };
+    unsafe { CloseHandle(handle) }?;
+    result?;
+    Ok(opened.unwrap())
+}
+
+fn buffer_desc(size: u64, flags: D3D12_RESOURCE_FLAGS) -> D3D12_RESOURCE_DESC {
+    D3D12_RESOURCE_DESC {
+        Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+        Alignment: 0,
+        Width: size,
+        Height: 1,
+        DepthOrArraySize: 1,
+        MipLevels: 1,
+        Format: DXGI_FORMAT_UNKNOWN,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+        Flags: flags,
+    }
+}
@@ -0,0 +1,122 @@
+use windows::Win32::Graphics::Direct3D12::*;
+
+use crate::{
+    command_encoder::CommandEncoder,
+    device::{Device, SubresourceFootprint},
+    id::{BufferId, ImageId},
+};
+
+pub(crate) enum UploadSource {
+    Staging(BufferId),
+    // Owned elsewhere and kept alive until the copy has run, like the upload adapter's shared heap.
+    Shared(ID3D12Resource),
+}
+
+enum PendingUpload {
+    Image {
+        source: UploadSource,
+        image: ImageId,
+        footprints: Vec<SubresourceFootprint>,
+        state: D3D12_RESOURCE_STATES,
+    },
+    Buffer {
+        staging: BufferId,
+        buffer: BufferId,
+        state: D3D12_RESOURCE_STATES,
+    },
+}
+
+// Copies out of filled staging buffers wait here for the next submission, so loading a scene
+// with dozens of textures costs one submission and fence wait instead of one per texture.
+// Targets are created in COPY_DEST and only readable once the queue has been flushed.
+#[derive(Default)]
+pub(crate) struct UploadQueue {
+    pending: Vec<PendingUpload>,
+}
+
+impl UploadQueue {
+    pub fn push_image(
+        &mut self,
+        source: UploadSource,
+        image: ImageId,
+        footprints: Vec<SubresourceFootprint>,
+        state: D3D12_RESOURCE_STATES,
+    ) {
+        self.pending.push(PendingUpload::Image {
+            source,
+            image,
+            footprints,
+            state,
+        });
+    }
+
+    pub fn push_buffer(
+        &mut self,
+        staging: BufferId,
+        buffer: BufferId,
+        state: D3D12_RESOURCE_STATES,
+    ) {
+        self.pending.push(PendingUpload::Buffer {
+            staging,
+            buffer,
+            state,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Records every pending copy and returns the staging buffers, which can be destroyed once
+    // the GPU has run the commands. Targets destroyed since their upload was queued are skipped;
+    // their staging buffers are still returned.
+    pub fn record(&mut self, device: &Device, encoder: &CommandEncoder) -> Vec<BufferId> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        tracing::debug!(uploads = self.pending.len(), "Recording uploads");
+        let mut staging_buffers = Vec::new();
+        for upload in self.pending.drain(..) {
+            match upload {
+                PendingUpload::Image {
+                    source,
+                    image,
+                    footprints,
+                    state,
+                } => {
+                    let source = match source {
+                        UploadSource::Staging(staging) => {
+                            staging_buffers.push(staging);
+                            device.get_buffer(staging).allocation.resource().clone()
+                        }
+                        UploadSource::Shared(resource) => resource,
+                    };
+                    let Some(image) = device.try_get_image(image) else {
+                        tracing::debug!(?image, "Skipping upload to destroyed image");
+                        continue;
+                    };
+                    encoder.copy_buffer_to_image(&source, image, &footprints);
+                    encoder.transition_image(
+                        image.allocation.resource(),
+                        D3D12_RESOURCE_STATE_COPY_DEST,
+                        state,
+                    );
+                }
+                PendingUpload::Buffer {
+                    staging,
+                    buffer,
+                    state,
+                } => {
+                    staging_buffers.push(staging);
+                    let Some(buffer) = device.try_get_buffer(buffer) else {
+                        tracing::debug!(?buffer, "Skipping upload to destroyed buffer");
+                        continue;
+                    };
+                    encoder.copy_buffer_to_buffer(device.get_buffer(staging), buffer);
+                    encoder.transition_buffer(buffer, D3D12_RESOURCE_STATE_COPY_DEST, state);
+                }
+            }
+        }
+        staging_buffers
+    }
+}